@@ -58,7 +58,7 @@ macro_rules! new_full_start {
 				))
 			})?
 			.with_import_queue(|
-				_config,
+				config,
 				client,
 				mut select_chain,
 				_transaction_pool,
@@ -85,14 +85,17 @@ macro_rules! new_full_start {
 					None,
 					client,
 					inherent_data_providers.clone(),
+					config.block_time_drift_tolerance,
 					spawn_task_handle,
 					registry,
-				)?;
+				)?
+				.with_mem_budget(config.import_queue_mem_budget);
 
 				import_setup = Some((grandpa_block_import, grandpa_link));
 
 				Ok(import_queue)
-			})?;
+			})?
+			.with_inherent_data_providers(&inherent_data_providers)?;
 
 		(builder, import_setup, inherent_data_providers)
 	}}
@@ -232,7 +235,7 @@ pub fn new_light(config: Configuration) -> Result<TaskManager, ServiceError> {
 			Ok(pool)
 		})?
 		.with_import_queue_and_fprb(|
-			_config,
+			config,
 			client,
 			backend,
 			fetcher,
@@ -261,9 +264,11 @@ pub fn new_light(config: Configuration) -> Result<TaskManager, ServiceError> {
 				Some(Box::new(finality_proof_import)),
 				client,
 				inherent_data_providers.clone(),
+				config.block_time_drift_tolerance,
 				spawn_task_handle,
 				prometheus_registry,
-			)?;
+			)?
+			.with_mem_budget(config.import_queue_mem_budget);
 
 			Ok((import_queue, finality_proof_request_builder))
 		})?
@@ -72,7 +72,7 @@ macro_rules! new_full_start {
 				))
 			})?
 			.with_import_queue(|
-				_config,
+				config,
 				client,
 				mut select_chain,
 				_transaction_pool,
@@ -102,9 +102,11 @@ macro_rules! new_full_start {
 					client,
 					select_chain,
 					inherent_data_providers.clone(),
+					config.block_time_drift_tolerance,
 					spawn_task_handle,
 					prometheus_registry,
-				)?;
+				)?
+				.with_mem_budget(config.import_queue_mem_budget);
 
 				import_setup = Some((block_import, grandpa_link, babe_link));
 				Ok(import_queue)
@@ -115,6 +117,10 @@ macro_rules! new_full_start {
 
 				let shared_authority_set = grandpa_link.shared_authority_set().clone();
 				let shared_voter_state = grandpa::SharedVoterState::empty();
+				let finality_proof_provider = Arc::new(grandpa::FinalityProofProvider::new(
+					builder.backend().clone(),
+					builder.client().clone() as Arc<dyn grandpa::StorageAndProofProvider<_, _>>,
+				));
 
 				rpc_setup = Some((shared_voter_state.clone()));
 
@@ -144,12 +150,14 @@ macro_rules! new_full_start {
 						grandpa: node_rpc::GrandpaDeps {
 							shared_voter_state: shared_voter_state.clone(),
 							shared_authority_set: shared_authority_set.clone(),
+							finality_proof_provider: finality_proof_provider.clone(),
 						},
 					};
 
 					node_rpc::create_full(deps)
 				})
-			})?;
+			})?
+			.with_inherent_data_providers(&inherent_data_providers)?;
 
 		(builder, import_setup, inherent_data_providers, rpc_setup)
 	}}
@@ -365,7 +373,7 @@ pub fn new_light_base(config: Configuration) -> Result<(
 			Ok(pool)
 		})?
 		.with_import_queue_and_fprb(|
-			_config,
+			config,
 			client,
 			backend,
 			fetcher,
@@ -406,9 +414,11 @@ pub fn new_light_base(config: Configuration) -> Result<(
 				client.clone(),
 				select_chain,
 				inherent_data_providers.clone(),
+				config.block_time_drift_tolerance,
 				spawn_task_handle,
 				registry,
-			)?;
+			)?
+			.with_mem_budget(config.import_queue_mem_budget);
 
 			Ok((import_queue, finality_proof_request_builder))
 		})?
@@ -350,6 +350,12 @@ impl BenchDb {
 			state_cache_child_ratio: Some((0, 100)),
 			pruning: PruningMode::ArchiveAll,
 			source: database_type.into_settings(dir.into()),
+			open_retries: 0,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: None,
 		};
 
 		let (client, backend) = sc_service::new_client(
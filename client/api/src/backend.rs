@@ -52,6 +52,26 @@ pub type TransactionForSB<B, Block> = <B as StateBackend<HashFor<Block>>>::Trans
 /// Extracts the transaction for the given backend.
 pub type TransactionFor<B, Block> = TransactionForSB<StateBackendFor<B, Block>, Block>;
 
+/// Hit and miss counters of a state cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StateCacheStats {
+	/// Number of reads answered from the cache.
+	pub hits: u64,
+	/// Number of reads that had to go to the database.
+	pub misses: u64,
+}
+
+/// State cache shared by the states of a backend.
+pub trait StateCache: Send + Sync {
+	/// Returns the hit and miss counters since the last flush.
+	fn stats(&self) -> StateCacheStats;
+
+	/// Evicts all the cached entries and resets the counters, returning them as they were.
+	///
+	/// Nothing but the cache is affected: the evicted entries are read from the database again.
+	fn flush(&self) -> StateCacheStats;
+}
+
 /// Import operation summary.
 ///
 /// Contains information about the block that just got imported,
@@ -435,6 +455,11 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 	/// Returns current usage statistics.
 	fn usage_info(&self) -> Option<UsageInfo>;
 
+	/// Returns a handle to the state cache, if the backend has one.
+	fn state_cache(&self) -> Option<Arc<dyn StateCache>> {
+		None
+	}
+
 	/// Returns reference to changes trie storage.
 	fn changes_trie_storage(&self) -> Option<&dyn PrunableStateChangesTrieStorage<Block>>;
 
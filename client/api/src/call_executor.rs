@@ -24,7 +24,7 @@ use sp_runtime::{
 	generic::BlockId, traits::{Block as BlockT, HashFor},
 };
 use sp_state_machine::{
-	OverlayedChanges, ExecutionManager, ExecutionStrategy, StorageProof,
+	OverlayedChanges, ExecutionManager, ExecutionStrategy, StorageProof, ExecutionTrace,
 };
 use sc_executor::{RuntimeVersion, NativeVersion};
 use sp_externalities::Extensions;
@@ -131,6 +131,17 @@ pub trait CallExecutor<B: BlockT> {
 		call_data: &[u8]
 	) -> Result<(Vec<u8>, StorageProof), sp_blockchain::Error>;
 
+	/// Execute a call to a contract on top of the state of the given block, tracing the storage
+	/// accesses.
+	///
+	/// No changes are made. Fails with `StateUnavailable` if the state has been discarded.
+	fn trace_call(
+		&self,
+		id: &BlockId<B>,
+		method: &str,
+		call_data: &[u8],
+	) -> Result<(Vec<u8>, ExecutionTrace), sp_blockchain::Error>;
+
 	/// Get runtime version if supported.
 	fn native_runtime_version(&self) -> Option<&NativeVersion>;
 }
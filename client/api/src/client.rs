@@ -103,7 +103,7 @@ pub trait ProvideUncles<Block: BlockT> {
 }
 
 /// Client info
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ClientInfo<Block: BlockT> {
 	/// Best block hash.
 	pub chain: Info<Block>,
@@ -267,6 +267,7 @@ impl<B: BlockT> From<FinalityNotification<B>> for sp_transaction_pool::ChainEven
 	fn from(n: FinalityNotification<B>) -> Self {
 		Self::Finalized {
 			hash: n.hash,
+			tree_route: Arc::new([]),
 		}
 	}
 }
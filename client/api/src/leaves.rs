@@ -192,6 +192,29 @@ impl<H, N> LeafSet<H, N> where
 		}
 	}
 
+	/// Stop tracking the lowest leaves until at most `max` are left, returning the dropped hashes.
+	///
+	/// Among leaves at the same height, the ones imported first are dropped first. Leaves in
+	/// `keep` are never dropped, so more than `max` leaves may be left.
+	pub fn cap(&mut self, max: usize, keep: &[H]) -> Vec<H> {
+		let excess = self.storage.values().map(|hashes| hashes.len()).sum::<usize>().saturating_sub(max);
+		let dropped = self.storage.iter()
+			.rev()
+			.flat_map(|(number, hashes)| hashes.iter().map(move |h| (number.clone(), h.clone())))
+			.filter(|(_, hash)| !keep.contains(hash))
+			.take(excess)
+			.collect::<Vec<_>>();
+
+		dropped.into_iter().map(|(number, hash)| {
+			assert!(
+				self.remove_leaf(&number, &hash),
+				"item comes from an iterator over storage; qed",
+			);
+			self.pending_removed.push(hash.clone());
+			hash
+		}).collect()
+	}
+
 	/// returns an iterator over all hashes in the leaf set
 	/// ordered by their block number descending.
 	pub fn hashes(&self) -> Vec<H> {
@@ -378,4 +401,21 @@ mod tests {
 		set.undo().undo_finalization(displaced);
 		assert!(set.contains(10, 10_1));
 	}
+
+	#[test]
+	fn cap_drops_lowest_leaves_except_kept_ones() {
+		let mut set = LeafSet::new();
+		set.import(10_1u32, 10u32, 0u32);
+		set.import(10_2, 10, 0);
+		set.import(11_1, 11, 0);
+		set.import(11_2, 11, 0);
+		set.import(12_1, 12, 0);
+
+		assert_eq!(set.cap(3, &[10_1]), vec![10_2, 11_1]);
+		assert_eq!(set.hashes(), vec![12_1, 11_2, 10_1]);
+
+		assert!(set.cap(1, &[12_1, 11_2, 10_1]).is_empty());
+		assert_eq!(set.cap(1, &[11_2]), vec![10_1, 12_1]);
+		assert_eq!(set.hashes(), vec![11_2]);
+	}
 }
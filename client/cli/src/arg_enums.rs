@@ -148,6 +148,46 @@ impl Into<sc_service::config::RpcMethods> for RpcMethods {
 	}
 }
 
+arg_enum! {
+	/// When to start serving RPC.
+	#[allow(missing_docs)]
+	#[derive(Debug, Copy, Clone, PartialEq)]
+	pub enum RpcReadiness {
+		// As soon as the node is started.
+		Immediately,
+		// Once the node is connected to at least one peer.
+		AfterFirstPeer,
+		// Once the node is connected to peers and not syncing anymore.
+		AfterSynced,
+	}
+}
+
+impl Into<sc_service::config::RpcReadiness> for RpcReadiness {
+	fn into(self) -> sc_service::config::RpcReadiness {
+		match self {
+			RpcReadiness::Immediately => sc_service::config::RpcReadiness::Immediately,
+			RpcReadiness::AfterFirstPeer => sc_service::config::RpcReadiness::AfterFirstPeer,
+			RpcReadiness::AfterSynced => sc_service::config::RpcReadiness::AfterSynced,
+		}
+	}
+}
+
+arg_enum! {
+	/// Source of boot nodes.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum BootNodeSource {
+		// The boot nodes of the chain specification.
+		ChainSpec,
+		// The boot nodes passed with `--bootnodes`.
+		CommandLine,
+		// The boot nodes listed in the `--bootnodes-file`.
+		File,
+		// The boot nodes passed with `--bootnodes-dns-seed`, resolved at startup.
+		DnsSeed,
+	}
+}
+
 arg_enum! {
 	/// Database backend
 	#[allow(missing_docs)]
@@ -159,6 +199,8 @@ arg_enum! {
 		SubDb,
 		// ParityDb. https://github.com/paritytech/parity-db/
 		ParityDb,
+		// Kept in memory and lost on exit, nothing is written to the disk.
+		InMemory,
 	}
 }
 
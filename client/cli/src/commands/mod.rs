@@ -187,6 +187,15 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn tx_propagation_delay(&self) -> $crate::Result<::std::option::Option<(
+				::std::time::Duration,
+				::std::time::Duration,
+			)>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.tx_propagation_delay()),*
+				}
+			}
+
 			fn network_config(
 				&self,
 				chain_spec: &::std::boxed::Box<dyn ::sc_service::ChainSpec>,
@@ -241,6 +250,61 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn import_queue_mem_budget(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.import_queue_mem_budget()),*
+				}
+			}
+
+			fn block_time_drift_tolerance(&self) -> $crate::Result<::std::time::Duration> {
+				match self {
+					$($enum::$variant(cmd) => cmd.block_time_drift_tolerance()),*
+				}
+			}
+
+			fn database_open_retries(&self) -> $crate::Result<u32> {
+				match self {
+					$($enum::$variant(cmd) => cmd.database_open_retries()),*
+				}
+			}
+
+			fn database_write_buffer_size(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.database_write_buffer_size()),*
+				}
+			}
+
+			fn database_max_open_files(&self) -> $crate::Result<::std::option::Option<u32>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.database_max_open_files()),*
+				}
+			}
+
+			fn genesis_build_threads(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.genesis_build_threads()),*
+				}
+			}
+
+			fn max_tracked_forks(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.max_tracked_forks()),*
+				}
+			}
+
+			fn skip_genesis_check(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.skip_genesis_check()),*
+				}
+			}
+
+			fn expected_genesis_code_hash(&self)
+			-> $crate::Result<::std::option::Option<::sp_core::H256>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.expected_genesis_code_hash()),*
+				}
+			}
+
 			fn state_cache_child_ratio(&self) -> $crate::Result<::std::option::Option<usize>> {
 				match self {
 					$($enum::$variant(cmd) => cmd.state_cache_child_ratio()),*
@@ -254,6 +318,13 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn justification_retention(&self)
+			-> $crate::Result<::sc_service::config::JustificationRetention> {
+				match self {
+					$($enum::$variant(cmd) => cmd.justification_retention()),*
+				}
+			}
+
 			fn chain_id(&self, is_dev: bool) -> $crate::Result<String> {
 				match self {
 					$($enum::$variant(cmd) => cmd.chain_id(is_dev)),*
@@ -309,12 +380,36 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn rpc_serve_after(&self) -> $crate::Result<sc_service::config::RpcReadiness> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_serve_after()),*
+				}
+			}
+
+			fn rpc_handler_mode(&self) -> $crate::Result<sc_service::config::RpcHandlerMode> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_handler_mode()),*
+				}
+			}
+
+			fn rpc_max_concurrent_runtime_calls(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_max_concurrent_runtime_calls()),*
+				}
+			}
+
 			fn rpc_ws_max_connections(&self) -> $crate::Result<::std::option::Option<usize>> {
 				match self {
 					$($enum::$variant(cmd) => cmd.rpc_ws_max_connections()),*
 				}
 			}
 
+			fn rpc_dual_stack(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_dual_stack()),*
+				}
+			}
+
 			fn rpc_cors(&self, is_dev: bool)
 			-> $crate::Result<::std::option::Option<::std::vec::Vec<String>>> {
 				match self {
@@ -345,6 +440,24 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn telemetry_metrics(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.telemetry_metrics()),*
+				}
+			}
+
+			fn telemetry_healthy_peer_threshold(&self) -> $crate::Result<usize> {
+				match self {
+					$($enum::$variant(cmd) => cmd.telemetry_healthy_peer_threshold()),*
+				}
+			}
+
+			fn finality_webhook(&self) -> $crate::Result<::std::option::Option<::sc_service::config::Url>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.finality_webhook()),*
+				}
+			}
+
 			fn default_heap_pages(&self) -> $crate::Result<::std::option::Option<u64>> {
 				match self {
 					$($enum::$variant(cmd) => cmd.default_heap_pages()),*
@@ -366,12 +479,32 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn max_announce_block_age(&self) -> $crate::Result<::std::option::Option<u32>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.max_announce_block_age()),*
+				}
+			}
+
+			fn block_announce_validation_limit(&self) -> $crate::Result<
+				::std::option::Option<::sc_service::config::AnnounceValidationLimit>
+			> {
+				match self {
+					$($enum::$variant(cmd) => cmd.block_announce_validation_limit()),*
+				}
+			}
+
 			fn disable_grandpa(&self) -> $crate::Result<bool> {
 				match self {
 					$($enum::$variant(cmd) => cmd.disable_grandpa()),*
 				}
 			}
 
+			fn overwrite_dev_keys(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.overwrite_dev_keys()),*
+				}
+			}
+
 			fn dev_key_seed(&self, is_dev: bool) -> $crate::Result<::std::option::Option<String>> {
 				match self {
 					$($enum::$variant(cmd) => cmd.dev_key_seed(is_dev)),*
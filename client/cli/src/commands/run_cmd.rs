@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::arg_enums::RpcMethods;
+use crate::arg_enums::{RpcMethods, RpcReadiness};
 use crate::error::{Error, Result};
 use crate::params::ImportParams;
 use crate::params::KeystoreParams;
@@ -25,15 +25,27 @@ use crate::params::OffchainWorkerParams;
 use crate::params::SharedParams;
 use crate::params::TransactionPoolParams;
 use crate::CliConfiguration;
+use crate::config::DEFAULT_MAX_RUNTIME_INSTANCES;
 use regex::Regex;
 use sc_service::{
-	config::{BasePath, MultiaddrWithPeerId, PrometheusConfig, TransactionPoolOptions},
+	config::{
+		AnnounceOverflow, AnnounceValidationLimit, BasePath, MultiaddrWithPeerId, PrometheusConfig,
+		TransactionPoolOptions, Url,
+	},
 	ChainSpec, Role,
 };
 use sc_telemetry::TelemetryEndpoints;
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
 use structopt::StructOpt;
 
+/// Smallest accepted `--task-watchdog-timeout`, in seconds.
+///
+/// The watched tasks make progress every 30 seconds, a shorter timeout would keep restarting them.
+const MIN_TASK_WATCHDOG_TIMEOUT: u64 = 60;
+
 /// The `run` command used to run a node.
 #[derive(Debug, StructOpt)]
 pub struct RunCmd {
@@ -102,6 +114,36 @@ pub struct RunCmd {
 	)]
 	pub rpc_methods: RpcMethods,
 
+	/// When to start serving RPC over HTTP, Websockets and IPC.
+	///
+	/// Delaying it keeps load balancers from routing requests to a node that is still warming up.
+	#[structopt(
+		long,
+		value_name = "CONDITION",
+		possible_values = &RpcReadiness::variants(),
+		case_insensitive = true,
+		default_value = "Immediately",
+	)]
+	pub rpc_serve_after: RpcReadiness,
+
+	/// Only build the RPC handlers on the first request, and drop them once they haven't been
+	/// used for the given number of seconds.
+	///
+	/// This saves memory on nodes that rarely serve RPC requests.
+	#[structopt(long = "rpc-lazy-handlers", value_name = "IDLE_SECONDS")]
+	pub rpc_lazy_handlers: Option<u64>,
+
+	/// Maximum number of runtime calls made concurrently on behalf of RPC clients, e.g. through
+	/// `state_call`.
+	///
+	/// Calls above the limit are rejected. It must be below `--max-runtime-instances` so that
+	/// block import and authorship always find a runtime instance.
+	///
+	/// Only the `state_call` and `state_traceBlock` RPCs of full nodes are limited. Custom RPCs
+	/// calling the runtime API directly, and light clients, are not.
+	#[structopt(long = "rpc-max-concurrent-runtime-calls", value_name = "COUNT")]
+	pub rpc_max_concurrent_runtime_calls: Option<usize>,
+
 	/// Listen to all Websocket interfaces.
 	///
 	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use an RPC proxy
@@ -138,6 +180,12 @@ pub struct RunCmd {
 	#[structopt(long = "ws-max-connections", value_name = "COUNT")]
 	pub ws_max_connections: Option<usize>,
 
+	/// Serve the HTTP and WebSockets RPC on both IPv4 and IPv6 when listening on all interfaces.
+	///
+	/// Without IPv6 support, only IPv4 is served.
+	#[structopt(long = "rpc-dual-stack")]
+	pub rpc_dual_stack: bool,
+
 	/// Specify browser Origins allowed to access the HTTP & WS RPC servers.
 	///
 	/// A comma-separated list of origins (protocol://domain or special `null`
@@ -157,6 +205,18 @@ pub struct RunCmd {
 	#[structopt(long = "no-prometheus")]
 	pub no_prometheus: bool,
 
+	/// Add a label to every Prometheus metric, e.g. `--prometheus-static-label chain=polkadot`.
+	///
+	/// Can be given multiple times, with a different name each time. The label must not already
+	/// be used by a metric.
+	#[structopt(
+		long = "prometheus-static-label",
+		value_name = "NAME=VALUE",
+		parse(try_from_str = parse_prometheus_static_label),
+		number_of_values = 1,
+	)]
+	pub prometheus_static_labels: Vec<(String, String)>,
+
 	/// The human-readable name for this node.
 	///
 	/// The node name will be reported to the telemetry server, if enabled.
@@ -178,6 +238,43 @@ pub struct RunCmd {
 	#[structopt(long = "telemetry-url", value_name = "URL VERBOSITY", parse(try_from_str = parse_telemetry_endpoints))]
 	pub telemetry_endpoints: Vec<(String, u8)>,
 
+	/// Also send the core metrics to the telemetry, e.g. when Prometheus can't be scraped.
+	///
+	/// The block heights, peer count, transaction pool size and finality lag are sent every few
+	/// seconds as a `node.metrics` event.
+	#[structopt(long = "telemetry-metrics")]
+	pub telemetry_metrics: bool,
+
+	/// Minimum number of connected peers for the node to be reported as healthy to the telemetry.
+	///
+	/// Use `0` to always report the node as healthy, e.g. for a single node development chain.
+	#[structopt(long = "telemetry-healthy-peers", value_name = "COUNT", default_value = "1")]
+	pub telemetry_healthy_peer_threshold: usize,
+
+	/// Post the header of every finalized block, as JSON, to this HTTP endpoint.
+	///
+	/// Failed posts are retried, the oldest headers are dropped if the endpoint can't keep up.
+	#[structopt(long = "finality-webhook", value_name = "URL")]
+	pub finality_webhook: Option<Url>,
+
+	/// Periodically write the peer reputations to this file, and restore them on startup.
+	///
+	/// This keeps misbehaving peers banned across restarts.
+	#[structopt(long = "persist-peer-reputations", value_name = "PATH", parse(from_os_str))]
+	pub persist_peer_reputations: Option<PathBuf>,
+
+	/// Discard the persisted peer reputations on startup if they are older than this.
+	#[structopt(long = "peer-reputations-ttl", value_name = "SECONDS", default_value = "3600")]
+	pub peer_reputations_ttl: u64,
+
+	/// Restart the background tasks that support it, such as the periodic report of the
+	/// network state to the telemetry, when they are stuck for this long.
+	///
+	/// Essential tasks that are stuck shut the node down instead. Must be at least 60 seconds,
+	/// since the watched tasks make progress every 30 seconds.
+	#[structopt(long = "task-watchdog-timeout", value_name = "SECONDS")]
+	pub task_watchdog_timeout: Option<u64>,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub offchain_worker_params: OffchainWorkerParams,
@@ -230,10 +327,53 @@ pub struct RunCmd {
 	#[structopt(long, conflicts_with_all = &["alice", "bob", "charlie", "dave", "eve", "ferdie", "one"])]
 	pub two: bool,
 
+	/// Keep the session keys already in the keystore.
+	///
+	/// Only the key types the keystore holds no key of are generated from the development seed,
+	/// e.g. `--alice`.
+	#[structopt(long = "keep-session-keys")]
+	pub keep_session_keys: bool,
+
 	/// Enable authoring even when offline.
 	#[structopt(long = "force-authoring")]
 	pub force_authoring: bool,
 
+	/// Let the inherent check failure hook of the node accept blocks with invalid inherents.
+	///
+	/// This is meant for test networks only: blocks that the rest of the network rejects may be
+	/// imported.
+	#[structopt(long = "unsafe-override-inherent-checks")]
+	pub unsafe_override_inherent_checks: bool,
+
+	/// Reject the blocks the runtime panics on while importing them, and penalize the peers that
+	/// sent them.
+	///
+	/// By default the import of such a block fails with an error. Blocks authored by the node
+	/// itself always do.
+	#[structopt(long = "reject-runtime-panics")]
+	pub reject_runtime_panics: bool,
+
+	/// Ignore the announcements of blocks this many blocks or more below the best block.
+	///
+	/// This mitigates the spam of peers announcing very old blocks. Announcements are never
+	/// ignored while the node is catching up with the chain.
+	#[structopt(long = "max-announce-block-age", value_name = "BLOCKS")]
+	pub max_announce_block_age: Option<u32>,
+
+	/// Validate at most this many block announcements of a single peer at once.
+	///
+	/// Announcements are validated in rounds, so that a peer flooding the node with
+	/// announcements can't delay the validation of the other peers' ones. The announcements of a
+	/// peer in excess of the limit are queued for the next rounds, unless
+	/// `--drop-excess-announcements` is passed.
+	#[structopt(long = "max-announce-validations-per-peer", value_name = "COUNT")]
+	pub max_announce_validations_per_peer: Option<usize>,
+
+	/// Drop the block announcements of a peer in excess of `--max-announce-validations-per-peer`
+	/// instead of queueing them.
+	#[structopt(long = "drop-excess-announcements", requires = "max-announce-validations-per-peer")]
+	pub drop_excess_announcements: bool,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub keystore_params: KeystoreParams,
@@ -341,6 +481,10 @@ impl CliConfiguration for RunCmd {
 		}))
 	}
 
+	fn overwrite_dev_keys(&self) -> Result<bool> {
+		Ok(!self.keep_session_keys)
+	}
+
 	fn telemetry_endpoints(
 		&self,
 		chain_spec: &Box<dyn ChainSpec>,
@@ -357,6 +501,36 @@ impl CliConfiguration for RunCmd {
 		})
 	}
 
+	fn telemetry_metrics(&self) -> Result<bool> {
+		Ok(self.telemetry_metrics)
+	}
+
+	fn telemetry_healthy_peer_threshold(&self) -> Result<usize> {
+		Ok(self.telemetry_healthy_peer_threshold)
+	}
+
+	fn finality_webhook(&self) -> Result<Option<Url>> {
+		Ok(self.finality_webhook.clone())
+	}
+
+	fn persist_peer_reputations(&self) -> Result<Option<PathBuf>> {
+		Ok(self.persist_peer_reputations.clone())
+	}
+
+	fn peer_reputations_ttl(&self) -> Result<Duration> {
+		Ok(Duration::from_secs(self.peer_reputations_ttl))
+	}
+
+	fn task_watchdog_timeout(&self) -> Result<Option<Duration>> {
+		match self.task_watchdog_timeout {
+			Some(timeout) if timeout < MIN_TASK_WATCHDOG_TIMEOUT => Err(Error::Input(format!(
+				"--task-watchdog-timeout must be at least {} seconds",
+				MIN_TASK_WATCHDOG_TIMEOUT,
+			))),
+			timeout => Ok(timeout.map(Duration::from_secs)),
+		}
+	}
+
 	fn role(&self, is_dev: bool) -> Result<Role> {
 		let keyring = self.get_keyring();
 		let is_light = self.light;
@@ -382,6 +556,35 @@ impl CliConfiguration for RunCmd {
 		Ok(self.shared_params.dev || self.force_authoring)
 	}
 
+	fn unsafe_override_inherent_checks(&self) -> Result<bool> {
+		Ok(self.unsafe_override_inherent_checks)
+	}
+
+	fn reject_runtime_panics(&self) -> Result<bool> {
+		Ok(self.reject_runtime_panics)
+	}
+
+	fn max_announce_block_age(&self) -> Result<Option<u32>> {
+		Ok(self.max_announce_block_age)
+	}
+
+	fn block_announce_validation_limit(&self) -> Result<Option<AnnounceValidationLimit>> {
+		if self.max_announce_validations_per_peer == Some(0) {
+			return Err(Error::Input(
+				"--max-announce-validations-per-peer must be at least 1".into()
+			));
+		}
+
+		Ok(self.max_announce_validations_per_peer.map(|max_per_peer| AnnounceValidationLimit {
+			max_per_peer,
+			overflow: if self.drop_excess_announcements {
+				AnnounceOverflow::Drop
+			} else {
+				AnnounceOverflow::Queue
+			},
+		}))
+	}
+
 	fn prometheus_config(&self) -> Result<Option<PrometheusConfig>> {
 		Ok(if self.no_prometheus {
 			None
@@ -398,6 +601,19 @@ impl CliConfiguration for RunCmd {
 		})
 	}
 
+	fn prometheus_static_labels(&self) -> Result<HashMap<String, String>> {
+		let mut labels = HashMap::new();
+		for (name, value) in &self.prometheus_static_labels {
+			if labels.insert(name.clone(), value.clone()).is_some() {
+				return Err(Error::Input(format!(
+					"--prometheus-static-label `{}` is given more than once",
+					name,
+				)));
+			}
+		}
+		Ok(labels)
+	}
+
 	fn disable_grandpa(&self) -> Result<bool> {
 		Ok(self.no_grandpa)
 	}
@@ -406,6 +622,10 @@ impl CliConfiguration for RunCmd {
 		Ok(self.ws_max_connections)
 	}
 
+	fn rpc_dual_stack(&self) -> Result<bool> {
+		Ok(self.rpc_dual_stack)
+	}
+
 	fn rpc_cors(&self, is_dev: bool) -> Result<Option<Vec<String>>> {
 		Ok(self
 			.rpc_cors
@@ -457,10 +677,43 @@ impl CliConfiguration for RunCmd {
 		Ok(self.rpc_methods.into())
 	}
 
+	fn rpc_serve_after(&self) -> Result<sc_service::config::RpcReadiness> {
+		Ok(self.rpc_serve_after.into())
+	}
+
+	fn rpc_handler_mode(&self) -> Result<sc_service::config::RpcHandlerMode> {
+		Ok(match self.rpc_lazy_handlers {
+			Some(secs) => sc_service::config::RpcHandlerMode::Lazy {
+				idle_timeout: Duration::from_secs(secs),
+			},
+			None => sc_service::config::RpcHandlerMode::Eager,
+		})
+	}
+
+	fn rpc_max_concurrent_runtime_calls(&self) -> Result<Option<usize>> {
+		let max_runtime_instances = self.max_runtime_instances()?
+			.unwrap_or(DEFAULT_MAX_RUNTIME_INSTANCES);
+
+		match self.rpc_max_concurrent_runtime_calls {
+			Some(0) => Err(Error::Input(
+				"--rpc-max-concurrent-runtime-calls must be at least 1".into()
+			)),
+			Some(max) if max >= max_runtime_instances => Err(Error::Input(format!(
+				"--rpc-max-concurrent-runtime-calls must be below the {} runtime instances",
+				max_runtime_instances,
+			))),
+			max => Ok(max),
+		}
+	}
+
 	fn transaction_pool(&self) -> Result<TransactionPoolOptions> {
 		Ok(self.pool_config.transaction_pool())
 	}
 
+	fn tx_propagation_delay(&self) -> Result<Option<(Duration, Duration)>> {
+		self.pool_config.tx_propagation_delay()
+	}
+
 	fn max_runtime_instances(&self) -> Result<Option<usize>> {
 		Ok(self.max_runtime_instances.map(|x| x.min(256)))
 	}
@@ -557,6 +810,11 @@ fn parse_telemetry_endpoints(s: &str) -> std::result::Result<(String, u8), Telem
 	}
 }
 
+fn parse_prometheus_static_label(s: &str) -> std::result::Result<(String, String), String> {
+	let pos = s.find('=').ok_or_else(|| format!("Expected NAME=VALUE, got `{}`", s))?;
+	Ok((s[..pos].to_string(), s[pos + 1..].to_string()))
+}
+
 /// CORS setting
 ///
 /// The type is introduced to overcome `Option<Option<T>>`
@@ -619,4 +877,60 @@ mod tests {
 		assert!(is_node_name_valid("www.visit.me").is_err());
 		assert!(is_node_name_valid("email@domain").is_err());
 	}
+
+	#[test]
+	fn task_watchdog_timeout_is_validated() {
+		let timeout = |args: &[&str]| RunCmd::from_iter(
+			["substrate"].iter().chain(args.iter())
+		).task_watchdog_timeout();
+
+		assert_eq!(timeout(&[]).unwrap(), None);
+		assert_eq!(
+			timeout(&["--task-watchdog-timeout", "90"]).unwrap(),
+			Some(Duration::from_secs(90)),
+		);
+		assert!(timeout(&["--task-watchdog-timeout", "0"]).is_err());
+		assert!(timeout(&["--task-watchdog-timeout", "30"]).is_err());
+	}
+
+	#[test]
+	fn rpc_max_concurrent_runtime_calls_is_validated() {
+		let max = |args: &[&str]| RunCmd::from_iter(
+			["substrate"].iter().chain(args.iter())
+		).rpc_max_concurrent_runtime_calls();
+
+		assert_eq!(max(&[]).unwrap(), None);
+		assert_eq!(max(&["--rpc-max-concurrent-runtime-calls", "4"]).unwrap(), Some(4));
+		assert!(max(&["--rpc-max-concurrent-runtime-calls", "0"]).is_err());
+		assert!(max(&["--rpc-max-concurrent-runtime-calls", "8"]).is_err());
+		assert_eq!(
+			max(&[
+				"--rpc-max-concurrent-runtime-calls", "8",
+				"--max-runtime-instances", "16",
+			]).unwrap(),
+			Some(8),
+		);
+	}
+
+	#[test]
+	fn duplicate_prometheus_static_labels_are_rejected() {
+		let labels = |args: &[&str]| RunCmd::from_iter(
+			["substrate"].iter().chain(args.iter())
+		).prometheus_static_labels();
+
+		assert_eq!(
+			labels(&[
+				"--prometheus-static-label", "chain=dev",
+				"--prometheus-static-label", "instance=node-1",
+			]).unwrap(),
+			vec![
+				("chain".to_string(), "dev".to_string()),
+				("instance".to_string(), "node-1".to_string()),
+			].into_iter().collect(),
+		);
+		assert!(labels(&[
+			"--prometheus-static-label", "chain=dev",
+			"--prometheus-static-label", "chain=local",
+		]).is_err());
+	}
 }
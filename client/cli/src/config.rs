@@ -27,12 +27,16 @@ use crate::{
 use names::{Generator, Name};
 use sc_client_api::execution_extensions::ExecutionStrategies;
 use sc_service::config::{
-	BasePath, Configuration, DatabaseConfig, ExtTransport, KeystoreConfig, NetworkConfiguration,
-	NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcMethods,
-	TaskExecutor, TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
+	AnnounceValidationLimit, BasePath, Configuration, DatabaseConfig, ExtTransport, KeystoreConfig,
+	NetworkConfiguration, JustificationRetention, NodeKeyConfig, OffchainWorkerConfig,
+	PrometheusConfig, PruningMode, Role, RpcHandlerMode, RpcMethods, RpcReadiness, TaskExecutor,
+	TelemetryEndpoints, TransactionPoolOptions, Url, WasmExecutionMethod,
 };
 use sc_service::{ChainSpec, TracingReceiver};
+use sp_core::H256;
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::time::Duration;
 use std::path::PathBuf;
 
 /// The maximum number of characters for a node name.
@@ -41,6 +45,9 @@ pub(crate) const NODE_NAME_MAX_LENGTH: usize = 64;
 /// default sub directory to store network config
 pub(crate) const DEFAULT_NETWORK_CONFIG_PATH: &'static str = "network";
 
+/// default size of the instances cache for each runtime
+pub(crate) const DEFAULT_MAX_RUNTIME_INSTANCES: usize = 8;
+
 /// A trait that allows converting an object to a Configuration
 pub trait CliConfiguration: Sized {
 	/// Get the SharedParams for this object
@@ -109,6 +116,13 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the range of the random delay before propagating the local transactions.
+	///
+	/// By default this is `None`, i.e. they are propagated immediately.
+	fn tx_propagation_delay(&self) -> Result<Option<(Duration, Duration)>> {
+		Ok(None)
+	}
+
 	/// Get the network configuration
 	///
 	/// By default this is retrieved from `NetworkParams` if it is available otherwise it creates
@@ -186,6 +200,7 @@ pub trait CliConfiguration: Sized {
 			Database::ParityDb => DatabaseConfig::ParityDb {
 				path: base_path.join("paritydb"),
 			},
+			Database::InMemory => DatabaseConfig::InMemory,
 		})
 	}
 
@@ -198,6 +213,72 @@ pub trait CliConfiguration: Sized {
 			.unwrap_or(Default::default()))
 	}
 
+	/// Get the maximum memory used by the blocks waiting in the import queue.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `None`.
+	fn import_queue_mem_budget(&self) -> Result<Option<usize>> {
+		Ok(self.import_params().and_then(|x| x.import_queue_mem_budget()))
+	}
+
+	/// Get the tolerance for blocks ahead of the local clock.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `0`.
+	fn block_time_drift_tolerance(&self) -> Result<Duration> {
+		Ok(self.import_params()
+			.map(|x| x.block_time_drift_tolerance())
+			.unwrap_or_default())
+	}
+
+	/// Get the number of times opening the database is retried after a transient failure.
+	///
+	/// By default this is `3`.
+	fn database_open_retries(&self) -> Result<u32> {
+		Ok(3)
+	}
+
+	/// Get the size in MiB of the database write buffer of each column.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
+	fn database_write_buffer_size(&self) -> Result<Option<usize>> {
+		Ok(self.database_params().and_then(|x| x.database_write_buffer_size()))
+	}
+
+	/// Get the maximum number of files kept open by the database.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
+	fn database_max_open_files(&self) -> Result<Option<u32>> {
+		Ok(self.database_params().and_then(|x| x.database_max_open_files()))
+	}
+
+	/// Get the number of threads used to build the genesis state.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
+	fn genesis_build_threads(&self) -> Result<Option<usize>> {
+		Ok(self.database_params().and_then(|x| x.genesis_build_threads()))
+	}
+
+	/// Get the maximum number of forks tracked by the backend.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
+	fn max_tracked_forks(&self) -> Result<Option<usize>> {
+		Ok(self.database_params().and_then(|x| x.max_tracked_forks()))
+	}
+
+	/// Returns `true` if the genesis block of an existing database shouldn't be checked against the
+	/// chain specification.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `false`.
+	fn skip_genesis_check(&self) -> Result<bool> {
+		Ok(self.database_params().map(|x| x.skip_genesis_check()).unwrap_or_default())
+	}
+
+	/// Get the Blake2-256 hash the runtime code of the genesis block must have.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
+	fn expected_genesis_code_hash(&self) -> Result<Option<H256>> {
+		Ok(self.database_params().and_then(|x| x.expected_genesis_code_hash()))
+	}
+
 	/// Get the state cache child ratio (if any).
 	///
 	/// By default this is `None`.
@@ -215,6 +296,14 @@ pub trait CliConfiguration: Sized {
 			.unwrap_or(Ok(Default::default()))
 	}
 
+	/// Get which justifications of finalized blocks are kept.
+	///
+	/// By default this is retrieved from `PruningParams` if it is available. Otherwise its
+	/// `JustificationRetention::default()`.
+	fn justification_retention(&self) -> Result<JustificationRetention> {
+		Ok(self.pruning_params().map(|x| x.justification_retention()).unwrap_or_default())
+	}
+
 	/// Get the chain ID (string).
 	///
 	/// By default this is retrieved from `SharedParams`.
@@ -283,6 +372,27 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Returns when to start serving RPC requests.
+	///
+	/// By default this is `RpcReadiness::Immediately`.
+	fn rpc_serve_after(&self) -> Result<RpcReadiness> {
+		Ok(Default::default())
+	}
+
+	/// Returns when to build the handlers of the RPC servers.
+	///
+	/// By default this is `RpcHandlerMode::Eager`.
+	fn rpc_handler_mode(&self) -> Result<RpcHandlerMode> {
+		Ok(Default::default())
+	}
+
+	/// Get the maximum number of runtime calls made concurrently by RPC (`None` if unlimited).
+	///
+	/// By default this is `None`.
+	fn rpc_max_concurrent_runtime_calls(&self) -> Result<Option<usize>> {
+		Ok(None)
+	}
+
 	/// Get the RPC websockets maximum connections (`None` if unlimited).
 	///
 	/// By default this is `None`.
@@ -290,6 +400,14 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Returns `Ok(true)` if the RPC servers listening on all interfaces should serve both IPv4
+	/// and IPv6.
+	///
+	/// By default this is `false`.
+	fn rpc_dual_stack(&self) -> Result<bool> {
+		Ok(false)
+	}
+
 	/// Get the RPC cors (`None` if disabled)
 	///
 	/// By default this is `None`.
@@ -304,6 +422,13 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the labels added to every Prometheus metric.
+	///
+	/// By default this is empty.
+	fn prometheus_static_labels(&self) -> Result<HashMap<String, String>> {
+		Ok(Default::default())
+	}
+
 	/// Get the telemetry endpoints (if any)
 	///
 	/// By default this is retrieved from the chain spec loaded by `load_spec`.
@@ -321,6 +446,42 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Returns `true` if the core metrics should also be sent to the telemetry.
+	///
+	/// By default this is `false`.
+	fn telemetry_metrics(&self) -> Result<bool> {
+		Ok(false)
+	}
+
+	/// Get the minimum number of connected peers for the node to be reported as healthy to the
+	/// telemetry.
+	///
+	/// By default this is `1`.
+	fn telemetry_healthy_peer_threshold(&self) -> Result<usize> {
+		Ok(1)
+	}
+
+	/// Get the file the peer reputations are persisted to (`None` if they aren't persisted).
+	///
+	/// By default this is `None`.
+	fn persist_peer_reputations(&self) -> Result<Option<PathBuf>> {
+		Ok(None)
+	}
+
+	/// Get the maximum age of the persisted peer reputations restored on startup.
+	///
+	/// By default this is one hour.
+	fn peer_reputations_ttl(&self) -> Result<Duration> {
+		Ok(Duration::from_secs(3600))
+	}
+
+	/// Get the endpoint the finalized block headers are posted to.
+	///
+	/// By default this is `None`.
+	fn finality_webhook(&self) -> Result<Option<Url>> {
+		Ok(Default::default())
+	}
+
 	/// Get the default value for heap pages
 	///
 	/// By default this is `None`.
@@ -344,6 +505,22 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Returns `Ok(true)` if the inherent check failure hook may accept blocks with invalid
+	/// inherents.
+	///
+	/// By default this is `false`.
+	fn unsafe_override_inherent_checks(&self) -> Result<bool> {
+		Ok(false)
+	}
+
+	/// Returns `Ok(true)` if the blocks the runtime panics on while they are imported should be
+	/// rejected as bad blocks.
+	///
+	/// By default this is `false`.
+	fn reject_runtime_panics(&self) -> Result<bool> {
+		Ok(false)
+	}
+
 	/// Returns `Ok(true)` if grandpa should be disabled
 	///
 	/// By default this is `false`.
@@ -358,6 +535,21 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Returns `Ok(true)` if the initial session keys should be generated on startup
+	///
+	/// By default this is `true`.
+	fn generate_session_keys(&self) -> Result<bool> {
+		Ok(true)
+	}
+
+	/// Returns `Ok(true)` if the session keys should be generated from the development seed even
+	/// if the keystore already holds them.
+	///
+	/// By default this is `true`.
+	fn overwrite_dev_keys(&self) -> Result<bool> {
+		Ok(true)
+	}
+
 	/// Get the tracing targets from the current object (if any)
 	///
 	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its
@@ -402,6 +594,64 @@ pub trait CliConfiguration: Sized {
 		Ok(true)
 	}
 
+	/// Returns `Ok(true)` if an authority node should refuse to start when its keystore lacks some
+	/// of the session keys expected by the runtime.
+	///
+	/// By default this is `false`.
+	fn require_authoring_keys(&self) -> Result<bool> {
+		Ok(Default::default())
+	}
+
+	/// Returns `Ok(true)` if the node should never announce blocks to its peers.
+	///
+	/// By default this is `false`.
+	fn suppress_block_announcements(&self) -> Result<bool> {
+		Ok(Default::default())
+	}
+
+	/// Get the age, in blocks below the best one, from which block announcements are ignored.
+	///
+	/// By default this is `None`, i.e. all of them are considered.
+	fn max_announce_block_age(&self) -> Result<Option<u32>> {
+		Ok(None)
+	}
+
+	/// Get the limit on the block announcements of a single peer that are validated at once.
+	///
+	/// By default this is `None`, i.e. all of them are validated as they arrive.
+	fn block_announce_validation_limit(&self) -> Result<Option<AnnounceValidationLimit>> {
+		Ok(None)
+	}
+
+	/// Get the time after which a block request to an unresponsive peer times out.
+	///
+	/// By default this is `sc_network::config::DEFAULT_SYNC_REQUEST_TIMEOUT`.
+	fn sync_request_timeout(&self) -> Result<Duration> {
+		Ok(sc_network::config::DEFAULT_SYNC_REQUEST_TIMEOUT)
+	}
+
+	/// Returns `Ok(true)` if finality notifications should be merged together before being
+	/// reported to the transaction pool
+	///
+	/// By default this is `false`.
+	fn coalesce_finality_notifications(&self) -> Result<bool> {
+		Ok(Default::default())
+	}
+
+	/// Returns `Ok(true)` if the time spent polling each task of the service should be accumulated.
+	///
+	/// By default this is `false`.
+	fn task_poll_time_accounting(&self) -> Result<bool> {
+		Ok(Default::default())
+	}
+
+	/// Get how long the watched tasks of the service may go without heartbeating.
+	///
+	/// By default this is `None`, i.e. the watchdog is disabled.
+	fn task_watchdog_timeout(&self) -> Result<Option<Duration>> {
+		Ok(None)
+	}
+
 	/// Create a Configuration object from the current object
 	fn create_configuration<C: SubstrateCli>(
 		&self,
@@ -425,7 +675,8 @@ pub trait CliConfiguration: Sized {
 		let database = self.database()?.unwrap_or(Database::RocksDb);
 		let node_key = self.node_key(&net_config_dir)?;
 		let role = self.role(is_dev)?;
-		let max_runtime_instances = self.max_runtime_instances()?.unwrap_or(8);
+		let max_runtime_instances = self.max_runtime_instances()?
+			.unwrap_or(DEFAULT_MAX_RUNTIME_INSTANCES);
 		let is_validator = role.is_network_authority();
 
 		let unsafe_pruning = self
@@ -438,6 +689,7 @@ pub trait CliConfiguration: Sized {
 			impl_version: C::impl_version(),
 			task_executor,
 			transaction_pool: self.transaction_pool()?,
+			tx_propagation_delay: self.tx_propagation_delay()?,
 			network: self.network_config(
 				&chain_spec,
 				is_dev,
@@ -446,35 +698,68 @@ pub trait CliConfiguration: Sized {
 				self.node_name()?.as_str(),
 				node_key,
 			)?,
+			persist_peer_reputations: self.persist_peer_reputations()?,
+			peer_reputations_ttl: self.peer_reputations_ttl()?,
 			keystore: self.keystore_config(&config_dir)?,
 			database: self.database_config(&config_dir, database_cache_size, database)?,
+			database_open_retries: self.database_open_retries()?,
+			database_write_buffer_size: self.database_write_buffer_size()?,
+			database_max_open_files: self.database_max_open_files()?,
+			genesis_build_threads: self.genesis_build_threads()?,
+			max_tracked_forks: self.max_tracked_forks()?,
+			skip_genesis_check: self.skip_genesis_check()?,
+			expected_genesis_code_hash: self.expected_genesis_code_hash()?,
 			state_cache_size: self.state_cache_size()?,
+			import_queue_mem_budget: self.import_queue_mem_budget()?,
+			block_time_drift_tolerance: self.block_time_drift_tolerance()?,
 			state_cache_child_ratio: self.state_cache_child_ratio()?,
 			pruning: self.pruning(unsafe_pruning, &role)?,
+			justification_retention: self.justification_retention()?,
 			wasm_method: self.wasm_method()?,
 			execution_strategies: self.execution_strategies(is_dev, is_validator)?,
 			rpc_http: self.rpc_http()?,
 			rpc_ws: self.rpc_ws()?,
 			rpc_ipc: self.rpc_ipc()?,
 			rpc_methods: self.rpc_methods()?,
+			rpc_serve_after: self.rpc_serve_after()?,
+			rpc_handler_mode: self.rpc_handler_mode()?,
+			rpc_max_concurrent_runtime_calls: self.rpc_max_concurrent_runtime_calls()?,
 			rpc_ws_max_connections: self.rpc_ws_max_connections()?,
+			rpc_dual_stack: self.rpc_dual_stack()?,
 			rpc_cors: self.rpc_cors(is_dev)?,
 			prometheus_config: self.prometheus_config()?,
+			prometheus_static_labels: self.prometheus_static_labels()?,
 			telemetry_endpoints: self.telemetry_endpoints(&chain_spec)?,
 			telemetry_external_transport: self.telemetry_external_transport()?,
+			telemetry_metrics: self.telemetry_metrics()?,
+			telemetry_healthy_peer_threshold: self.telemetry_healthy_peer_threshold()?,
+			finality_webhook: self.finality_webhook()?,
 			default_heap_pages: self.default_heap_pages()?,
 			offchain_worker: self.offchain_worker(&role)?,
 			force_authoring: self.force_authoring()?,
+			unsafe_override_inherent_checks: self.unsafe_override_inherent_checks()?,
+			reject_runtime_panics: self.reject_runtime_panics()?,
 			disable_grandpa: self.disable_grandpa()?,
 			dev_key_seed: self.dev_key_seed(is_dev)?,
+			generate_session_keys: self.generate_session_keys()?,
+			overwrite_dev_keys: self.overwrite_dev_keys()?,
+			require_authoring_keys: self.require_authoring_keys()?,
 			tracing_targets: self.tracing_targets()?,
 			tracing_receiver: self.tracing_receiver()?,
 			chain_spec,
 			max_runtime_instances,
 			announce_block: self.announce_block()?,
+			suppress_block_announcements: self.suppress_block_announcements()?,
+			max_announce_block_age: self.max_announce_block_age()?,
+			block_announce_validation_limit: self.block_announce_validation_limit()?,
+			sync_request_timeout: self.sync_request_timeout()?,
 			role,
 			base_path: Some(base_path),
 			informant_output_format: Default::default(),
+			coalesce_finality_notifications: self.coalesce_finality_notifications()?,
+			structured_events_sink: None,
+			task_poll_time_accounting: self.task_poll_time_accounting()?,
+			task_watchdog_timeout: self.task_watchdog_timeout()?,
 		})
 	}
 
@@ -232,22 +232,25 @@ pub trait SubstrateCli: Sized {
 pub fn init_logger(pattern: &str) {
 	use ansi_term::Colour;
 
-	let mut builder = env_logger::Builder::new();
-	// Disable info logging by default for some modules:
-	builder.filter(Some("ws"), log::LevelFilter::Off);
-	builder.filter(Some("yamux"), log::LevelFilter::Off);
-	builder.filter(Some("hyper"), log::LevelFilter::Warn);
-	builder.filter(Some("cranelift_wasm"), log::LevelFilter::Warn);
-	// Always log the special target `sc_tracing`, overrides global level
-	builder.filter(Some("sc_tracing"), log::LevelFilter::Info);
-	// Enable info for others.
-	builder.filter(None, log::LevelFilter::Info);
+	// Disable info logging by default for some modules, always log the special target
+	// `sc_tracing` and enable info for others.
+	let mut directives = String::from(
+		"ws=off,yamux=off,hyper=warn,cranelift_wasm=warn,sc_tracing=info,info",
+	);
 
 	if let Ok(lvl) = std::env::var("RUST_LOG") {
-		builder.parse_filters(&lvl);
+		directives.push(',');
+		directives.push_str(&lvl);
+	}
+
+	if !pattern.is_empty() {
+		directives.push(',');
+		directives.push_str(pattern);
 	}
 
-	builder.parse_filters(pattern);
+	// The filter is applied on top of the logger, so that it can be changed at runtime.
+	let mut builder = env_logger::Builder::new();
+	builder.filter(None, log::LevelFilter::Trace);
 	let isatty = atty::is(atty::Stream::Stderr);
 	let enable_color = isatty;
 
@@ -292,7 +295,7 @@ pub fn init_logger(pattern: &str) {
 		writeln!(buf, "{}", output)
 	});
 
-	if builder.try_init().is_err() {
+	if sc_tracing::logging::init_logger(Box::new(builder.build()), &directives).is_err() {
 		info!("💬 Not registering Substrate logger, as there is already a global logger registered!");
 	}
 }
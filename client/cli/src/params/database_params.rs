@@ -17,6 +17,8 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::arg_enums::Database;
+use sp_core::H256;
+use std::str::FromStr;
 use structopt::StructOpt;
 
 /// Parameters for block import.
@@ -34,6 +36,43 @@ pub struct DatabaseParams {
 	/// Limit the memory the database cache can use.
 	#[structopt(long = "db-cache", value_name = "MiB")]
 	pub database_cache_size: Option<usize>,
+
+	/// Size of the write buffer of each database column.
+	///
+	/// Larger buffers reduce write amplification. The buffers are taken out of the database
+	/// cache, which must hold one of them per column.
+	#[structopt(long = "db-write-buffer", value_name = "MiB")]
+	pub database_write_buffer_size: Option<usize>,
+
+	/// Maximum number of files the database keeps open.
+	#[structopt(long = "db-max-open-files", value_name = "COUNT")]
+	pub database_max_open_files: Option<u32>,
+
+	/// Number of threads used to build the genesis state when initializing the database.
+	///
+	/// The child tries of the genesis storage are built in parallel, then the top trie is split by
+	/// the first nibble of its keys and its sub-tries are built in parallel as well. The genesis
+	/// hash doesn't depend on this.
+	#[structopt(long = "genesis-build-threads", value_name = "COUNT")]
+	pub genesis_build_threads: Option<usize>,
+
+	/// Maximum number of forks the node keeps tracking.
+	///
+	/// When exceeded, the forks with the lowest head stop being tracked, their blocks stay in
+	/// the database. The best and the finalized chain are always tracked.
+	#[structopt(long = "max-tracked-forks", value_name = "COUNT")]
+	pub max_tracked_forks: Option<usize>,
+
+	/// Don't check that the genesis block of an existing database is the one of the chain
+	/// specification.
+	#[structopt(long = "skip-genesis-check")]
+	pub skip_genesis_check: bool,
+
+	/// Refuse to start unless the runtime code of the genesis block has this Blake2-256 hash.
+	///
+	/// The genesis code is checked even if the runtime has been upgraded since then.
+	#[structopt(long = "expected-genesis-code-hash", value_name = "HASH", parse(try_from_str = parse_hash))]
+	pub expected_genesis_code_hash: Option<H256>,
 }
 
 impl DatabaseParams {
@@ -46,4 +85,39 @@ impl DatabaseParams {
 	pub fn database_cache_size(&self) -> Option<usize> {
 		self.database_cache_size
 	}
+
+	/// Size of the write buffer of each database column.
+	pub fn database_write_buffer_size(&self) -> Option<usize> {
+		self.database_write_buffer_size
+	}
+
+	/// Maximum number of files the database keeps open.
+	pub fn database_max_open_files(&self) -> Option<u32> {
+		self.database_max_open_files
+	}
+
+	/// Number of threads used to build the genesis state.
+	pub fn genesis_build_threads(&self) -> Option<usize> {
+		self.genesis_build_threads
+	}
+
+	/// Maximum number of forks that are tracked.
+	pub fn max_tracked_forks(&self) -> Option<usize> {
+		self.max_tracked_forks
+	}
+
+	/// Whether the genesis block of an existing database isn't checked.
+	pub fn skip_genesis_check(&self) -> bool {
+		self.skip_genesis_check
+	}
+
+	/// The hash the runtime code of the genesis block must have, if any.
+	pub fn expected_genesis_code_hash(&self) -> Option<H256> {
+		self.expected_genesis_code_hash
+	}
+}
+
+/// Parses a hex encoded hash, with or without the `0x` prefix.
+fn parse_hash(s: &str) -> Result<H256, String> {
+	H256::from_str(s.trim_start_matches("0x")).map_err(|e| format!("Invalid hash: {}", e))
 }
@@ -24,6 +24,7 @@ use crate::arg_enums::{
 use crate::params::DatabaseParams;
 use crate::params::PruningParams;
 use sc_client_api::execution_extensions::ExecutionStrategies;
+use std::time::Duration;
 use structopt::StructOpt;
 
 /// Parameters for block import.
@@ -67,6 +68,19 @@ pub struct ImportParams {
 	)]
 	pub state_cache_size: usize,
 
+	/// Maximum memory used by the blocks waiting to be imported.
+	///
+	/// Block downloads are paused while the budget is exhausted. Unlimited by default.
+	#[structopt(long = "import-queue-mem-budget", value_name = "Bytes")]
+	pub import_queue_mem_budget: Option<usize>,
+
+	/// Accept blocks up to this many milliseconds ahead of the local clock.
+	///
+	/// Helps with clock skew between authorities, blocks of the next slot are always accepted on
+	/// top of it. Can't be above 30000.
+	#[structopt(long = "block-time-drift-tolerance", value_name = "MS", default_value = "0")]
+	pub block_time_drift_tolerance: u64,
+
 	/// Comma separated list of targets for tracing.
 	#[structopt(long = "tracing-targets", value_name = "TARGETS")]
 	pub tracing_targets: Option<String>,
@@ -98,6 +112,16 @@ impl ImportParams {
 		self.state_cache_size
 	}
 
+	/// Maximum memory used by the blocks waiting to be imported.
+	pub fn import_queue_mem_budget(&self) -> Option<usize> {
+		self.import_queue_mem_budget
+	}
+
+	/// Tolerance for blocks ahead of the local clock.
+	pub fn block_time_drift_tolerance(&self) -> Duration {
+		Duration::from_millis(self.block_time_drift_tolerance)
+	}
+
 	/// Get the WASM execution method from the parameters
 	pub fn wasm_method(&self) -> sc_service::config::WasmExecutionMethod {
 		self.wasm_method.into()
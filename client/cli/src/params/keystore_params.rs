@@ -16,10 +16,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::error::Result;
-use sc_service::config::KeystoreConfig;
+use crate::error::{Error, Result};
+use sc_service::config::{KeystoreBackup, KeystoreConfig};
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 use structopt::StructOpt;
 use sp_core::crypto::SecretString;
 
@@ -56,6 +57,17 @@ pub struct KeystoreParams {
 		conflicts_with_all = &[ "password-interactive", "password" ]
 	)]
 	pub password_filename: Option<PathBuf>,
+
+	/// Periodically back up the keystore to this directory.
+	///
+	/// Every backup replaces the previous one. Keys are backed up as they are stored on disk, so
+	/// the backup of a password-protected keystore requires the same password.
+	#[structopt(long = "keystore-backup-path", value_name = "PATH", parse(from_os_str))]
+	pub keystore_backup_path: Option<PathBuf>,
+
+	/// Number of seconds between two backups of the keystore. Must be greater than 0.
+	#[structopt(long = "keystore-backup-interval", value_name = "SECONDS", default_value = "3600")]
+	pub keystore_backup_interval: u64,
 }
 
 /// Parse a sercret string, returning a displayable error.
@@ -96,7 +108,15 @@ impl KeystoreParams {
 			.clone()
 			.unwrap_or(base_path.join(DEFAULT_KEYSTORE_CONFIG_PATH));
 
-		Ok(KeystoreConfig::Path { path, password })
+		if self.keystore_backup_path.is_some() && self.keystore_backup_interval == 0 {
+			return Err(Error::Input("--keystore-backup-interval must be greater than 0".into()));
+		}
+		let backup = self.keystore_backup_path.clone().map(|path| KeystoreBackup {
+			path,
+			interval: Duration::from_secs(self.keystore_backup_interval),
+		});
+
+		Ok(KeystoreConfig::Path { path, password, backup })
 	}
 }
 
@@ -16,13 +16,16 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::params::node_key_params::NodeKeyParams;
+use crate::{arg_enums::BootNodeSource, params::node_key_params::NodeKeyParams};
+use log::warn;
 use sc_network::{
 	config::{NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, TransportConfig},
 	multiaddr::Protocol,
 };
 use sc_service::{ChainSpec, config::{Multiaddr, MultiaddrWithPeerId}};
-use std::path::PathBuf;
+use std::{
+	collections::HashSet, io, iter, net::{IpAddr, ToSocketAddrs}, path::{Path, PathBuf},
+};
 use structopt::StructOpt;
 
 /// Parameters used to create the network configuration.
@@ -32,6 +35,33 @@ pub struct NetworkParams {
 	#[structopt(long = "bootnodes", value_name = "ADDR")]
 	pub bootnodes: Vec<MultiaddrWithPeerId>,
 
+	/// Specify a file listing bootnodes, one address per line.
+	///
+	/// Empty lines and lines starting with `#` are ignored.
+	#[structopt(long = "bootnodes-file", value_name = "PATH", parse(from_os_str))]
+	pub bootnodes_file: Option<PathBuf>,
+
+	/// Specify a list of bootnodes with a `/dns4` or `/dns6` address, resolved when starting.
+	///
+	/// The seeds that fail to resolve are skipped.
+	#[structopt(long = "bootnodes-dns-seed", value_name = "ADDR")]
+	pub bootnodes_dns_seed: Vec<MultiaddrWithPeerId>,
+
+	/// Order in which the sources of bootnodes are merged, the first one having the highest
+	/// priority.
+	///
+	/// A node given by several sources is only taken from the first of them. Sources missing
+	/// from the list are ignored.
+	#[structopt(
+		long = "bootnodes-priority",
+		value_name = "SOURCE",
+		possible_values = &BootNodeSource::variants(),
+		case_insensitive = true,
+		use_delimiter = true,
+		default_value = "ChainSpec,CommandLine,File,DnsSeed"
+	)]
+	pub bootnodes_priority: Vec<BootNodeSource>,
+
 	/// Specify a list of reserved node addresses.
 	#[structopt(long = "reserved-nodes", value_name = "ADDR")]
 	pub reserved_nodes: Vec<MultiaddrWithPeerId>,
@@ -102,6 +132,27 @@ pub struct NetworkParams {
 	/// By default this option is true for `--dev` and false otherwise.
 	#[structopt(long)]
 	pub discover_local: bool,
+
+	/// Serve the blocks of the chain to the nodes bootstrapping from this one.
+	#[structopt(long = "serve-block-archive")]
+	pub serve_block_archive: bool,
+
+	/// Trusted node to download the blocks from when starting, before syncing from the network.
+	///
+	/// The node must serve its blocks with `--serve-block-archive`. The blocks are still verified.
+	#[structopt(long = "block-archive-source", value_name = "ADDR")]
+	pub block_archive_source: Option<MultiaddrWithPeerId>,
+
+	/// Reputation gained by a peer every time it provides a finality proof that can be imported.
+	///
+	/// Light clients then prefer the peers that reliably serve finality proofs, set to 0 to
+	/// disable it. Peers providing invalid proofs are banned regardless.
+	#[structopt(
+		long = "finality-proof-reputation-boost",
+		value_name = "REPUTATION",
+		default_value = "128"
+	)]
+	pub finality_proof_reputation_boost: i32,
 }
 
 impl NetworkParams {
@@ -132,8 +183,15 @@ impl NetworkParams {
 
 		let public_addresses = self.public_addr.clone();
 
-		let mut boot_nodes = chain_spec.boot_nodes().to_vec();
-		boot_nodes.extend(self.bootnodes.clone());
+		let boot_nodes = merge_boot_nodes(self.bootnodes_priority.iter().map(|source| match source {
+			BootNodeSource::ChainSpec => chain_spec.boot_nodes().to_vec(),
+			BootNodeSource::CommandLine => self.bootnodes.clone(),
+			BootNodeSource::File => self.bootnodes_file.as_deref()
+				.map(boot_nodes_from_file)
+				.unwrap_or_default(),
+			BootNodeSource::DnsSeed =>
+				boot_nodes_from_dns_seeds(&self.bootnodes_dns_seed, resolve_host),
+		}));
 
 		NetworkConfiguration {
 			boot_nodes,
@@ -160,6 +218,188 @@ impl NetworkParams {
 			},
 			max_parallel_downloads: self.max_parallel_downloads,
 			allow_non_globals_in_dht: self.discover_local || is_dev,
+			serve_block_archive: self.serve_block_archive,
+			block_archive_source: self.block_archive_source.clone(),
+			finality_proof_reputation_boost: self.finality_proof_reputation_boost,
+		}
+	}
+}
+
+/// Merges the boot nodes of several sources, the first source having the highest priority.
+///
+/// A node given by several sources is only taken from the first of them, with all the addresses
+/// that this source has for it.
+fn merge_boot_nodes(
+	sources: impl IntoIterator<Item = Vec<MultiaddrWithPeerId>>,
+) -> Vec<MultiaddrWithPeerId> {
+	let mut merged: Vec<MultiaddrWithPeerId> = Vec::new();
+	let mut known = HashSet::new();
+
+	for source in sources {
+		let mut from_source = HashSet::new();
+		for node in source {
+			if known.contains(&node.peer_id) {
+				continue;
+			}
+			from_source.insert(node.peer_id.clone());
+			let duplicate = merged.iter()
+				.any(|n| n.peer_id == node.peer_id && n.multiaddr == node.multiaddr);
+			if !duplicate {
+				merged.push(node);
+			}
 		}
+		known.extend(from_source);
+	}
+
+	merged
+}
+
+/// Reads the boot nodes listed in the file at `path`, one address per line.
+///
+/// The file is skipped if it can't be read, and so are the lines that aren't valid addresses.
+fn boot_nodes_from_file(path: &Path) -> Vec<MultiaddrWithPeerId> {
+	let content = match std::fs::read_to_string(path) {
+		Ok(content) => content,
+		Err(err) => {
+			warn!("Failed to read the bootnodes file {}: {}", path.display(), err);
+			return Vec::new();
+		},
+	};
+
+	content.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.filter_map(|line| match line.parse() {
+			Ok(node) => Some(node),
+			Err(err) => {
+				warn!("Invalid bootnode `{}` in {}: {}", line, path.display(), err);
+				None
+			},
+		})
+		.collect()
+}
+
+/// Resolves the boot nodes with a DNS address into one boot node per IP address, looking the host
+/// names up with `resolve`.
+///
+/// The seeds that fail to resolve are skipped.
+fn boot_nodes_from_dns_seeds(
+	seeds: &[MultiaddrWithPeerId],
+	resolve: impl Fn(&str) -> io::Result<Vec<IpAddr>>,
+) -> Vec<MultiaddrWithPeerId> {
+	seeds.iter()
+		.flat_map(|seed| match resolve_dns_seed(seed, &resolve) {
+			Ok(nodes) => nodes,
+			Err(err) => {
+				warn!("Failed to resolve the bootnode DNS seed {}: {}", seed, err);
+				Vec::new()
+			},
+		})
+		.collect()
+}
+
+/// Looks `host` up with the system resolver.
+fn resolve_host(host: &str) -> io::Result<Vec<IpAddr>> {
+	Ok((host, 0).to_socket_addrs()?.map(|addr| addr.ip()).collect())
+}
+
+fn resolve_dns_seed(
+	seed: &MultiaddrWithPeerId,
+	resolve: impl Fn(&str) -> io::Result<Vec<IpAddr>>,
+) -> io::Result<Vec<MultiaddrWithPeerId>> {
+	let mut protocols = seed.multiaddr.iter();
+	let (host, ipv4) = match protocols.next() {
+		Some(Protocol::Dns4(host)) => (host, true),
+		Some(Protocol::Dns6(host)) => (host, false),
+		// nothing to resolve.
+		_ => return Ok(vec![seed.clone()]),
+	};
+	let rest = protocols.collect::<Vec<_>>();
+
+	let mut ips = resolve(&*host)?
+		.into_iter()
+		.filter(|ip| ip.is_ipv4() == ipv4)
+		.collect::<Vec<_>>();
+	ips.dedup();
+	if ips.is_empty() {
+		return Err(io::Error::new(io::ErrorKind::NotFound, "no address of the requested type"));
+	}
+
+	Ok(ips.into_iter().map(|ip| MultiaddrWithPeerId {
+		multiaddr: iter::once(Protocol::from(ip)).chain(rest.iter().cloned()).collect(),
+		peer_id: seed.peer_id.clone(),
+	}).collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Write;
+	use sc_network::PeerId;
+
+	fn node(addr: &str, peer_id: &PeerId) -> MultiaddrWithPeerId {
+		MultiaddrWithPeerId { multiaddr: addr.parse().unwrap(), peer_id: peer_id.clone() }
+	}
+
+	#[test]
+	fn boot_nodes_are_merged_by_priority_and_deduplicated() {
+		let (a, b, c, d) = (PeerId::random(), PeerId::random(), PeerId::random(), PeerId::random());
+
+		let chain_spec = vec![
+			node("/ip4/10.0.0.1/tcp/30333", &a),
+			node("/ip4/10.0.0.2/tcp/30333", &b),
+		];
+
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		writeln!(file, "# overridden by the chain spec").unwrap();
+		writeln!(file, "{}", node("/ip4/192.168.0.1/tcp/30333", &b)).unwrap();
+		writeln!(file).unwrap();
+		writeln!(file, "{}", node("/ip4/10.0.0.3/tcp/30333", &c)).unwrap();
+		writeln!(file, "{}", node("/ip6/::3/tcp/30333", &c)).unwrap();
+		writeln!(file, "not an address").unwrap();
+		let from_file = boot_nodes_from_file(file.path());
+		assert_eq!(from_file.len(), 3);
+
+		let resolve = |host: &str| -> io::Result<Vec<IpAddr>> { match host {
+			"seed.example" => Ok(vec![
+				"10.0.1.1".parse().unwrap(),
+				"fd00::1".parse().unwrap(),
+				"10.0.1.2".parse().unwrap(),
+			]),
+			"ipv6.example" => Ok(vec!["fd00::2".parse().unwrap()]),
+			_ => Err(io::Error::new(io::ErrorKind::NotFound, "unknown host")),
+		}};
+		let from_dns = boot_nodes_from_dns_seeds(
+			&[
+				node("/dns4/seed.example/tcp/30333", &d),
+				// overridden by the file.
+				node("/dns4/seed.example/tcp/30334", &c),
+				// skipped, the host doesn't resolve.
+				node("/dns4/down.example/tcp/30333", &a),
+				// skipped, the host has no IPv4 address.
+				node("/dns4/ipv6.example/tcp/30333", &a),
+			],
+			resolve,
+		);
+		assert_eq!(from_dns.len(), 4);
+
+		let merged = merge_boot_nodes(vec![
+			chain_spec.clone(),
+			from_file,
+			from_dns,
+			// duplicate of the highest priority source.
+			chain_spec,
+		]);
+		assert_eq!(
+			merged.iter().map(ToString::to_string).collect::<Vec<_>>(),
+			vec![
+				node("/ip4/10.0.0.1/tcp/30333", &a),
+				node("/ip4/10.0.0.2/tcp/30333", &b),
+				node("/ip4/10.0.0.3/tcp/30333", &c),
+				node("/ip6/::3/tcp/30333", &c),
+				node("/ip4/10.0.1.1/tcp/30333", &d),
+				node("/ip4/10.0.1.2/tcp/30333", &d),
+			].iter().map(ToString::to_string).collect::<Vec<_>>(),
+		);
 	}
 }
@@ -23,6 +23,7 @@
 //! targeted at handling input parameter parsing providing
 //! a reasonable abstraction.
 
+use std::num::NonZeroUsize;
 use structopt::StructOpt;
 use sc_service::config::OffchainWorkerConfig;
 use sc_network::config::Role;
@@ -55,6 +56,14 @@ pub struct OffchainWorkerParams {
 		value_name = "ENABLE_OFFCHAIN_INDEXING"
 	)]
 	pub indexing_enabled: bool,
+
+	/// Maximum number of HTTP requests that offchain workers can have in flight at the same
+	/// time, across all blocks.
+	///
+	/// A request is in flight until its response headers are received. Must be at least 1, by
+	/// default the number of requests is unlimited.
+	#[structopt(long = "offchain-max-http-requests", value_name = "COUNT")]
+	pub max_global_http_requests: Option<NonZeroUsize>,
 }
 
 impl OffchainWorkerParams {
@@ -73,6 +82,10 @@ impl OffchainWorkerParams {
 
 		let indexing_enabled = enabled && self.indexing_enabled;
 
-		Ok(OffchainWorkerConfig { enabled, indexing_enabled })
+		Ok(OffchainWorkerConfig {
+			enabled,
+			indexing_enabled,
+			max_global_http_requests: self.max_global_http_requests,
+		})
 	}
 }
@@ -17,7 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::error;
-use sc_service::{PruningMode, Role};
+use sc_service::{JustificationRetention, PruningMode, Role};
 use structopt::StructOpt;
 
 /// Parameters to define the pruning mode
@@ -30,6 +30,20 @@ pub struct PruningParams {
 	/// 256 blocks.
 	#[structopt(long = "pruning", value_name = "PRUNING_MODE")]
 	pub pruning: Option<String>,
+
+	/// Prune the justifications of blocks finalized more than this number of blocks ago.
+	///
+	/// Default is to keep all justifications. The most recent one is never pruned.
+	#[structopt(long = "justifications-window", value_name = "COUNT")]
+	pub justifications_window: Option<u32>,
+
+	/// Keep the justifications of blocks whose number is a multiple of this when pruning them.
+	#[structopt(
+		long = "justifications-period",
+		value_name = "COUNT",
+		requires = "justifications-window",
+	)]
+	pub justifications_period: Option<u32>,
 }
 
 impl PruningParams {
@@ -58,4 +72,15 @@ impl PruningParams {
 			}
 		})
 	}
+
+	/// Get which justifications of finalized blocks are kept.
+	pub fn justification_retention(&self) -> JustificationRetention {
+		match self.justifications_window {
+			Some(window) => JustificationRetention::KeepRecent {
+				window,
+				period: self.justifications_period,
+			},
+			None => JustificationRetention::KeepAll,
+		}
+	}
 }
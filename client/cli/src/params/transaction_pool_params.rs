@@ -16,7 +16,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
 use sc_service::config::TransactionPoolOptions;
+use sp_runtime::Percent;
 use structopt::StructOpt;
 
 /// Parameters used to create the pool configuration.
@@ -29,6 +32,37 @@ pub struct TransactionPoolParams {
 	/// Maximum number of kilobytes of all transactions stored in the pool.
 	#[structopt(long = "pool-kbytes", value_name = "COUNT", default_value = "20480")]
 	pub pool_kbytes: usize,
+
+	/// Percentage of the ready queue reserved for locally submitted transactions.
+	///
+	/// Transactions received from the network may still use the reserved part
+	/// while there are no local transactions.
+	#[structopt(long = "pool-local-reserve", value_name = "PERCENT", default_value = "0")]
+	pub pool_local_reserve: u8,
+
+	/// Interval in seconds at which an idle pool drops stale future transactions.
+	///
+	/// Compaction is disabled unless this is set.
+	#[structopt(long = "pool-compaction-interval", value_name = "SECONDS")]
+	pub pool_compaction_interval: Option<u64>,
+
+	/// Number of seconds after which a future transaction is considered stale.
+	///
+	/// Defaults to 600 seconds.
+	#[structopt(long = "pool-future-stale-after", value_name = "SECONDS")]
+	pub pool_future_stale_after: Option<u64>,
+
+	/// Delay the propagation of the transactions submitted locally or over RPC by a random
+	/// number of milliseconds between `MIN_MS` and `MAX_MS`.
+	///
+	/// This makes it harder for peers to tell that a transaction originates from this node.
+	/// Transactions received from the network are still propagated immediately.
+	#[structopt(
+		long = "tx-propagation-delay",
+		value_names = &["MIN_MS", "MAX_MS"],
+		number_of_values = 2
+	)]
+	pub tx_propagation_delay: Option<Vec<u64>>,
 }
 
 impl TransactionPoolParams {
@@ -44,7 +78,26 @@ impl TransactionPoolParams {
 		let factor = 10;
 		opts.future.count = self.pool_limit / factor;
 		opts.future.total_bytes = self.pool_kbytes * 1024 / factor;
+		opts.local_reserve = Percent::from_percent(self.pool_local_reserve);
+
+		// idle compaction
+		opts.compaction_interval = self.pool_compaction_interval.map(Duration::from_secs);
+		if let Some(stale_after) = self.pool_future_stale_after {
+			opts.future_stale_after = Duration::from_secs(stale_after);
+		}
 
 		opts
 	}
+
+	/// Get the delay range of the propagation of local transactions from the cli parameters.
+	pub fn tx_propagation_delay(&self) -> crate::Result<Option<(Duration, Duration)>> {
+		match self.tx_propagation_delay.as_deref() {
+			None => Ok(None),
+			Some(&[min, max]) if min <= max =>
+				Ok(Some((Duration::from_millis(min), Duration::from_millis(max)))),
+			Some(_) => Err(crate::Error::Input(
+				"The minimum transaction propagation delay must not be above the maximum".into(),
+			)),
+		}
+	}
 }
@@ -72,6 +72,7 @@ use sc_telemetry::{telemetry, CONSENSUS_TRACE, CONSENSUS_DEBUG, CONSENSUS_INFO};
 
 use sc_consensus_slots::{
 	CheckedHeader, SlotWorker, SlotInfo, SlotCompatible, StorageChanges, check_equivocation,
+	check_block_time_drift_tolerance, max_accepted_slot,
 };
 
 use sc_keystore::KeyStorePtr;
@@ -496,6 +497,8 @@ pub struct AuraVerifier<C, P> {
 	client: Arc<C>,
 	phantom: PhantomData<P>,
 	inherent_data_providers: sp_inherents::InherentDataProviders,
+	slot_duration: u64,
+	drift_tolerance: Duration,
 }
 
 impl<C, P> AuraVerifier<C, P>
@@ -521,6 +524,7 @@ impl<C, P> AuraVerifier<C, P>
 		if !inherent_res.ok() {
 			inherent_res
 				.into_errors()
+				.filter(|(i, e)| !self.inherent_data_providers.is_check_failure_overridden(i, e))
 				.try_for_each(|(i, e)| match TIError::try_from(&i, &e) {
 					Some(TIError::ValidAtTimestamp(timestamp)) => {
 						// halt import until timestamp is valid.
@@ -583,12 +587,11 @@ impl<B: BlockT, C, P> Verifier<B> for AuraVerifier<C, P> where
 		let authorities = authorities(self.client.as_ref(), &BlockId::Hash(parent_hash))
 			.map_err(|e| format!("Could not fetch authorities at {:?}: {:?}", parent_hash, e))?;
 
-		// we add one to allow for some small drift.
 		// FIXME #1019 in the future, alter this queue to allow deferring of
 		// headers
 		let checked_header = check_header::<C, B, P>(
 			&self.client,
-			slot_now + 1,
+			max_accepted_slot(slot_now, self.slot_duration, self.drift_tolerance),
 			header,
 			hash,
 			&authorities[..],
@@ -815,6 +818,9 @@ impl<Block: BlockT, C, I, P> BlockImport<Block> for AuraBlockImport<Block, C, I,
 }
 
 /// Start an import queue for the Aura consensus algorithm.
+///
+/// Blocks of the slots within `drift_tolerance` ahead of the local clock are accepted, on top of
+/// the next slot. It can't be above `sc_consensus_slots::MAX_BLOCK_TIME_DRIFT_TOLERANCE`.
 pub fn import_queue<B, I, C, P, S>(
 	slot_duration: SlotDuration,
 	block_import: I,
@@ -822,6 +828,7 @@ pub fn import_queue<B, I, C, P, S>(
 	finality_proof_import: Option<BoxFinalityProofImport<B>>,
 	client: Arc<C>,
 	inherent_data_providers: InherentDataProviders,
+	drift_tolerance: Duration,
 	spawner: &S,
 	registry: Option<&Registry>,
 ) -> Result<AuraImportQueue<B, sp_api::TransactionFor<C, B>>, sp_consensus::Error> where
@@ -835,6 +842,7 @@ pub fn import_queue<B, I, C, P, S>(
 	P::Signature: Encode + Decode,
 	S: sp_core::traits::SpawnNamed,
 {
+	check_block_time_drift_tolerance(drift_tolerance).map_err(ConsensusError::ClientImport)?;
 	register_aura_inherent_data_provider(&inherent_data_providers, slot_duration.get())?;
 	initialize_authorities_cache(&*client)?;
 
@@ -842,6 +850,8 @@ pub fn import_queue<B, I, C, P, S>(
 		client: client.clone(),
 		inherent_data_providers,
 		phantom: PhantomData,
+		slot_duration: slot_duration.get(),
+		drift_tolerance,
 	};
 
 	Ok(BasicQueue::new(
@@ -954,6 +964,8 @@ mod tests {
 						client,
 						inherent_data_providers,
 						phantom: Default::default(),
+						slot_duration: slot_duration.get(),
+						drift_tolerance: Default::default(),
 					}
 				},
 				PeersClient::Light(_, _) => unreachable!("No (yet) tests for light client + Aura"),
@@ -1104,4 +1116,48 @@ mod tests {
 		assert!(worker.claim_slot(&head, 6, &authorities).is_none());
 		assert!(worker.claim_slot(&head, 7, &authorities).is_some());
 	}
+
+	#[test]
+	fn blocks_ahead_are_accepted_within_drift_tolerance() {
+		use std::time::{SystemTime, UNIX_EPOCH};
+
+		let mut net = AuraTestNet::new(1);
+		let client = net.peer(0).client().as_full().expect("full clients are created").clone();
+
+		// a block four slots, so four seconds, ahead of the local clock.
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("after the unix epoch");
+		let slot = now.as_millis() as u64 / SLOT_DURATION + 4;
+		let author = [Keyring::Alice, Keyring::Bob, Keyring::Charlie][(slot % 3) as usize];
+		let mut header = Header::new(
+			1,
+			Default::default(),
+			Default::default(),
+			client.chain_info().genesis_hash,
+			Default::default(),
+		);
+		header.digest_mut().push(
+			<DigestItemFor<TestBlock> as CompatibleDigestItem<AuthorityPair>>::aura_pre_digest(slot),
+		);
+		let signature = AuthorityPair::from(author.pair()).sign(header.hash().as_ref());
+		header.digest_mut().push(
+			<DigestItemFor<TestBlock> as CompatibleDigestItem<AuthorityPair>>::aura_seal(signature),
+		);
+
+		let verify = |drift_tolerance| {
+			let inherent_data_providers = InherentDataProviders::new();
+			register_aura_inherent_data_provider(&inherent_data_providers, SLOT_DURATION)
+				.expect("Registers aura inherent data provider");
+			let mut verifier = AuraVerifier::<_, AuthorityPair> {
+				client: client.clone(),
+				inherent_data_providers,
+				phantom: PhantomData,
+				slot_duration: SLOT_DURATION,
+				drift_tolerance,
+			};
+			verifier.verify(BlockOrigin::NetworkBroadcast, header.clone(), None, None)
+		};
+
+		assert!(verify(Duration::default()).is_err());
+		assert!(verify(Duration::from_millis(5 * SLOT_DURATION)).is_ok());
+	}
 }
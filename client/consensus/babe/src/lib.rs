@@ -112,6 +112,7 @@ use log::{debug, info, log, trace, warn};
 use prometheus_endpoint::Registry;
 use sc_consensus_slots::{
 	SlotWorker, SlotInfo, SlotCompatible, StorageChanges, CheckedHeader, check_equivocation,
+	check_block_time_drift_tolerance, max_accepted_slot,
 };
 use sc_consensus_epochs::{
 	descendent_query, SharedEpochChanges, EpochChangesFor, Epoch as EpochT, ViableEpochDescriptor,
@@ -727,6 +728,7 @@ pub struct BabeVerifier<Block: BlockT, Client, SelectChain> {
 	config: Config,
 	epoch_changes: SharedEpochChanges<Block, Epoch>,
 	time_source: TimeSource,
+	drift_tolerance: Duration,
 }
 
 impl<Block, Client, SelectChain> BabeVerifier<Block, Client, SelectChain>
@@ -752,6 +754,7 @@ where
 		if !inherent_res.ok() {
 			inherent_res
 				.into_errors()
+				.filter(|(i, e)| !self.inherent_data_providers.is_check_failure_overridden(i, e))
 				.try_for_each(|(i, e)| {
 					Err(Error::CheckInherents(self.inherent_data_providers.error_to_string(&i, &e)))
 				})
@@ -895,12 +898,15 @@ where
 			|slot| Epoch::genesis(&self.config, slot)
 		).ok_or_else(|| Error::<Block>::FetchEpoch(parent_hash))?;
 
-		// We add one to the current slot to allow for some small drift.
 		// FIXME #1019 in the future, alter this queue to allow deferring of headers
 		let v_params = verification::VerificationParams {
 			header: header.clone(),
 			pre_digest: Some(pre_digest),
-			slot_now: slot_now + 1,
+			slot_now: max_accepted_slot(
+				slot_now,
+				self.config.slot_duration,
+				self.drift_tolerance,
+			),
 			epoch: viable_epoch.as_ref(),
 		};
 
@@ -1358,6 +1364,9 @@ pub fn block_import<Client, Block: BlockT, I>(
 ///
 /// The block import object provided must be the `BabeBlockImport` or a wrapper
 /// of it, otherwise crucial import logic will be omitted.
+///
+/// Blocks of the slots within `drift_tolerance` ahead of the local clock are accepted, on top of
+/// the next slot. It can't be above `sc_consensus_slots::MAX_BLOCK_TIME_DRIFT_TOLERANCE`.
 pub fn import_queue<Block: BlockT, Client, SelectChain, Inner>(
 	babe_link: BabeLink<Block>,
 	block_import: Inner,
@@ -1366,6 +1375,7 @@ pub fn import_queue<Block: BlockT, Client, SelectChain, Inner>(
 	client: Arc<Client>,
 	select_chain: SelectChain,
 	inherent_data_providers: InherentDataProviders,
+	drift_tolerance: Duration,
 	spawner: &impl sp_core::traits::SpawnNamed,
 	registry: Option<&Registry>,
 ) -> ClientResult<BabeImportQueue<Block, sp_api::TransactionFor<Client, Block>>> where
@@ -1376,6 +1386,7 @@ pub fn import_queue<Block: BlockT, Client, SelectChain, Inner>(
 	Client::Api: BlockBuilderApi<Block> + BabeApi<Block> + ApiExt<Block, Error = sp_blockchain::Error>,
 	SelectChain: sp_consensus::SelectChain<Block> + 'static,
 {
+	check_block_time_drift_tolerance(drift_tolerance).map_err(sp_blockchain::Error::Msg)?;
 	register_babe_inherent_data_provider(&inherent_data_providers, babe_link.config.slot_duration)?;
 
 	let verifier = BabeVerifier {
@@ -1385,6 +1396,7 @@ pub fn import_queue<Block: BlockT, Client, SelectChain, Inner>(
 		config: babe_link.config,
 		epoch_changes: babe_link.epoch_changes,
 		time_source: babe_link.time_source,
+		drift_tolerance,
 	};
 
 	Ok(BasicQueue::new(
@@ -320,6 +320,7 @@ impl TestNetFactory for BabeTestNet {
 				config: data.link.config.clone(),
 				epoch_changes: data.link.epoch_changes.clone(),
 				time_source: data.link.time_source.clone(),
+				drift_tolerance: Default::default(),
 			},
 			mutator: MUTATOR.with(|m| m.borrow().clone()),
 		}
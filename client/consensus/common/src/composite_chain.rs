@@ -0,0 +1,230 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Chain selection that follows the blocks finalized by several finality gadgets.
+
+use std::sync::Arc;
+use std::marker::PhantomData;
+use log::warn;
+use sc_client_api::backend;
+use sp_consensus::{SelectChain, Error as ConsensusError};
+use sp_blockchain::{Backend, HeaderBackend, lowest_common_ancestor};
+use sp_runtime::{
+	traits::{NumberFor, Block as BlockT},
+	generic::BlockId,
+};
+
+/// The last block finalized by a [`FinalitySource`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinalizedBlock<Block: BlockT> {
+	/// Hash of the block.
+	pub hash: Block::Hash,
+	/// Number of the block.
+	pub number: NumberFor<Block>,
+	/// Weight backing the finalization of the block, e.g. the stake of the voters.
+	pub weight: u64,
+}
+
+/// A finality gadget, as seen by [`CompositeSelectChain`].
+pub trait FinalitySource<Block: BlockT>: Send + Sync {
+	/// Returns the last block finalized by the gadget.
+	fn finalized(&self) -> Result<FinalizedBlock<Block>, ConsensusError>;
+}
+
+impl<Block, F> FinalitySource<Block> for F
+	where
+		Block: BlockT,
+		F: Fn() -> Result<FinalizedBlock<Block>, ConsensusError> + Send + Sync,
+{
+	fn finalized(&self) -> Result<FinalizedBlock<Block>, ConsensusError> {
+		(self)()
+	}
+}
+
+/// Builds a [`CompositeSelectChain`].
+pub struct CompositeSelectChainBuilder<B, Block: BlockT> {
+	backend: Arc<B>,
+	sources: Vec<(&'static str, Arc<dyn FinalitySource<Block>>)>,
+}
+
+impl<B, Block> CompositeSelectChainBuilder<B, Block>
+	where
+		B: backend::Backend<Block>,
+		Block: BlockT,
+{
+	/// Starts building a chain selection over the blocks of `backend`.
+	pub fn new(backend: Arc<B>) -> Self {
+		CompositeSelectChainBuilder {
+			backend,
+			sources: Vec::new(),
+		}
+	}
+
+	/// Adds a finality gadget to consult, `name` is used when logging conflicts.
+	pub fn with_finality_source(
+		mut self,
+		name: &'static str,
+		source: Arc<dyn FinalitySource<Block>>,
+	) -> Self {
+		self.sources.push((name, source));
+		self
+	}
+
+	/// Builds the chain selection.
+	///
+	/// Without any finality source, it behaves like `LongestChain`.
+	pub fn build(self) -> CompositeSelectChain<B, Block> {
+		CompositeSelectChain {
+			backend: self.backend,
+			sources: self.sources.into(),
+			_phantom: Default::default(),
+		}
+	}
+}
+
+/// Longest chain selection, restricted to the chains that contain the blocks finalized by a set
+/// of finality gadgets, e.g. GRANDPA and a fast-finality overlay.
+///
+/// When the gadgets disagree, i.e. finalized blocks on different forks, the one with the most
+/// finalized weight wins, and the chains that only the others have finalized are ignored.
+pub struct CompositeSelectChain<B, Block: BlockT> {
+	backend: Arc<B>,
+	sources: Arc<[(&'static str, Arc<dyn FinalitySource<Block>>)]>,
+	_phantom: PhantomData<Block>,
+}
+
+impl<B, Block: BlockT> Clone for CompositeSelectChain<B, Block> {
+	fn clone(&self) -> Self {
+		CompositeSelectChain {
+			backend: self.backend.clone(),
+			sources: self.sources.clone(),
+			_phantom: Default::default(),
+		}
+	}
+}
+
+impl<B, Block> CompositeSelectChain<B, Block>
+	where
+		B: backend::Backend<Block>,
+		Block: BlockT,
+{
+	/// Returns whether `ancestor` is `descendant` or one of its ancestors.
+	fn is_ancestor(
+		&self,
+		ancestor: Block::Hash,
+		descendant: Block::Hash,
+	) -> sp_blockchain::Result<bool> {
+		let common = lowest_common_ancestor(self.backend.blockchain(), ancestor, descendant)?;
+		Ok(common.hash == ancestor)
+	}
+
+	/// Returns the block that the selected chains must contain.
+	///
+	/// This is the highest block finalized by the gadget with the most finalized weight, or by
+	/// any gadget that agrees with it.
+	fn finalized_anchor(&self) -> Result<Block::Hash, ConsensusError> {
+		let lookup = |e: sp_blockchain::Error| ConsensusError::ChainLookup(e.to_string());
+
+		let mut finalized = Vec::with_capacity(self.sources.len());
+		for (name, source) in self.sources.iter() {
+			finalized.push((*name, source.finalized()?));
+		}
+
+		let (strongest_name, strongest) = match finalized.iter()
+			.max_by(|(_, a), (_, b)| a.weight.cmp(&b.weight).then(a.number.cmp(&b.number)))
+		{
+			Some(strongest) => strongest.clone(),
+			None => return Ok(self.backend.blockchain().info().finalized_hash),
+		};
+
+		let mut anchor = strongest.clone();
+		for (name, block) in &finalized {
+			if self.is_ancestor(block.hash, anchor.hash).map_err(lookup)? {
+				continue;
+			}
+			if self.is_ancestor(anchor.hash, block.hash).map_err(lookup)? {
+				anchor = block.clone();
+				continue;
+			}
+
+			warn!(
+				"Conflicting finality: {} finalized #{} ({}) with weight {}, but {} finalized #{} ({}) \
+				with weight {}. Following {}.",
+				name, block.number, block.hash, block.weight,
+				strongest_name, strongest.number, strongest.hash, strongest.weight,
+				strongest_name,
+			);
+		}
+
+		Ok(anchor.hash)
+	}
+
+	fn best_containing(
+		&self,
+		target_hash: Block::Hash,
+		maybe_max_number: Option<NumberFor<Block>>,
+	) -> Result<Option<Block::Hash>, ConsensusError> {
+		let import_lock = self.backend.get_import_lock();
+		self.backend.blockchain().best_containing(target_hash, maybe_max_number, import_lock)
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))
+	}
+}
+
+impl<B, Block> SelectChain<Block> for CompositeSelectChain<B, Block>
+	where
+		B: backend::Backend<Block>,
+		Block: BlockT,
+{
+	fn leaves(&self) -> Result<Vec<<Block as BlockT>::Hash>, ConsensusError> {
+		let lookup = |e: sp_blockchain::Error| ConsensusError::ChainLookup(e.to_string());
+
+		let anchor = self.finalized_anchor()?;
+		let mut leaves = Vec::new();
+		for leaf in self.backend.blockchain().leaves().map_err(lookup)? {
+			if self.is_ancestor(anchor, leaf).map_err(lookup)? {
+				leaves.push(leaf);
+			}
+		}
+		Ok(leaves)
+	}
+
+	fn best_chain(&self) -> Result<<Block as BlockT>::Header, ConsensusError> {
+		let anchor = self.finalized_anchor()?;
+		let best_hash = self.best_containing(anchor, None)?.unwrap_or(anchor);
+		self.backend.blockchain().header(BlockId::Hash(best_hash))
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.ok_or_else(|| ConsensusError::ChainLookup(format!("Missing header of {}", best_hash)))
+	}
+
+	fn finality_target(
+		&self,
+		target_hash: Block::Hash,
+		maybe_max_number: Option<NumberFor<Block>>,
+	) -> Result<Option<Block::Hash>, ConsensusError> {
+		let lookup = |e: sp_blockchain::Error| ConsensusError::ChainLookup(e.to_string());
+
+		let anchor = self.finalized_anchor()?;
+		if self.is_ancestor(target_hash, anchor).map_err(lookup)? {
+			self.best_containing(anchor, maybe_max_number)
+		} else if self.is_ancestor(anchor, target_hash).map_err(lookup)? {
+			self.best_containing(target_hash, maybe_max_number)
+		} else {
+			Ok(None)
+		}
+	}
+}
@@ -15,6 +15,12 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Collection of common consensus specific implementations
+mod composite_chain;
 mod longest_chain;
+mod swappable_chain;
 
+pub use composite_chain::{
+	CompositeSelectChain, CompositeSelectChainBuilder, FinalitySource, FinalizedBlock,
+};
 pub use longest_chain::LongestChain;
+pub use swappable_chain::SwappableSelectChain;
@@ -0,0 +1,109 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Chain selection rule that can be replaced while the node is running.
+
+use std::sync::Arc;
+use parking_lot::RwLock;
+use sp_consensus::{SelectChain, Error as ConsensusError};
+use sp_runtime::traits::{NumberFor, Block as BlockT};
+
+/// Object safe version of [`SelectChain`], which isn't because of its `Clone` bound.
+trait DynSelectChain<Block: BlockT>: Send + Sync {
+	fn leaves(&self) -> Result<Vec<Block::Hash>, ConsensusError>;
+
+	fn best_chain(&self) -> Result<Block::Header, ConsensusError>;
+
+	fn finality_target(
+		&self,
+		target_hash: Block::Hash,
+		maybe_max_number: Option<NumberFor<Block>>,
+	) -> Result<Option<Block::Hash>, ConsensusError>;
+}
+
+impl<Block: BlockT, T: SelectChain<Block>> DynSelectChain<Block> for T {
+	fn leaves(&self) -> Result<Vec<Block::Hash>, ConsensusError> {
+		SelectChain::leaves(self)
+	}
+
+	fn best_chain(&self) -> Result<Block::Header, ConsensusError> {
+		SelectChain::best_chain(self)
+	}
+
+	fn finality_target(
+		&self,
+		target_hash: Block::Hash,
+		maybe_max_number: Option<NumberFor<Block>>,
+	) -> Result<Option<Block::Hash>, ConsensusError> {
+		SelectChain::finality_target(self, target_hash, maybe_max_number)
+	}
+}
+
+/// Chain selection forwarding to a rule that can be swapped at any time, e.g. to experiment with
+/// fork-choice rules without restarting the node.
+///
+/// Clones share the rule, swapping it through one of them affects every task using the others.
+/// Each call is answered by either the rule before the swap or the one after, never a mix of
+/// both, but two consecutive calls may be answered by different rules.
+pub struct SwappableSelectChain<Block: BlockT> {
+	rule: Arc<RwLock<Arc<dyn DynSelectChain<Block>>>>,
+}
+
+impl<Block: BlockT> Clone for SwappableSelectChain<Block> {
+	fn clone(&self) -> Self {
+		SwappableSelectChain {
+			rule: self.rule.clone(),
+		}
+	}
+}
+
+impl<Block: BlockT> SwappableSelectChain<Block> {
+	/// Starts with `rule`.
+	pub fn new(rule: impl SelectChain<Block> + 'static) -> Self {
+		SwappableSelectChain {
+			rule: Arc::new(RwLock::new(Arc::new(rule))),
+		}
+	}
+
+	/// Replaces the rule. Calls already in progress finish with the previous one.
+	pub fn swap(&self, rule: impl SelectChain<Block> + 'static) {
+		*self.rule.write() = Arc::new(rule);
+	}
+
+	fn current(&self) -> Arc<dyn DynSelectChain<Block>> {
+		self.rule.read().clone()
+	}
+}
+
+impl<Block: BlockT> SelectChain<Block> for SwappableSelectChain<Block> {
+	fn leaves(&self) -> Result<Vec<Block::Hash>, ConsensusError> {
+		self.current().leaves()
+	}
+
+	fn best_chain(&self) -> Result<Block::Header, ConsensusError> {
+		self.current().best_chain()
+	}
+
+	fn finality_target(
+		&self,
+		target_hash: Block::Hash,
+		maybe_max_number: Option<NumberFor<Block>>,
+	) -> Result<Option<Block::Hash>, ConsensusError> {
+		self.current().finality_target(target_hash, maybe_max_number)
+	}
+}
@@ -251,6 +251,7 @@ impl<B, I, C, S, Algorithm> PowBlockImport<B, I, C, S, Algorithm> where
 		if !inherent_res.ok() {
 			inherent_res
 				.into_errors()
+				.filter(|(i, e)| !self.inherent_data_providers.is_check_failure_overridden(i, e))
 				.try_for_each(|(i, e)| match TIError::try_from(&i, &e) {
 					Some(TIError::ValidAtTimestamp(timestamp)) => {
 						if timestamp > timestamp_now + MAX_TIMESTAMP_DRIFT_SECS {
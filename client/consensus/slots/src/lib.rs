@@ -549,6 +549,33 @@ pub fn slot_lenience_linear(parent_slot: u64, slot_info: &SlotInfo) -> Option<Du
 	}
 }
 
+/// Upper bound of the tolerance for blocks ahead of the local clock.
+///
+/// The timestamp pallet rejects blocks more than 30 seconds in the future, accepting them
+/// earlier would be pointless and only widen the window for misbehaving authors.
+pub const MAX_BLOCK_TIME_DRIFT_TOLERANCE: Duration = Duration::from_secs(30);
+
+/// Fails if `drift_tolerance` is above `MAX_BLOCK_TIME_DRIFT_TOLERANCE`.
+pub fn check_block_time_drift_tolerance(drift_tolerance: Duration) -> Result<(), String> {
+	if drift_tolerance > MAX_BLOCK_TIME_DRIFT_TOLERANCE {
+		return Err(format!(
+			"Block time drift tolerance of {:?} is above the maximum of {:?}",
+			drift_tolerance,
+			MAX_BLOCK_TIME_DRIFT_TOLERANCE,
+		));
+	}
+	Ok(())
+}
+
+/// Returns the latest slot a block can claim to be accepted at `slot_now`.
+///
+/// Blocks of the next slot are always accepted, to allow for some small drift. On top of that,
+/// `drift_tolerance` allows as many more slots as fit in it.
+pub fn max_accepted_slot(slot_now: u64, slot_duration: u64, drift_tolerance: Duration) -> u64 {
+	let drift_slots = drift_tolerance.as_millis() as u64 / std::cmp::max(slot_duration, 1);
+	slot_now.saturating_add(1).saturating_add(drift_slots)
+}
+
 #[cfg(test)]
 mod test {
 	use std::time::{Duration, Instant};
@@ -611,4 +638,22 @@ mod test {
 			Some(SLOT_DURATION * 2u32.pow(7)),
 		);
 	}
+
+	#[test]
+	fn block_time_drift_tolerance() {
+		let slot_duration = SLOT_DURATION.as_millis() as u64;
+
+		// without tolerance only the next slot is accepted
+		assert_eq!(super::max_accepted_slot(10, slot_duration, Duration::default()), 11);
+
+		// partial slots don't count
+		assert_eq!(super::max_accepted_slot(10, slot_duration, SLOT_DURATION / 2), 11);
+		assert_eq!(super::max_accepted_slot(10, slot_duration, SLOT_DURATION * 3), 14);
+
+		// the tolerance is bounded
+		assert!(super::check_block_time_drift_tolerance(super::MAX_BLOCK_TIME_DRIFT_TOLERANCE).is_ok());
+		assert!(super::check_block_time_drift_tolerance(
+			super::MAX_BLOCK_TIME_DRIFT_TOLERANCE + Duration::from_millis(1),
+		).is_err());
+	}
 }
@@ -63,13 +63,14 @@ use sp_blockchain::{
 	well_known_cache_keys, HeaderBackend,
 };
 use codec::{Decode, Encode};
-use hash_db::Prefix;
-use sp_trie::{MemoryDB, PrefixedMemoryDB, prefixed_key};
+use hash_db::{Prefix, HashDB, EMPTY_PREFIX};
+use sp_trie::{MemoryDB, PrefixedMemoryDB, prefixed_key, nibble_ops};
+use trie_db::{ChildReference, NodeCodec as NodeCodecT, node::{Node, NodeHandle}};
 use sp_database::Transaction;
 use parking_lot::RwLock;
 use sp_core::ChangesTrieConfiguration;
 use sp_core::offchain::storage::{OffchainOverlayedChange, OffchainOverlayedChanges};
-use sp_core::storage::{well_known_keys, ChildInfo};
+use sp_core::storage::{well_known_keys, ChildInfo, StorageChild};
 use sp_runtime::{generic::BlockId, Justification, Storage};
 use sp_runtime::traits::{
 	Block as BlockT, Header as HeaderT, NumberFor, Zero, One, SaturatedConversion, HashFor,
@@ -259,6 +260,7 @@ impl<B: BlockT> StateBackend<HashFor<B>> for RefTrackingState<B> {
 }
 
 /// Database settings.
+#[derive(Clone)]
 pub struct DatabaseSettings {
 	/// State cache size.
 	pub state_cache_size: usize,
@@ -268,8 +270,62 @@ pub struct DatabaseSettings {
 	pub pruning: PruningMode,
 	/// Where to find the database.
 	pub source: DatabaseSettingsSrc,
+	/// How many times opening the database is retried after a transient failure, e.g. when
+	/// the database is still locked by a previous instance of the node that is shutting down.
+	pub open_retries: u32,
+	/// Size in MiB of the write buffer of each column, if not the default.
+	///
+	/// Only used by RocksDB, and must lie within [`WRITE_BUFFER_SIZE_BOUNDS`].
+	pub write_buffer_size: Option<usize>,
+	/// Maximum number of files kept open by the database, if not the default.
+	///
+	/// Only used by RocksDB, and must lie within [`MAX_OPEN_FILES_BOUNDS`].
+	pub max_open_files: Option<u32>,
+	/// Which justifications of finalized blocks are kept. Only used by the full backend.
+	pub justification_retention: JustificationRetention,
+	/// Number of threads used to build the genesis state, if more than one.
+	///
+	/// The child tries and the sub-tries of the top trie, split by the first nibble of the keys,
+	/// are built in parallel. The resulting state root is the same as when building it serially.
+	/// Only used by the full backend.
+	pub genesis_build_threads: Option<usize>,
+	/// Maximum number of leaves, i.e. forks, that are tracked, if limited.
+	///
+	/// The lowest leaves beyond it stop being tracked, but their blocks are kept in the database.
+	/// The best block and the last finalized block are always tracked. Only used by the full
+	/// backend.
+	pub max_tracked_forks: Option<usize>,
+}
+
+/// Which justifications of finalized blocks are kept in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JustificationRetention {
+	/// Keep all justifications.
+	KeepAll,
+	/// Prune the justifications of blocks finalized more than `window` blocks ago.
+	///
+	/// The justifications of blocks whose number is a multiple of `period` are kept regardless,
+	/// e.g. for light clients. The most recent justification is never pruned.
+	KeepRecent {
+		/// Number of most recently finalized blocks whose justifications are kept.
+		window: u32,
+		/// Justifications of blocks whose number is a multiple of this are always kept.
+		period: Option<u32>,
+	},
+}
+
+impl Default for JustificationRetention {
+	fn default() -> Self {
+		JustificationRetention::KeepAll
+	}
 }
 
+/// Accepted values of [`DatabaseSettings::write_buffer_size`], in MiB.
+pub const WRITE_BUFFER_SIZE_BOUNDS: std::ops::RangeInclusive<usize> = 1..=1024;
+
+/// Accepted values of [`DatabaseSettings::max_open_files`].
+pub const MAX_OPEN_FILES_BOUNDS: std::ops::RangeInclusive<u32> = 16..=65536;
+
 /// Where to find the database..
 #[derive(Debug, Clone)]
 pub enum DatabaseSettingsSrc {
@@ -293,6 +349,11 @@ pub enum DatabaseSettingsSrc {
 		path: PathBuf,
 	},
 
+	/// Use a new database kept in memory, which is lost when the backend is dropped.
+	///
+	/// Nothing is written to the disk, which is useful for short-lived nodes, e.g. in tests.
+	InMemory,
+
 	/// Use a custom already-open database.
 	Custom(Arc<dyn Database<DbHash>>),
 }
@@ -304,7 +365,7 @@ impl DatabaseSettingsSrc {
 			DatabaseSettingsSrc::RocksDb { path, .. } => Some(path.as_path()),
 			DatabaseSettingsSrc::ParityDb { path, .. } => Some(path.as_path()),
 			DatabaseSettingsSrc::SubDb { path, .. } => Some(path.as_path()),
-			DatabaseSettingsSrc::Custom(_) => None,
+			DatabaseSettingsSrc::InMemory | DatabaseSettingsSrc::Custom(_) => None,
 		}
 	}
 	/// Check if database supports internal ref counting for state data.
@@ -322,6 +383,7 @@ impl std::fmt::Display for DatabaseSettingsSrc {
 			DatabaseSettingsSrc::RocksDb { .. } => "RocksDb",
 			DatabaseSettingsSrc::ParityDb { .. } => "ParityDb",
 			DatabaseSettingsSrc::SubDb { .. } => "SubDb",
+			DatabaseSettingsSrc::InMemory => "InMemory",
 			DatabaseSettingsSrc::Custom(_) => "Custom",
 		};
 		write!(f, "{}", name)
@@ -540,6 +602,9 @@ pub struct BlockImportOperation<Block: BlockT> {
 	finalized_blocks: Vec<(BlockId<Block>, Option<Justification>)>,
 	set_head: Option<BlockId<Block>>,
 	commit_state: bool,
+	genesis_build_threads: Option<usize>,
+	/// Genesis header of the chain, when the pending block is imported from a state snapshot.
+	snapshot_genesis: Option<Block::Header>,
 }
 
 impl<Block: BlockT> BlockImportOperation<Block> {
@@ -611,24 +676,26 @@ impl<Block: BlockT> sc_client_api::backend::BlockImportOperation<Block> for Bloc
 			return Err(sp_blockchain::Error::GenesisInvalid.into());
 		}
 
-		let child_delta = storage.children_default.iter().map(|(_storage_key, child_content)|(
-			&child_content.child_info,
-			child_content.data.iter().map(|(k, v)| (&k[..], Some(&v[..]))),
-		));
+		let changes_trie_config: Option<ChangesTrieConfiguration> = storage.top
+			.get(well_known_keys::CHANGES_TRIE_CONFIG)
+			.map(|v| Decode::decode(&mut &v[..])
+				.expect("changes trie configuration is encoded properly at genesis"));
+
+		let (root, transaction) = match self.genesis_build_threads {
+			Some(threads) if threads > 1 =>
+				parallel_genesis_storage_root::<Block>(storage, threads),
+			_ => {
+				let child_delta = storage.children_default.iter().map(|(_storage_key, child_content)|(
+					&child_content.child_info,
+					child_content.data.iter().map(|(k, v)| (&k[..], Some(&v[..]))),
+				));
 
-		let mut changes_trie_config: Option<ChangesTrieConfiguration> = None;
-		let (root, transaction) = self.old_state.full_storage_root(
-			storage.top.iter().map(|(k, v)| {
-				if &k[..] == well_known_keys::CHANGES_TRIE_CONFIG {
-					changes_trie_config = Some(
-						Decode::decode(&mut &v[..])
-							.expect("changes trie configuration is encoded properly at genesis")
-					);
-				}
-				(&k[..], Some(&v[..]))
-			}),
-			child_delta
-		);
+				self.old_state.full_storage_root(
+					storage.top.iter().map(|(k, v)| (&k[..], Some(&v[..]))),
+					child_delta
+				)
+			},
+		};
 
 		self.db_updates = transaction;
 		self.changes_trie_config_update = Some(changes_trie_config);
@@ -730,6 +797,169 @@ impl<Block: BlockT> sp_state_machine::Storage<HashFor<Block>> for DbGenesisStora
 	}
 }
 
+/// Empty state on top of which the genesis state is built.
+fn genesis_state<Block: BlockT>() -> DbState<Block> {
+	let genesis_storage = DbGenesisStorage::<Block>::new();
+	let root = genesis_storage.0.clone();
+	DbState::<Block>::new(Arc::new(genesis_storage), root)
+}
+
+/// Computes the root of the genesis `storage` along with the trie nodes to insert, spreading
+/// the work over up to `threads` threads.
+///
+/// Each child trie lives under its own top-level key and doesn't depend on the rest of the
+/// storage, so they are built first. The top trie is then split by the first nibble of the
+/// keys: the sub-tries found under the root are built separately, and joined under a root
+/// branch. The result is the same as calling `full_storage_root` on the genesis state.
+fn parallel_genesis_storage_root<Block: BlockT>(
+	storage: Storage,
+	threads: usize,
+) -> (Block::Hash, PrefixedMemoryDB<HashFor<Block>>) {
+	let mut transaction = PrefixedMemoryDB::<HashFor<Block>>::default();
+	let children = storage.children_default.into_iter().map(|(_, child)| child).collect();
+	let child_roots = on_threads(children, threads, genesis_child_root::<Block>);
+	let mut top = storage.top.into_iter().collect::<Vec<_>>();
+	for (key, root, child_transaction) in child_roots {
+		transaction.consolidate(child_transaction);
+		// empty child tries aren't stored at all.
+		top.extend(root.map(|root| (key, root)));
+	}
+
+	let mut sub_tries = (0..nibble_ops::NIBBLE_LENGTH as u8)
+		.map(|nibble| (nibble, Vec::new()))
+		.collect::<Vec<_>>();
+	let mut empty_key = Vec::new();
+	for (key, value) in top {
+		match key.first() {
+			Some(first) => sub_tries[(first >> 4) as usize].1.push((key, value)),
+			None => empty_key.push((key, value)),
+		}
+	}
+	sub_tries.retain(|(_, entries)| !entries.is_empty());
+
+	// the root is only a branch without partial key nor value if there are several sub-tries,
+	// and no value for the empty key.
+	if sub_tries.len() < 2 || !empty_key.is_empty() {
+		let (root, top_transaction) = genesis_state::<Block>().storage_root(
+			sub_tries.iter()
+				.flat_map(|(_, entries)| entries.iter())
+				.chain(empty_key.iter())
+				.map(|(k, v)| (&k[..], Some(&v[..]))),
+		);
+		transaction.consolidate(top_transaction);
+		return (root, transaction);
+	}
+
+	let mut children: [Option<ChildReference<Block::Hash>>; nibble_ops::NIBBLE_LENGTH] =
+		Default::default();
+	let sub_tries = on_threads(sub_tries, threads, genesis_sub_trie::<Block>);
+	for (nibble, child, sub_transaction) in sub_tries {
+		children[nibble as usize] = Some(child);
+		transaction.consolidate(sub_transaction);
+	}
+	let root_node = <GenesisCodec<Block> as NodeCodecT>::branch_node_nibbled(
+		std::iter::empty(),
+		0,
+		children.iter(),
+		None,
+	);
+	let root = transaction.insert(EMPTY_PREFIX, &root_node);
+	(root, transaction)
+}
+
+type GenesisCodec<Block> = sp_trie::NodeCodec<HashFor<Block>>;
+
+/// Maps `items` with `f`, spread over up to `threads` threads.
+fn on_threads<T: Send + 'static, R: Send + 'static>(
+	items: Vec<T>,
+	threads: usize,
+	f: fn(T) -> R,
+) -> Vec<R> {
+	let mut groups: Vec<Vec<T>> = (0..threads).map(|_| Vec::new()).collect();
+	for (i, item) in items.into_iter().enumerate() {
+		groups[i % threads].push(item);
+	}
+
+	let workers = groups.into_iter()
+		.filter(|group| !group.is_empty())
+		.map(|group| std::thread::spawn(move || group.into_iter().map(f).collect::<Vec<_>>()))
+		.collect::<Vec<_>>();
+
+	workers.into_iter()
+		.flat_map(|worker| worker.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+		.collect()
+}
+
+/// Builds a genesis child trie, returning the top-level key and value of its root, `None` if it
+/// is empty.
+fn genesis_child_root<Block: BlockT>(
+	child: StorageChild,
+) -> (Vec<u8>, Option<Vec<u8>>, PrefixedMemoryDB<HashFor<Block>>) {
+	let (child_root, empty, transaction) = genesis_state::<Block>().child_storage_root(
+		&child.child_info,
+		child.data.iter().map(|(k, v)| (&k[..], Some(&v[..]))),
+	);
+	let prefixed_storage_key = child.child_info.prefixed_storage_key().into_inner();
+	(prefixed_storage_key, if empty { None } else { Some(child_root.encode()) }, transaction)
+}
+
+/// Builds the sub-trie of the genesis top trie found under the child `nibble` of the root, from
+/// the `entries` whose keys start with that nibble.
+fn genesis_sub_trie<Block: BlockT>(
+	(nibble, entries): (u8, Vec<(Vec<u8>, Vec<u8>)>),
+) -> (u8, ChildReference<Block::Hash>, PrefixedMemoryDB<HashFor<Block>>) {
+	let (root, mut transaction) = genesis_state::<Block>().storage_root(
+		entries.iter().map(|(k, v)| (&k[..], Some(&v[..]))),
+	);
+
+	// On its own, the root of the sub-trie is the node found under the root of the whole trie,
+	// with `nibble` still in front of its partial key. The nodes below are the same.
+	let node = transaction.remove_and_purge(&root, EMPTY_PREFIX)
+		.expect("the root of a non-empty trie is stored; qed");
+	let node = match <GenesisCodec<Block> as NodeCodecT>::decode(&node)
+		.expect("the node was just encoded; qed")
+	{
+		Node::Leaf(partial, value) => {
+			let partial = partial.mid(1);
+			<GenesisCodec<Block> as NodeCodecT>::leaf_node(partial.right(), value)
+		},
+		Node::NibbledBranch(partial, children, value) => {
+			let partial = partial.mid(1);
+			<GenesisCodec<Block> as NodeCodecT>::branch_node_nibbled(
+				partial.right_iter(),
+				partial.len(),
+				children.iter().map(|child| child.map(child_reference::<Block::Hash>)),
+				value,
+			)
+		},
+		_ => unreachable!("the root of a non-empty trie is a leaf or a branch; qed"),
+	};
+
+	// like in the whole trie, small nodes are inlined in their parent.
+	let mut reference = Block::Hash::default();
+	let child = if node.len() < reference.as_ref().len() {
+		reference.as_mut()[..node.len()].copy_from_slice(&node);
+		ChildReference::Inline(reference, node.len())
+	} else {
+		ChildReference::Hash(transaction.insert((&[], Some(nibble << 4)), &node))
+	};
+	(nibble, child, transaction)
+}
+
+fn child_reference<H: Default + AsMut<[u8]>>(handle: NodeHandle) -> ChildReference<H> {
+	let mut reference = H::default();
+	match handle {
+		NodeHandle::Hash(hash) => {
+			reference.as_mut().copy_from_slice(hash);
+			ChildReference::Hash(reference)
+		},
+		NodeHandle::Inline(node) => {
+			reference.as_mut()[..node.len()].copy_from_slice(node);
+			ChildReference::Inline(reference, node.len())
+		},
+	}
+}
+
 /// Frozen `value` at time `at`.
 ///
 /// Used as inner structure under lock in `FrozenForDuration`.
@@ -784,6 +1014,9 @@ pub struct Backend<Block: BlockT> {
 	is_archive: bool,
 	io_stats: FrozenForDuration<(kvdb::IoStats, StateUsageInfo)>,
 	state_usage: Arc<StateUsageStats>,
+	justification_retention: JustificationRetention,
+	genesis_build_threads: Option<usize>,
+	max_tracked_forks: Option<usize>,
 }
 
 impl<Block: BlockT> Backend<Block> {
@@ -805,11 +1038,50 @@ impl<Block: BlockT> Backend<Block> {
 			state_cache_child_ratio: Some((50, 100)),
 			pruning: PruningMode::keep_blocks(keep_blocks),
 			source: DatabaseSettingsSrc::Custom(db),
+			open_retries: 0,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: None,
 		};
 
 		Self::new(db_setting, canonicalization_delay).expect("failed to create test-db")
 	}
 
+	/// Initialize an empty database with the state of `header`, e.g. restored from a snapshot,
+	/// instead of the genesis state.
+	///
+	/// `header` becomes the best and finalized block, and `storage` must match its state root. Only
+	/// the header of `genesis` is kept from the blocks before it.
+	pub fn import_state_snapshot(
+		&self,
+		genesis: Block::Header,
+		header: Block::Header,
+		storage: Storage,
+	) -> ClientResult<()> {
+		use sc_client_api::backend::{Backend as _, BlockImportOperation as _};
+
+		if self.blockchain.meta.read().genesis_hash != Default::default() {
+			return Err(sp_blockchain::Error::Backend(
+				"State snapshots can only be imported into an empty database".into(),
+			));
+		}
+
+		let mut operation = self.begin_operation()?;
+		self.begin_state_operation(&mut operation, BlockId::Hash(Default::default()))?;
+		let state_root = operation.reset_storage(storage)?;
+		if state_root != *header.state_root() {
+			return Err(sp_blockchain::Error::InvalidStateRoot);
+		}
+
+		if !header.number().is_zero() {
+			operation.snapshot_genesis = Some(genesis);
+		}
+		operation.set_block_data(header, None, None, NewBlockState::Final)?;
+		self.commit_operation(operation)
+	}
+
 	fn from_database(
 		db: Arc<dyn Database<DbHash>>,
 		canonicalization_delay: u64,
@@ -862,6 +1134,9 @@ impl<Block: BlockT> Backend<Block> {
 			is_archive: is_archive_pruning,
 			io_stats: FrozenForDuration::new(std::time::Duration::from_secs(1)),
 			state_usage: Arc::new(StateUsageStats::new()),
+			justification_retention: config.justification_retention,
+			genesis_build_threads: config.genesis_build_threads,
+			max_tracked_forks: config.max_tracked_forks,
 		})
 	}
 
@@ -1024,13 +1299,21 @@ impl<Block: BlockT> Backend<Block> {
 		operation.apply_aux(&mut transaction);
 		operation.apply_offchain(&mut transaction);
 
+		let snapshot_genesis = operation.snapshot_genesis.take();
 		let mut meta_updates = Vec::with_capacity(operation.finalized_blocks.len());
-		let mut last_finalized_hash = self.blockchain.meta.read().finalized_hash;
+		let (mut last_finalized_hash, last_finalized_number) = {
+			let meta = self.blockchain.meta.read();
+			(meta.finalized_hash, meta.finalized_number)
+		};
+		let mut latest_justification = None;
 
 		let mut changes_trie_cache_ops = None;
 		for (block, justification) in operation.finalized_blocks {
 			let block_hash = self.blockchain.expect_block_hash_from_id(&block)?;
 			let block_header = self.blockchain.expect_header(BlockId::Hash(block_hash))?;
+			if justification.is_some() {
+				latest_justification = Some(*block_header.number());
+			}
 
 			meta_updates.push(self.finalize_block_with_transaction(
 				&mut transaction,
@@ -1077,6 +1360,28 @@ impl<Block: BlockT> Backend<Block> {
 			}
 			if let Some(justification) = pending_block.justification {
 				transaction.set_from_vec(columns::JUSTIFICATION, &lookup_key, justification.encode());
+				latest_justification = Some(number);
+			}
+
+			if let Some(genesis) = &snapshot_genesis {
+				// the chain starts at the snapshot block, only the genesis header is kept before it.
+				let genesis_hash = genesis.hash();
+				let genesis_number = *genesis.number();
+				let genesis_key = utils::number_and_hash_to_lookup_key(genesis_number, genesis_hash)?;
+				utils::insert_hash_to_key_mapping(
+					&mut transaction,
+					columns::KEY_LOOKUP,
+					genesis_number,
+					genesis_hash,
+				)?;
+				utils::insert_number_to_key_mapping(
+					&mut transaction,
+					columns::KEY_LOOKUP,
+					genesis_number,
+					genesis_hash,
+				)?;
+				transaction.set_from_vec(columns::HEADER, &genesis_key, genesis.encode());
+				transaction.set(columns::META, meta_keys::GENESIS_HASH, genesis_hash.as_ref());
 			}
 
 			if number.is_zero() {
@@ -1178,7 +1483,9 @@ impl<Block: BlockT> Backend<Block> {
 
 			if finalized {
 				// TODO: ensure best chain contains this block.
-				self.ensure_sequential_finalization(header, Some(last_finalized_hash))?;
+				if snapshot_genesis.is_none() {
+					self.ensure_sequential_finalization(header, Some(last_finalized_hash))?;
+				}
 				self.note_finalized(
 					&mut transaction,
 					true,
@@ -1197,6 +1504,19 @@ impl<Block: BlockT> Backend<Block> {
 			let displaced_leaf = {
 				let mut leaves = self.blockchain.leaves.write();
 				let displaced_leaf = leaves.import(hash, number, parent_hash);
+				if let Some(max_tracked_forks) = self.max_tracked_forks {
+					let best_hash = if is_best { hash } else { self.blockchain.meta.read().best_hash };
+					let dropped = leaves.cap(max_tracked_forks, &[best_hash, last_finalized_hash]);
+					if !dropped.is_empty() {
+						debug!(
+							target: "db",
+							"Stopped tracking {} fork(s) beyond the limit of {}: {:?}",
+							dropped.len(),
+							max_tracked_forks,
+							dropped,
+						);
+					}
+				}
 				leaves.prepare_transaction(&mut transaction, columns::META, meta_keys::LEAF_PREFIX);
 
 				displaced_leaf
@@ -1243,6 +1563,18 @@ impl<Block: BlockT> Backend<Block> {
 			None
 		};
 
+		let finalized_number = meta_updates.iter()
+			.filter(|(_, _, _, is_finalized)| *is_finalized)
+			.map(|(_, number, _, _)| *number)
+			.max()
+			.unwrap_or(last_finalized_number);
+		self.prune_justifications(
+			&mut transaction,
+			last_finalized_number,
+			finalized_number,
+			latest_justification,
+		)?;
+
 		self.storage.db.commit(transaction)?;
 
 		if let Some((
@@ -1277,6 +1609,9 @@ impl<Block: BlockT> Backend<Block> {
 		for (hash, number, is_best, is_finalized) in meta_updates {
 			self.blockchain.update_meta(hash, number, is_best, is_finalized);
 		}
+		if let Some(genesis) = snapshot_genesis {
+			self.blockchain.meta.write().genesis_hash = genesis.hash();
+		}
 
 		Ok(())
 	}
@@ -1324,6 +1659,63 @@ impl<Block: BlockT> Backend<Block> {
 
 		Ok(())
 	}
+
+	// prunes the justifications of the blocks that left the retention window now that the
+	// finalized block went from `last_finalized` to `finalized`. `latest_justification` is the
+	// number of the most recent block whose justification is written by `transaction`, if any.
+	fn prune_justifications(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		last_finalized: NumberFor<Block>,
+		finalized: NumberFor<Block>,
+		latest_justification: Option<NumberFor<Block>>,
+	) -> ClientResult<()> {
+		let (window, period) = match self.justification_retention {
+			JustificationRetention::KeepAll => return Ok(()),
+			JustificationRetention::KeepRecent { window, period } => (u64::from(window), period),
+		};
+
+		let stored_latest = self.storage.db.get(columns::META, meta_keys::LAST_JUSTIFICATION);
+		let stored_latest = match stored_latest {
+			Some(encoded) => Some(NumberFor::<Block>::decode(&mut &encoded[..]).map_err(|err|
+				sp_blockchain::Error::Backend(format!("Error decoding last justification: {}", err))
+			)?.saturated_into::<u64>()),
+			None => None,
+		};
+		if let Some(number) = latest_justification {
+			transaction.set_from_vec(columns::META, meta_keys::LAST_JUSTIFICATION, number.encode());
+		}
+		let latest = latest_justification.map(|n| n.saturated_into::<u64>()).or(stored_latest);
+
+		// blocks that left the window with this transaction.
+		let last_finalized = last_finalized.saturated_into::<u64>();
+		let finalized = finalized.saturated_into::<u64>();
+		let candidates = if finalized >= window {
+			let first = (last_finalized + 1).saturating_sub(window);
+			first ..= finalized - window
+		} else {
+			1 ..= 0
+		};
+		// the previous latest justification was spared when it left the window, but now it's
+		// superseded.
+		let superseded = stored_latest
+			.filter(|n| latest != Some(*n) && n + window <= last_finalized);
+
+		let keep = |number: u64| {
+			Some(number) == latest || period.map_or(false, |p| p != 0 && number % u64::from(p) == 0)
+		};
+		for number in superseded.into_iter().chain(candidates).filter(|n| !keep(*n)) {
+			let number = number.saturated_into::<NumberFor<Block>>();
+			if let Some(hash) = self.blockchain.hash(number)? {
+				transaction.remove(
+					columns::JUSTIFICATION,
+					&utils::number_and_hash_to_lookup_key(number, hash)?,
+				);
+			}
+		}
+
+		Ok(())
+	}
 }
 
 fn apply_state_commit(transaction: &mut Transaction<DbHash>, commit: sc_state_db::CommitSet<Vec<u8>>) {
@@ -1389,6 +1781,8 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			finalized_blocks: Vec::new(),
 			set_head: None,
 			commit_state: false,
+			genesis_build_threads: self.genesis_build_threads,
+			snapshot_genesis: None,
 		})
 	}
 
@@ -1427,6 +1821,8 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		let hash = self.blockchain.expect_block_hash_from_id(&block)?;
 		let header = self.blockchain.expect_header(block)?;
 		let mut displaced = None;
+		let last_finalized_number = self.blockchain.meta.read().finalized_number;
+		let latest_justification = justification.as_ref().map(|_| *header.number());
 
 		let mut changes_trie_cache_ops = None;
 		let (hash, number, is_best, is_finalized) = self.finalize_block_with_transaction(
@@ -1438,6 +1834,12 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			&mut changes_trie_cache_ops,
 			&mut displaced,
 		)?;
+		self.prune_justifications(
+			&mut transaction,
+			last_finalized_number,
+			number,
+			latest_justification,
+		)?;
 		self.storage.db.commit(transaction)?;
 		self.blockchain.update_meta(hash, number, is_best, is_finalized);
 		self.changes_tries_storage.post_commit(changes_trie_cache_ops);
@@ -1448,6 +1850,10 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		Some(&self.changes_tries_storage)
 	}
 
+	fn state_cache(&self) -> Option<Arc<dyn sc_client_api::backend::StateCache>> {
+		Some(self.shared_cache.clone())
+	}
+
 	fn offchain_storage(&self) -> Option<Self::OffchainStorage> {
 		Some(self.offchain_storage.clone())
 	}
@@ -1575,9 +1981,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		// special case for genesis initialization
 		match block {
 			BlockId::Hash(h) if h == Default::default() => {
-				let genesis_storage = DbGenesisStorage::<Block>::new();
-				let root = genesis_storage.0.clone();
-				let db_state = DbState::<Block>::new(Arc::new(genesis_storage), root);
+				let db_state = genesis_state::<Block>();
 				let state = RefTrackingState::new(db_state, self.storage.clone(), None);
 				let caching_state = CachingState::new(
 					state,
@@ -1783,6 +2187,12 @@ pub(crate) mod tests {
 			state_cache_child_ratio: Some((50, 100)),
 			pruning: PruningMode::keep_blocks(1),
 			source: DatabaseSettingsSrc::Custom(backing),
+			open_retries: 0,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: None,
 		}, 0).unwrap();
 		assert_eq!(backend.blockchain().info().best_number, 9);
 		for i in 0..10 {
@@ -1790,6 +2200,105 @@ pub(crate) mod tests {
 		}
 	}
 
+	#[test]
+	fn parallel_genesis_build_matches_serial_one() {
+		let storage = || {
+			let mut storage = Storage::default();
+			for prefix in 0..32u8 {
+				for i in 0..256u32 {
+					let key = [&[prefix][..], &i.encode()[..]].concat();
+					storage.top.insert(key, vec![prefix; 1 + i as usize % 64]);
+				}
+			}
+			for id in 0..24u32 {
+				let child_info = ChildInfo::new_default(&id.encode());
+				// the first child trie is empty, and thus not stored at all.
+				let len = if id == 0 { 0 } else { 256u32 };
+				let data = (0..len)
+					.map(|i| (i.encode(), vec![id as u8; 1 + i as usize % 64]))
+					.collect();
+				storage.children_default.insert(
+					child_info.storage_key().to_vec(),
+					StorageChild { data, child_info },
+				);
+			}
+			storage
+		};
+
+		let build_genesis = |storage: Storage, genesis_build_threads| {
+			let backend = Backend::<Block>::new(DatabaseSettings {
+				state_cache_size: 16777216,
+				state_cache_child_ratio: Some((50, 100)),
+				pruning: PruningMode::keep_blocks(1),
+				source: DatabaseSettingsSrc::Custom(sp_database::as_database(
+					kvdb_memorydb::create(crate::utils::NUM_COLUMNS),
+				)),
+				open_retries: 0,
+				write_buffer_size: None,
+				max_open_files: None,
+				justification_retention: Default::default(),
+				genesis_build_threads,
+				max_tracked_forks: None,
+			}, 0).unwrap();
+
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(Default::default())).unwrap();
+			let state_root = op.reset_storage(storage).unwrap();
+			let header = Header {
+				number: 0,
+				parent_hash: Default::default(),
+				state_root,
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			op.set_block_data(header, Some(vec![]), None, NewBlockState::Final).unwrap();
+			backend.commit_operation(op).unwrap();
+			(state_root, backend)
+		};
+
+		let (serial_root, _) = build_genesis(storage(), None);
+		let (parallel_root, backend) = build_genesis(storage(), Some(4));
+		assert_eq!(serial_root, parallel_root);
+
+		let state = backend.state_at(BlockId::Number(0)).unwrap();
+		assert_eq!(state.storage(&[3, 7, 0, 0, 0]).unwrap(), Some(vec![3; 8]));
+		assert_eq!(
+			state.child_storage(&ChildInfo::new_default(&5u32.encode()), &7u32.encode()).unwrap(),
+			Some(vec![5; 8]),
+		);
+		let empty_child_key = ChildInfo::new_default(&0u32.encode()).prefixed_storage_key();
+		assert_eq!(state.storage(empty_child_key.as_slice()).unwrap(), None);
+
+		// a large top trie, spread under all the children of the root, is built in parallel too.
+		let large_top = |first_nibbles: std::ops::Range<u8>| {
+			let mut storage = Storage::default();
+			for i in 0..20_000u32 {
+				let key = sp_core::blake2_128(&i.encode()).to_vec();
+				if first_nibbles.contains(&(key[0] >> 4)) {
+					storage.top.insert(key, vec![i as u8; 1 + i as usize % 64]);
+				}
+			}
+			storage
+		};
+		let with_inline_node = || {
+			let mut storage = large_top(0..0xf);
+			// the only key under its nibble, small enough to be inlined in the root.
+			storage.top.insert(vec![0xf0], vec![1]);
+			storage
+		};
+		let (serial_root, _) = build_genesis(with_inline_node(), None);
+		let (parallel_root, backend) = build_genesis(with_inline_node(), Some(4));
+		assert_eq!(serial_root, parallel_root);
+		let state = backend.state_at(BlockId::Number(0)).unwrap();
+		assert_eq!(state.storage(&[0xf0]).unwrap(), Some(vec![1]));
+		assert_eq!(state.pairs().len(), with_inline_node().top.len());
+
+		// as well as one under a single child of the root.
+		let (serial_root, _) = build_genesis(large_top(0xa..0xb), None);
+		let (parallel_root, _) = build_genesis(large_top(0xa..0xb), Some(4));
+		assert_eq!(serial_root, parallel_root);
+	}
+
 	#[test]
 	fn set_state_data() {
 		let db = Backend::<Block>::new_test(2, 0);
@@ -2235,6 +2744,42 @@ pub(crate) mod tests {
 		assert_eq!(backend.blockchain().leaves().unwrap(), vec![block2_a, block2_b, block2_c]);
 	}
 
+	#[test]
+	fn forks_beyond_the_limit_stop_being_tracked() {
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			pruning: PruningMode::keep_blocks(10),
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(
+				kvdb_memorydb::create(crate::utils::NUM_COLUMNS),
+			)),
+			open_retries: 0,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: Some(3),
+		}, 0).unwrap();
+
+		let block0 = insert_header(&backend, 0, Default::default(), None, Default::default());
+		let block1 = insert_header(&backend, 1, block0, None, Default::default());
+		backend.finalize_block(BlockId::hash(block1), None).unwrap();
+
+		let forks = (0..10u8)
+			.map(|i| insert_header(&backend, 2, block1, None, [i; 32].into()))
+			.collect::<Vec<_>>();
+
+		let leaves = backend.blockchain().leaves().unwrap();
+		assert_eq!(leaves, forks[7..].to_vec());
+		assert_eq!(backend.blockchain().info().best_hash, forks[9]);
+		assert_eq!(backend.blockchain().info().finalized_hash, block1);
+
+		// the blocks of the dropped forks are still in the database.
+		for fork in &forks[..7] {
+			assert!(backend.blockchain().header(BlockId::Hash(*fork)).unwrap().is_some());
+		}
+	}
+
 	#[test]
 	fn test_aux() {
 		let backend: Backend<substrate_test_runtime_client::runtime::Block> = Backend::new_test(0, 0);
@@ -2288,6 +2833,67 @@ pub(crate) mod tests {
 		}
 	}
 
+	#[test]
+	fn old_justifications_are_pruned_per_retention_policy() {
+		use sc_client_api::blockchain::{Backend as BlockChainBackend};
+
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			pruning: PruningMode::keep_blocks(10),
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(
+				kvdb_memorydb::create(crate::utils::NUM_COLUMNS),
+			)),
+			open_retries: 0,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: JustificationRetention::KeepRecent {
+				window: 4,
+				period: Some(8),
+			},
+			genesis_build_threads: None,
+			max_tracked_forks: None,
+		}, 0).unwrap();
+		let justification = |number: u64| {
+			backend.blockchain().justification(BlockId::Number(number)).unwrap()
+		};
+
+		let mut hashes = vec![
+			insert_header(&backend, 0, Default::default(), None, Default::default()),
+		];
+		for number in 1..=31 {
+			let parent = hashes[number as usize - 1];
+			hashes.push(insert_header(&backend, number, parent, None, Default::default()));
+		}
+
+		for number in 1..=20 {
+			backend.finalize_block(BlockId::Number(number), Some(vec![number as u8])).unwrap();
+		}
+		for number in 1..=20 {
+			let kept = number > 16 || number % 8 == 0;
+			assert_eq!(justification(number).is_some(), kept, "justification of #{}", number);
+		}
+
+		// the justification of #20 leaves the window, but remains the most recent one.
+		{
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(hashes[20])).unwrap();
+			for hash in &hashes[21..=30] {
+				op.mark_finalized(BlockId::Hash(*hash), None).unwrap();
+			}
+			backend.commit_operation(op).unwrap();
+		}
+		assert_eq!(justification(19), None);
+		assert_eq!(justification(20), Some(vec![20]));
+
+		// until it's superseded.
+		backend.finalize_block(BlockId::Number(31), Some(vec![31])).unwrap();
+		assert_eq!(justification(20), None);
+		assert_eq!(justification(31), Some(vec![31]));
+		assert_eq!(justification(8), Some(vec![8]));
+		assert_eq!(justification(16), Some(vec![16]));
+	}
+
 	#[test]
 	fn test_finalize_non_sequential() {
 		let backend = Backend::<Block>::new_test(10, 10);
@@ -2302,4 +2908,78 @@ pub(crate) mod tests {
 			backend.commit_operation(op).unwrap_err();
 		}
 	}
+
+	#[test]
+	fn rocksdb_tuning_is_applied_and_validated() {
+		let tmp = tempfile::tempdir().unwrap();
+		let settings = DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			pruning: PruningMode::ArchiveAll,
+			source: DatabaseSettingsSrc::RocksDb {
+				path: tmp.path().into(),
+				cache_size: 1024,
+			},
+			open_retries: 0,
+			write_buffer_size: Some(8),
+			max_open_files: Some(128),
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: None,
+		};
+
+		let (block0, block1) = {
+			let backend = Backend::<Block>::new(settings.clone(), 0).unwrap();
+			let block0 = insert_header(&backend, 0, Default::default(), None, Default::default());
+			let block1 = insert_header(&backend, 1, block0, None, Default::default());
+			(block0, block1)
+		};
+
+		let backend = Backend::<Block>::new(settings.clone(), 0).unwrap();
+		assert_eq!(backend.blockchain().info().best_number, 1);
+		assert_eq!(backend.blockchain().hash(0).unwrap(), Some(block0));
+		assert_eq!(backend.blockchain().hash(1).unwrap(), Some(block1));
+		drop(backend);
+
+		// RocksDB records the options it was opened with next to the data.
+		let options = std::fs::read_dir(tmp.path()).unwrap()
+			.map(|entry| entry.unwrap().path())
+			.filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("OPTIONS-"))
+			.max()
+			.unwrap();
+		let options = std::fs::read_to_string(options).unwrap();
+		let mut section = "";
+		let mut write_buffer_sizes = HashMap::new();
+		let mut max_open_files = None;
+		for line in options.lines().map(str::trim) {
+			let mut option = line.splitn(2, '=');
+			match (option.next(), option.next()) {
+				_ if line.starts_with('[') => section = line,
+				(Some("write_buffer_size"), Some(size)) => {
+					write_buffer_sizes.insert(section, size.parse::<usize>().unwrap());
+				},
+				(Some("max_open_files"), Some(files)) => max_open_files = Some(files.to_owned()),
+				_ => (),
+			}
+		}
+		assert_eq!(max_open_files.as_deref(), Some("128"));
+		for column in 0..utils::NUM_COLUMNS {
+			let size = write_buffer_sizes[format!("[CFOptions \"col{}\"]", column).as_str()];
+			if column == columns::STATE {
+				assert!(size >= 8 * 1024 * 1024);
+			} else {
+				assert_eq!(size, 8 * 1024 * 1024);
+			}
+		}
+
+		let too_large = DatabaseSettings { write_buffer_size: Some(4096), ..settings.clone() };
+		assert!(Backend::<Block>::new(too_large, 0).is_err());
+		let small_cache = DatabaseSettings {
+			source: DatabaseSettingsSrc::RocksDb { path: tmp.path().into(), cache_size: 128 },
+			..settings.clone()
+		};
+		assert!(Backend::<Block>::new(small_cache, 0).is_err());
+		let too_few = DatabaseSettings { max_open_files: Some(1), ..settings };
+		assert!(Backend::<Block>::new(too_few, 0).is_err());
+	}
 }
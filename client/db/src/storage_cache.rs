@@ -30,6 +30,7 @@ use sp_state_machine::{
 	StorageCollection, ChildStorageCollection,
 };
 use log::trace;
+use sc_client_api::backend::{StateCache, StateCacheStats};
 use crate::{utils::Meta, stats::StateUsageStats};
 
 const STATE_CACHE_BLOCKS: usize = 12;
@@ -47,6 +48,10 @@ pub struct Cache<B: BlockT> {
 	/// Information on the modifications in recently committed blocks; specifically which keys
 	/// changed in which block. Ordered by block number.
 	modifications: VecDeque<BlockChanges<B::Header>>,
+	/// Number of reads answered from the cache since the last flush.
+	hits: u64,
+	/// Number of reads that went to the database since the last flush.
+	misses: u64,
 }
 
 struct LRUMap<K, V>(LinkedHashMap<K, V>, usize, usize);
@@ -238,11 +243,34 @@ pub fn new_shared_cache<B: BlockT>(
 					LinkedHashMap::new(), 0, shared_cache_size * child_ratio.0 / child_ratio.1
 				),
 				modifications: VecDeque::new(),
+				hits: 0,
+				misses: 0,
 			}
 		)
 	)
 }
 
+impl<B: BlockT> StateCache for Mutex<Cache<B>> {
+	fn stats(&self) -> StateCacheStats {
+		let cache = self.lock();
+		StateCacheStats { hits: cache.hits, misses: cache.misses }
+	}
+
+	fn flush(&self) -> StateCacheStats {
+		let mut cache = self.lock();
+		trace!("Flushing shared cache");
+		// Modifications are kept, they tell which values cached by the states being imported
+		// can be propagated.
+		cache.lru_storage.clear();
+		cache.lru_child_storage.clear();
+		cache.lru_hashes.clear();
+		StateCacheStats {
+			hits: std::mem::take(&mut cache.hits),
+			misses: std::mem::take(&mut cache.misses),
+		}
+	}
+}
+
 #[derive(Debug)]
 /// Accumulates a list of storage changed in a block.
 struct BlockChanges<B: Header> {
@@ -510,11 +538,13 @@ impl<S: StateBackend<HashFor<B>>, B: BlockT> StateBackend<HashFor<B>> for Cachin
 		if Self::is_allowed(Some(key), None, &self.cache.parent_hash, &cache.modifications) {
 			if let Some(entry) = cache.lru_storage.get(key).map(|a| a.clone()) {
 				trace!("Found in shared cache: {:?}", HexDisplay::from(&key));
+				cache.hits += 1;
 				self.usage.tally_key_read(key, entry.as_ref(), true);
 				return Ok(entry)
 			}
 		}
 		trace!("Cache miss: {:?}", HexDisplay::from(&key));
+		cache.misses += 1;
 		let value = self.state.storage(key)?;
 		RwLockUpgradableReadGuard::upgrade(local_cache).storage.insert(key.to_vec(), value.clone());
 		self.usage.tally_key_read(key, value.as_ref(), false);
@@ -531,10 +561,12 @@ impl<S: StateBackend<HashFor<B>>, B: BlockT> StateBackend<HashFor<B>> for Cachin
 		if Self::is_allowed(Some(key), None, &self.cache.parent_hash, &cache.modifications) {
 			if let Some(entry) = cache.lru_hashes.get(key).map(|a| a.0.clone()) {
 				trace!("Found hash in shared cache: {:?}", HexDisplay::from(&key));
+				cache.hits += 1;
 				return Ok(entry)
 			}
 		}
 		trace!("Cache hash miss: {:?}", HexDisplay::from(&key));
+		cache.misses += 1;
 		let hash = self.state.storage_hash(key)?;
 		RwLockUpgradableReadGuard::upgrade(local_cache).hashes.insert(key.to_vec(), hash);
 		Ok(hash)
@@ -557,12 +589,14 @@ impl<S: StateBackend<HashFor<B>>, B: BlockT> StateBackend<HashFor<B>> for Cachin
 		if Self::is_allowed(None, Some(&key), &self.cache.parent_hash, &cache.modifications) {
 			if let Some(entry) = cache.lru_child_storage.get(&key).map(|a| a.clone()) {
 				trace!("Found in shared cache: {:?}", key);
+				cache.hits += 1;
 				return Ok(
 					self.usage.tally_child_key_read(&key, entry, true)
 				)
 			}
 		}
 		trace!("Cache miss: {:?}", key);
+		cache.misses += 1;
 		let value = self.state.child_storage(child_info, &key.1[..])?;
 
 		// just pass it through the usage counter
@@ -103,6 +103,12 @@ mod tests {
 			state_cache_child_ratio: None,
 			pruning: PruningMode::ArchiveAll,
 			source: DatabaseSettingsSrc::RocksDb { path: db_path.to_owned(), cache_size: 128 },
+			open_retries: 0,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: None,
 		}, DatabaseType::Full).map(|_| ())
 	}
 
@@ -59,6 +59,8 @@ pub mod meta_keys {
 	pub const LEAF_PREFIX: &[u8; 4] = b"leaf";
 	/// Children prefix list key.
 	pub const CHILDREN_PREFIX: &[u8; 8] = b"children";
+	/// Number of the block with the most recent justification.
+	pub const LAST_JUSTIFICATION: &[u8; 5] = b"ljust";
 }
 
 /// Database metadata.
@@ -218,6 +220,8 @@ pub fn open_database<Block: BlockT>(
 		),
 	);
 
+	check_tuning(config)?;
+
 	let db: Arc<dyn Database<DbHash>> = match &config.source {
 		#[cfg(any(feature = "with-kvdb-rocksdb", test))]
 		DatabaseSettingsSrc::RocksDb { path, cache_size } => {
@@ -226,21 +230,16 @@ pub fn open_database<Block: BlockT>(
 
 			// and now open database assuming that it has the latest version
 			let mut db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
-			let state_col_budget = (*cache_size as f64 * 0.9) as usize;
-			let other_col_budget = (cache_size - state_col_budget) / (NUM_COLUMNS as usize - 1);
-			let mut memory_budget = std::collections::HashMap::new();
+			let memory_budget = column_memory_budgets(*cache_size, config.write_buffer_size)?;
+			let state_col_budget = memory_budget[&crate::columns::STATE];
+			let other_col_budget = memory_budget[&crate::columns::META];
 			let path = path.to_str()
 				.ok_or_else(|| sp_blockchain::Error::Backend("Invalid database path".into()))?;
 
-			for i in 0..NUM_COLUMNS {
-				if i == crate::columns::STATE {
-					memory_budget.insert(i, state_col_budget);
-				} else {
-					memory_budget.insert(i, other_col_budget);
-				}
-			}
-
 			db_config.memory_budget = memory_budget;
+			if let Some(max_open_files) = config.max_open_files {
+				db_config.max_open_files = max_open_files as i32;
+			}
 
 			log::trace!(
 				target: "db",
@@ -277,6 +276,7 @@ pub fn open_database<Block: BlockT>(
 		DatabaseSettingsSrc::ParityDb { .. } => {
 			return db_open_error("with-parity-db");
 		},
+		DatabaseSettingsSrc::InMemory => sp_database::as_database(kvdb_memorydb::create(NUM_COLUMNS)),
 		DatabaseSettingsSrc::Custom(db) => db.clone(),
 	};
 
@@ -285,6 +285,69 @@ pub fn open_database<Block: BlockT>(
 	Ok(db)
 }
 
+/// Split the database cache of `cache_size` MiB between the columns.
+///
+/// RocksDB sizes its block cache after the total of the budgets, and the write buffer of a
+/// column to a quarter of the budget of that column. A `write_buffer_size` is thus applied by
+/// moving budget from the state column to the others, which leaves the block cache unchanged.
+/// The state column keeps a write buffer at least as large.
+fn column_memory_budgets(
+	cache_size: usize,
+	write_buffer_size: Option<usize>,
+) -> sp_blockchain::Result<std::collections::HashMap<u32, usize>> {
+	let (state_col_budget, other_col_budget) = match write_buffer_size {
+		None => {
+			let state_col_budget = (cache_size as f64 * 0.9) as usize;
+			(state_col_budget, (cache_size - state_col_budget) / (NUM_COLUMNS as usize - 1))
+		},
+		Some(write_buffer_size) => {
+			let other_col_budget = write_buffer_size * 4;
+			match cache_size.checked_sub(other_col_budget * (NUM_COLUMNS as usize - 1)) {
+				Some(state_col_budget) if state_col_budget >= other_col_budget =>
+					(state_col_budget, other_col_budget),
+				_ => return Err(sp_blockchain::Error::Backend(format!(
+					"Database write buffers of {} MiB need a database cache of at least {} MiB",
+					write_buffer_size,
+					other_col_budget * NUM_COLUMNS as usize,
+				))),
+			}
+		},
+	};
+
+	Ok((0..NUM_COLUMNS)
+		.map(|i| if i == crate::columns::STATE {
+			(i, state_col_budget)
+		} else {
+			(i, other_col_budget)
+		})
+		.collect())
+}
+
+/// Check that the low-level tuning of the database lies within sane bounds.
+fn check_tuning(config: &DatabaseSettings) -> sp_blockchain::Result<()> {
+	if let Some(size) = config.write_buffer_size {
+		if !crate::WRITE_BUFFER_SIZE_BOUNDS.contains(&size) {
+			return Err(sp_blockchain::Error::Backend(format!(
+				"Database write buffer size of {} MiB is out of bounds {:?}",
+				size,
+				crate::WRITE_BUFFER_SIZE_BOUNDS,
+			)));
+		}
+	}
+
+	if let Some(files) = config.max_open_files {
+		if !crate::MAX_OPEN_FILES_BOUNDS.contains(&files) {
+			return Err(sp_blockchain::Error::Backend(format!(
+				"Database maximum of {} open files is out of bounds {:?}",
+				files,
+				crate::MAX_OPEN_FILES_BOUNDS,
+			)));
+		}
+	}
+
+	Ok(())
+}
+
 /// Check database type.
 pub fn check_database_type(db: &dyn Database<DbHash>, db_type: DatabaseType) -> sp_blockchain::Result<()> {
 	match db.get(COLUMN_META, meta_keys::TYPE) {
@@ -439,4 +502,14 @@ mod tests {
 		assert_eq!(DatabaseType::Full.as_str(), "full");
 		assert_eq!(DatabaseType::Light.as_str(), "light");
 	}
+
+	#[test]
+	fn write_buffer_size_doesnt_change_total_memory_budget() {
+		let budgets = column_memory_budgets(1024, Some(8)).unwrap();
+		assert_eq!(budgets.values().sum::<usize>(), 1024);
+		assert_eq!(budgets[&crate::columns::HEADER], 32);
+		assert_eq!(budgets[&crate::columns::STATE], 1024 - 32 * (NUM_COLUMNS as usize - 1));
+
+		assert!(column_memory_budgets(128, Some(8)).is_err());
+	}
 }
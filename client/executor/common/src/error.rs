@@ -81,6 +81,27 @@ pub enum Error {
 	/// Execution of a host function failed.
 	#[display(fmt="Host function {} execution failed with: {}", _0, _1)]
 	FunctionExecution(String, String),
+	/// A host function with the same name has already been registered.
+	#[display(fmt="Host function `{}` is registered more than once", _0)]
+	#[from(ignore)]
+	DuplicateHostFunction(String),
+}
+
+impl Error {
+	/// Returns whether the runtime panicked.
+	///
+	/// A runtime compiled to wasm can't unwind, its panic handler ends with the `unreachable`
+	/// instruction, so hitting it is reported as a panic as well.
+	pub fn is_runtime_panic(&self) -> bool {
+		match self {
+			Error::RuntimePanicked(_) => true,
+			Error::Trap(trap) => match trap.kind() {
+				wasmi::TrapKind::Unreachable => true,
+				_ => false,
+			},
+			_ => false,
+		}
+	}
 }
 
 impl std::error::Error for Error {
@@ -26,7 +26,7 @@ use sp_core::{
 	NativeOrEncoded, traits::{CodeExecutor, Externalities, RuntimeCode, MissingHostFunctions},
 };
 use log::trace;
-use std::{result, panic::{UnwindSafe, AssertUnwindSafe}, sync::Arc};
+use std::{collections::HashSet, result, panic::{UnwindSafe, AssertUnwindSafe}, sync::Arc};
 use sp_wasm_interface::{HostFunctions, Function};
 use sc_executor_common::wasm_runtime::WasmInstance;
 
@@ -236,10 +236,58 @@ impl<D: NativeExecutionDispatch> NativeExecutor<D> {
 		default_heap_pages: Option<u64>,
 		max_runtime_instances: usize,
 	) -> Self {
+		Self::with_all_host_functions(
+			fallback_method,
+			default_heap_pages,
+			max_runtime_instances,
+			Self::dispatch_host_functions(),
+		)
+	}
+
+	/// Create new instance, registering `host_functions` in addition to the ones of `D`.
+	///
+	/// This allows a chain to provide host functions, e.g. extra crypto, that are only known at
+	/// runtime. Fails if one of `host_functions` has the name of another registered host function.
+	pub fn with_host_functions(
+		fallback_method: WasmExecutionMethod,
+		default_heap_pages: Option<u64>,
+		max_runtime_instances: usize,
+		host_functions: Vec<&'static dyn Function>,
+	) -> Result<Self> {
+		let mut all_host_functions = Self::dispatch_host_functions();
+		let mut names = all_host_functions.iter()
+			.map(|function| function.name().to_owned())
+			.collect::<HashSet<_>>();
+		for function in host_functions {
+			if !names.insert(function.name().to_owned()) {
+				return Err(Error::DuplicateHostFunction(function.name().to_owned()));
+			}
+			all_host_functions.push(function);
+		}
+
+		Ok(Self::with_all_host_functions(
+			fallback_method,
+			default_heap_pages,
+			max_runtime_instances,
+			all_host_functions,
+		))
+	}
+
+	/// The standard host functions and the custom ones of `D`.
+	fn dispatch_host_functions() -> Vec<&'static dyn Function> {
 		let mut host_functions = sp_io::SubstrateHostFunctions::host_functions();
 
 		// Add the custom host functions provided by the user.
 		host_functions.extend(D::ExtendHostFunctions::host_functions());
+		host_functions
+	}
+
+	fn with_all_host_functions(
+		fallback_method: WasmExecutionMethod,
+		default_heap_pages: Option<u64>,
+		max_runtime_instances: usize,
+		host_functions: Vec<&'static dyn Function>,
+	) -> Self {
 		let wasm_executor = WasmExecutor::new(
 			fallback_method,
 			default_heap_pages,
@@ -460,7 +508,13 @@ macro_rules! native_executor_instance {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use sc_runtime_test::WASM_BINARY;
+	use sp_core::traits::CallInWasm;
+	use sp_runtime::traits::BlakeTwo256;
 	use sp_runtime_interface::runtime_interface;
+	use sp_state_machine::TestExternalities;
+	use sp_wasm_interface::{FunctionContext, Signature, Value, ValueType};
 
 	#[runtime_interface]
 	trait MyInterface {
@@ -492,4 +546,71 @@ mod tests {
 
 		my_interface::say_hello_world("hey");
 	}
+
+	static MISSING_EXTERNAL_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+	/// Provides the `missing_external` function that the test runtime imports.
+	struct MissingExternal;
+
+	impl Function for MissingExternal {
+		fn name(&self) -> &str {
+			"missing_external"
+		}
+
+		fn signature(&self) -> Signature {
+			Signature::new_with_args(Vec::<ValueType>::new())
+		}
+
+		fn execute(
+			&self,
+			_: &mut dyn FunctionContext,
+			_: &mut dyn Iterator<Item = Value>,
+		) -> sp_wasm_interface::Result<Option<Value>> {
+			MISSING_EXTERNAL_CALLS.fetch_add(1, Ordering::SeqCst);
+			Ok(None)
+		}
+	}
+
+	#[test]
+	fn native_executor_calls_host_functions_registered_at_runtime() {
+		let executor = NativeExecutor::<MyExecutor>::with_host_functions(
+			WasmExecutionMethod::Interpreted,
+			None,
+			8,
+			vec![&MissingExternal as &dyn Function],
+		).unwrap();
+
+		let mut ext = TestExternalities::<BlakeTwo256>::default();
+		executor.call_in_wasm(
+			&WASM_BINARY[..],
+			None,
+			"test_calling_missing_external",
+			&[],
+			&mut ext.ext(),
+			MissingHostFunctions::Allow,
+		).unwrap();
+		assert_eq!(MISSING_EXTERNAL_CALLS.load(Ordering::SeqCst), 1);
+	}
+
+	#[test]
+	fn native_executor_rejects_duplicate_host_functions() {
+		let duplicate = |host_functions| NativeExecutor::<MyExecutor>::with_host_functions(
+			WasmExecutionMethod::Interpreted,
+			None,
+			8,
+			host_functions,
+		).err().map(|e| e.to_string());
+
+		assert_eq!(
+			duplicate(vec![&MissingExternal as &dyn Function, &MissingExternal]),
+			Some("Host function `missing_external` is registered more than once".into()),
+		);
+
+		// conflicts with a host function of the dispatch.
+		let say_hello_world = my_interface::HostFunctions::host_functions()[0];
+		assert_eq!(
+			duplicate(vec![say_hello_world]),
+			Some(format!("Host function `{}` is registered more than once", say_hello_world.name())),
+		);
+	}
 }
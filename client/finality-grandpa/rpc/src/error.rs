@@ -16,7 +16,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::NOT_READY_ERROR_CODE;
+use crate::{NOT_READY_ERROR_CODE, PROVE_FINALITY_ERROR_CODE};
+use sc_finality_grandpa::FinalityProofError;
 
 #[derive(derive_more::Display, derive_more::From)]
 /// Top-level error type for the RPC handler
@@ -30,13 +31,20 @@ pub enum Error {
 	/// GRANDPA reports voter state with round id or weights larger than 32-bits.
 	#[display(fmt = "GRANDPA reports voter state as unreasonably large")]
 	VoterStateReportsUnreasonablyLargeNumbers,
+	/// A finality proof can't be provided for the requested block.
+	#[display(fmt = "Unable to prove finality: {}", _0)]
+	ProveFinalityFailed(FinalityProofError),
 }
 
 impl From<Error> for jsonrpc_core::Error {
 	fn from(error: Error) -> Self {
+		let code = match error {
+			Error::ProveFinalityFailed(_) => PROVE_FINALITY_ERROR_CODE,
+			_ => NOT_READY_ERROR_CODE,
+		};
 		jsonrpc_core::Error {
 			message: format!("{}", error),
-			code: jsonrpc_core::ErrorCode::ServerError(NOT_READY_ERROR_CODE),
+			code: jsonrpc_core::ErrorCode::ServerError(code),
 			data: None,
 		}
 	}
@@ -0,0 +1,40 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Finality proofs served over RPC.
+
+use sc_client_api::backend::Backend;
+use sc_finality_grandpa::{FinalityProofError, FinalityProofProvider};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+/// Provides SCALE-encoded finality proofs for the RPC handler.
+pub trait RpcFinalityProofProvider<Block: BlockT> {
+	/// Prove finality of the given block.
+	fn rpc_prove_finality(&self, block: NumberFor<Block>) -> Result<Vec<u8>, FinalityProofError>;
+}
+
+impl<B, Block> RpcFinalityProofProvider<Block> for FinalityProofProvider<B, Block>
+where
+	Block: BlockT,
+	NumberFor<Block>: finality_grandpa::BlockNumberOps,
+	B: Backend<Block> + Send + Sync + 'static,
+{
+	fn rpc_prove_finality(&self, block: NumberFor<Block>) -> Result<Vec<u8>, FinalityProofError> {
+		self.prove_block_finality(block)
+	}
+}
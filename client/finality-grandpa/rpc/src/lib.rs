@@ -19,68 +19,110 @@
 //! RPC API for GRANDPA.
 #![warn(missing_docs)]
 
+use std::{marker::PhantomData, sync::Arc};
+
 use futures::{FutureExt, TryFutureExt};
 use jsonrpc_derive::rpc;
+use sp_core::Bytes;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
 
 mod error;
+mod finality;
 mod report;
 
 use report::{ReportAuthoritySet, ReportVoterState, ReportedRoundStates};
+pub use finality::RpcFinalityProofProvider;
 
 /// Returned when Grandpa RPC endpoint is not ready.
 pub const NOT_READY_ERROR_CODE: i64 = 1;
 
+/// Returned when a finality proof can't be provided for the requested block.
+pub const PROVE_FINALITY_ERROR_CODE: i64 = 2;
+
 type FutureResult<T> =
 	Box<dyn jsonrpc_core::futures::Future<Item = T, Error = jsonrpc_core::Error> + Send>;
 
 /// Provides RPC methods for interacting with GRANDPA.
 #[rpc]
-pub trait GrandpaApi {
+pub trait GrandpaApi<Number> {
 	/// Returns the state of the current best round state as well as the
 	/// ongoing background rounds.
 	#[rpc(name = "grandpa_roundState")]
 	fn round_state(&self) -> FutureResult<ReportedRoundStates>;
+
+	/// Returns the SCALE-encoded proof of finality of the given block.
+	#[rpc(name = "grandpa_proveFinality")]
+	fn prove_finality(&self, block: Number) -> FutureResult<Bytes>;
 }
 
 /// Implements the GrandpaApi RPC trait for interacting with GRANDPA.
-pub struct GrandpaRpcHandler<AuthoritySet, VoterState> {
+pub struct GrandpaRpcHandler<AuthoritySet, VoterState, Block, ProofProvider> {
 	authority_set: AuthoritySet,
 	voter_state: VoterState,
+	finality_proof_provider: Arc<ProofProvider>,
+	_marker: PhantomData<Block>,
 }
 
-impl<AuthoritySet, VoterState> GrandpaRpcHandler<AuthoritySet, VoterState> {
+impl<AuthoritySet, VoterState, Block, ProofProvider>
+	GrandpaRpcHandler<AuthoritySet, VoterState, Block, ProofProvider>
+{
 	/// Creates a new GrandpaRpcHander instance.
-	pub fn new(authority_set: AuthoritySet, voter_state: VoterState) -> Self {
+	pub fn new(
+		authority_set: AuthoritySet,
+		voter_state: VoterState,
+		finality_proof_provider: Arc<ProofProvider>,
+	) -> Self {
 		Self {
 			authority_set,
 			voter_state,
+			finality_proof_provider,
+			_marker: PhantomData,
 		}
 	}
 }
 
-impl<AuthoritySet, VoterState> GrandpaApi for GrandpaRpcHandler<AuthoritySet, VoterState>
+impl<AuthoritySet, VoterState, Block, ProofProvider> GrandpaApi<NumberFor<Block>>
+	for GrandpaRpcHandler<AuthoritySet, VoterState, Block, ProofProvider>
 where
 	VoterState: ReportVoterState + Send + Sync + 'static,
 	AuthoritySet: ReportAuthoritySet + Send + Sync + 'static,
+	Block: BlockT,
+	ProofProvider: RpcFinalityProofProvider<Block> + Send + Sync + 'static,
 {
 	fn round_state(&self) -> FutureResult<ReportedRoundStates> {
 		let round_states = ReportedRoundStates::from(&self.authority_set, &self.voter_state);
 		let future = async move { round_states }.boxed();
 		Box::new(future.map_err(jsonrpc_core::Error::from).compat())
 	}
+
+	fn prove_finality(&self, block: NumberFor<Block>) -> FutureResult<Bytes> {
+		let result = self.finality_proof_provider
+			.rpc_prove_finality(block)
+			.map(Bytes)
+			.map_err(|e| {
+				log::debug!("Unable to prove finality of block #{}: {}", block, e);
+				error::Error::ProveFinalityFailed(e)
+			});
+		let future = async move { result }.boxed();
+		Box::new(future.map_err(jsonrpc_core::Error::from).compat())
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use jsonrpc_core::IoHandler;
-	use sc_finality_grandpa::{report, AuthorityId};
+	use sc_finality_grandpa::{report, AuthorityId, FinalityProofError};
 	use sp_core::crypto::Public;
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
 	use std::{collections::HashSet, convert::TryInto};
 
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
 	struct TestAuthoritySet;
 	struct TestVoterState;
 	struct EmptyVoterState;
+	struct TestFinalityProofProvider;
 
 	fn voters() -> HashSet<AuthorityId> {
 		let voter_id_1 = AuthorityId::from_slice(&[1; 32]);
@@ -133,11 +175,33 @@ mod tests {
 		}
 	}
 
-	#[test]
-	fn uninitialized_rpc_handler() {
-		let handler = GrandpaRpcHandler::new(TestAuthoritySet, EmptyVoterState);
+	impl RpcFinalityProofProvider<Block> for TestFinalityProofProvider {
+		fn rpc_prove_finality(&self, block: u64) -> Result<Vec<u8>, FinalityProofError> {
+			match block {
+				1 => Ok(vec![1, 2, 3]),
+				2 => Err(FinalityProofError::BlockNotYetFinalized),
+				_ => Err(FinalityProofError::ProofUnavailable),
+			}
+		}
+	}
+
+	fn setup_io_handler<VoterState>(voter_state: VoterState) -> IoHandler
+	where
+		VoterState: ReportVoterState + Send + Sync + 'static,
+	{
+		let handler = GrandpaRpcHandler::<_, _, Block, _>::new(
+			TestAuthoritySet,
+			voter_state,
+			Arc::new(TestFinalityProofProvider),
+		);
 		let mut io = IoHandler::new();
 		io.extend_with(GrandpaApi::to_delegate(handler));
+		io
+	}
+
+	#[test]
+	fn uninitialized_rpc_handler() {
+		let io = setup_io_handler(EmptyVoterState);
 
 		let request = r#"{"jsonrpc":"2.0","method":"grandpa_roundState","params":[],"id":1}"#;
 		let response = r#"{"jsonrpc":"2.0","error":{"code":1,"message":"GRANDPA RPC endpoint not ready"},"id":1}"#;
@@ -147,9 +211,7 @@ mod tests {
 
 	#[test]
 	fn working_rpc_handler() {
-		let handler = GrandpaRpcHandler::new(TestAuthoritySet, TestVoterState);
-		let mut io = IoHandler::new();
-		io.extend_with(GrandpaApi::to_delegate(handler));
+		let io = setup_io_handler(TestVoterState);
 
 		let request = r#"{"jsonrpc":"2.0","method":"grandpa_roundState","params":[],"id":1}"#;
 		let response = "{\"jsonrpc\":\"2.0\",\"result\":{\
@@ -168,4 +230,24 @@ mod tests {
 
 		assert_eq!(io.handle_request_sync(request), Some(response.into()));
 	}
+
+	#[test]
+	fn prove_finality_of_finalized_block() {
+		let io = setup_io_handler(TestVoterState);
+
+		let request = r#"{"jsonrpc":"2.0","method":"grandpa_proveFinality","params":[1],"id":1}"#;
+		let response = r#"{"jsonrpc":"2.0","result":"0x010203","id":1}"#;
+
+		assert_eq!(io.handle_request_sync(request), Some(response.into()));
+	}
+
+	#[test]
+	fn prove_finality_of_unfinalized_block_fails() {
+		let io = setup_io_handler(TestVoterState);
+
+		let request = r#"{"jsonrpc":"2.0","method":"grandpa_proveFinality","params":[2],"id":1}"#;
+		let response = r#"{"jsonrpc":"2.0","error":{"code":2,"message":"Unable to prove finality: Block is not finalized yet"},"id":1}"#;
+
+		assert_eq!(io.handle_request_sync(request), Some(response.into()));
+	}
 }
@@ -49,7 +49,7 @@ use parity_scale_codec::{Encode, Decode};
 use finality_grandpa::BlockNumberOps;
 use sp_runtime::{
 	Justification, generic::BlockId,
-	traits::{NumberFor, Block as BlockT, Header as HeaderT, One},
+	traits::{NumberFor, Block as BlockT, Header as HeaderT, One, Zero},
 };
 use sp_core::storage::StorageKey;
 use sc_telemetry::{telemetry, CONSENSUS_INFO};
@@ -191,7 +191,7 @@ impl<B, Block> sc_network::config::FinalityProofProvider<Block> for FinalityProo
 			FinalityProofRequest::Original(request) => prove_finality::<_, _, GrandpaJustification<Block>>(
 				&*self.backend.blockchain(),
 				&*self.authority_provider,
-				request.authorities_set_id,
+				Some(request.authorities_set_id),
 				request.last_finalized,
 				for_block,
 			),
@@ -199,6 +199,47 @@ impl<B, Block> sc_network::config::FinalityProofProvider<Block> for FinalityProo
 	}
 }
 
+impl<B, Block> FinalityProofProvider<B, Block>
+	where
+		Block: BlockT,
+		NumberFor<Block>: BlockNumberOps,
+		B: Backend<Block> + Send + Sync + 'static,
+{
+	/// Prove finality of the given block, e.g. to serve it over RPC.
+	///
+	/// The returned proof is SCALE-encoded and is not restricted to any authority set.
+	pub fn prove_block_finality(&self, block: NumberFor<Block>) -> Result<Vec<u8>, FinalityProofError> {
+		prove_block_finality::<_, _, GrandpaJustification<Block>>(
+			&*self.backend.blockchain(),
+			&*self.authority_provider,
+			block,
+		)
+	}
+}
+
+/// Error returned when proving finality of a single block.
+#[derive(Debug, derive_more::Display, derive_more::From)]
+pub enum FinalityProofError {
+	/// The block is not finalized yet.
+	#[display(fmt = "Block is not finalized yet")]
+	BlockNotYetFinalized,
+	/// No proof can be built for the block, e.g. because it has been pruned.
+	#[display(fmt = "Finality proof is not available for the block")]
+	ProofUnavailable,
+	/// Backend error.
+	#[display(fmt = "Backend error: {}", _0)]
+	Client(ClientError),
+}
+
+impl std::error::Error for FinalityProofError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			FinalityProofError::Client(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
 /// The effects of block finality.
 #[derive(Debug, PartialEq)]
 pub struct FinalityEffects<Header: HeaderT> {
@@ -268,11 +309,14 @@ pub(crate) fn make_finality_proof_request<H: Encode + Decode>(last_finalized: H,
 /// It is assumed that the caller already have a proof-of-finality for the block 'begin'.
 /// It is assumed that the caller already knows all blocks in the range (begin; end].
 ///
+/// If `authorities_set_id` is given, the proof is only returned if its first justification is
+/// signed by that set.
+///
 /// Returns None if there are no finalized blocks unknown to the caller.
 pub(crate) fn prove_finality<Block: BlockT, B: BlockchainBackend<Block>, J>(
 	blockchain: &B,
 	authorities_provider: &dyn AuthoritySetForFinalityProver<Block>,
-	authorities_set_id: u64,
+	authorities_set_id: Option<u64>,
 	begin: Block::Hash,
 	end: Block::Hash,
 ) -> ::sp_blockchain::Result<Option<Vec<u8>>>
@@ -353,7 +397,7 @@ pub(crate) fn prove_finality<Block: BlockT, B: BlockchainBackend<Block>, J>(
 			let justifies_authority_set_change = proof_fragment.authorities_proof.is_some();
 			if justifies_end_block || justifies_authority_set_change {
 				// check if the proof is generated by the requested authority set
-				if finality_proof.is_empty() {
+				if let (true, Some(authorities_set_id)) = (finality_proof.is_empty(), authorities_set_id) {
 					let justification_check_result = J::decode_and_verify(
 						&proof_fragment.justification,
 						authorities_set_id,
@@ -423,6 +467,37 @@ pub(crate) fn prove_finality<Block: BlockT, B: BlockchainBackend<Block>, J>(
 	}
 }
 
+/// Prepare proof-of-finality for the given block, for a caller that knows nothing about the chain.
+///
+/// The proof is not restricted to any authority set, it is up to the caller to check it.
+pub(crate) fn prove_block_finality<Block: BlockT, B: BlockchainBackend<Block>, J>(
+	blockchain: &B,
+	authorities_provider: &dyn AuthoritySetForFinalityProver<Block>,
+	block: NumberFor<Block>,
+) -> Result<Vec<u8>, FinalityProofError>
+	where
+		J: ProvableJustification<Block::Header>,
+{
+	if block > blockchain.info().finalized_number {
+		return Err(FinalityProofError::BlockNotYetFinalized);
+	}
+
+	// the genesis block is final by definition and has no justification
+	if block.is_zero() {
+		return Err(FinalityProofError::ProofUnavailable);
+	}
+
+	let header = blockchain.header(BlockId::Number(block))?
+		.ok_or(FinalityProofError::ProofUnavailable)?;
+	prove_finality::<_, _, J>(
+		blockchain,
+		authorities_provider,
+		None,
+		*header.parent_hash(),
+		header.hash(),
+	)?.ok_or(FinalityProofError::ProofUnavailable)
+}
+
 /// Check GRANDPA proof-of-finality for the given block.
 ///
 /// Returns the vector of headers that MUST be validated + imported
@@ -683,12 +758,54 @@ pub(crate) mod tests {
 				|_| unreachable!("should return before calling GetAuthorities"),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(2).hash(),
 			header(2).hash(),
 		).unwrap_err();
 	}
 
+	#[test]
+	fn block_finality_is_proved_without_known_authority_set() {
+		let blockchain = test_blockchain();
+		let authorities = vec![(AuthorityId::from_slice(&[1u8; 32]), 1u64)];
+		let just4 = TestJustification((5, authorities.clone()), vec![4]).encode();
+		blockchain.insert(header(4).hash(), header(4), Some(just4.clone()), None, NewBlockState::Final).unwrap();
+		blockchain.insert(header(5).hash(), header(5), None, None, NewBlockState::Best).unwrap();
+		let authorities_provider = (
+			move |_: BlockId<Block>| -> ClientResult<AuthorityList> { Ok(authorities.clone()) },
+			|_: BlockId<Block>| -> ClientResult<StorageProof> { unreachable!("authorities do not change") },
+		);
+
+		// block 2 has no justification of its own, so it is proved by the one of block 3
+		let proof_of_2: FinalityProof = Decode::decode(&mut &prove_block_finality::<_, _, TestJustification>(
+			&blockchain,
+			&authorities_provider,
+			2,
+		).unwrap()[..]).unwrap();
+		assert_eq!(proof_of_2.last().unwrap().block, header(3).hash());
+
+		let proof_of_4: FinalityProof = Decode::decode(&mut &prove_block_finality::<_, _, TestJustification>(
+			&blockchain,
+			&authorities_provider,
+			4,
+		).unwrap()[..]).unwrap();
+		assert_eq!(proof_of_4, vec![FinalityProofFragment {
+			block: header(4).hash(),
+			justification: just4,
+			unknown_headers: Vec::new(),
+			authorities_proof: None,
+		}]);
+
+		assert_matches::assert_matches!(
+			prove_block_finality::<_, _, TestJustification>(&blockchain, &authorities_provider, 5),
+			Err(FinalityProofError::BlockNotYetFinalized)
+		);
+		assert_matches::assert_matches!(
+			prove_block_finality::<_, _, TestJustification>(&blockchain, &authorities_provider, 0),
+			Err(FinalityProofError::ProofUnavailable)
+		);
+	}
+
 	#[test]
 	fn finality_proof_is_none_if_no_more_last_finalized_blocks() {
 		let blockchain = test_blockchain();
@@ -703,7 +820,7 @@ pub(crate) mod tests {
 				|_| unreachable!("should return before calling GetAuthorities"),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(4).hash(),
 		).unwrap();
@@ -729,7 +846,7 @@ pub(crate) mod tests {
 				|_| unreachable!("should return before calling GetAuthorities"),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			side_header(4).hash(),
 			second_side_header(5).hash(),
 		).unwrap_err();
@@ -748,7 +865,7 @@ pub(crate) mod tests {
 				|_| Ok(vec![(AuthorityId::from_slice(&[1u8; 32]), 1u64)]),
 				|_| unreachable!("authorities didn't change => ProveAuthorities won't be called"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(4).hash(),
 		).unwrap();
@@ -772,7 +889,7 @@ pub(crate) mod tests {
 				|_| Ok(authorities.clone()),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(5).hash(),
 		).unwrap().unwrap()[..]).unwrap();
@@ -798,7 +915,7 @@ pub(crate) mod tests {
 				|_| Ok(vec![(AuthorityId::from_slice(&[1u8; 32]), 1u64)]),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(5).hash(),
 		).unwrap().unwrap()[..]).unwrap();
@@ -842,7 +959,7 @@ pub(crate) mod tests {
 					_ => unreachable!("no other authorities should be proved: {:?}", block_id),
 				},
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(6).hash(),
 		).unwrap().unwrap()[..]).unwrap();
@@ -1019,7 +1136,7 @@ pub(crate) mod tests {
 				|_| Ok(vec![(AuthorityId::from_slice(&[1u8; 32]), 1u64)]),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(4).hash(),
 		).unwrap();
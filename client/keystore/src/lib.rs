@@ -17,7 +17,9 @@
 //! Keystore (and session key management) for ed25519 based chains like Polkadot.
 
 #![warn(missing_docs)]
-use std::{collections::{HashMap, HashSet}, path::PathBuf, fs::{self, File}, io::{self, Write}, sync::Arc};
+use std::{
+	collections::{HashMap, HashSet}, path::{Path, PathBuf}, fs::{self, File}, io::{self, Write}, sync::Arc,
+};
 use sp_core::{
 	crypto::{IsWrappedBy, CryptoTypePublicPair, KeyTypeId, Pair as PairT, ExposeSecret, SecretString, Public},
 	traits::{BareCryptoStore, Error as TraitError},
@@ -95,6 +97,9 @@ pub struct Store {
 	path: Option<PathBuf>,
 	/// Map over `(KeyTypeId, Raw public key)` -> `Key phrase/seed`
 	additional: HashMap<(KeyTypeId, Vec<u8>), String>,
+	/// Map over `(KeyTypeId, Raw public key)` -> `Key phrase` of the keys generated by an
+	/// in-memory store, which has no file system store to place them into.
+	generated: HashMap<(KeyTypeId, Vec<u8>), String>,
 	password: Option<SecretString>,
 }
 
@@ -106,7 +111,12 @@ impl Store {
 		let path = path.into();
 		fs::create_dir_all(&path)?;
 
-		let instance = Self { path: Some(path), additional: HashMap::new(), password };
+		let instance = Self {
+			path: Some(path),
+			additional: HashMap::new(),
+			generated: HashMap::new(),
+			password,
+		};
 		Ok(Arc::new(RwLock::new(instance)))
 	}
 
@@ -115,6 +125,7 @@ impl Store {
 		Arc::new(RwLock::new(Self {
 			path: None,
 			additional: HashMap::new(),
+			generated: HashMap::new(),
 			password: None
 		}))
 	}
@@ -171,21 +182,27 @@ impl Store {
 
 	/// Generate a new key.
 	///
-	/// Places it into the file system store.
-	pub fn generate_by_type<Pair: PairT>(&self, key_type: KeyTypeId) -> Result<Pair> {
+	/// Places it into the file system store, or in memory for an in-memory store.
+	pub fn generate_by_type<Pair: PairT>(&mut self, key_type: KeyTypeId) -> Result<Pair> {
 		let (pair, phrase, _) = Pair::generate_with_phrase(self.password());
-		if let Some(path) = self.key_file_path(pair.public().as_slice(), key_type) {
-			let mut file = File::create(path)?;
-			serde_json::to_writer(&file, &phrase)?;
-			file.flush()?;
+		let public = pair.public().to_raw_vec();
+		match self.key_file_path(&public, key_type) {
+			Some(path) => {
+				let mut file = File::create(path)?;
+				serde_json::to_writer(&file, &phrase)?;
+				file.flush()?;
+			},
+			None => {
+				self.generated.insert((key_type, public), phrase);
+			},
 		}
 		Ok(pair)
 	}
 
 	/// Generate a new key.
 	///
-	/// Places it into the file system store.
-	pub fn generate<Pair: AppPair>(&self) -> Result<Pair> {
+	/// Places it into the file system store, or in memory for an in-memory store.
+	pub fn generate<Pair: AppPair>(&mut self) -> Result<Pair> {
 		self.generate_by_type::<Pair::Generic>(Pair::ID).map(Into::into)
 	}
 
@@ -209,11 +226,54 @@ impl Store {
 		self.insert_ephemeral_from_seed_by_type::<Pair::Generic>(seed, Pair::ID).map(Into::into)
 	}
 
+	/// Remove a key, from memory and from the file system store.
+	///
+	/// Returns `false` if there was no such key.
+	pub fn remove_by_type(&mut self, public: &[u8], key_type: KeyTypeId) -> Result<bool> {
+		let key = (key_type, public.to_vec());
+		let mut removed = self.additional.remove(&key).is_some();
+		removed |= self.generated.remove(&key).is_some();
+		if let Some(path) = self.key_file_path(public, key_type) {
+			match fs::remove_file(path) {
+				Ok(()) => removed = true,
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+				Err(e) => return Err(Error::Io(e)),
+			}
+		}
+		Ok(removed)
+	}
+
+	/// Copy a key from `other`.
+	///
+	/// Keys `other` created from a seed are only kept in memory, the others are placed into the
+	/// file system store. Both stores must use the same password.
+	pub fn insert_from(&mut self, other: &Store, public: &[u8], key_type: KeyTypeId) -> Result<()> {
+		let key = (key_type, public.to_vec());
+		if let Some(seed) = other.get_additional_pair(public, key_type) {
+			self.additional.insert(key, seed.clone());
+			return Ok(())
+		}
+
+		if self.password() != other.password() {
+			return Err(Error::InvalidPassword)
+		}
+		let phrase = other.key_phrase_by_type(public, key_type)?;
+		if self.path.is_some() {
+			self.insert_unknown(key_type, &phrase, public)
+		} else {
+			self.generated.insert(key, phrase);
+			Ok(())
+		}
+	}
+
 	/// Get the key phrase for a given public key and key type.
 	fn key_phrase_by_type(&self, public: &[u8], key_type: KeyTypeId) -> Result<String> {
 		if let Some(phrase) = self.get_additional_pair(public, key_type) {
 			return Ok(phrase.clone())
 		}
+		if let Some(phrase) = self.generated.get(&(key_type, public.to_vec())) {
+			return Ok(phrase.clone())
+		}
 
 		let path = self.key_file_path(public, key_type).ok_or_else(|| Error::Unavailable)?;
 		let file = File::open(path)?;
@@ -268,10 +328,33 @@ impl Store {
 		Some(buf)
 	}
 
+	/// Writes a snapshot of the keys stored on disk to the `backup_path` directory.
+	///
+	/// See [`Snapshot::write_to`].
+	pub fn backup(&self, backup_path: &Path) -> Result<()> {
+		self.snapshot()?.write_to(backup_path)
+	}
+
+	/// Reads the keys stored on disk.
+	///
+	/// The key files are kept as they are, so that the snapshot holds nothing the keystore doesn't
+	/// already write to disk. In particular, keys that only live in memory are left out.
+	pub fn snapshot(&self) -> Result<Snapshot> {
+		let path = self.path.as_ref().ok_or_else(|| Error::Unavailable)?;
+		let mut files = Vec::new();
+		for entry in fs::read_dir(path)? {
+			let entry = entry?;
+			if entry.file_type()?.is_file() && is_key_file_name(&entry.file_name()) {
+				files.push((entry.file_name(), fs::read(entry.path())?));
+			}
+		}
+		Ok(Snapshot { files })
+	}
+
 	/// Returns a list of raw public keys filtered by `KeyTypeId`
 	fn raw_public_keys(&self, id: KeyTypeId) -> Result<Vec<Vec<u8>>> {
 		let mut public_keys: Vec<Vec<u8>> = self.additional.keys()
-			.into_iter()
+			.chain(self.generated.keys())
 			.filter_map(|k| if k.0 == id { Some(k.1.clone()) } else { None })
 			.collect();
 
@@ -300,6 +383,66 @@ impl Store {
 	}
 }
 
+/// Whether `name` is the name of a key file, i.e. a hex encoded key type followed by a public key.
+fn is_key_file_name(name: &std::ffi::OsStr) -> bool {
+	name.to_str().and_then(|name| hex::decode(name).ok()).map_or(false, |hex| hex.len() > 4)
+}
+
+/// Returns `path` with `.suffix` appended to its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut file_name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+	file_name.push(".");
+	file_name.push(suffix);
+	path.with_file_name(file_name)
+}
+
+/// The keys stored on disk by a keystore, see [`Store::snapshot`].
+pub struct Snapshot {
+	files: Vec<(std::ffi::OsString, Vec<u8>)>,
+}
+
+impl Snapshot {
+	/// Writes the snapshot to the `backup_path` directory.
+	///
+	/// The backup is itself a keystore that opens with the same password. The snapshot is written
+	/// next to `backup_path` before being moved in place, so that a partial snapshot never
+	/// replaces the previous one.
+	pub fn write_to(&self, backup_path: &Path) -> Result<()> {
+		let staging = with_suffix(backup_path, "tmp");
+		let previous = with_suffix(backup_path, "old");
+
+		if staging.exists() {
+			fs::remove_dir_all(&staging)?;
+		}
+		fs::create_dir_all(&staging)?;
+		for (name, content) in &self.files {
+			fs::write(staging.join(name), content)?;
+		}
+
+		if backup_path.exists() {
+			if previous.exists() {
+				fs::remove_dir_all(&previous)?;
+			}
+			fs::rename(backup_path, &previous)?;
+		}
+		fs::rename(&staging, backup_path)?;
+		if previous.exists() {
+			fs::remove_dir_all(&previous)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Drop for Snapshot {
+	fn drop(&mut self) {
+		use sp_core::crypto::Zeroize;
+		for (_, content) in &mut self.files {
+			content.zeroize();
+		}
+	}
+}
+
 impl BareCryptoStore for Store {
 	fn keys(
 		&self,
@@ -503,6 +646,58 @@ mod tests {
 		assert!(store.read().key_pair::<ed25519::AppPair>(&pair.public()).is_err());
 	}
 
+	#[test]
+	fn keys_can_be_removed() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+
+		let stored: ed25519::AppPair = store.write().insert("//Alice").unwrap();
+		let ephemeral: ed25519::AppPair = store.write()
+			.insert_ephemeral_from_seed("//Bob")
+			.unwrap();
+		let kept: ed25519::AppPair = store.write().insert("//Charlie").unwrap();
+		let public_keys = || store.read().public_keys::<ed25519::AppPublic>().unwrap();
+		assert_eq!(public_keys().len(), 3);
+
+		let key_type = ed25519::AppPublic::ID;
+		let ephemeral = ephemeral.public().to_raw_vec();
+		assert!(store.write().remove_by_type(&ephemeral, key_type).unwrap());
+		assert!(!store.write().remove_by_type(&ephemeral, key_type).unwrap());
+		let stored = stored.public().to_raw_vec();
+		assert!(store.write().remove_by_type(&stored, key_type).unwrap());
+		assert!(!store.write().remove_by_type(&stored, key_type).unwrap());
+		assert_eq!(public_keys(), vec![kept.public()]);
+		assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn keys_can_be_copied_from_an_in_memory_store() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		let in_memory = Store::new_in_memory();
+
+		let generated: ed25519::AppPair = in_memory.write().generate().unwrap();
+		let ephemeral: ed25519::AppPair = in_memory.write()
+			.insert_ephemeral_from_seed("//Alice")
+			.unwrap();
+		assert_eq!(in_memory.read().public_keys::<ed25519::AppPublic>().unwrap().len(), 2);
+
+		let key_type = ed25519::AppPublic::ID;
+		for public in &[generated.public(), ephemeral.public()] {
+			store.write().insert_from(&in_memory.read(), &public.to_raw_vec(), key_type).unwrap();
+			let copied = store.read().key_pair::<ed25519::AppPair>(public).unwrap();
+			assert_eq!(copied.public(), *public);
+		}
+
+		// Only the generated key is placed into the file system store.
+		drop(store);
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		assert_eq!(
+			store.read().public_keys::<ed25519::AppPublic>().unwrap(),
+			vec![generated.public()],
+		);
+	}
+
 	#[test]
 	fn password_being_used() {
 		let password = String::from("password");
@@ -589,4 +784,40 @@ mod tests {
 			store.read().sr25519_public_keys(SR25519).is_empty(),
 		);
 	}
+
+	#[test]
+	fn backup_opens_with_the_same_password() {
+		let password = || Some(FromStr::from_str("password").unwrap());
+		let temp_dir = TempDir::new().unwrap();
+		let backup_path = temp_dir.path().join("backup");
+		let store = Store::open(temp_dir.path().join("keystore"), password()).unwrap();
+
+		let old_key: sr25519::AppPair = store.write().generate().unwrap();
+		store.read().backup(&backup_path).unwrap();
+
+		// rotate the keys, the next backup replaces the previous one.
+		let new_key: sr25519::AppPair = store.write().generate().unwrap();
+		let ephemeral_key: sr25519::AppPair = store.write()
+			.insert_ephemeral_from_seed("//Alice")
+			.unwrap();
+		store.read().backup(&backup_path).unwrap();
+		assert!(!with_suffix(&backup_path, "tmp").exists());
+		assert!(!with_suffix(&backup_path, "old").exists());
+
+		let backup = Store::open(&backup_path, password()).unwrap();
+		let mut public_keys = backup.read().public_keys::<sr25519::AppPublic>().unwrap();
+		public_keys.sort();
+		let mut expected = vec![old_key.public(), new_key.public()];
+		expected.sort();
+		assert_eq!(public_keys, expected);
+		assert_eq!(
+			backup.read().key_pair::<sr25519::AppPair>(&new_key.public()).unwrap().public(),
+			new_key.public(),
+		);
+		assert!(backup.read().key_pair::<sr25519::AppPair>(&ephemeral_key.public()).is_err());
+
+		// the backup is just as useless as the keystore without the password.
+		let backup = Store::open(&backup_path, None).unwrap();
+		assert!(backup.read().key_pair::<sr25519::AppPair>(&new_key.public()).is_err());
+	}
 }
@@ -31,6 +31,7 @@ use sp_externalities::Extensions;
 use sp_state_machine::{
 	self, Backend as StateBackend, OverlayedChanges, ExecutionStrategy, create_proof_check_backend,
 	execution_proof_check_on_trie_backend, ExecutionManager, StorageProof, CloneableSpawn,
+	ExecutionTrace,
 };
 use hash_db::Hasher;
 
@@ -166,6 +167,15 @@ impl<Block, B, Local> CallExecutor<Block> for
 		Err(ClientError::NotAvailableOnLightClient)
 	}
 
+	fn trace_call(
+		&self,
+		_id: &BlockId<Block>,
+		_method: &str,
+		_call_data: &[u8],
+	) -> ClientResult<(Vec<u8>, ExecutionTrace)> {
+		Err(ClientError::NotAvailableOnLightClient)
+	}
+
 	fn native_runtime_version(&self) -> Option<&NativeVersion> {
 		None
 	}
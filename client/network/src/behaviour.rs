@@ -15,8 +15,8 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{
-	config::{ProtocolId, Role}, block_requests, light_client_handler, finality_requests,
-	peer_info, discovery::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut},
+	config::{ProtocolId, Role}, block_archive, block_requests, light_client_handler,
+	finality_requests, peer_info, discovery::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut},
 	protocol::{message::{self, Roles}, CustomMessageOutcome, Protocol},
 	Event, ObservedRole, DhtEvent, ExHashT,
 };
@@ -26,6 +26,7 @@ use libp2p::NetworkBehaviour;
 use libp2p::core::{Multiaddr, PeerId, PublicKey};
 use libp2p::kad::record;
 use libp2p::swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters};
+use libp2p::swarm::toggle::Toggle;
 use log::debug;
 use sp_consensus::{BlockOrigin, import_queue::{IncomingBlock, Origin}};
 use sp_runtime::{traits::{Block as BlockT, NumberFor}, ConsensusEngineId, Justification};
@@ -52,8 +53,12 @@ pub struct Behaviour<B: BlockT, H: ExHashT> {
 	block_requests: block_requests::BlockRequests<B>,
 	/// Finality proof request handling.
 	finality_proof_requests: finality_requests::FinalityProofRequests<B>,
+	/// Finality proof request handling on the secondary protocol, if configured.
+	secondary_finality_proof_requests: Toggle<finality_requests::FinalityProofRequests<B>>,
 	/// Light client request handling.
 	light_client_handler: light_client_handler::LightClientHandler<B>,
+	/// Block archive serving, and download from a trusted source.
+	block_archive: block_archive::BlockArchive<B>,
 
 	/// Queue of events to produce for the outside.
 	#[behaviour(ignore)]
@@ -114,7 +119,9 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		local_public_key: PublicKey,
 		block_requests: block_requests::BlockRequests<B>,
 		finality_proof_requests: finality_requests::FinalityProofRequests<B>,
+		secondary_finality_proof_requests: Option<finality_requests::FinalityProofRequests<B>>,
 		light_client_handler: light_client_handler::LightClientHandler<B>,
+		block_archive: block_archive::BlockArchive<B>,
 		disco_config: DiscoveryConfig,
 	) -> Self {
 		Behaviour {
@@ -123,12 +130,32 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 			discovery: disco_config.finish(),
 			block_requests,
 			finality_proof_requests,
+			secondary_finality_proof_requests: secondary_finality_proof_requests.into(),
 			light_client_handler,
+			block_archive,
 			events: VecDeque::new(),
 			role,
 		}
 	}
 
+	/// Requests a finality proof from `target` on the secondary finality proof protocol.
+	///
+	/// The response is reported as an [`Event::SecondaryFinalityProof`].
+	pub fn request_secondary_finality_proof(
+		&mut self,
+		target: &PeerId,
+		block_hash: B::Hash,
+		request: Vec<u8>,
+	) {
+		match self.secondary_finality_proof_requests.as_mut() {
+			Some(requests) => requests.send_request(target, block_hash, request),
+			None => log::warn!(
+				target: "sync",
+				"Requested a secondary finality proof, but no secondary protocol is configured",
+			),
+		}
+	}
+
 	/// Returns the list of nodes that we know exist in the network.
 	pub fn known_peers(&mut self) -> HashSet<PeerId> {
 		self.discovery.known_peers()
@@ -203,6 +230,12 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		&mut self.substrate
 	}
 
+	/// Pauses the download of blocks while the import queue is saturated.
+	pub fn set_import_queue_saturated(&mut self, saturated: bool) {
+		self.substrate.set_import_queue_saturated(saturated);
+		self.block_archive.set_import_queue_saturated(saturated);
+	}
+
 	/// Start querying a record from the DHT. Will later produce either a `ValueFound` or a `ValueNotFound` event.
 	pub fn get_value(&mut self, key: &record::Key) {
 		self.discovery.get_value(key);
@@ -344,7 +377,18 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<block_requests::Event<B
 impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<finality_requests::Event<B>> for Behaviour<B, H> {
 	fn inject_event(&mut self, event: finality_requests::Event<B>) {
 		match event {
-			finality_requests::Event::Response { peer, block_hash, proof } => {
+			finality_requests::Event::Response { peer, protocol, block_hash, proof } => {
+				let is_secondary = self.secondary_finality_proof_requests.as_ref()
+					.map_or(false, |requests| requests.protocol_name() == &protocol[..]);
+				if is_secondary {
+					self.events.push_back(BehaviourOut::Event(Event::SecondaryFinalityProof {
+						remote: peer,
+						block_hash: block_hash.encode(),
+						proof,
+					}));
+					return;
+				}
+
 				let response = message::FinalityProofResponse {
 					id: 0,
 					block: block_hash,
@@ -361,6 +405,25 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<finality_requests::Even
 	}
 }
 
+impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<block_archive::Event<B>>
+	for Behaviour<B, H> {
+	fn inject_event(&mut self, event: block_archive::Event<B>) {
+		match event {
+			block_archive::Event::Blocks { peer, blocks } => {
+				debug!(
+					target: "sync",
+					"Received {} blocks from the block archive of {}",
+					blocks.len(),
+					peer,
+				);
+				self.events.push_back(
+					BehaviourOut::BlockImport(BlockOrigin::NetworkInitialSync, blocks)
+				);
+			}
+		}
+	}
+}
+
 impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<peer_info::PeerInfoEvent>
 	for Behaviour<B, H> {
 	fn inject_event(&mut self, event: peer_info::PeerInfoEvent) {
@@ -0,0 +1,640 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! `NetworkBehaviour` implementation of the block archive protocol, used to bootstrap a node from
+//! a trusted peer.
+//!
+//! A request asks for the blocks of the best chain of the remote starting at a given number, and
+//! is answered with as many consecutive blocks as fit in a response. Both are SCALE encoded. An
+//! empty response means that the remote has no block at the requested number.
+//!
+//! On the serving side, the protocol is only advertised when enabled. On the requesting side, the
+//! trusted source is dialed and asked for the blocks following our best one, range after range,
+//! until it has nothing more to give. The blocks received are handed over to the import queue,
+//! which verifies them like any other.
+
+use bytes::Bytes;
+use codec::{Compact, Encode, Decode};
+use crate::{chain::Client, config::{MultiaddrWithPeerId, ProtocolId}};
+use futures::{future::BoxFuture, prelude::*, stream::FuturesUnordered};
+use futures_timer::Delay;
+use libp2p::{
+	core::{
+		Multiaddr,
+		PeerId,
+		connection::ConnectionId,
+		upgrade::{InboundUpgrade, OutboundUpgrade, ReadOneError, UpgradeInfo},
+		upgrade::{read_one, write_one},
+	},
+	swarm::{
+		DialPeerCondition,
+		NegotiatedSubstream,
+		NetworkBehaviour,
+		NetworkBehaviourAction,
+		NotifyHandler,
+		OneShotHandler,
+		OneShotHandlerConfig,
+		PollParameters,
+		SubstreamProtocol,
+	},
+};
+use sp_consensus::import_queue::IncomingBlock;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block, Header, NumberFor, One},
+	Justification,
+};
+use std::{
+	collections::VecDeque,
+	io,
+	iter,
+	marker::PhantomData,
+	sync::Arc,
+	time::Duration,
+	task::{Context, Poll},
+};
+
+/// Type alias for convenience.
+pub type Error = Box<dyn std::error::Error + 'static>;
+
+/// Delay before dialing the trusted source again after a failed attempt, doubled after each
+/// failure in a row.
+const REDIAL_DELAY: Duration = Duration::from_secs(5);
+
+/// Number of failed attempts in a row after which the trusted source is given up.
+const MAX_FAILURES: u32 = 5;
+
+/// Request for the blocks of the best chain, starting at `from`.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ArchiveRequest<Number> {
+	/// Number of the first block.
+	pub from: Number,
+	/// Maximum number of blocks to send back.
+	pub max_blocks: u32,
+}
+
+/// A block, as found in the responses to `ArchiveRequest`s.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ArchivedBlock<Header, Extrinsic> {
+	/// Block header.
+	pub header: Header,
+	/// Block body.
+	pub body: Vec<Extrinsic>,
+	/// Block justification, if any.
+	pub justification: Option<Justification>,
+}
+
+/// Event generated by the block archive behaviour.
+#[derive(Debug)]
+pub enum Event<B: Block> {
+	/// Blocks received from the trusted source, to be imported.
+	Blocks {
+		/// The trusted source.
+		peer: PeerId,
+		/// The blocks, in ascending order.
+		blocks: Vec<IncomingBlock<B>>,
+	},
+}
+
+/// Configuration options for `BlockArchive`.
+#[derive(Debug, Clone)]
+pub struct Config {
+	max_request_len: usize,
+	max_response_len: usize,
+	max_blocks_per_request: u32,
+	inactivity_timeout: Duration,
+	protocol: Bytes,
+}
+
+impl Config {
+	/// Create a fresh configuration with the following options:
+	///
+	/// - max. request size = 1 KiB
+	/// - max. response size = 16 MiB
+	/// - max. blocks per request = 128
+	/// - inactivity timeout = 15s
+	pub fn new(id: &ProtocolId) -> Self {
+		let mut c = Config {
+			max_request_len: 1024,
+			max_response_len: 16 * 1024 * 1024,
+			max_blocks_per_request: 128,
+			inactivity_timeout: Duration::from_secs(15),
+			protocol: Bytes::new(),
+		};
+		c.set_protocol(id);
+		c
+	}
+
+	/// Set protocol to use for upgrade negotiation.
+	pub fn set_protocol(&mut self, id: &ProtocolId) -> &mut Self {
+		let mut v = Vec::new();
+		v.extend_from_slice(b"/");
+		v.extend_from_slice(id.as_bytes());
+		v.extend_from_slice(b"/block-archive/1");
+		self.protocol = v.into();
+		self
+	}
+}
+
+/// State of the download from the trusted source.
+struct Source<B: Block> {
+	/// The trusted source.
+	peer: PeerId,
+	/// Its address.
+	address: Multiaddr,
+	/// Number of the next block to request, `None` once the source has no more blocks.
+	next: Option<NumberFor<B>>,
+	/// Whether we're connected to the source.
+	connected: bool,
+	/// Whether we're trying to connect to the source.
+	dialing: bool,
+	/// Delay before dialing the source again, after a failure.
+	redial: Option<Delay>,
+	/// Number of failed attempts since the last response.
+	failures: u32,
+	/// Whether a request is waiting for a response.
+	in_flight: bool,
+	/// Whether the requests are paused, because the import queue is saturated.
+	paused: bool,
+}
+
+impl<B: Block> Source<B> {
+	/// Registers a failed attempt, either delaying the next one or giving up on the source.
+	fn on_failure(&mut self) {
+		self.failures += 1;
+		if self.failures >= MAX_FAILURES {
+			log::warn!(
+				target: "sync",
+				"Giving up on the block archive of {} after {} failed attempts",
+				self.peer,
+				self.failures,
+			);
+			self.next = None;
+		} else {
+			self.redial = Some(Delay::new(REDIAL_DELAY * 2u32.pow(self.failures - 1)));
+		}
+	}
+}
+
+/// The block archive behaviour.
+pub struct BlockArchive<B: Block> {
+	/// This behaviour's configuration.
+	config: Config,
+	/// Blockchain client.
+	chain: Arc<dyn Client<B>>,
+	/// Whether the blocks of `chain` are served to other nodes.
+	serve: bool,
+	/// The download from the trusted source, if one is configured.
+	source: Option<Source<B>>,
+	/// Futures sending back the responses.
+	outgoing: FuturesUnordered<BoxFuture<'static, ()>>,
+	/// Events to return as soon as possible from `poll`.
+	pending_events: VecDeque<NetworkBehaviourAction<OutboundProtocol<B>, Event<B>>>,
+}
+
+impl<B> BlockArchive<B>
+where
+	B: Block,
+{
+	/// Initializes the behaviour.
+	///
+	/// The blocks of `chain` are served if `serve` is `true`, and the blocks following its best
+	/// one are downloaded from `source`, if any.
+	pub fn new(
+		cfg: Config,
+		chain: Arc<dyn Client<B>>,
+		serve: bool,
+		source: Option<MultiaddrWithPeerId>,
+	) -> Self {
+		let source = source.map(|source| Source {
+			peer: source.peer_id,
+			address: source.multiaddr,
+			next: Some(chain.info().best_number + One::one()),
+			connected: false,
+			dialing: false,
+			redial: None,
+			failures: 0,
+			in_flight: false,
+			paused: false,
+		});
+
+		BlockArchive {
+			config: cfg,
+			chain,
+			serve,
+			source,
+			outgoing: FuturesUnordered::new(),
+			pending_events: VecDeque::new(),
+		}
+	}
+
+	/// Pauses the download from the trusted source while the import queue is saturated.
+	pub fn set_import_queue_saturated(&mut self, saturated: bool) {
+		if let Some(source) = self.source.as_mut() {
+			source.paused = saturated;
+		}
+	}
+
+	/// Callback, invoked when a new request has been received from remote.
+	fn on_archive_request(&mut self, peer: &PeerId, request: &ArchiveRequest<NumberFor<B>>)
+		-> Result<Vec<ArchivedBlock<B::Header, B::Extrinsic>>, Error>
+	{
+		log::trace!(target: "sync", "Block archive request from {} for #{}", peer, request.from);
+
+		let max_blocks = request.max_blocks.min(self.config.max_blocks_per_request);
+		let mut blocks = Vec::new();
+		let mut number = request.from;
+		let mut total_size = Compact(max_blocks).encoded_size();
+		while blocks.len() < max_blocks as usize {
+			let header = match self.chain.header(BlockId::Number(number))? {
+				Some(header) => header,
+				None => break,
+			};
+			let hash = header.hash();
+			let block = ArchivedBlock {
+				body: self.chain.block_body(&BlockId::Hash(hash))?.unwrap_or_default(),
+				justification: self.chain.justification(&BlockId::Hash(hash))?,
+				header,
+			};
+
+			// The response must stay readable by the requester. A block that doesn't fit on its
+			// own can't be downloaded from us at all.
+			total_size += block.encoded_size();
+			if total_size > self.config.max_response_len {
+				if blocks.is_empty() {
+					return Err(format!(
+						"Block #{} is larger than the maximum response size of {} bytes",
+						number,
+						self.config.max_response_len,
+					).into())
+				}
+				break
+			}
+
+			blocks.push(block);
+			number = number + One::one();
+		}
+
+		Ok(blocks)
+	}
+
+	/// Callback, invoked when a response has been received from the trusted source.
+	fn on_archive_response(
+		&mut self,
+		peer: PeerId,
+		blocks: Vec<ArchivedBlock<B::Header, B::Extrinsic>>,
+	) {
+		let source = match self.source.as_mut() {
+			Some(source) if source.peer == peer => source,
+			_ => return,
+		};
+		source.in_flight = false;
+		source.failures = 0;
+
+		let last = match blocks.last() {
+			Some(block) => *block.header.number(),
+			None => {
+				if let Some(next) = source.next.take() {
+					log::info!(
+						target: "sync",
+						"📦 Downloaded the block archive of {} up to #{}",
+						peer,
+						next - One::one(),
+					);
+				}
+				return
+			}
+		};
+		source.next = Some(last + One::one());
+
+		let blocks = blocks.into_iter().map(|block| IncomingBlock {
+			hash: block.header.hash(),
+			header: Some(block.header),
+			body: Some(block.body),
+			justification: block.justification,
+			origin: Some(peer.clone()),
+			allow_missing_state: false,
+			import_existing: false,
+		}).collect();
+
+		let ev = Event::Blocks { peer, blocks };
+		self.pending_events.push_back(NetworkBehaviourAction::GenerateEvent(ev));
+	}
+}
+
+impl<B> NetworkBehaviour for BlockArchive<B>
+where
+	B: Block
+{
+	type ProtocolsHandler = OneShotHandler<
+		InboundProtocol<B>,
+		OutboundProtocol<B>,
+		NodeEvent<B, NegotiatedSubstream>,
+	>;
+	type OutEvent = Event<B>;
+
+	fn new_handler(&mut self) -> Self::ProtocolsHandler {
+		let p = InboundProtocol {
+			max_request_len: self.config.max_request_len,
+			protocol: if self.serve {
+				Some(self.config.protocol.clone())
+			} else {
+				None
+			},
+			marker: PhantomData,
+		};
+		let mut cfg = OneShotHandlerConfig::default();
+		cfg.keep_alive_timeout = self.config.inactivity_timeout;
+		OneShotHandler::new(SubstreamProtocol::new(p), cfg)
+	}
+
+	fn addresses_of_peer(&mut self, peer: &PeerId) -> Vec<Multiaddr> {
+		match &self.source {
+			Some(source) if source.peer == *peer => vec![source.address.clone()],
+			_ => Vec::new(),
+		}
+	}
+
+	fn inject_connected(&mut self, peer: &PeerId) {
+		if let Some(source) = self.source.as_mut().filter(|s| s.peer == *peer) {
+			source.connected = true;
+			source.dialing = false;
+		}
+	}
+
+	fn inject_disconnected(&mut self, peer: &PeerId) {
+		if let Some(source) = self.source.as_mut().filter(|s| s.peer == *peer) {
+			source.connected = false;
+			// Losing the connection with a request in flight means the request failed, e.g.
+			// because the source doesn't serve its archive or sent back an invalid response.
+			if source.in_flight {
+				log::debug!(target: "sync", "Block archive request to {} failed", peer);
+				source.in_flight = false;
+				source.on_failure();
+			}
+		}
+	}
+
+	fn inject_dial_failure(&mut self, peer: &PeerId) {
+		if let Some(source) = self.source.as_mut().filter(|s| s.peer == *peer) {
+			log::debug!(target: "sync", "Failed to dial the block archive source {}", peer);
+			source.dialing = false;
+			source.on_failure();
+		}
+	}
+
+	fn inject_event(
+		&mut self,
+		peer: PeerId,
+		_connection: ConnectionId,
+		event: NodeEvent<B, NegotiatedSubstream>
+	) {
+		match event {
+			NodeEvent::Request(request, mut stream) => {
+				match self.on_archive_request(&peer, &request) {
+					Ok(blocks) => {
+						log::trace!("enqueueing block archive response for peer {}", peer);
+						let data = blocks.encode();
+						let future = async move {
+							if let Err(e) = write_one(&mut stream, data).await {
+								log::debug!("error writing block archive response: {}", e)
+							}
+						};
+						self.outgoing.push(future.boxed())
+					}
+					Err(e) => log::debug!(
+						"error handling block archive request from peer {}: {}",
+						peer,
+						e,
+					)
+				}
+			}
+			NodeEvent::Response(blocks) => self.on_archive_response(peer, blocks),
+		}
+	}
+
+	fn poll(&mut self, cx: &mut Context, _: &mut impl PollParameters)
+		-> Poll<NetworkBehaviourAction<OutboundProtocol<B>, Event<B>>>
+	{
+		if let Some(ev) = self.pending_events.pop_front() {
+			return Poll::Ready(ev);
+		}
+
+		while let Poll::Ready(Some(_)) = self.outgoing.poll_next_unpin(cx) {}
+
+		if let Some(source) = self.source.as_mut() {
+			if let Some(redial) = source.redial.as_mut() {
+				match redial.poll_unpin(cx) {
+					Poll::Ready(()) => source.redial = None,
+					Poll::Pending => return Poll::Pending,
+				}
+			}
+
+			let next = match source.next {
+				Some(next) if !source.in_flight && !source.paused => next,
+				_ => return Poll::Pending,
+			};
+
+			if !source.connected {
+				if !source.dialing {
+					source.dialing = true;
+					return Poll::Ready(NetworkBehaviourAction::DialPeer {
+						peer_id: source.peer.clone(),
+						condition: DialPeerCondition::Disconnected,
+					});
+				}
+				return Poll::Pending;
+			}
+
+			let request = ArchiveRequest {
+				from: next,
+				max_blocks: self.config.max_blocks_per_request,
+			};
+			log::trace!(
+				target: "sync",
+				"Requesting the block archive of {} from #{}",
+				source.peer,
+				next,
+			);
+			source.in_flight = true;
+			return Poll::Ready(NetworkBehaviourAction::NotifyHandler {
+				peer_id: source.peer.clone(),
+				handler: NotifyHandler::Any,
+				event: OutboundProtocol {
+					request: request.encode(),
+					max_response_size: self.config.max_response_len,
+					protocol: self.config.protocol.clone(),
+					marker: PhantomData,
+				},
+			});
+		}
+
+		Poll::Pending
+	}
+}
+
+/// Output type of inbound and outbound substream upgrades.
+#[derive(Debug)]
+pub enum NodeEvent<B: Block, T> {
+	/// Incoming request from remote and substream to use for the response.
+	Request(ArchiveRequest<NumberFor<B>>, T),
+	/// Incoming response from remote.
+	Response(Vec<ArchivedBlock<B::Header, B::Extrinsic>>),
+}
+
+/// Substream upgrade protocol.
+///
+/// We attempt to parse an incoming SCALE encoded `ArchiveRequest`, which will be handled by the
+/// `BlockArchive` behaviour.
+#[derive(Debug, Clone)]
+pub struct InboundProtocol<B> {
+	/// The max. request length in bytes.
+	max_request_len: usize,
+	/// The protocol to use during upgrade negotiation. If `None`, then the incoming protocol
+	/// is simply disabled.
+	protocol: Option<Bytes>,
+	/// Marker to pin the block type.
+	marker: PhantomData<B>,
+}
+
+impl<B: Block> UpgradeInfo for InboundProtocol<B> {
+	type Info = Bytes;
+	// This iterator will return either 0 elements if `self.protocol` is `None`, or 1 element if
+	// it is `Some`.
+	type InfoIter = std::option::IntoIter<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		self.protocol.clone().into_iter()
+	}
+}
+
+impl<B, T> InboundUpgrade<T> for InboundProtocol<B>
+where
+	B: Block,
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = NodeEvent<B, T>;
+	type Error = ReadOneError;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_inbound(self, mut s: T, _: Self::Info) -> Self::Future {
+		async move {
+			let vec = read_one(&mut s, self.max_request_len).await?;
+			match ArchiveRequest::decode(&mut &vec[..]) {
+				Ok(r) => Ok(NodeEvent::Request(r, s)),
+				Err(e) => Err(ReadOneError::Io(io::Error::new(io::ErrorKind::Other, e.what())))
+			}
+		}.boxed()
+	}
+}
+
+/// Substream upgrade protocol.
+///
+/// Sends a request to remote and awaits the response.
+#[derive(Debug, Clone)]
+pub struct OutboundProtocol<B> {
+	/// The SCALE encoded request.
+	request: Vec<u8>,
+	/// The max. response length in bytes.
+	max_response_size: usize,
+	/// The protocol to use for upgrade negotiation.
+	protocol: Bytes,
+	/// Marker to pin the block type.
+	marker: PhantomData<B>,
+}
+
+impl<B: Block> UpgradeInfo for OutboundProtocol<B> {
+	type Info = Bytes;
+	type InfoIter = iter::Once<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		iter::once(self.protocol.clone())
+	}
+}
+
+impl<B, T> OutboundUpgrade<T> for OutboundProtocol<B>
+where
+	B: Block,
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = NodeEvent<B, T>;
+	type Error = ReadOneError;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_outbound(self, mut s: T, _: Self::Info) -> Self::Future {
+		async move {
+			write_one(&mut s, &self.request).await?;
+			let vec = read_one(&mut s, self.max_response_size).await?;
+
+			Decode::decode(&mut &vec[..])
+				.map(NodeEvent::Response)
+				.map_err(|e| ReadOneError::Io(io::Error::new(io::ErrorKind::Other, e.what())))
+		}.boxed()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use substrate_test_runtime_client::runtime::Block;
+
+	fn archive(source: Option<MultiaddrWithPeerId>) -> BlockArchive<Block> {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		BlockArchive::new(Config::new(&ProtocolId::from(&b"test"[..])), client, true, source)
+	}
+
+	#[test]
+	fn source_is_given_up_after_repeated_failures() {
+		let peer = PeerId::random();
+		let mut archive = archive(Some(MultiaddrWithPeerId {
+			multiaddr: Multiaddr::empty(),
+			peer_id: peer.clone(),
+		}));
+
+		// Each request lost with the connection delays the next attempt.
+		for failures in 1..MAX_FAILURES {
+			archive.inject_connected(&peer);
+			archive.source.as_mut().unwrap().in_flight = true;
+			archive.inject_disconnected(&peer);
+
+			let source = archive.source.as_ref().unwrap();
+			assert_eq!(source.failures, failures);
+			assert!(source.redial.is_some());
+			assert!(source.next.is_some());
+		}
+
+		// Idle connections closing aren't failures.
+		archive.inject_connected(&peer);
+		archive.inject_disconnected(&peer);
+		assert_eq!(archive.source.as_ref().unwrap().failures, MAX_FAILURES - 1);
+
+		archive.inject_dial_failure(&peer);
+		assert!(archive.source.as_ref().unwrap().next.is_none());
+	}
+
+	#[test]
+	fn block_larger_than_a_response_is_not_served() {
+		let mut archive = archive(None);
+		let peer = PeerId::random();
+		let request = ArchiveRequest { from: 0, max_blocks: 1 };
+		assert_eq!(archive.on_archive_request(&peer, &request).unwrap().len(), 1);
+
+		archive.config.max_response_len = 16;
+		assert!(archive.on_archive_request(&peer, &request).is_err());
+	}
+}
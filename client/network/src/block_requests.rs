@@ -26,7 +26,7 @@ use bytes::Bytes;
 use codec::{Encode, Decode};
 use crate::{
 	chain::Client,
-	config::ProtocolId,
+	config::{ProtocolId, DEFAULT_SYNC_REQUEST_TIMEOUT},
 	protocol::{message::{self, BlockAttributes}},
 	schema,
 };
@@ -133,14 +133,14 @@ impl Config {
 	/// - max. request size = 1 MiB
 	/// - max. response size = 16 MiB
 	/// - inactivity timeout = 15s
-	/// - request timeout = 40s
+	/// - request timeout = `DEFAULT_SYNC_REQUEST_TIMEOUT` (40s)
 	pub fn new(id: &ProtocolId) -> Self {
 		let mut c = Config {
 			max_block_data_response: 128,
 			max_request_len: 1024 * 1024,
 			max_response_len: 16 * 1024 * 1024,
 			inactivity_timeout: Duration::from_secs(15),
-			request_timeout: Duration::from_secs(40),
+			request_timeout: DEFAULT_SYNC_REQUEST_TIMEOUT,
 			protocol: Bytes::new(),
 		};
 		c.set_protocol(id);
@@ -171,6 +171,12 @@ impl Config {
 		self
 	}
 
+	/// Set the max. duration to wait for the response to one of our requests.
+	pub fn set_request_timeout(&mut self, v: Duration) -> &mut Self {
+		self.request_timeout = v;
+		self
+	}
+
 	/// Set protocol to use for upgrade negotiation.
 	pub fn set_protocol(&mut self, id: &ProtocolId) -> &mut Self {
 		let mut v = Vec::new();
@@ -843,3 +849,87 @@ pub(crate) fn build_protobuf_block_request<Hash: Encode, Number: Encode>(
 		max_blocks: max_blocks.unwrap_or(0),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_matches::assert_matches;
+	use futures::executor::block_on;
+	use substrate_test_runtime_client::runtime::Block;
+
+	struct EmptyPollParams(PeerId);
+
+	impl PollParameters for EmptyPollParams {
+		type SupportedProtocolsIter = iter::Empty<Vec<u8>>;
+		type ListenedAddressesIter = iter::Empty<Multiaddr>;
+		type ExternalAddressesIter = iter::Empty<Multiaddr>;
+
+		fn supported_protocols(&self) -> Self::SupportedProtocolsIter {
+			iter::empty()
+		}
+
+		fn listened_addresses(&self) -> Self::ListenedAddressesIter {
+			iter::empty()
+		}
+
+		fn external_addresses(&self) -> Self::ExternalAddressesIter {
+			iter::empty()
+		}
+
+		fn local_peer_id(&self) -> &PeerId {
+			&self.0
+		}
+	}
+
+	fn block_request() -> message::BlockRequest<Block> {
+		message::BlockRequest::<Block> {
+			id: 0,
+			fields: BlockAttributes::HEADER,
+			from: message::FromBlock::Number(1),
+			to: None,
+			direction: message::Direction::Ascending,
+			max: Some(1),
+		}
+	}
+
+	#[test]
+	fn unanswered_request_times_out_after_configured_timeout() {
+		let mut config = Config::new(&ProtocolId::from(&b"test"[..]));
+		config.set_request_timeout(Duration::from_millis(100));
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let mut behaviour = BlockRequests::<Block>::new(config, client);
+		let mut params = EmptyPollParams(PeerId::random());
+
+		// A peer that never answers our request.
+		let slow_peer = PeerId::random();
+		let endpoint = ConnectedPoint::Dialer { address: Multiaddr::empty() };
+		behaviour.inject_connection_established(&slow_peer, &ConnectionId::new(1), &endpoint);
+		behaviour.inject_connected(&slow_peer);
+		assert_matches!(behaviour.send_request(&slow_peer, block_request()), SendRequestOutcome::Ok);
+
+		let started = Instant::now();
+		let event = block_on(futures::future::poll_fn(|cx| {
+			match behaviour.poll(cx, &mut params) {
+				Poll::Ready(NetworkBehaviourAction::GenerateEvent(ev)) => Poll::Ready(ev),
+				// Sending the request to the handler.
+				Poll::Ready(_) => {
+					cx.waker().wake_by_ref();
+					Poll::Pending
+				},
+				Poll::Pending => Poll::Pending,
+			}
+		}));
+
+		assert!(started.elapsed() >= Duration::from_millis(100));
+		match event {
+			Event::RequestTimeout { peer, original_request, .. } => {
+				assert_eq!(peer, slow_peer);
+				assert_eq!(original_request, block_request());
+			},
+			_ => panic!("Unexpected event"),
+		}
+
+		// The request is over, so a new one can be issued to the peer without replacing it.
+		assert_matches!(behaviour.send_request(&slow_peer, block_request()), SendRequestOutcome::Ok);
+	}
+}
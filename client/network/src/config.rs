@@ -22,7 +22,11 @@
 //! See the documentation of [`Params`].
 
 pub use crate::chain::{Client, FinalityProofProvider};
-pub use crate::on_demand_layer::{AlwaysBadChecker, OnDemand};
+pub use crate::light_client_handler::Request as LightClientRequest;
+pub use crate::on_demand_layer::{
+	AlwaysBadChecker, BatchingScheduler, ImmediateScheduler, LightClientRequests, OnDemand,
+	RequestScheduler,
+};
 pub use libp2p::{identity, core::PublicKey, wasm_ext::ExtTransport, build_multiaddr};
 
 // Note: this re-export shouldn't be part of the public API of the crate and will be removed in
@@ -49,9 +53,18 @@ use std::{
 	net::Ipv4Addr,
 	path::{Path, PathBuf},
 	sync::Arc,
+	time::Duration,
 };
 use zeroize::Zeroize;
 
+/// Default time after which a block request to a peer that didn't answer is abandoned and the
+/// blocks are requested from another peer.
+///
+/// Peers serving large responses over a slow link may legitimately need a while to answer, and
+/// every timeout disconnects the peer and restarts the request from scratch. Lowering this value
+/// well below the default therefore tends to waste more bandwidth than it saves.
+pub const DEFAULT_SYNC_REQUEST_TIMEOUT: Duration = Duration::from_secs(40);
+
 /// Network initialization parameters.
 pub struct Params<B: BlockT, H: ExHashT> {
 	/// Assigned role for our node (full, light, ...).
@@ -78,6 +91,12 @@ pub struct Params<B: BlockT, H: ExHashT> {
 	/// This object, if `Some`, is used when we need a proof of finality from another node.
 	pub finality_proof_request_builder: Option<BoxFinalityProofRequestBuilder<B>>,
 
+	/// Additional finality proof protocol, e.g. serving proofs in the format expected by the
+	/// verifier of a bridged chain.
+	///
+	/// Requests are sent with `NetworkService::request_secondary_finality_proof`.
+	pub secondary_finality_proof: Option<SecondaryFinalityProofConfig<B>>,
+
 	/// The `OnDemand` object acts as a "receiver" for block data requests from the client.
 	/// If `Some`, the network worker will process these requests and answer them.
 	/// Normally used only for light clients.
@@ -101,10 +120,60 @@ pub struct Params<B: BlockT, H: ExHashT> {
 	/// Type to check incoming block announcements.
 	pub block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 
+	/// If `true`, the node never announces blocks to its peers, not even when explicitly asked
+	/// to. Blocks are still served to peers that request them.
+	pub suppress_block_announcements: bool,
+
+	/// Ignore the announcements of blocks this many blocks or more below our best block, unless
+	/// we are doing a major sync. `None` to consider all of them.
+	pub max_announce_block_age: Option<u32>,
+
+	/// Limit on the block announcements of a single peer that are validated at once, so that a
+	/// peer flooding us with announcements can't delay the validation of the others'. `None` to
+	/// validate all of them as they arrive.
+	pub block_announce_validation_limit: Option<AnnounceValidationLimit>,
+
+	/// Time after which a block request to an unresponsive peer times out and is issued to
+	/// another peer. See [`DEFAULT_SYNC_REQUEST_TIMEOUT`].
+	pub sync_request_timeout: Duration,
+
 	/// Registry for recording prometheus metrics to.
 	pub metrics_registry: Option<Registry>,
 }
 
+/// Limit on the block announcements of a single peer that are validated at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnnounceValidationLimit {
+	/// Maximum number of announcements of a single peer validated at once. Announcements are
+	/// validated in rounds, and each round validates at most this many of a peer's.
+	pub max_per_peer: usize,
+	/// What happens to the announcements of a peer in excess of `max_per_peer`.
+	pub overflow: AnnounceOverflow,
+}
+
+/// What happens to the block announcements of a peer in excess of its validation limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnounceOverflow {
+	/// They are queued and validated in the next rounds. A peer's queue holds at most a few dozen
+	/// announcements, the oldest ones are dropped beyond that.
+	Queue,
+	/// They are dropped.
+	Drop,
+}
+
+/// Configuration of a finality proof protocol used in addition to the one of the sync.
+pub struct SecondaryFinalityProofConfig<B: BlockT> {
+	/// Name of the protocol, appended to the protocol id of the chain, e.g. `bridge-finality/1`.
+	///
+	/// Must differ from `finality-proof/1`, which is used by the sync.
+	pub protocol_name: String,
+
+	/// Provider answering the requests of other nodes.
+	///
+	/// If `None`, we can only send requests on this protocol.
+	pub provider: Option<Arc<dyn FinalityProofProvider<B>>>,
+}
+
 /// Role of the local node.
 #[derive(Debug, Clone)]
 pub enum Role {
@@ -425,6 +494,16 @@ pub struct NetworkConfiguration {
 	pub max_parallel_downloads: u32,
 	/// Should we insert non-global addresses into the DHT?
 	pub allow_non_globals_in_dht: bool,
+	/// Serve the blocks of the chain to the nodes bootstrapping from us.
+	pub serve_block_archive: bool,
+	/// Trusted node to download the blocks following our best one from, when starting.
+	///
+	/// The blocks are still verified when imported.
+	pub block_archive_source: Option<MultiaddrWithPeerId>,
+	/// Reputation gained by a peer every time it provides a finality proof that we can import.
+	///
+	/// Light clients then prefer the peers that reliably serve finality proofs.
+	pub finality_proof_reputation_boost: i32,
 }
 
 impl NetworkConfiguration {
@@ -456,6 +535,9 @@ impl NetworkConfiguration {
 			},
 			max_parallel_downloads: 5,
 			allow_non_globals_in_dht: false,
+			serve_block_archive: false,
+			block_archive_source: None,
+			finality_proof_reputation_boost: 1 << 7,
 		}
 	}
 }
@@ -61,6 +61,17 @@ pub enum Error {
 		/// The invalid addresses.
 		addresses: Vec<Multiaddr>,
 	},
+	/// A justification was submitted for a block that isn't present in the local chain.
+	#[display(fmt = "Cannot import justification for unknown block {}", _0)]
+	#[from(ignore)]
+	UnknownBlock(String),
+	/// The network worker has shut down before the request could be processed.
+	#[display(fmt = "Network worker has shut down")]
+	WorkerShutdown,
+	/// The secondary finality proof protocol has the same name as the primary one.
+	#[display(fmt = "Secondary finality proof protocol conflicts with `{}`", _0)]
+	#[from(ignore)]
+	ConflictingFinalityProofProtocol(String),
 }
 
 // Make `Debug` use the `Display` implementation.
@@ -78,6 +89,9 @@ impl std::error::Error for Error {
 			Error::DuplicateBootnode { .. } => None,
 			Error::Prometheus(ref err) => Some(err),
 			Error::AddressesForAnotherTransport { .. } => None,
+			Error::UnknownBlock(_) => None,
+			Error::WorkerShutdown => None,
+			Error::ConflictingFinalityProofProtocol(_) => None,
 		}
 	}
 }
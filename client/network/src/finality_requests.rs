@@ -74,6 +74,8 @@ pub enum Event<B: Block> {
 	/// A response to a finality proof request has arrived.
 	Response {
 		peer: PeerId,
+		/// Name of the protocol the request was sent on.
+		protocol: Bytes,
 		/// Block hash originally passed to `send_request`.
 		block_hash: B::Hash,
 		/// Finality proof returned by the remote.
@@ -127,10 +129,18 @@ impl Config {
 
 	/// Set protocol to use for upgrade negotiation.
 	pub fn set_protocol(&mut self, id: &ProtocolId) -> &mut Self {
+		self.set_protocol_name(id, "finality-proof/1")
+	}
+
+	/// Set protocol to use for upgrade negotiation to `/<id>/<name>`.
+	///
+	/// Allows serving finality proofs in another format than the one used by the sync.
+	pub fn set_protocol_name(&mut self, id: &ProtocolId, name: &str) -> &mut Self {
 		let mut v = Vec::new();
 		v.extend_from_slice(b"/");
 		v.extend_from_slice(id.as_bytes());
-		v.extend_from_slice(b"/finality-proof/1");
+		v.extend_from_slice(b"/");
+		v.extend_from_slice(name.as_bytes());
 		self.protocol = v.into();
 		self
 	}
@@ -165,6 +175,11 @@ where
 		}
 	}
 
+	/// Returns the name of the protocol used by this behaviour.
+	pub fn protocol_name(&self) -> &[u8] {
+		&self.config.protocol
+	}
+
 	/// Issue a new finality proof request.
 	///
 	/// If the response doesn't arrive in time, or if the remote answers improperly, the target
@@ -277,6 +292,7 @@ where
 			NodeEvent::Response(response, block_hash) => {
 				let ev = Event::Response {
 					peer,
+					protocol: self.config.protocol.clone(),
 					block_hash,
 					proof: response.proof,
 				};
@@ -243,6 +243,7 @@
 //!
 
 mod behaviour;
+mod block_archive;
 mod block_requests;
 mod chain;
 mod peer_info;
@@ -302,6 +303,8 @@ pub struct NetworkStatus<B: BlockT> {
 	pub sync_state: SyncState,
 	/// Target sync block number.
 	pub best_seen_block: Option<NumberFor<B>>,
+	/// Highest block number received but not imported yet.
+	pub best_queued_block: Option<NumberFor<B>>,
 	/// Number of peers participating in syncing.
 	pub num_sync_peers: u32,
 	/// Total number of connected peers
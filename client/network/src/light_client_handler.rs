@@ -152,6 +152,11 @@ impl Config {
 		self
 	}
 
+	/// The max. request duration.
+	pub fn request_timeout(&self) -> Duration {
+		self.request_timeout
+	}
+
 	/// Set protocol to use for upgrade negotiation.
 	pub fn set_protocol(&mut self, id: &ProtocolId) -> &mut Self {
 		let mut vl = Vec::new();
@@ -20,7 +20,8 @@
 
 use crate::light_client_handler;
 
-use futures::{channel::oneshot, prelude::*};
+use futures::{channel::oneshot, prelude::*, stream::BoxStream};
+use futures_timer::Delay;
 use parking_lot::Mutex;
 use sc_client_api::{
 	FetchChecker, Fetcher, RemoteBodyRequest, RemoteCallRequest, RemoteChangesRequest,
@@ -29,7 +30,12 @@ use sc_client_api::{
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
 use sp_blockchain::Error as ClientError;
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor};
-use std::{collections::HashMap, pin::Pin, sync::Arc, task::Context, task::Poll};
+use std::{
+	cmp, collections::HashMap, mem, pin::Pin, sync::Arc, task::Context, task::Poll, time::Duration,
+};
+
+/// Stream of the requests issued by the light client through an [`OnDemand`].
+pub type LightClientRequests<B> = BoxStream<'static, light_client_handler::Request<B>>;
 
 /// Implements the `Fetcher` trait of the client. Makes it possible for the light client to perform
 /// network requests for some state.
@@ -49,6 +55,197 @@ pub struct OnDemand<B: BlockT> {
 
 	/// Sending side of `requests_queue`.
 	requests_send: TracingUnboundedSender<light_client_handler::Request<B>>,
+
+	/// Scheduler applied to `requests_queue` when it is extracted by the network.
+	scheduler: Mutex<Option<Box<dyn RequestScheduler<B>>>>,
+}
+
+/// Decides when, and in which form, the requests of the light client are sent to the network.
+pub trait RequestScheduler<B: BlockT>: Send {
+	/// Turns the requests issued through the [`OnDemand`] into the requests sent to the network.
+	///
+	/// Every request sent to the network is timed out after `request_timeout`, a scheduler holding
+	/// requests back must only delay them by a fraction of it.
+	fn schedule(
+		self: Box<Self>,
+		requests: LightClientRequests<B>,
+		request_timeout: Duration,
+	) -> LightClientRequests<B>;
+}
+
+/// Scheduler sending the requests to the network as soon as they are issued.
+#[derive(Debug, Default, Clone)]
+pub struct ImmediateScheduler;
+
+impl<B: BlockT> RequestScheduler<B> for ImmediateScheduler {
+	fn schedule(
+		self: Box<Self>,
+		requests: LightClientRequests<B>,
+		_request_timeout: Duration,
+	) -> LightClientRequests<B> {
+		requests
+	}
+}
+
+/// Scheduler coalescing the storage reads issued at the same block within a short window into a
+/// single network request.
+///
+/// The window is capped to a quarter of the request timeout. Other requests are sent right away.
+#[derive(Debug, Clone)]
+pub struct BatchingScheduler {
+	window: Duration,
+}
+
+impl BatchingScheduler {
+	/// Creates a scheduler holding the reads back for at most `window`.
+	pub fn new(window: Duration) -> Self {
+		BatchingScheduler { window }
+	}
+}
+
+impl<B: BlockT> RequestScheduler<B> for BatchingScheduler {
+	fn schedule(
+		self: Box<Self>,
+		requests: LightClientRequests<B>,
+		request_timeout: Duration,
+	) -> LightClientRequests<B> {
+		Box::pin(BatchedRequests {
+			requests: Some(requests),
+			window: cmp::min(self.window, request_timeout / 4),
+			flush: None,
+			batches: Vec::new(),
+			ready: Vec::new(),
+			in_flight: Vec::new(),
+		})
+	}
+}
+
+/// Result of a remote storage read.
+type ReadResult = Result<HashMap<Vec<u8>, Option<Vec<u8>>>, ClientError>;
+
+/// Reads of the same block waiting to be sent as a single request.
+struct ReadBatch<B: BlockT> {
+	request: RemoteReadRequest<B::Header>,
+	/// Keys and reply channel of every read of the batch.
+	readers: Vec<(Vec<Vec<u8>>, oneshot::Sender<ReadResult>)>,
+}
+
+/// Stream returned by [`BatchingScheduler`].
+struct BatchedRequests<B: BlockT> {
+	/// Requests issued by the client, `None` once exhausted.
+	requests: Option<LightClientRequests<B>>,
+	window: Duration,
+	/// Fires when the current batches must be sent.
+	flush: Option<Delay>,
+	batches: Vec<ReadBatch<B>>,
+	/// Requests waiting to be returned.
+	ready: Vec<light_client_handler::Request<B>>,
+	/// Batched reads sent to the network, with the readers to dispatch the response to.
+	in_flight: Vec<(oneshot::Receiver<ReadResult>, Vec<(Vec<Vec<u8>>, oneshot::Sender<ReadResult>)>)>,
+}
+
+// Nothing of the stream is structurally pinned.
+impl<B: BlockT> Unpin for BatchedRequests<B> {}
+
+impl<B: BlockT> BatchedRequests<B> {
+	fn push_read(&mut self, request: RemoteReadRequest<B::Header>, sender: oneshot::Sender<ReadResult>) {
+		if self.flush.is_none() {
+			self.flush = Some(Delay::new(self.window));
+		}
+
+		match self.batches.iter_mut().find(|batch| batch.request.block == request.block) {
+			Some(batch) => {
+				for key in &request.keys {
+					if !batch.request.keys.contains(key) {
+						batch.request.keys.push(key.clone());
+					}
+				}
+				batch.request.retry_count = cmp::max(batch.request.retry_count, request.retry_count);
+				batch.readers.push((request.keys, sender));
+			},
+			None => {
+				let keys = request.keys.clone();
+				self.batches.push(ReadBatch { request, readers: vec![(keys, sender)] });
+			},
+		}
+	}
+
+	fn flush(&mut self) {
+		self.flush = None;
+		for mut batch in mem::replace(&mut self.batches, Vec::new()) {
+			if batch.readers.len() == 1 {
+				let (_, sender) = batch.readers.remove(0);
+				self.ready.push(light_client_handler::Request::Read { request: batch.request, sender });
+				continue;
+			}
+
+			let (sender, receiver) = oneshot::channel();
+			self.ready.push(light_client_handler::Request::Read { request: batch.request, sender });
+			self.in_flight.push((receiver, batch.readers));
+		}
+	}
+}
+
+impl<B: BlockT> Stream for BatchedRequests<B> {
+	type Item = light_client_handler::Request<B>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let this = &mut *self;
+
+		while let Some(requests) = this.requests.as_mut() {
+			match requests.poll_next_unpin(cx) {
+				Poll::Ready(Some(light_client_handler::Request::Read { request, sender })) =>
+					this.push_read(request, sender),
+				Poll::Ready(Some(request)) => this.ready.push(request),
+				Poll::Ready(None) => {
+					this.requests = None;
+					this.flush();
+				},
+				Poll::Pending => break,
+			}
+		}
+
+		if let Some(flush) = this.flush.as_mut() {
+			if flush.poll_unpin(cx).is_ready() {
+				this.flush();
+			}
+		}
+
+		// Dispatch the responses of the batched reads, dropping the readers' senders on failure
+		// cancels their requests.
+		let mut n = 0;
+		while n < this.in_flight.len() {
+			let response = match this.in_flight[n].0.poll_unpin(cx) {
+				Poll::Ready(response) => response,
+				Poll::Pending => {
+					n += 1;
+					continue
+				},
+			};
+			let (_, readers) = this.in_flight.swap_remove(n);
+			if let Ok(Ok(values)) = response {
+				for (keys, sender) in readers {
+					let values = keys.into_iter()
+						.map(|key| {
+							let value = values.get(&key).cloned().flatten();
+							(key, value)
+						})
+						.collect();
+					let _ = sender.send(Ok(values));
+				}
+			}
+		}
+
+		if !this.ready.is_empty() {
+			return Poll::Ready(Some(this.ready.remove(0)))
+		}
+
+		if this.requests.is_none() && this.in_flight.is_empty() {
+			Poll::Ready(None)
+		} else {
+			Poll::Pending
+		}
+	}
 }
 
 /// Dummy implementation of `FetchChecker` that always assumes that responses are bad.
@@ -115,6 +312,14 @@ where
 {
 	/// Creates new on-demand service.
 	pub fn new(checker: Arc<dyn FetchChecker<B>>) -> Self {
+		Self::with_scheduler(checker, Box::new(ImmediateScheduler))
+	}
+
+	/// Creates new on-demand service, whose requests are sent to the network by `scheduler`.
+	pub fn with_scheduler(
+		checker: Arc<dyn FetchChecker<B>>,
+		scheduler: Box<dyn RequestScheduler<B>>,
+	) -> Self {
 		let (requests_send, requests_queue) = tracing_unbounded("mpsc_ondemand");
 		let requests_queue = Mutex::new(Some(requests_queue));
 
@@ -122,6 +327,7 @@ where
 			checker,
 			requests_queue,
 			requests_send,
+			scheduler: Mutex::new(Some(scheduler)),
 		}
 	}
 
@@ -130,17 +336,19 @@ where
 		&self.checker
 	}
 
-	/// Extracts the queue of requests.
+	/// Extracts the queue of requests, as scheduled by the scheduler of the service.
 	///
 	/// Whenever one of the methods of the `Fetcher` trait is called, an element is pushed on this
 	/// channel.
 	///
 	/// If this function returns `None`, that means that the receiver has already been extracted in
 	/// the past, and therefore that something already handles the requests.
-	pub(crate) fn extract_receiver(&self)
-		-> Option<TracingUnboundedReceiver<light_client_handler::Request<B>>>
+	pub(crate) fn extract_receiver(&self, request_timeout: Duration)
+		-> Option<LightClientRequests<B>>
 	{
-		self.requests_queue.lock().take()
+		let requests = self.requests_queue.lock().take()?.boxed();
+		let scheduler = self.scheduler.lock().take()?;
+		Some(scheduler.schedule(requests, request_timeout))
 	}
 }
 
@@ -226,3 +434,74 @@ impl<T> Future for RemoteResponse<T> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{channel::mpsc, executor::block_on};
+	use sp_core::H256;
+	use substrate_test_runtime_client::runtime::{Block, Header};
+
+	type ReadResponse = RemoteResponse<HashMap<Vec<u8>, Option<Vec<u8>>>>;
+
+	fn read(
+		requests: &mpsc::UnboundedSender<light_client_handler::Request<Block>>,
+		block: u8,
+		keys: &[&str],
+	) -> ReadResponse {
+		let (sender, receiver) = oneshot::channel();
+		let request = RemoteReadRequest {
+			block: H256::repeat_byte(block),
+			header: Header {
+				parent_hash: Default::default(),
+				number: 0,
+				state_root: Default::default(),
+				extrinsics_root: Default::default(),
+				digest: Default::default(),
+			},
+			keys: keys.iter().map(|key| key.as_bytes().to_vec()).collect(),
+			retry_count: None,
+		};
+		requests.unbounded_send(light_client_handler::Request::Read { request, sender }).unwrap();
+		RemoteResponse { receiver }
+	}
+
+	fn values(keys: &[&str]) -> HashMap<Vec<u8>, Option<Vec<u8>>> {
+		keys.iter().map(|key| (key.as_bytes().to_vec(), Some(key.repeat(2).into_bytes()))).collect()
+	}
+
+	#[test]
+	fn batching_scheduler_coalesces_reads_of_the_same_block() {
+		let (requests, receiver) = mpsc::unbounded();
+		let scheduler: Box<dyn RequestScheduler<Block>> =
+			Box::new(BatchingScheduler::new(Duration::from_millis(10)));
+		let mut scheduled = scheduler.schedule(receiver.boxed(), Duration::from_secs(15));
+
+		let first = read(&requests, 1, &["a", "b"]);
+		let second = read(&requests, 1, &["b", "c"]);
+		let other_block = read(&requests, 2, &["a"]);
+
+		for _ in 0..2 {
+			match block_on(scheduled.next()) {
+				Some(light_client_handler::Request::Read { request, sender }) => {
+					let keys = request.keys.iter()
+						.map(|key| std::str::from_utf8(key).unwrap())
+						.collect::<Vec<_>>();
+					if request.block == H256::repeat_byte(1) {
+						assert_eq!(keys, vec!["a", "b", "c"]);
+					} else {
+						assert_eq!(keys, vec!["a"]);
+					}
+					sender.send(Ok(values(&keys))).unwrap();
+				},
+				_ => panic!("Expected a read request"),
+			}
+		}
+
+		drop(requests);
+		assert!(block_on(scheduled.next()).is_none());
+		assert_eq!(block_on(first).unwrap(), values(&["a", "b"]));
+		assert_eq!(block_on(second).unwrap(), values(&["b", "c"]));
+		assert_eq!(block_on(other_block).unwrap(), values(&["a"]));
+	}
+}
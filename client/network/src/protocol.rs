@@ -20,6 +20,7 @@ use crate::{
 	ExHashT,
 	chain::{Client, FinalityProofProvider},
 	config::{BoxFinalityProofRequestBuilder, ProtocolId, TransactionPool, TransactionImportFuture, TransactionImport},
+	config::AnnounceValidationLimit,
 	error,
 	utils::interval
 };
@@ -55,7 +56,7 @@ use std::collections::{BTreeMap, HashMap, HashSet, VecDeque, hash_map::Entry};
 use std::sync::Arc;
 use std::fmt::Write;
 use std::{cmp, io, num::NonZeroUsize, pin::Pin, task::Poll, time};
-use log::{log, Level, trace, debug, warn, error};
+use log::{log, Level, trace, debug, info, warn, error};
 use sc_client_api::{ChangesProof, StorageProof};
 use util::LruHashSet;
 use wasm_timer::Instant;
@@ -132,6 +133,8 @@ mod rep {
 	pub const BAD_ROLE: Rep = Rep::new_fatal("Unsupported role");
 	/// Peer response data does not have requested bits.
 	pub const BAD_RESPONSE: Rep = Rep::new(-(1 << 12), "Incomplete response");
+	/// Peer provided a finality proof that failed to decode or to verify.
+	pub const BAD_FINALITY_PROOF: Rep = Rep::new_fatal("Invalid finality proof");
 }
 
 struct Metrics {
@@ -306,6 +309,14 @@ pub struct ProtocolConfig {
 	pub roles: Roles,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
+	/// Never announce blocks to peers.
+	pub suppress_block_announcements: bool,
+	/// Ignore the announcements of blocks this many blocks or more below our best block.
+	pub max_announce_block_age: Option<u32>,
+	/// Limit on the block announcements of a single peer that are validated at once.
+	pub block_announce_validation_limit: Option<AnnounceValidationLimit>,
+	/// Reputation gained by a peer every time it provides a finality proof that we can import.
+	pub finality_proof_reputation_boost: i32,
 }
 
 impl Default for ProtocolConfig {
@@ -313,6 +324,10 @@ impl Default for ProtocolConfig {
 		ProtocolConfig {
 			roles: Roles::FULL,
 			max_parallel_downloads: 5,
+			suppress_block_announcements: false,
+			max_announce_block_age: None,
+			block_announce_validation_limit: None,
+			finality_proof_reputation_boost: 1 << 7,
 		}
 	}
 }
@@ -393,6 +408,8 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			finality_proof_request_builder,
 			block_announce_validator,
 			config.max_parallel_downloads,
+			config.max_announce_block_age,
+			config.block_announce_validation_limit,
 		);
 
 		let important_peers = {
@@ -507,6 +524,11 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.behaviour.peerset_debug_info()
 	}
 
+	/// Returns the peers with a non-zero reputation in the peerset manager.
+	pub fn peerset_reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.behaviour.peerset_reputations()
+	}
+
 	/// Returns the number of peers we're connected to.
 	pub fn num_connected_peers(&self) -> usize {
 		self.context_data.peers.values().count()
@@ -541,6 +563,11 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.sync.status().queued_blocks
 	}
 
+	/// Highest block number waiting in the import queue.
+	pub fn best_queued_block(&self) -> Option<NumberFor<B>> {
+		self.sync.status().best_queued_block
+	}
+
 	/// Number of downloaded blocks.
 	pub fn num_downloaded_blocks(&self) -> usize {
 		self.sync.num_downloaded_blocks()
@@ -1245,6 +1272,11 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 	/// In chain-based consensus, we often need to make sure non-best forks are
 	/// at least temporarily synced.
 	pub fn announce_block(&mut self, hash: B::Hash, data: Vec<u8>) {
+		if self.config.suppress_block_announcements {
+			trace!(target: "sync", "Not announcing block {:?}, block announcements are suppressed", hash);
+			return;
+		}
+
 		let header = match self.context_data.chain.header(BlockId::Hash(hash)) {
 			Ok(Some(header)) => header,
 			Ok(None) => {
@@ -1311,12 +1343,28 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		announce: BlockAnnounce<B::Header>,
 	) -> CustomMessageOutcome<B> {
 		let hash = announce.header.hash();
-		let number = *announce.header.number();
 
 		if let Some(ref mut peer) = self.context_data.peers.get_mut(&who) {
 			peer.known_blocks.insert(hash.clone());
 		}
 
+		// With a per-peer limit, announcements are validated in rounds when polled.
+		if self.config.block_announce_validation_limit.is_some() {
+			self.sync.push_block_announce(who, announce);
+			return CustomMessageOutcome::None
+		}
+
+		self.validate_block_announce(who, hash, announce)
+	}
+
+	fn validate_block_announce(
+		&mut self,
+		who: PeerId,
+		hash: B::Hash,
+		announce: BlockAnnounce<B::Header>,
+	) -> CustomMessageOutcome<B> {
+		let number = *announce.header.number();
+
 		let is_their_best = match announce.state.unwrap_or(message::BlockState::Best) {
 			message::BlockState::Best => true,
 			message::BlockState::Normal => false,
@@ -1436,6 +1484,14 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.sync.request_justification(&hash, number)
 	}
 
+	/// Returns `true` if the block with the given hash and number is present in the local chain.
+	pub fn is_known_block(&self, hash: &B::Hash, number: NumberFor<B>) -> bool {
+		match self.context_data.chain.header(BlockId::Hash(*hash)) {
+			Ok(Some(header)) => *header.number() == number,
+			_ => false,
+		}
+	}
+
 	/// Request syncing for the given block from given set of peers.
 	/// Uses `protocol` to queue a new block download request and tries to dispatch all pending
 	/// requests.
@@ -1443,6 +1499,12 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.sync.set_sync_fork_request(peers, hash, number)
 	}
 
+	/// Notify the protocol that the import queue is, or isn't anymore, out of memory for more
+	/// blocks. Blocks aren't requested while it is.
+	pub fn set_import_queue_saturated(&mut self, saturated: bool) {
+		self.sync.set_import_queue_saturated(saturated)
+	}
+
 	/// A batch of blocks have been processed, with or without errors.
 	/// Call this when a batch of blocks have been processed by the importqueue, with or without
 	/// errors.
@@ -1506,12 +1568,27 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.behaviour.add_discovered_nodes(peer_ids)
 	}
 
+	/// Must be called with the result of the import of a finality proof provided by `who`.
+	///
+	/// Peers providing valid proofs gain reputation, so that they are preferred when we're a
+	/// light client, while peers providing invalid or malformed ones are disconnected and banned.
 	pub fn finality_proof_import_result(
 		&mut self,
+		who: PeerId,
 		request_block: (B::Hash, NumberFor<B>),
 		finalization_result: Result<(B::Hash, NumberFor<B>), ()>,
 	) {
-		self.sync.on_finality_proof_import(request_block, finalization_result)
+		let imported = finalization_result.is_ok();
+		self.sync.on_finality_proof_import(request_block, finalization_result);
+
+		if !imported {
+			info!("💔 Invalid finality proof provided by {} for #{}", who, request_block.0);
+			self.disconnect_peer(&who);
+		}
+		let boost = self.config.finality_proof_reputation_boost;
+		if let Some(change) = finality_proof_reputation_change(imported, boost) {
+			self.report_peer(who, change);
+		}
 	}
 
 	fn on_remote_read_request(
@@ -1852,6 +1929,18 @@ pub enum CustomMessageOutcome<B: BlockT> {
 	None,
 }
 
+/// Returns the reputation change of a peer that provided a finality proof, depending on whether
+/// the proof could be imported.
+fn finality_proof_reputation_change(imported: bool, boost: i32) -> Option<sc_peerset::ReputationChange> {
+	if !imported {
+		Some(rep::BAD_FINALITY_PROOF)
+	} else if boost != 0 {
+		Some(sc_peerset::ReputationChange::new(boost, "Good finality proof"))
+	} else {
+		None
+	}
+}
+
 fn update_peer_request<B: BlockT, H: ExHashT>(
 	peers: &mut HashMap<PeerId, Peer<B, H>>,
 	who: &PeerId,
@@ -1945,6 +2034,16 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 			self.propagate_transactions();
 		}
 
+		for (who, announce) in self.sync.block_announces_to_validate() {
+			let hash = announce.header.hash();
+			let outcome = self.validate_block_announce(who.clone(), hash, announce);
+			self.update_peer_info(&who);
+			if let CustomMessageOutcome::None = outcome {
+				continue
+			}
+			self.pending_messages.push_back(outcome);
+		}
+
 		for (id, mut r) in self.sync.block_requests() {
 			update_peer_request(&mut self.context_data.peers, &id, &mut r);
 			let event = CustomMessageOutcome::BlockRequest {
@@ -2104,3 +2203,40 @@ impl<B: BlockT, H: ExHashT> Drop for Protocol<B, H> {
 		debug!(target: "sync", "Network stats:\n{}", self.format_stats());
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_peerset::{Peerset, PeersetConfig};
+
+	fn reputation(peerset: &mut Peerset, peer_id: &PeerId) -> i64 {
+		peerset.debug_info()["nodes"][peer_id.to_base58()]["reputation"].as_i64().unwrap()
+	}
+
+	#[test]
+	fn finality_proofs_adjust_the_reputation_of_their_provider() {
+		let (mut peerset, _) = Peerset::from_config(PeersetConfig {
+			in_peers: 25,
+			out_peers: 25,
+			bootnodes: Vec::new(),
+			reserved_only: false,
+			priority_groups: Vec::new(),
+		});
+		let boost = ProtocolConfig::default().finality_proof_reputation_boost;
+		let good = PeerId::random();
+		let bad = PeerId::random();
+
+		for _ in 0..3 {
+			peerset.report_peer(good.clone(), finality_proof_reputation_change(true, boost).unwrap());
+			peerset.report_peer(bad.clone(), finality_proof_reputation_change(false, boost).unwrap());
+		}
+
+		// Reputations slowly decay towards zero, so the total may be slightly lower.
+		assert!(reputation(&mut peerset, &good) > 2 * boost as i64);
+		assert!(reputation(&mut peerset, &bad) < 0);
+
+		// Valid proofs are not rewarded when disabled, but invalid ones are still penalized.
+		assert!(finality_proof_reputation_change(true, 0).is_none());
+		assert!(finality_proof_reputation_change(false, 0).is_some());
+	}
+}
@@ -74,6 +74,16 @@ pub enum Event {
 		/// Concerned protocol and associated message.
 		messages: Vec<(ConsensusEngineId, Bytes)>,
 	},
+
+	/// Received a response to a request sent on the secondary finality proof protocol.
+	SecondaryFinalityProof {
+		/// Node we received the proof from.
+		remote: PeerId,
+		/// SCALE-encoded hash of the block the proof was requested for.
+		block_hash: Vec<u8>,
+		/// The proof, empty if the remote couldn't provide one.
+		proof: Vec<u8>,
+	},
 }
 
 /// Role that the peer sent to us during the handshake, with the addition of what our local node
@@ -600,6 +600,11 @@ impl GenericProto {
 		self.peerset.debug_info()
 	}
 
+	/// Returns the peers with a non-zero reputation in the peerset manager.
+	pub fn peerset_reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.peerset.reputations()
+	}
+
 	/// Function that is called when the peerset wants us to connect to a peer.
 	fn peerset_report_connect(&mut self, peer_id: PeerId) {
 		let mut occ_entry = match self.peers.entry(peer_id) {
@@ -35,7 +35,7 @@ use sp_consensus::{BlockOrigin, BlockStatus,
 	import_queue::{IncomingBlock, BlockImportResult, BlockImportError}
 };
 use crate::{
-	config::BoxFinalityProofRequestBuilder,
+	config::{AnnounceOverflow, AnnounceValidationLimit, BoxFinalityProofRequestBuilder},
 	protocol::message::{self, generic::FinalityProofRequest, BlockAnnounce, BlockAttributes, BlockRequest, BlockResponse,
 	FinalityProofResponse, Roles},
 };
@@ -73,6 +73,9 @@ const MAJOR_SYNC_BLOCKS: u8 = 5;
 /// Number of recently announced blocks to track for each peer.
 const ANNOUNCE_HISTORY_SIZE: usize = 64;
 
+/// Maximum number of block announcements of a single peer queued for validation.
+const MAX_QUEUED_ANNOUNCES: usize = 64;
+
 mod rep {
 	use sc_peerset::ReputationChange as Rep;
 	/// Reputation change when a peer sent us a message that led to a
@@ -92,6 +95,12 @@ mod rep {
 	/// Reputation change for peers which send us a known bad block.
 	pub const BAD_BLOCK: Rep = Rep::new(-(1 << 29), "Bad block");
 
+	/// Reputation change for peers which send us a block reverting an already finalized block.
+	pub const CONFLICTS_WITH_FINALITY: Rep = Rep::new(-(1 << 29), "Block conflicts with finality");
+
+	/// Reputation change for peers which send us a block the runtime panics on.
+	pub const RUNTIME_PANICKED: Rep = Rep::new(-(1 << 29), "Runtime panicked on block");
+
 	/// Peer did not provide us with advertised block data.
 	pub const NO_BLOCK: Rep = Rep::new(-(1 << 29), "No requested block data");
 
@@ -190,8 +199,18 @@ pub struct ChainSync<B: BlockT> {
 	block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	max_parallel_downloads: u32,
+	/// Announcements of blocks this many blocks or more below our best block are ignored, unless
+	/// we are doing a major sync.
+	max_announce_block_age: Option<u32>,
+	/// Limit on the block announcements of a single peer that are validated at once, if any.
+	block_announce_validation_limit: Option<AnnounceValidationLimit>,
+	/// Block announcements awaiting validation, by the peer that sent them. Only used when
+	/// `block_announce_validation_limit` is set.
+	pending_block_announces: HashMap<PeerId, VecDeque<BlockAnnounce<B::Header>>>,
 	/// Total number of downloaded blocks.
 	downloaded_blocks: usize,
+	/// Whether the import queue is out of memory for more blocks.
+	import_queue_saturated: bool,
 }
 
 /// All the data we have about a Peer that we are trying to sync with
@@ -283,6 +302,8 @@ pub struct Status<B: BlockT> {
 	pub num_peers: u32,
 	/// Number of blocks queued for import
 	pub queued_blocks: u32,
+	/// Highest block number queued for import, if any block is still waiting to be imported.
+	pub best_queued_block: Option<NumberFor<B>>,
 }
 
 /// A peer did not behave as expected and should be reported.
@@ -352,6 +373,8 @@ impl<B: BlockT> ChainSync<B> {
 		request_builder: Option<BoxFinalityProofRequestBuilder<B>>,
 		block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 		max_parallel_downloads: u32,
+		max_announce_block_age: Option<u32>,
+		block_announce_validation_limit: Option<AnnounceValidationLimit>,
 	) -> Self {
 		let mut required_block_attributes = BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION;
 
@@ -376,7 +399,11 @@ impl<B: BlockT> ChainSync<B> {
 			pending_requests: Default::default(),
 			block_announce_validator,
 			max_parallel_downloads,
+			max_announce_block_age,
+			block_announce_validation_limit,
+			pending_block_announces: HashMap::new(),
 			downloaded_blocks: 0,
+			import_queue_saturated: false,
 		}
 	}
 
@@ -408,6 +435,11 @@ impl<B: BlockT> ChainSync<B> {
 			best_seen_block: best_seen,
 			num_peers: self.peers.len() as u32,
 			queued_blocks: self.queue_blocks.len() as u32,
+			best_queued_block: if self.queue_blocks.is_empty() {
+				None
+			} else {
+				Some(self.best_queued_number)
+			},
 		}
 	}
 
@@ -639,6 +671,11 @@ impl<B: BlockT> ChainSync<B> {
 		})
 	}
 
+	/// Pauses the block requests while the import queue is saturated.
+	pub fn set_import_queue_saturated(&mut self, saturated: bool) {
+		self.import_queue_saturated = saturated;
+	}
+
 	/// Get an iterator over all block requests of all peers.
 	pub fn block_requests(&mut self) -> impl Iterator<Item = (&PeerId, BlockRequest<B>)> + '_ {
 		if self.pending_requests.is_empty() {
@@ -648,6 +685,10 @@ impl<B: BlockT> ChainSync<B> {
 			trace!(target: "sync", "Too many blocks in the queue.");
 			return Either::Left(std::iter::empty())
 		}
+		if self.import_queue_saturated {
+			trace!(target: "sync", "Import queue is out of memory for more blocks.");
+			return Either::Left(std::iter::empty())
+		}
 		let major_sync = self.status().state == SyncState::Downloading;
 		let blocks = &mut self.blocks;
 		let attrs = &self.required_block_attributes;
@@ -1066,6 +1107,18 @@ impl<B: BlockT> ChainSync<B> {
 						output.push(Err(BadPeer(peer, rep::BAD_BLOCK)));
 					}
 				},
+				Err(BlockImportError::ConflictsWithFinality(who)) => {
+					if let Some(peer) = who {
+						info!("💔 Block {:?} received from peer {} conflicts with finality", hash, peer);
+						output.push(Err(BadPeer(peer, rep::CONFLICTS_WITH_FINALITY)));
+					}
+				},
+				Err(BlockImportError::RuntimePanicked(who)) => {
+					if let Some(peer) = who {
+						warn!("💔 Runtime panicked on block {:?} received from peer {}", hash, peer);
+						output.push(Err(BadPeer(peer, rep::RUNTIME_PANICKED)));
+					}
+				},
 				Err(BlockImportError::MissingState) => {
 					// This may happen if the chain we were requesting upon has been discarded
 					// in the meantime because other chain has been finalized.
@@ -1172,6 +1225,11 @@ impl<B: BlockT> ChainSync<B> {
 			warn!(target: "sync", "💔 Ignored genesis block (#0) announcement from {}: {}", who, hash);
 			return OnBlockAnnounce::Nothing
 		}
+		if self.is_stale_announcement(number) {
+			// Not necessarily malicious, the peer may just be lagging behind, so it isn't punished.
+			debug!(target: "sync", "Ignored stale block (#{}) announcement from {}: {}", number, who, hash);
+			return OnBlockAnnounce::Nothing
+		}
 		let parent_status = self.block_status(header.parent_hash()).ok().unwrap_or(BlockStatus::Unknown);
 		let known_parent = parent_status != BlockStatus::Unknown;
 		let ancient_parent = parent_status == BlockStatus::InChainPruned;
@@ -1261,12 +1319,76 @@ impl<B: BlockT> ChainSync<B> {
 		OnBlockAnnounce::Nothing
 	}
 
+	/// Queues a block announcement of `who` for validation.
+	///
+	/// Announcements in excess of the peer's limit are dropped, or queued for the next rounds
+	/// depending on the configuration.
+	pub fn push_block_announce(&mut self, who: PeerId, announce: BlockAnnounce<B::Header>) {
+		let limit = match self.block_announce_validation_limit {
+			Some(limit) => limit,
+			None => return,
+		};
+		let pending = self.pending_block_announces.entry(who.clone()).or_default();
+		let capacity = match limit.overflow {
+			AnnounceOverflow::Queue => MAX_QUEUED_ANNOUNCES.max(limit.max_per_peer),
+			AnnounceOverflow::Drop => limit.max_per_peer,
+		};
+		if pending.len() >= capacity {
+			debug!(
+				target: "sync",
+				"Dropped block announcement from {}, which has {} awaiting validation",
+				who,
+				pending.len(),
+			);
+			if limit.overflow == AnnounceOverflow::Drop {
+				return
+			}
+			pending.pop_front();
+		}
+		pending.push_back(announce);
+	}
+
+	/// Returns the next round of block announcements to validate, along with the peer that sent
+	/// them.
+	///
+	/// A round holds at most the configured number of announcements of each peer, interleaved so
+	/// that a peer with many of them doesn't delay the others.
+	pub fn block_announces_to_validate(&mut self) -> Vec<(PeerId, BlockAnnounce<B::Header>)> {
+		let max_per_peer = match self.block_announce_validation_limit {
+			Some(limit) => limit.max_per_peer,
+			None => return Vec::new(),
+		};
+		let mut announces = Vec::new();
+		for _ in 0..max_per_peer {
+			for (who, pending) in self.pending_block_announces.iter_mut() {
+				if let Some(announce) = pending.pop_front() {
+					announces.push((who.clone(), announce));
+				}
+			}
+		}
+		self.pending_block_announces.retain(|_, pending| !pending.is_empty());
+		announces
+	}
+
+	/// Returns whether an announced block with the given number is too old to be considered.
+	///
+	/// Old blocks are still accepted during a major sync, since we may need them.
+	fn is_stale_announcement(&self, number: NumberFor<B>) -> bool {
+		let max_age = match self.max_announce_block_age {
+			Some(max_age) => max_age,
+			None => return false,
+		};
+		self.status().state == SyncState::Idle
+			&& number <= self.best_queued_number.saturating_sub(max_age.into())
+	}
+
 	/// Call when a peer has disconnected.
 	pub fn peer_disconnected(&mut self, who: &PeerId) {
 		self.blocks.clear_peer_download(who);
 		self.peers.remove(who);
 		self.extra_justifications.peer_disconnected(who);
 		self.extra_finality_proofs.peer_disconnected(who);
+		self.pending_block_announces.remove(who);
 		self.pending_requests.set_all();
 	}
 
@@ -1580,6 +1702,8 @@ mod test {
 			None,
 			block_announce_validator,
 			1,
+			None,
+			None,
 		);
 
 		let (a1_hash, a1_number) = {
@@ -1639,4 +1763,269 @@ mod test {
 			})
 		);
 	}
+
+	#[test]
+	fn no_block_requests_while_import_queue_is_saturated() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+		let mut sync = ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			None,
+			Box::new(DefaultBlockAnnounceValidator),
+			1,
+			None,
+			None,
+		);
+
+		// a peer far ahead of us, whose blocks we'd start downloading.
+		let peer_id = PeerId::random();
+		sync.new_peer(peer_id.clone(), Default::default(), 100).unwrap();
+
+		sync.set_import_queue_saturated(true);
+		assert_eq!(sync.block_requests().count(), 0);
+
+		// the request is issued once the import queue has room again.
+		sync.set_import_queue_saturated(false);
+		let requests = sync.block_requests().map(|(who, _)| who.clone()).collect::<Vec<_>>();
+		assert_eq!(requests, vec![peer_id]);
+	}
+
+	#[test]
+	fn stale_block_announcements_are_ignored_unless_major_syncing() {
+		use sp_consensus::BlockOrigin;
+		use substrate_test_runtime_client::{AccountKeyring, ClientBlockImportExt, runtime::Transfer};
+
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		for _ in 0..10 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			client.import(BlockOrigin::Own, block).unwrap();
+		}
+		let info = client.info();
+
+		// a fork of the chain at #1, which is 9 blocks below our best block.
+		let mut builder = client.new_block_at(&BlockId::Number(0), Default::default(), false).unwrap();
+		builder.push_transfer(Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+			amount: 1,
+			nonce: 0,
+		}).unwrap();
+		let stale = builder.build().unwrap().block;
+		let announce = BlockAnnounce {
+			header: stale.header.clone(),
+			state: Some(message::BlockState::Best),
+			data: None,
+		};
+
+		let new_sync = |max_announce_block_age| ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			None,
+			Box::new(DefaultBlockAnnounceValidator),
+			1,
+			max_announce_block_age,
+			None,
+		);
+		let peer_id = PeerId::random();
+
+		// the announcement is ignored by a synced node, and the peer stays connected.
+		let mut sync = new_sync(Some(5));
+		sync.new_peer(peer_id.clone(), info.best_hash, info.best_number).unwrap();
+		assert_eq!(sync.status().state, SyncState::Idle);
+		assert_eq!(
+			sync.on_block_announce(&peer_id, stale.hash(), &announce, true),
+			OnBlockAnnounce::Nothing,
+		);
+		assert!(!sync.fork_targets.contains_key(&stale.hash()));
+		assert_eq!(sync.peer_info(&peer_id).unwrap().best_hash, info.best_hash);
+
+		// it is still considered without a maximum age.
+		let mut sync = new_sync(None);
+		sync.new_peer(peer_id.clone(), info.best_hash, info.best_number).unwrap();
+		sync.on_block_announce(&peer_id, stale.hash(), &announce, true);
+		assert!(sync.fork_targets.contains_key(&stale.hash()));
+
+		// and during a major sync.
+		let mut sync = new_sync(Some(5));
+		sync.new_peer(peer_id.clone(), info.best_hash, info.best_number).unwrap();
+		sync.new_peer(PeerId::random(), Default::default(), info.best_number + 100).unwrap();
+		assert_eq!(sync.status().state, SyncState::Downloading);
+		sync.on_block_announce(&peer_id, stale.hash(), &announce, false);
+		assert!(sync.fork_targets.contains_key(&stale.hash()));
+	}
+
+	#[test]
+	fn blocks_conflicting_with_finality_are_rejected_and_penalized() {
+		use assert_matches::assert_matches;
+		use sp_consensus::{
+			BlockImportParams, BlockOrigin,
+			import_queue::{CacheKeyId, Verifier, import_single_block},
+		};
+		use sp_runtime::Justification;
+		use substrate_test_runtime_client::{
+			AccountKeyring, ClientBlockImportExt, ClientExt,
+			runtime::{Extrinsic, Header, Transfer},
+		};
+
+		struct RejectingVerifier;
+
+		impl Verifier<Block> for RejectingVerifier {
+			fn verify(
+				&mut self,
+				_: BlockOrigin,
+				_: Header,
+				_: Option<Justification>,
+				_: Option<Vec<Extrinsic>>,
+			) -> Result<(BlockImportParams<Block, ()>, Option<Vec<(CacheKeyId, Vec<u8>)>>), String> {
+				Err("the block is rejected before being verified".into())
+			}
+		}
+
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		let mut blocks = Vec::new();
+		for _ in 0..3 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			blocks.push(block.clone());
+			client.import(BlockOrigin::Own, block).unwrap();
+		}
+		ClientExt::finalize_block(&*client, BlockId::Number(2), None).unwrap();
+
+		// a fork of the chain at #1, which reverts the finalized block #2.
+		let mut builder = client.new_block_at(&BlockId::Number(0), Default::default(), false).unwrap();
+		builder.push_transfer(Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+			amount: 1,
+			nonce: 0,
+		}).unwrap();
+		let conflicting = builder.build().unwrap().block;
+
+		let peer_id = PeerId::random();
+		let import = |block: Block| {
+			let incoming = IncomingBlock {
+				hash: block.hash(),
+				header: Some(block.header),
+				body: Some(block.extrinsics),
+				justification: None,
+				origin: Some(peer_id.clone()),
+				allow_missing_state: false,
+				import_existing: false,
+			};
+			import_single_block(
+				&mut &*client,
+				BlockOrigin::NetworkBroadcast,
+				incoming,
+				&mut RejectingVerifier,
+			)
+		};
+
+		// a block of the finalized chain is stale, but consistent with finality.
+		assert_matches!(import(blocks[0].clone()), Ok(BlockImportResult::ImportedKnown(1)));
+
+		let result = import(conflicting.clone());
+		match &result {
+			Err(BlockImportError::ConflictsWithFinality(Some(who))) => assert_eq!(*who, peer_id),
+			other => panic!("Expected the block to conflict with finality, got {:?}", other),
+		}
+
+		let info = client.info();
+		let mut sync = ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			None,
+			Box::new(DefaultBlockAnnounceValidator),
+			1,
+			None,
+			None,
+		);
+		sync.new_peer(peer_id.clone(), info.best_hash, info.best_number).unwrap();
+		let bad_peers = sync.on_blocks_processed(0, 1, vec![(result, conflicting.hash())])
+			.filter_map(|result| result.err())
+			.collect::<Vec<_>>();
+		assert_eq!(bad_peers, vec![BadPeer(peer_id, rep::CONFLICTS_WITH_FINALITY)]);
+	}
+
+	#[test]
+	fn flooding_peer_does_not_delay_the_validation_of_other_announcements() {
+		/// Records the associated data of the announcements it validates, which is the sender.
+		struct RecordingValidator(Arc<std::sync::Mutex<Vec<u8>>>);
+
+		impl BlockAnnounceValidator<Block> for RecordingValidator {
+			fn validate(
+				&mut self,
+				_: &<Block as BlockT>::Header,
+				data: &[u8],
+			) -> Result<Validation, Box<dyn std::error::Error + Send>> {
+				self.0.lock().unwrap().extend_from_slice(data);
+				Ok(Validation::Failure)
+			}
+		}
+
+		const FLOODER: u8 = 0;
+		const HONEST: u8 = 1;
+
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+		let announce = |number, sender| BlockAnnounce {
+			header: <Block as BlockT>::Header::new(
+				number,
+				Default::default(),
+				Default::default(),
+				info.genesis_hash,
+				Default::default(),
+			),
+			state: Some(message::BlockState::Best),
+			data: Some(vec![sender]),
+		};
+		let flooder = PeerId::random();
+		let honest = PeerId::random();
+
+		for &(overflow, flooder_validations) in &[
+			(AnnounceOverflow::Queue, MAX_QUEUED_ANNOUNCES),
+			(AnnounceOverflow::Drop, 2),
+		] {
+			let validated = Arc::new(std::sync::Mutex::new(Vec::new()));
+			let mut sync = ChainSync::new(
+				Roles::FULL,
+				client.clone(),
+				&info,
+				None,
+				Box::new(RecordingValidator(validated.clone())),
+				1,
+				None,
+				Some(AnnounceValidationLimit { max_per_peer: 2, overflow }),
+			);
+			sync.new_peer(flooder.clone(), info.best_hash, info.best_number).unwrap();
+			sync.new_peer(honest.clone(), info.best_hash, info.best_number).unwrap();
+
+			for number in 1..=100 {
+				sync.push_block_announce(flooder.clone(), announce(number, FLOODER));
+			}
+			sync.push_block_announce(honest.clone(), announce(1, HONEST));
+
+			let mut validate_round = || {
+				let round = sync.block_announces_to_validate();
+				for (who, announce) in &round {
+					sync.on_block_announce(who, announce.header.hash(), announce, true);
+				}
+				round.len()
+			};
+			let count = |sender| validated.lock().unwrap().iter().filter(|s| **s == sender).count();
+
+			// the honest peer's announcement is validated in the first round, along with at most
+			// 2 of the flooder's.
+			assert_eq!(validate_round(), 3);
+			assert_eq!(count(HONEST), 1);
+			assert_eq!(count(FLOODER), 2);
+
+			// the flooder's other announcements are either queued, up to a limit, or dropped.
+			while validate_round() > 0 {}
+			assert_eq!(count(HONEST), 1);
+			assert_eq!(count(FLOODER), flooder_validations, "{:?}", overflow);
+		}
+	}
 }
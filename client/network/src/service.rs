@@ -36,12 +36,12 @@ use crate::{
 	network_state::{
 		NetworkState, NotConnectedPeer as NetworkStateNotConnectedPeer, Peer as NetworkStatePeer,
 	},
-	on_demand_layer::AlwaysBadChecker,
-	light_client_handler, block_requests, finality_requests,
+	on_demand_layer::{AlwaysBadChecker, LightClientRequests},
+	light_client_handler, block_archive, block_requests, finality_requests,
 	protocol::{self, event::Event, LegacyConnectionKillError, sync::SyncState, PeerInfo, Protocol},
 	transport, ReputationChange,
 };
-use futures::prelude::*;
+use futures::{prelude::*, channel::oneshot};
 use libp2p::{PeerId, Multiaddr};
 use libp2p::core::{ConnectedPoint, Executor, connection::{ConnectionError, PendingConnectionError}, either::EitherError};
 use libp2p::kad::record;
@@ -56,7 +56,7 @@ use sc_peerset::PeersetHandle;
 use sp_consensus::import_queue::{BlockImportError, BlockImportResult, ImportQueue, Link};
 use sp_runtime::{
 	traits::{Block as BlockT, NumberFor},
-	ConsensusEngineId,
+	ConsensusEngineId, Justification,
 };
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
 use std::{
@@ -120,6 +120,10 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			params.network_config.reserved_nodes.iter().map(|x| &x.multiaddr),
 			&params.network_config.transport,
 		)?;
+		ensure_addresses_consistent_with_transport(
+			params.network_config.block_archive_source.iter().map(|x| &x.multiaddr),
+			&params.network_config.transport,
+		)?;
 		ensure_addresses_consistent_with_transport(
 			params.network_config.public_addresses.iter(),
 			&params.network_config.transport,
@@ -231,6 +235,10 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			protocol::ProtocolConfig {
 				roles: From::from(&params.role),
 				max_parallel_downloads: params.network_config.max_parallel_downloads,
+				suppress_block_announcements: params.suppress_block_announcements,
+				max_announce_block_age: params.max_announce_block_age,
+				block_announce_validation_limit: params.block_announce_validation_limit,
+				finality_proof_reputation_boost: params.network_config.finality_proof_reputation_boost,
 			},
 			local_peer_id.clone(),
 			params.chain.clone(),
@@ -245,6 +253,9 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			metrics.as_ref().map(|m| m.notifications_queues_size.clone()),
 		)?;
 
+		let light_client_config = light_client_handler::Config::new(&params.protocol_id);
+		let light_client_request_timeout = light_client_config.request_timeout();
+
 		// Build the swarm.
 		let (mut swarm, bandwidth): (Swarm<B, H>, _) = {
 			let user_agent = format!(
@@ -253,17 +264,38 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 				params.network_config.node_name
 			);
 			let block_requests = {
-				let config = block_requests::Config::new(&params.protocol_id);
+				let mut config = block_requests::Config::new(&params.protocol_id);
+				config.set_request_timeout(params.sync_request_timeout);
 				block_requests::BlockRequests::new(config, params.chain.clone())
 			};
 			let finality_proof_requests = {
 				let config = finality_requests::Config::new(&params.protocol_id);
 				finality_requests::FinalityProofRequests::new(config, params.finality_proof_provider.clone())
 			};
+			let secondary_finality_proof_requests = match params.secondary_finality_proof {
+				Some(secondary) => {
+					let mut config = finality_requests::Config::new(&params.protocol_id);
+					config.set_protocol_name(&params.protocol_id, &secondary.protocol_name);
+					let requests =
+						finality_requests::FinalityProofRequests::new(config, secondary.provider);
+					if requests.protocol_name() == finality_proof_requests.protocol_name() {
+						return Err(Error::ConflictingFinalityProofProtocol(
+							String::from_utf8_lossy(requests.protocol_name()).into_owned(),
+						));
+					}
+					Some(requests)
+				},
+				None => None,
+			};
+			let block_archive = block_archive::BlockArchive::new(
+				block_archive::Config::new(&params.protocol_id),
+				params.chain.clone(),
+				params.network_config.serve_block_archive,
+				params.network_config.block_archive_source.clone(),
+			);
 			let light_client_handler = {
-				let config = light_client_handler::Config::new(&params.protocol_id);
 				light_client_handler::LightClientHandler::new(
-					config,
+					light_client_config,
 					params.chain,
 					checker,
 					peerset_handle.clone(),
@@ -298,7 +330,9 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 				local_public,
 				block_requests,
 				finality_proof_requests,
+				secondary_finality_proof_requests,
 				light_client_handler,
+				block_archive,
 				discovery_config
 			);
 
@@ -362,7 +396,8 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			service,
 			import_queue: params.import_queue,
 			from_worker,
-			light_client_rqs: params.on_demand.and_then(|od| od.extract_receiver()),
+			light_client_rqs: params.on_demand
+				.and_then(|od| od.extract_receiver(light_client_request_timeout)),
 			event_streams: out_events::OutChannels::new(params.metrics_registry.as_ref())?,
 			metrics,
 			boot_node_ids,
@@ -409,6 +444,11 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 		self.network_service.user_protocol().num_queued_blocks()
 	}
 
+	/// Highest block number waiting in the import queue.
+	pub fn best_queued_block(&self) -> Option<NumberFor<B>> {
+		self.network_service.user_protocol().best_queued_block()
+	}
+
 	/// Returns the number of downloaded blocks.
 	pub fn num_downloaded_blocks(&self) -> usize {
 		self.network_service.user_protocol().num_downloaded_blocks()
@@ -424,6 +464,11 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 		self.network_service.add_known_address(peer_id, addr);
 	}
 
+	/// Returns the peers whose reputation isn't zero, along with their reputation.
+	pub fn peer_reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.network_service.user_protocol_mut().peerset_reputations()
+	}
+
 	/// Return a `NetworkService` that can be shared through the code base and can be used to
 	/// manipulate the worker.
 	pub fn service(&self) -> &Arc<NetworkService<B, H>> {
@@ -648,6 +693,36 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 			.unbounded_send(ServiceToWorkerMsg::RequestJustification(*hash, number));
 	}
 
+	/// Request a finality proof for the given block from `target` on the secondary finality proof
+	/// protocol configured with [`Params::secondary_finality_proof`].
+	///
+	/// The response is reported as an [`Event::SecondaryFinalityProof`] on the event streams.
+	pub fn request_secondary_finality_proof(&self, target: PeerId, block_hash: B::Hash, request: Vec<u8>) {
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::RequestSecondaryFinalityProof(target, block_hash, request));
+	}
+
+	/// Submit a justification for an already imported block to the import queue.
+	///
+	/// This allows driving finality forward with justifications obtained out of band, without
+	/// re-importing the block they refer to. The returned future resolves once the justification
+	/// has been handed to the import queue, or with [`Error::UnknownBlock`] if the block isn't
+	/// part of the local chain.
+	pub fn import_justification(
+		&self,
+		hash: B::Hash,
+		number: NumberFor<B>,
+		justification: Justification,
+	) -> impl Future<Output = Result<(), Error>> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::ImportJustification(hash, number, justification, tx));
+
+		rx.map(|result| result.unwrap_or(Err(Error::WorkerShutdown)))
+	}
+
 	/// Are we in the process of downloading the chain?
 	pub fn is_major_syncing(&self) -> bool {
 		self.is_major_syncing.load(Ordering::Relaxed)
@@ -820,6 +895,8 @@ enum ServiceToWorkerMsg<B: BlockT, H: ExHashT> {
 	PropagateTransaction(H),
 	PropagateTransactions,
 	RequestJustification(B::Hash, NumberFor<B>),
+	RequestSecondaryFinalityProof(PeerId, B::Hash, Vec<u8>),
+	ImportJustification(B::Hash, NumberFor<B>, Justification, oneshot::Sender<Result<(), Error>>),
 	AnnounceBlock(B::Hash, Vec<u8>),
 	GetValue(record::Key),
 	PutValue(record::Key, Vec<u8>),
@@ -860,7 +937,7 @@ pub struct NetworkWorker<B: BlockT + 'static, H: ExHashT> {
 	/// Messages from the `NetworkService` and that must be processed.
 	from_worker: TracingUnboundedReceiver<ServiceToWorkerMsg<B, H>>,
 	/// Receiver for queries from the light client that must be processed.
-	light_client_rqs: Option<TracingUnboundedReceiver<light_client_handler::Request<B>>>,
+	light_client_rqs: Option<LightClientRequests<B>>,
 	/// Senders for events that happen on the network.
 	event_streams: out_events::OutChannels,
 	/// Prometheus network metrics.
@@ -1121,6 +1198,8 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 		this.import_queue.poll_actions(cx, &mut NetworkLink {
 			protocol: &mut this.network_service,
 		});
+		let import_queue_saturated = this.import_queue.is_saturated();
+		this.network_service.set_import_queue_saturated(import_queue_saturated);
 
 		// Check for new incoming light client requests.
 		if let Some(light_client_rqs) = this.light_client_rqs.as_mut() {
@@ -1148,6 +1227,21 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 					this.network_service.user_protocol_mut().announce_block(hash, data),
 				ServiceToWorkerMsg::RequestJustification(hash, number) =>
 					this.network_service.user_protocol_mut().request_justification(&hash, number),
+				ServiceToWorkerMsg::RequestSecondaryFinalityProof(target, block_hash, request) =>
+					this.network_service.request_secondary_finality_proof(&target, block_hash, request),
+				ServiceToWorkerMsg::ImportJustification(hash, number, justification, sender) => {
+					let result = if this.network_service.user_protocol().is_known_block(&hash, number) {
+						if let Some(metrics) = this.metrics.as_ref() {
+							metrics.import_queue_justifications_submitted.inc();
+						}
+						let local_peer_id = Swarm::<B, H>::local_peer_id(&this.network_service).clone();
+						this.import_queue.import_justification(local_peer_id, hash, number, justification);
+						Ok(())
+					} else {
+						Err(Error::UnknownBlock(format!("#{} ({})", number, hash)))
+					};
+					let _ = sender.send(result);
+				},
 				ServiceToWorkerMsg::PropagateTransaction(hash) =>
 					this.network_service.user_protocol_mut().propagate_transaction(&hash),
 				ServiceToWorkerMsg::PropagateTransactions =>
@@ -1461,7 +1555,9 @@ impl<'a, B: BlockT, H: ExHashT> Link<B> for NetworkLink<'a, B, H> {
 	}
 	fn justification_imported(&mut self, who: PeerId, hash: &B::Hash, number: NumberFor<B>, success: bool) {
 		self.protocol.user_protocol_mut().justification_import_result(hash.clone(), number, success);
-		if !success {
+		if !success && who == *Swarm::<B, H>::local_peer_id(&*self.protocol) {
+			info!("💔 Invalid justification submitted locally for #{}", hash);
+		} else if !success {
 			info!("💔 Invalid justification provided by {} for #{}", who, hash);
 			self.protocol.user_protocol_mut().disconnect_peer(&who);
 			self.protocol.user_protocol_mut().report_peer(who, ReputationChange::new_fatal("Invalid justification"));
@@ -1479,13 +1575,7 @@ impl<'a, B: BlockT, H: ExHashT> Link<B> for NetworkLink<'a, B, H> {
 		request_block: (B::Hash, NumberFor<B>),
 		finalization_result: Result<(B::Hash, NumberFor<B>), ()>,
 	) {
-		let success = finalization_result.is_ok();
-		self.protocol.user_protocol_mut().finality_proof_import_result(request_block, finalization_result);
-		if !success {
-			info!("💔 Invalid finality proof provided by {} for #{}", who, request_block.0);
-			self.protocol.user_protocol_mut().disconnect_peer(&who);
-			self.protocol.user_protocol_mut().report_peer(who, ReputationChange::new_fatal("Invalid finality proof"));
-		}
+		self.protocol.user_protocol_mut().finality_proof_import_result(who, request_block, finalization_result);
 	}
 }
 
@@ -228,6 +228,11 @@ impl Metrics {
 					.with_label_values(&["dht", "sent", name])
 					.inc_by(num);
 			}
+			Event::SecondaryFinalityProof { .. } => {
+				self.events_total
+					.with_label_values(&["secondary-finality-proof", "sent", name])
+					.inc_by(num);
+			}
 			Event::NotificationStreamOpened { engine_id, .. } => {
 				self.events_total
 					.with_label_values(&[&format!("notif-open-{:?}", engine_id), "sent", name])
@@ -258,6 +263,11 @@ impl Metrics {
 					.with_label_values(&["dht", "received", name])
 					.inc();
 			}
+			Event::SecondaryFinalityProof { .. } => {
+				self.events_total
+					.with_label_values(&["secondary-finality-proof", "received", name])
+					.inc();
+			}
 			Event::NotificationStreamOpened { engine_id, .. } => {
 				self.events_total
 					.with_label_values(&[&format!("notif-open-{:?}", engine_id), "received", name])
@@ -18,14 +18,17 @@
 
 use crate::{config, Event, NetworkService, NetworkWorker};
 
+use codec::Encode as _;
 use libp2p::PeerId;
 use futures::prelude::*;
 use sp_runtime::traits::{Block as BlockT, Header as _};
 use std::{sync::Arc, time::Duration};
 use substrate_test_runtime_client::{TestClientBuilder, TestClientBuilderExt as _};
 
+type TestBlock = substrate_test_runtime_client::runtime::Block;
+
 type TestNetworkService = NetworkService<
-	substrate_test_runtime_client::runtime::Block,
+	TestBlock,
 	substrate_test_runtime_client::runtime::Hash,
 >;
 
@@ -37,6 +40,15 @@ type TestNetworkService = NetworkService<
 fn build_test_full_node(config: config::NetworkConfiguration)
 	-> (Arc<TestNetworkService>, impl Stream<Item = Event>)
 {
+	try_build_test_full_node(config, None).unwrap()
+}
+
+/// Same as [`build_test_full_node`], but with an optional secondary finality proof protocol, and
+/// returns the error if the network worker can't be created.
+fn try_build_test_full_node(
+	config: config::NetworkConfiguration,
+	secondary_finality_proof: Option<config::SecondaryFinalityProofConfig<TestBlock>>,
+) -> Result<(Arc<TestNetworkService>, impl Stream<Item = Event>), crate::error::Error> {
 	let client = Arc::new(
 		TestClientBuilder::with_default_backend()
 			.build_with_longest_chain()
@@ -99,6 +111,7 @@ fn build_test_full_node(config: config::NetworkConfiguration)
 		chain: client.clone(),
 		finality_proof_provider: None,
 		finality_proof_request_builder: None,
+		secondary_finality_proof,
 		on_demand: None,
 		transaction_pool: Arc::new(crate::config::EmptyTransactionPool),
 		protocol_id: config::ProtocolId::from(&b"/test-protocol-name"[..]),
@@ -106,9 +119,12 @@ fn build_test_full_node(config: config::NetworkConfiguration)
 		block_announce_validator: Box::new(
 			sp_consensus::block_validation::DefaultBlockAnnounceValidator,
 		),
+		suppress_block_announcements: false,
+		max_announce_block_age: None,
+		block_announce_validation_limit: None,
+		sync_request_timeout: config::DEFAULT_SYNC_REQUEST_TIMEOUT,
 		metrics_registry: None,
-	})
-	.unwrap();
+	})?;
 
 	let service = worker.service().clone();
 	let event_stream = service.event_stream("test");
@@ -118,7 +134,7 @@ fn build_test_full_node(config: config::NetworkConfiguration)
 		let _ = worker.await;
 	});
 
-	(service, event_stream)
+	Ok((service, event_stream))
 }
 
 const ENGINE_ID: sp_runtime::ConsensusEngineId = *b"foo\0";
@@ -270,11 +286,46 @@ fn notifications_state_consistent() {
 				// Add new events here.
 				future::Either::Left(Event::Dht(_)) => {}
 				future::Either::Right(Event::Dht(_)) => {}
+				future::Either::Left(Event::SecondaryFinalityProof { .. }) => {}
+				future::Either::Right(Event::SecondaryFinalityProof { .. }) => {}
 			};
 		}
 	});
 }
 
+#[test]
+fn repeated_penalties_disconnect_peer() {
+	let (node1, mut events_stream1, node2, _) = build_nodes_one_proto();
+
+	async_std::task::block_on(async move {
+		loop {
+			match events_stream1.next().await.unwrap() {
+				Event::NotificationStreamOpened { remote, .. } => {
+					assert_eq!(remote, *node2.local_peer_id());
+					break;
+				}
+				_ => {}
+			}
+		}
+
+		// A single one of these doesn't get the peer under the ban threshold.
+		let penalty = crate::ReputationChange::new(i32::min_value() / 10, "Test penalty");
+		for _ in 0..10 {
+			node1.report_peer(node2.local_peer_id().clone(), penalty);
+		}
+
+		loop {
+			match events_stream1.next().await.unwrap() {
+				Event::NotificationStreamClosed { remote, .. } => {
+					assert_eq!(remote, *node2.local_peer_id());
+					break;
+				}
+				_ => {}
+			}
+		}
+	});
+}
+
 #[test]
 fn lots_of_incoming_peers_works() {
 	let listen_addr = config::build_multiaddr![Memory(rand::random::<u64>())];
@@ -345,6 +396,88 @@ fn lots_of_incoming_peers_works() {
 	});
 }
 
+#[test]
+fn secondary_finality_proof_works() {
+	struct EchoProofProvider;
+	impl config::FinalityProofProvider<TestBlock> for EchoProofProvider {
+		fn prove_finality(
+			&self,
+			_for_block: <TestBlock as BlockT>::Hash,
+			request: &[u8],
+		) -> Result<Option<Vec<u8>>, sp_blockchain::Error> {
+			Ok(Some([b"proof of ", request].concat()))
+		}
+	}
+
+	let listen_addr = config::build_multiaddr![Memory(rand::random::<u64>())];
+
+	let (node1, _) = try_build_test_full_node(
+		config::NetworkConfiguration {
+			notifications_protocols: vec![(ENGINE_ID, From::from(&b"/foo"[..]))],
+			listen_addresses: vec![listen_addr.clone()],
+			transport: config::TransportConfig::MemoryOnly,
+			.. config::NetworkConfiguration::new_local()
+		},
+		Some(config::SecondaryFinalityProofConfig {
+			protocol_name: "bridge-finality/1".into(),
+			provider: Some(Arc::new(EchoProofProvider)),
+		}),
+	).unwrap();
+
+	let (node2, mut events_stream2) = try_build_test_full_node(
+		config::NetworkConfiguration {
+			notifications_protocols: vec![(ENGINE_ID, From::from(&b"/foo"[..]))],
+			listen_addresses: vec![],
+			reserved_nodes: vec![config::MultiaddrWithPeerId {
+				multiaddr: listen_addr,
+				peer_id: node1.local_peer_id().clone(),
+			}],
+			transport: config::TransportConfig::MemoryOnly,
+			.. config::NetworkConfiguration::new_local()
+		},
+		Some(config::SecondaryFinalityProofConfig {
+			protocol_name: "bridge-finality/1".into(),
+			provider: None,
+		}),
+	).unwrap();
+
+	let block_hash = <TestBlock as BlockT>::Hash::repeat_byte(1);
+
+	async_std::task::block_on(async move {
+		loop {
+			match events_stream2.next().await.unwrap() {
+				Event::NotificationStreamOpened { remote, .. } => {
+					assert_eq!(remote, *node1.local_peer_id());
+					node2.request_secondary_finality_proof(remote, block_hash, b"request".to_vec());
+				}
+				Event::SecondaryFinalityProof { remote, block_hash: hash, proof } => {
+					assert_eq!(remote, *node1.local_peer_id());
+					assert_eq!(hash, block_hash.encode());
+					assert_eq!(proof, b"proof of request".to_vec());
+					break;
+				}
+				_ => {}
+			}
+		}
+	});
+}
+
+#[test]
+fn secondary_finality_proof_protocol_must_differ_from_sync_one() {
+	let result = try_build_test_full_node(
+		config::NetworkConfiguration::new_local(),
+		Some(config::SecondaryFinalityProofConfig {
+			protocol_name: "finality-proof/1".into(),
+			provider: None,
+		}),
+	);
+
+	match result {
+		Err(crate::error::Error::ConflictingFinalityProofProtocol(_)) => {}
+		_ => panic!("Expected the conflicting protocol to be rejected"),
+	}
+}
+
 #[test]
 #[should_panic(expected = "don't match the transport")]
 fn ensure_listen_addresses_consistent_with_transport_memory() {
@@ -20,7 +20,8 @@
 
 use sp_consensus::ImportedAux;
 use sp_consensus::import_queue::{
-	import_single_block, BasicQueue, BlockImportError, BlockImportResult, IncomingBlock,
+	import_single_block, BasicQueue, BlockImportError, BlockImportResult, ImportQueue, IncomingBlock,
+	Link,
 };
 use substrate_test_runtime_client::{self, prelude::*};
 use substrate_test_runtime_client::runtime::{Block, Hash};
@@ -114,3 +115,117 @@ fn async_import_queue_drops() {
 		drop(queue);
 	}
 }
+
+/// Builds `count` blocks on top of genesis, each with a justification of `justification_size`
+/// bytes.
+fn prepare_large_blocks(count: usize, justification_size: usize) -> Vec<IncomingBlock<Block>> {
+	let mut client = substrate_test_runtime_client::new();
+	(0..count).map(|_| {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::File, block.clone()).unwrap();
+		let (header, body) = block.deconstruct();
+		IncomingBlock {
+			hash: header.hash(),
+			header: Some(header),
+			body: Some(body),
+			justification: Some(vec![0; justification_size]),
+			origin: None,
+			allow_missing_state: false,
+			import_existing: false,
+		}
+	}).collect()
+}
+
+/// Counts the blocks imported by an import queue.
+#[derive(Default)]
+struct CountingLink {
+	imported: usize,
+	processed: usize,
+}
+
+impl Link<Block> for CountingLink {
+	fn blocks_processed(
+		&mut self,
+		imported: usize,
+		count: usize,
+		_results: Vec<(Result<BlockImportResult<u64>, BlockImportError>, Hash)>,
+	) {
+		self.imported += imported;
+		self.processed += count;
+	}
+}
+
+#[test]
+fn import_queue_buffered_memory_stays_under_budget() {
+	const BLOCKS: usize = 32;
+	const JUSTIFICATION_SIZE: usize = 64 * 1024;
+	const BUDGET: usize = 4 * JUSTIFICATION_SIZE;
+
+	let executor = sp_core::testing::SpawnBlockingExecutor::new();
+	let mut queue = BasicQueue::new(
+		PassThroughVerifier::new(true),
+		Box::new(substrate_test_runtime_client::new()),
+		None,
+		None,
+		&executor,
+		None,
+	).with_mem_budget(Some(BUDGET));
+
+	for block in prepare_large_blocks(BLOCKS, JUSTIFICATION_SIZE) {
+		queue.import_blocks(BlockOrigin::NetworkInitialSync, vec![block]);
+	}
+
+	let mut link = CountingLink::default();
+	let mut buffered_memory = queue.buffered_memory();
+	futures::executor::block_on(futures::future::poll_fn(|cx| {
+		queue.poll_actions(cx, &mut link);
+		// handing the blocks kept back over to the worker doesn't change the total.
+		assert!(queue.buffered_memory() <= buffered_memory);
+		buffered_memory = queue.buffered_memory();
+		if link.processed == BLOCKS {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	}));
+
+	assert_eq!(link.imported, BLOCKS);
+	assert_eq!(queue.buffered_memory(), 0);
+	assert!(!queue.is_saturated());
+}
+
+#[test]
+fn import_queue_imports_blocks_larger_than_the_budget() {
+	const BLOCKS: usize = 4;
+	const BUDGET: usize = 1024;
+
+	let executor = sp_core::testing::SpawnBlockingExecutor::new();
+	let mut queue = BasicQueue::new(
+		PassThroughVerifier::new(true),
+		Box::new(substrate_test_runtime_client::new()),
+		None,
+		None,
+		&executor,
+		None,
+	).with_mem_budget(Some(BUDGET));
+
+	queue.import_blocks(
+		BlockOrigin::NetworkInitialSync,
+		prepare_large_blocks(BLOCKS, 4 * BUDGET),
+	);
+	assert!(queue.is_saturated());
+	// only the first block is handed over to the worker, the others are still accounted for.
+	assert!(queue.buffered_memory() >= (BLOCKS - 1) * 4 * BUDGET);
+
+	let mut link = CountingLink::default();
+	futures::executor::block_on(futures::future::poll_fn(|cx| {
+		queue.poll_actions(cx, &mut link);
+		if link.processed == BLOCKS {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	}));
+
+	assert_eq!(link.imported, BLOCKS);
+}
@@ -48,7 +48,10 @@ use sp_consensus::Error as ConsensusError;
 use sp_consensus::{BlockOrigin, ForkChoiceStrategy, BlockImportParams, BlockCheckParams, JustificationImport};
 use futures::prelude::*;
 use sc_network::{NetworkWorker, NetworkService, config::ProtocolId};
-use sc_network::config::{NetworkConfiguration, TransportConfig, BoxFinalityProofRequestBuilder};
+use sc_network::config::{
+	NetworkConfiguration, TransportConfig, BoxFinalityProofRequestBuilder, MultiaddrWithPeerId,
+	NonReservedPeerMode,
+};
 use libp2p::PeerId;
 use parking_lot::Mutex;
 use sp_core::H256;
@@ -222,6 +225,7 @@ pub struct Peer<D> {
 	select_chain: Option<LongestChain<substrate_test_runtime_client::Backend, Block>>,
 	backend: Option<Arc<substrate_test_runtime_client::Backend>>,
 	network: NetworkWorker<Block, <Block as BlockT>::Hash>,
+	listen_addr: sc_network::Multiaddr,
 	imported_blocks_stream: Pin<Box<dyn Stream<Item = BlockImportNotification<Block>> + Send>>,
 	finality_notification_stream: Pin<Box<dyn Stream<Item = FinalityNotification<Block>> + Send>>,
 }
@@ -232,6 +236,11 @@ impl<D> Peer<D> {
 		self.network.service().local_peer_id().clone()
 	}
 
+	/// Get the address this peer listens on, along with its ID.
+	pub fn listen_address(&self) -> MultiaddrWithPeerId {
+		MultiaddrWithPeerId { multiaddr: self.listen_addr.clone(), peer_id: self.id() }
+	}
+
 	/// Returns true if we're major syncing.
 	pub fn is_major_syncing(&self) -> bool {
 		self.network.service().is_major_syncing()
@@ -553,6 +562,14 @@ pub struct FullPeerConfig {
 	pub keep_blocks: Option<u32>,
 	/// Block announce validator.
 	pub block_announce_validator: Option<Box<dyn BlockAnnounceValidator<Block> + Send + Sync>>,
+	/// Never announce blocks to other peers.
+	pub suppress_block_announcements: bool,
+	/// Only connect to reserved peers, of which there are none.
+	pub reserved_only: bool,
+	/// Serve the blocks of the chain to the nodes bootstrapping from us.
+	pub serve_block_archive: bool,
+	/// Node to download the blocks from when starting.
+	pub block_archive_source: Option<MultiaddrWithPeerId>,
 }
 
 pub trait TestNetFactory: Sized {
@@ -663,6 +680,11 @@ pub trait TestNetFactory: Sized {
 		network_config.transport = TransportConfig::MemoryOnly;
 		network_config.listen_addresses = vec![listen_addr.clone()];
 		network_config.allow_non_globals_in_dht = true;
+		if config.reserved_only {
+			network_config.non_reserved_mode = NonReservedPeerMode::Deny;
+		}
+		network_config.serve_block_archive = config.serve_block_archive;
+		network_config.block_archive_source = config.block_archive_source;
 
 		let network = NetworkWorker::new(sc_network::config::Params {
 			role: Role::Full,
@@ -673,12 +695,17 @@ pub trait TestNetFactory: Sized {
 				PeersClient::Full(client.clone(), backend.clone()),
 			),
 			finality_proof_request_builder,
+			secondary_finality_proof: None,
 			on_demand: None,
 			transaction_pool: Arc::new(EmptyTransactionPool),
 			protocol_id: ProtocolId::from(&b"test-protocol-name"[..]),
 			import_queue,
 			block_announce_validator: config.block_announce_validator
 				.unwrap_or(Box::new(DefaultBlockAnnounceValidator)),
+			suppress_block_announcements: config.suppress_block_announcements,
+			max_announce_block_age: None,
+			block_announce_validation_limit: None,
+			sync_request_timeout: sc_network::config::DEFAULT_SYNC_REQUEST_TIMEOUT,
 			metrics_registry: None,
 		}).unwrap();
 
@@ -700,6 +727,7 @@ pub trait TestNetFactory: Sized {
 				block_import,
 				verifier,
 				network,
+				listen_addr,
 			});
 		});
 	}
@@ -753,11 +781,16 @@ pub trait TestNetFactory: Sized {
 				PeersClient::Light(client.clone(), backend.clone())
 			),
 			finality_proof_request_builder,
+			secondary_finality_proof: None,
 			on_demand: None,
 			transaction_pool: Arc::new(EmptyTransactionPool),
 			protocol_id: ProtocolId::from(&b"test-protocol-name"[..]),
 			import_queue,
 			block_announce_validator: Box::new(DefaultBlockAnnounceValidator),
+			suppress_block_announcements: false,
+			max_announce_block_age: None,
+			block_announce_validation_limit: None,
+			sync_request_timeout: sc_network::config::DEFAULT_SYNC_REQUEST_TIMEOUT,
 			metrics_registry: None,
 		}).unwrap();
 
@@ -779,6 +812,7 @@ pub trait TestNetFactory: Sized {
 				imported_blocks_stream,
 				finality_notification_stream,
 				network,
+				listen_addr,
 			});
 		});
 	}
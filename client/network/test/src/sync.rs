@@ -313,6 +313,48 @@ fn sync_justifications_across_forks() {
 	}));
 }
 
+#[test]
+fn justifications_can_be_imported_separately_from_blocks() {
+	let _ = ::env_logger::try_init();
+	let mut net = JustificationTestNet::new(1);
+	let hash = net.peer(0).push_blocks(10, false);
+	assert_eq!(net.peer(0).client().info().finalized_number, 0);
+
+	// a justification for a block we don't have is rejected
+	let mut unknown = net.peer(0).network_service()
+		.import_justification(Default::default(), 11, Vec::new())
+		.boxed_local();
+	let result = block_on(futures::future::poll_fn(|cx| {
+		net.poll(cx);
+		unknown.poll_unpin(cx)
+	}));
+	match result {
+		Err(sc_network::error::Error::UnknownBlock(_)) => {},
+		other => panic!("Unexpected result: {:?}", other),
+	}
+	assert_eq!(net.peer(0).client().info().finalized_number, 0);
+
+	// a justification for an imported block finalizes it
+	let mut import = net.peer(0).network_service()
+		.import_justification(hash, 10, Vec::new())
+		.boxed_local();
+	block_on(futures::future::poll_fn(|cx| {
+		net.poll(cx);
+		import.poll_unpin(cx)
+	})).unwrap();
+	block_on(futures::future::poll_fn::<(), _>(|cx| {
+		net.poll(cx);
+
+		if net.peer(0).client().info().finalized_number == 10 {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	}));
+
+	assert_eq!(net.peer(0).client().justification(&BlockId::Number(10)).unwrap(), Some(Vec::new()));
+}
+
 #[test]
 fn sync_after_fork_works() {
 	let _ = ::env_logger::try_init();
@@ -750,3 +792,64 @@ fn sync_blocks_when_block_announce_validator_says_it_is_new_best() {
 	// that flags all blocks as `is_new_best` and thus, it should have synced the blocks.
 	assert!(!net.peer(1).has_block(&block_hash));
 }
+
+#[test]
+fn suppressed_block_announcements_are_not_sent_but_blocks_are_served() {
+	let _ = env_logger::try_init();
+	let mut net = TestNet::new(0);
+	net.add_full_peer_with_config(FullPeerConfig {
+		suppress_block_announcements: true,
+		..Default::default()
+	});
+	net.add_full_peer();
+
+	net.block_until_connected();
+
+	// Neither the automatic nor the explicit announcement reaches peer 1.
+	let block_hash = net.peer(0).push_blocks(1, false);
+	net.peer(0).announce_block(block_hash, Vec::new());
+	net.block_until_idle();
+	assert!(!net.peer(1).has_block(&block_hash));
+
+	// A new peer learns about the best block of peer 0 when connecting and downloads it.
+	net.add_full_peer();
+	while !net.peer(2).has_block(&block_hash) {
+		net.block_until_idle();
+	}
+}
+
+#[test]
+fn bootstraps_from_block_archive() {
+	let _ = env_logger::try_init();
+	let mut net = TestNet::new(0);
+	net.add_full_peer_with_config(FullPeerConfig {
+		serve_block_archive: true,
+		..Default::default()
+	});
+	// More blocks than fit in a single response.
+	let best_hash = net.peer(0).push_blocks(300, false);
+
+	// Peer 1 doesn't accept any peer for syncing, so the blocks can only come from the archive.
+	let source = net.peer(0).listen_address();
+	net.add_full_peer_with_config(FullPeerConfig {
+		reserved_only: true,
+		block_archive_source: Some(source),
+		..Default::default()
+	});
+
+	block_on(futures::future::poll_fn::<(), _>(|cx| {
+		net.poll(cx);
+		if net.peer(1).client().info().best_hash == best_hash {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	}));
+
+	assert_eq!(net.peer(1).num_peers(), 0);
+	assert_eq!(net.peer(1).client().info().best_number, 300);
+	assert_eq!(
+		net.peer(1).client().header(&BlockId::Hash(best_hash)).unwrap(),
+		net.peer(0).client().header(&BlockId::Hash(best_hash)).unwrap(),
+	);
+}
@@ -31,19 +31,86 @@ use fnv::FnvHashMap;
 use futures::{prelude::*, future, channel::mpsc};
 use log::error;
 use sp_core::offchain::{HttpRequestId, Timestamp, HttpRequestStatus, HttpError};
-use std::{convert::TryFrom, fmt, io::Read as _, pin::Pin, task::{Context, Poll}};
+use std::{
+	convert::TryFrom, fmt, io::Read as _, num::NonZeroUsize, pin::Pin,
+	task::{Context, Poll, Waker},
+};
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender, TracingUnboundedReceiver};
 use std::sync::Arc;
 use hyper::{Client as HyperClient, Body, client};
 use hyper_rustls::HttpsConnector;
+use parking_lot::Mutex;
 
 /// Wrapper struct used for keeping the hyper_rustls client running.
 #[derive(Clone)]
-pub struct SharedClient(Arc<HyperClient<HttpsConnector<client::HttpConnector>, Body>>);
+pub struct SharedClient {
+	/// The engine that runs HTTP requests.
+	client: Arc<HyperClient<HttpsConnector<client::HttpConnector>, Body>>,
+	/// Limit on the number of requests in flight, shared by everyone using this client.
+	request_limit: Option<RequestLimit>,
+}
 
 impl SharedClient {
 	pub fn new() -> Self {
-		Self(Arc::new(HyperClient::builder().build(HttpsConnector::new())))
+		Self::with_request_limit(None)
+	}
+
+	/// Creates a client that allows at most `max_requests` HTTP requests to be in flight at the
+	/// same time across all the offchain workers sharing it.
+	///
+	/// A request is in flight from the moment it is dispatched until its response headers are
+	/// received.
+	pub fn with_request_limit(max_requests: Option<NonZeroUsize>) -> Self {
+		Self {
+			client: Arc::new(HyperClient::builder().build(HttpsConnector::new())),
+			request_limit: max_requests.map(RequestLimit::new),
+		}
+	}
+}
+
+/// Semaphore bounding the number of HTTP requests in flight.
+#[derive(Clone)]
+struct RequestLimit(Arc<Mutex<RequestSlots>>);
+
+struct RequestSlots {
+	/// Number of requests that can still be dispatched.
+	available: usize,
+	/// Tasks waiting for a slot to be freed.
+	waiting: Vec<Waker>,
+}
+
+/// A slot taken from a [`RequestLimit`]. The slot is freed when this is dropped.
+struct RequestPermit(Arc<Mutex<RequestSlots>>);
+
+impl RequestLimit {
+	fn new(max_requests: NonZeroUsize) -> Self {
+		RequestLimit(Arc::new(Mutex::new(RequestSlots {
+			available: max_requests.get(),
+			waiting: Vec::new(),
+		})))
+	}
+
+	fn poll_acquire(&self, cx: &mut Context) -> Poll<RequestPermit> {
+		let mut slots = self.0.lock();
+		if slots.available > 0 {
+			slots.available -= 1;
+			Poll::Ready(RequestPermit(self.0.clone()))
+		} else {
+			if !slots.waiting.iter().any(|waker| waker.will_wake(cx.waker())) {
+				slots.waiting.push(cx.waker().clone());
+			}
+			Poll::Pending
+		}
+	}
+}
+
+impl Drop for RequestPermit {
+	fn drop(&mut self) {
+		let mut slots = self.0.lock();
+		slots.available += 1;
+		for waker in slots.waiting.drain(..) {
+			waker.wake();
+		}
 	}
 }
 
@@ -64,7 +131,8 @@ pub fn http(shared_client: SharedClient) -> (HttpApi, HttpWorker) {
 	let engine = HttpWorker {
 		to_api,
 		from_api,
-		http_client: shared_client.0,
+		http_client: shared_client.client,
+		request_limit: shared_client.request_limit,
 		requests: Vec::new(),
 	};
 
@@ -565,14 +633,19 @@ pub struct HttpWorker {
 	from_api: TracingUnboundedReceiver<ApiToWorker>,
 	/// The engine that runs HTTP requests.
 	http_client: Arc<HyperClient<HttpsConnector<client::HttpConnector>, Body>>,
+	/// Limit on the number of requests in flight across all workers, if any.
+	request_limit: Option<RequestLimit>,
 	/// HTTP requests that are being worked on by the engine.
 	requests: Vec<(HttpRequestId, HttpWorkerRequest)>,
 }
 
 /// HTTP request being processed by the worker.
 enum HttpWorkerRequest {
-	/// Request has been dispatched and is waiting for a response from the Internet.
-	Dispatched(hyper::client::ResponseFuture),
+	/// Request is waiting for a free slot in the global request limit before being sent out.
+	Queued(hyper::Request<hyper::Body>),
+	/// Request has been dispatched and is waiting for a response from the Internet. Holds a slot
+	/// in the global request limit, if any, until the response headers are received.
+	Dispatched(hyper::client::ResponseFuture, Option<RequestPermit>),
 	/// Progressively reading the body of the response and sending it to the channel.
 	ReadBody {
 		/// Body to read `Chunk`s from. Only used if the channel is ready to accept data.
@@ -596,11 +669,26 @@ impl Future for HttpWorker {
 		for n in (0..me.requests.len()).rev() {
 			let (id, request) = me.requests.swap_remove(n);
 			match request {
-				HttpWorkerRequest::Dispatched(mut future) => {
+				HttpWorkerRequest::Queued(request) => {
+					let permit = match me.request_limit.as_ref().map(|limit| limit.poll_acquire(cx)) {
+						None => None,
+						Some(Poll::Ready(permit)) => Some(permit),
+						Some(Poll::Pending) => {
+							me.requests.push((id, HttpWorkerRequest::Queued(request)));
+							continue
+						}
+					};
+
+					let future = me.http_client.request(request);
+					me.requests.push((id, HttpWorkerRequest::Dispatched(future, permit)));
+					cx.waker().wake_by_ref();	// reschedule the task to poll the request
+				}
+
+				HttpWorkerRequest::Dispatched(mut future, permit) => {
 					// Check for an HTTP response from the Internet.
 					let response = match Future::poll(Pin::new(&mut future), cx) {
 						Poll::Pending => {
-							me.requests.push((id, HttpWorkerRequest::Dispatched(future)));
+							me.requests.push((id, HttpWorkerRequest::Dispatched(future, permit)));
 							continue
 						},
 						Poll::Ready(Ok(response)) => response,
@@ -610,7 +698,9 @@ impl Future for HttpWorker {
 						}
 					};
 
-					// We received a response! Decompose it into its parts.
+					// We received a response! The request isn't in flight anymore, free its slot and
+					// decompose the response into its parts.
+					drop(permit);
 					let (head, body) = response.into_parts();
 					let (status_code, headers) = (head.status, head.headers);
 
@@ -664,10 +754,9 @@ impl Future for HttpWorker {
 			Poll::Pending => {},
 			Poll::Ready(None) => return Poll::Ready(()),	// stops the worker
 			Poll::Ready(Some(ApiToWorker::Dispatch { id, request })) => {
-				let future = me.http_client.request(request);
 				debug_assert!(me.requests.iter().all(|(i, _)| *i != id));
-				me.requests.push((id, HttpWorkerRequest::Dispatched(future)));
-				cx.waker().wake_by_ref();	// reschedule the task to poll the request
+				me.requests.push((id, HttpWorkerRequest::Queued(request)));
+				cx.waker().wake_by_ref();	// reschedule the task to dispatch the request
 			}
 		}
 
@@ -686,7 +775,9 @@ impl fmt::Debug for HttpWorker {
 impl fmt::Debug for HttpWorkerRequest {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match self {
-			HttpWorkerRequest::Dispatched(_) =>
+			HttpWorkerRequest::Queued(_) =>
+				f.debug_tuple("HttpWorkerRequest::Queued").finish(),
+			HttpWorkerRequest::Dispatched(..) =>
 				f.debug_tuple("HttpWorkerRequest::Dispatched").finish(),
 			HttpWorkerRequest::ReadBody { .. } =>
 				f.debug_tuple("HttpWorkerRequest::Response").finish(),
@@ -700,6 +791,7 @@ mod tests {
 	use crate::api::timestamp;
 	use super::{http, SharedClient};
 	use sp_core::offchain::{HttpError, HttpRequestId, HttpRequestStatus, Duration};
+	use std::num::NonZeroUsize;
 	use futures::future;
 	use lazy_static::lazy_static;
 	
@@ -710,10 +802,15 @@ mod tests {
 	}
 
 	// Returns an `HttpApi` whose worker is ran in the background, and a `SocketAddr` to an HTTP
-	// server that runs in the background as well.
+	// server that runs in the background as well. The server optionally waits for `$delay` before
+	// answering each request.
 	macro_rules! build_api_server {
-		() => {{
-			let hyper_client = SHARED_CLIENT.clone();
+		() => {
+			build_api_server!(SHARED_CLIENT.clone(), std::time::Duration::from_millis(0))
+		};
+		($client:expr, $delay:expr) => {{
+			let hyper_client = $client;
+			let delay = $delay;
 			let (api, worker) = http(hyper_client.clone());
 
 			let (addr_tx, addr_rx) = std::sync::mpsc::channel();
@@ -724,6 +821,7 @@ mod tests {
 					let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
 						.serve(hyper::service::make_service_fn(|_| { async move {
 							Ok::<_, Infallible>(hyper::service::service_fn(move |_req| async move {
+								futures_timer::Delay::new(delay).await;
 								Ok::<_, Infallible>(
 									hyper::Response::new(hyper::Body::from("Hello World!"))
 								)
@@ -762,6 +860,63 @@ mod tests {
 		assert_eq!(&buf[..n], b"Hello World!");
 	}
 
+	#[test]
+	fn global_request_limit_throttles_requests() {
+		let deadline = timestamp::now().add(Duration::from_millis(10_000));
+
+		// Two workers sharing a client that allows a single request in flight.
+		let client = SharedClient::with_request_limit(NonZeroUsize::new(1));
+		let (mut slow_api, slow_addr) =
+			build_api_server!(client.clone(), std::time::Duration::from_millis(1_000));
+		let (mut api, addr) = build_api_server!(client, std::time::Duration::from_millis(0));
+
+		// The first request holds the only slot until its response headers are received.
+		let slow_id = slow_api.request_start("POST", &format!("http://{}", slow_addr)).unwrap();
+		slow_api.request_write_body(slow_id, &[], Some(deadline)).unwrap();
+
+		// Requests of the other worker wait for a slot in the meantime.
+		let id = api.request_start("POST", &format!("http://{}", addr)).unwrap();
+		api.request_write_body(id, &[], Some(deadline)).unwrap();
+		let short_deadline = timestamp::now().add(Duration::from_millis(100));
+		match api.response_wait(&[id], Some(short_deadline))[0] {
+			HttpRequestStatus::DeadlineReached => {},
+			v => panic!("Request should have been throttled: {:?}", v)
+		}
+
+		// Receiving the response of the first request frees the slot, even if its body isn't
+		// read yet.
+		match slow_api.response_wait(&[slow_id], Some(deadline))[0] {
+			HttpRequestStatus::Finished(200) => {},
+			v => panic!("Connecting to localhost failed: {:?}", v)
+		}
+		match api.response_wait(&[id], Some(deadline))[0] {
+			HttpRequestStatus::Finished(200) => {},
+			v => panic!("Request should have been dispatched: {:?}", v)
+		}
+
+		let mut buf = vec![0; 2048];
+		let n = slow_api.response_read_body(slow_id, &mut buf, Some(deadline)).unwrap();
+		assert_eq!(&buf[..n], b"Hello World!");
+	}
+
+	#[test]
+	fn global_request_limit_doesnt_block_waiting_on_several_requests() {
+		let client = SharedClient::with_request_limit(NonZeroUsize::new(1));
+		let (mut api, addr) = build_api_server!(client, std::time::Duration::from_millis(100));
+
+		let ids = (0..3)
+			.map(|_| api.request_start("POST", &format!("http://{}", addr)).unwrap())
+			.collect::<Vec<_>>();
+
+		// The requests are sent one after the other, without a deadline to give up on.
+		for status in api.response_wait(&ids, None) {
+			match status {
+				HttpRequestStatus::Finished(200) => {},
+				v => panic!("Request should have been dispatched: {:?}", v)
+			}
+		}
+	}
+
 	#[test]
 	fn request_start_invalid_call() {
 		let (mut api, addr) = build_api_server!();
@@ -27,6 +27,10 @@ impl SharedClient {
 	pub fn new() -> Self {
 		Self
 	}
+
+	pub fn with_request_limit(_: Option<std::num::NonZeroUsize>) -> Self {
+		Self
+	}
 }
 
 /// Creates a pair of [`HttpApi`] and [`HttpWorker`].
@@ -33,7 +33,7 @@
 
 #![warn(missing_docs)]
 
-use std::{fmt, marker::PhantomData, sync::Arc};
+use std::{fmt, marker::PhantomData, num::NonZeroUsize, sync::Arc};
 
 use parking_lot::Mutex;
 use threadpool::ThreadPool;
@@ -62,7 +62,17 @@ pub struct OffchainWorkers<Client, Storage, Block: traits::Block> {
 impl<Client, Storage, Block: traits::Block> OffchainWorkers<Client, Storage, Block> {
 	/// Creates new `OffchainWorkers`.
 	pub fn new(client: Arc<Client>, db: Storage) -> Self {
-		let shared_client = SharedClient::new();
+		Self::with_http_request_limit(client, db, None)
+	}
+
+	/// Creates new `OffchainWorkers` that allow at most `max_http_requests` HTTP requests to be
+	/// in flight at the same time, across all the spawned workers.
+	pub fn with_http_request_limit(
+		client: Arc<Client>,
+		db: Storage,
+		max_http_requests: Option<NonZeroUsize>,
+	) -> Self {
+		let shared_client = SharedClient::with_request_limit(max_http_requests);
 		Self {
 			client,
 			db,
@@ -624,6 +624,29 @@ impl Peerset {
 		})
 	}
 
+	/// Returns the known peers whose reputation isn't zero, along with their reputation.
+	///
+	/// Reputations can be restored, e.g. after a restart, by reporting them as a
+	/// [`ReputationChange`] to a new peerset.
+	pub fn reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.update_time();
+
+		self.data.peers().cloned().collect::<Vec<_>>().into_iter().filter_map(|peer_id| {
+			let reputation = match self.data.peer(&peer_id) {
+				peersstate::Peer::Connected(entry) => entry.reputation(),
+				peersstate::Peer::NotConnected(entry) => entry.reputation(),
+				peersstate::Peer::Unknown(_) =>
+					unreachable!("We iterate over the known peers; QED")
+			};
+
+			if reputation == 0 {
+				None
+			} else {
+				Some((peer_id, reputation))
+			}
+		}).collect()
+	}
+
 	/// Returns the number of peers that we have discovered.
 	pub fn num_discovered_peers(&self) -> usize {
 		self.data.peers().len()
@@ -99,6 +99,8 @@ const POOL_CYCLE_DETECTED: i64 = POOL_INVALID_TX + 5;
 const POOL_IMMEDIATELY_DROPPED: i64 = POOL_INVALID_TX + 6;
 /// The key type crypto is not known.
 const UNSUPPORTED_KEY_TYPE: i64 = POOL_INVALID_TX + 7;
+/// The transaction or its sender is banned by the node operator.
+const POOL_BANNED: i64 = POOL_INVALID_TX + 8;
 
 impl From<Error> for rpc::Error {
 	fn from(e: Error) -> Self {
@@ -138,6 +140,11 @@ impl From<Error> for rpc::Error {
 				message: "Transaction is temporarily banned".into(),
 				data: None,
 			},
+			Error::Pool(PoolError::Banned) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(POOL_BANNED),
+				message: "Transaction is banned".into(),
+				data: None,
+			},
 			Error::Pool(PoolError::AlreadyImported(hash)) => rpc::Error {
 				code: rpc::ErrorCode::ServerError(POOL_ALREADY_IMPORTED),
 				message: "Transaction Already Imported".into(),
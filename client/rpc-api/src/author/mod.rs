@@ -20,6 +20,7 @@
 
 pub mod error;
 pub mod hash;
+pub mod pool;
 
 use jsonrpc_derive::rpc;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
@@ -70,6 +71,11 @@ pub trait AuthorApi<Hash, BlockHash> {
 	#[rpc(name = "author_pendingExtrinsics")]
 	fn pending_extrinsics(&self) -> Result<Vec<Bytes>>;
 
+	/// Returns the ready and future transactions of the pool, with the reason they are not
+	/// included yet.
+	#[rpc(name = "author_pendingExtrinsicsStatus")]
+	fn pending_extrinsics_status(&self) -> Result<Vec<pool::PoolTransaction<Hash>>>;
+
 	/// Remove given extrinsic from the pool and temporarily ban it to prevent reimporting.
 	#[rpc(name = "author_removeExtrinsic")]
 	fn remove_extrinsic(&self,
@@ -0,0 +1,49 @@
+// Copyright 2019-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Status of the transactions in the pool.
+
+use sp_core::Bytes;
+use serde::{Serialize, Deserialize};
+
+/// Whether a transaction of the pool can be included in a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PoolTransactionStatus {
+	/// Can be included in the next block, transactions with a higher priority go first.
+	Ready,
+	/// Requires something that neither the chain nor the other transactions of the pool provide
+	/// yet, e.g. the transaction with the previous nonce of the same sender.
+	Future,
+}
+
+/// A transaction of the pool, as reported by `author_pendingExtrinsicsStatus`.
+///
+/// Only the hash and the status are filled when unsafe RPC calls are denied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolTransaction<Hash> {
+	/// The hash of the transaction.
+	pub hash: Hash,
+	/// Whether the transaction is ready.
+	pub status: PoolTransactionStatus,
+	/// Human readable explanation of the status.
+	pub reason: Option<String>,
+	/// Priority of the transaction, as returned by the runtime validation.
+	pub priority: Option<u64>,
+	/// Raw extrinsic bytes.
+	pub extrinsic: Option<Bytes>,
+}
@@ -51,12 +51,23 @@ pub enum Error {
 		/// Maximum allowed value
 		max: u32,
 	},
+	/// The state of the block is not available anymore, e.g. because it has been pruned.
+	#[display(fmt = "State of block {} is not available", _0)]
+	#[from(ignore)]
+	StateUnavailable(String),
+	/// Call to an unsafe RPC was denied.
+	UnsafeRpcCalled(crate::policy::UnsafeRpcError),
+	/// Too many runtime calls are already being made on behalf of RPC clients.
+	#[display(fmt = "Too many concurrent runtime calls, the limit is {}", _0)]
+	#[from(ignore)]
+	TooManyRuntimeCalls(usize),
 }
 
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
 			Error::Client(ref err) => Some(&**err),
+			Error::UnsafeRpcCalled(ref err) => Some(err),
 			_ => None,
 		}
 	}
@@ -78,6 +89,17 @@ impl From<Error> for rpc::Error {
 				message: format!("{}", e),
 				data: None,
 			},
+			Error::StateUnavailable(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 3),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::TooManyRuntimeCalls(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 4),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::UnsafeRpcCalled(e) => e.into(),
 			e => errors::internal(e),
 		}
 	}
@@ -18,7 +18,7 @@
 
 //! Substrate state API helpers.
 
-use sp_core::Bytes;
+use sp_core::{Bytes, storage::{StorageKey, StorageData}};
 use serde::{Serialize, Deserialize};
 
 /// ReadProof struct returned by the RPC
@@ -30,3 +30,15 @@ pub struct ReadProof<Hash> {
 	/// A proof used to prove that storage entries are included in the storage trie
 	pub proof: Vec<Bytes>,
 }
+
+/// Storage accesses of a block, returned by the RPC
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTrace<Hash> {
+	/// Hash of the traced block
+	pub at: Hash,
+	/// Keys read from the state of the parent block, in lexicographic order
+	pub reads: Vec<StorageKey>,
+	/// Keys written by the block, in lexicographic order, with their new value or `None` if removed
+	pub writes: Vec<(StorageKey, Option<StorageData>)>,
+}
@@ -31,7 +31,7 @@ use sp_version::RuntimeVersion;
 use self::error::FutureResult;
 
 pub use self::gen_client::Client as StateClient;
-pub use self::helpers::ReadProof;
+pub use self::helpers::{ReadProof, BlockTrace};
 
 /// Substrate state API
 #[rpc]
@@ -108,6 +108,13 @@ pub trait StateApi<Hash> {
 	#[rpc(name = "state_getReadProof")]
 	fn read_proof(&self, keys: Vec<StorageKey>, hash: Option<Hash>) -> FutureResult<ReadProof<Hash>>;
 
+	/// Re-executes a block on top of the state of its parent, and returns the storage keys it
+	/// read and wrote.
+	///
+	/// Only the top-level storage is traced.
+	#[rpc(name = "state_traceBlock")]
+	fn trace_block(&self, hash: Hash) -> FutureResult<BlockTrace<Hash>>;
+
 	/// New runtime version subscription
 	#[pubsub(
 		subscription = "state_runtimeVersion",
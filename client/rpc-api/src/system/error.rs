@@ -32,9 +32,28 @@ pub enum Error {
 	NotHealthy(Health),
 	/// Peer argument is malformatted.
 	MalformattedPeerArg(String),
+	/// The node has no state cache.
+	#[display(fmt = "The node has no state cache")]
+	NoStateCache,
+	/// The log filter couldn't be read or changed.
+	#[display(fmt = "{}", _0)]
+	#[from(ignore)]
+	LogFilter(String),
+	/// The node has no inherent data providers.
+	#[display(fmt = "The node has no inherent data providers")]
+	NoInherentDataProviders,
+	/// Call to an unsafe RPC was denied.
+	UnsafeRpcCalled(crate::policy::UnsafeRpcError),
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::UnsafeRpcCalled(err) => Some(err),
+			_ => None,
+		}
+	}
+}
 
 /// Base code for all system errors.
 const BASE_ERROR: i64 = 2000;
@@ -51,7 +70,23 @@ impl From<Error> for rpc::Error {
 				code :rpc::ErrorCode::ServerError(BASE_ERROR + 2),
 				message: e.clone(),
 				data: None,
-			}
+			},
+			Error::NoStateCache => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 3),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::LogFilter(ref e) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 4),
+				message: e.clone(),
+				data: None,
+			},
+			Error::NoInherentDataProviders => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 5),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::UnsafeRpcCalled(e) => e.into(),
 		}
 	}
 }
@@ -21,6 +21,7 @@
 use std::fmt;
 use serde::{Serialize, Deserialize};
 use sp_chain_spec::{Properties, ChainType};
+use sp_core::Bytes;
 
 /// Running node's static details.
 #[derive(Clone, Debug)]
@@ -88,6 +89,58 @@ pub enum NodeRole {
 	Sentry,
 }
 
+/// Whether the node is set up to act as an authority.
+///
+/// Only reports whether the keys are present, never the keys themselves.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorityStatus {
+	/// The roles the node is running as
+	pub roles: Vec<NodeRole>,
+	/// Does the keystore hold session keys
+	///
+	/// `None` if the node doesn't know which keys identify an authority.
+	pub has_session_keys: Option<bool>,
+	/// Is one of the session keys part of the current authority set
+	///
+	/// `None` if the current authority set isn't known.
+	pub is_active_authority: Option<bool>,
+}
+
+/// Hit and miss counters of the state cache.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateCacheStats {
+	/// Number of reads answered from the cache
+	pub hits: u64,
+	/// Number of reads that had to go to the database
+	pub misses: u64,
+}
+
+/// Counters of the state cache around a flush.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateCacheFlush {
+	/// Counters accumulated until the flush
+	pub before: StateCacheStats,
+	/// Counters right after the flush
+	pub after: StateCacheStats,
+}
+
+/// What an inherent data provider of the node currently provides.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InherentDataEntry {
+	/// Identifier of the inherent, e.g. `timstap0`
+	pub identifier: String,
+	/// SCALE encoded data, `None` if the provider failed
+	pub data: Option<Bytes>,
+	/// Human readable form of the data, if the provider can decode it
+	pub decoded: Option<String>,
+	/// Error returned by the provider
+	pub error: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -117,4 +170,40 @@ mod tests {
 			r#"{"peerId":"2","roles":"a","protocolVersion":2,"bestHash":5,"bestNumber":6}"#,
 		);
 	}
+
+	#[test]
+	fn should_serialize_authority_status() {
+		assert_eq!(
+			::serde_json::to_string(&AuthorityStatus {
+				roles: vec![NodeRole::Authority],
+				has_session_keys: Some(true),
+				is_active_authority: None,
+			}).unwrap(),
+			r#"{"roles":["Authority"],"hasSessionKeys":true,"isActiveAuthority":null}"#,
+		);
+	}
+
+	#[test]
+	fn should_serialize_state_cache_flush() {
+		assert_eq!(
+			::serde_json::to_string(&StateCacheFlush {
+				before: StateCacheStats { hits: 3, misses: 1 },
+				after: StateCacheStats::default(),
+			}).unwrap(),
+			r#"{"before":{"hits":3,"misses":1},"after":{"hits":0,"misses":0}}"#,
+		);
+	}
+
+	#[test]
+	fn should_serialize_inherent_data_entry() {
+		assert_eq!(
+			::serde_json::to_string(&InherentDataEntry {
+				identifier: "timstap0".into(),
+				data: Some(vec![1, 2].into()),
+				decoded: None,
+				error: None,
+			}).unwrap(),
+			r#"{"identifier":"timstap0","data":"0x0102","decoded":null,"error":null}"#,
+		);
+	}
 }
@@ -27,7 +27,10 @@ use futures::{future::BoxFuture, compat::Compat};
 
 use self::error::Result as SystemResult;
 
-pub use self::helpers::{SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{
+	SystemInfo, Health, PeerInfo, NodeRole, AuthorityStatus, StateCacheStats, StateCacheFlush,
+	InherentDataEntry,
+};
 pub use self::gen_client::Client as SystemClient;
 
 /// Substrate system RPC API
@@ -103,4 +106,31 @@ pub trait SystemApi<Hash, Number> {
 	/// Returns the roles the node is running as.
 	#[rpc(name = "system_nodeRoles", returns = "Vec<NodeRole>")]
 	fn system_node_roles(&self) -> Receiver<Vec<NodeRole>>;
+
+	/// Returns whether the node holds session keys and whether they are part of the current
+	/// authority set.
+	#[rpc(name = "system_authorityStatus", returns = "AuthorityStatus")]
+	fn system_authority_status(&self) -> Receiver<AuthorityStatus>;
+
+	/// Evicts all the entries of the state cache and resets its hit and miss counters.
+	///
+	/// Returns the counters accumulated until the flush, and right after it.
+	#[rpc(name = "system_flushStateCache")]
+	fn system_flush_state_cache(&self) -> SystemResult<StateCacheFlush>;
+
+	/// Returns the directives of the current log filter, e.g. `sync=debug,info`.
+	#[rpc(name = "system_logFilter")]
+	fn system_log_filter(&self) -> SystemResult<String>;
+
+	/// Replaces the log filter with the given directives.
+	///
+	/// The current filter is kept if any of the directives is invalid.
+	#[rpc(name = "system_setLogFilter")]
+	fn system_set_log_filter(&self, directives: String) -> SystemResult<()>;
+
+	/// Runs the inherent data providers of the node, and returns what each of them provides.
+	///
+	/// A failing provider is reported in its entry, the other ones are still run.
+	#[rpc(name = "system_inherentData")]
+	fn system_inherent_data(&self) -> SystemResult<Vec<InherentDataEntry>>;
 }
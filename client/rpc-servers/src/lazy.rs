@@ -0,0 +1,172 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! RPC handler that is only built when requests come in.
+
+use std::{sync::{Arc, Weak}, time::{Duration, Instant}};
+use jsonrpc_core::{
+	futures::{future::Either, Future},
+	middleware, MetaIoHandler, Middleware, Request, Response,
+};
+use log::debug;
+use parking_lot::Mutex;
+use pubsub::{PubSubMetadata, Session};
+use crate::RpcHandler;
+
+struct Inner<M: PubSubMetadata> {
+	build: Mutex<Box<dyn FnMut() -> RpcHandler<M> + Send>>,
+	idle_timeout: Duration,
+	state: Mutex<State<M>>,
+}
+
+struct State<M: PubSubMetadata> {
+	handler: Option<Arc<RpcHandler<M>>>,
+	last_request: Instant,
+	/// Sessions that went through the handler, they may hold subscriptions.
+	sessions: Vec<Weak<Session>>,
+}
+
+/// Middleware answering the requests with an RPC handler that is built on the first request, and
+/// dropped once it has been unused for a while.
+///
+/// The handler is kept as long as a session that used it, e.g. a Websocket connection, is open,
+/// since the session may have subscriptions.
+pub struct LazyRpcHandler<M: PubSubMetadata>(Arc<Inner<M>>);
+
+impl<M: PubSubMetadata> Clone for LazyRpcHandler<M> {
+	fn clone(&self) -> Self {
+		LazyRpcHandler(self.0.clone())
+	}
+}
+
+impl<M: PubSubMetadata> LazyRpcHandler<M> {
+	/// Creates a lazy handler, `build` is called every time the handler has to be rebuilt.
+	pub fn new(
+		idle_timeout: Duration,
+		build: impl FnMut() -> RpcHandler<M> + Send + 'static,
+	) -> Self {
+		LazyRpcHandler(Arc::new(Inner {
+			build: Mutex::new(Box::new(build)),
+			idle_timeout,
+			state: Mutex::new(State {
+				handler: None,
+				last_request: Instant::now(),
+				sessions: Vec::new(),
+			}),
+		}))
+	}
+
+	/// Returns whether the handler is currently built.
+	pub fn is_built(&self) -> bool {
+		self.0.state.lock().handler.is_some()
+	}
+
+	/// Drops the handler if it didn't receive any request for the idle timeout, and none of the
+	/// sessions that used it is still open.
+	pub fn drop_if_idle(&self) {
+		let mut state = self.0.state.lock();
+		state.sessions.retain(|session| session.strong_count() > 0);
+		if state.handler.is_some()
+			&& state.sessions.is_empty()
+			&& state.last_request.elapsed() >= self.0.idle_timeout
+		{
+			debug!("Dropping the idle RPC handler");
+			state.handler = None;
+		}
+	}
+
+	/// Wraps the lazy handler into an `RpcHandler` that can be given to the servers.
+	pub fn into_rpc_handler(self) -> RpcHandler<M, Self> {
+		pubsub::PubSubHandler::new(MetaIoHandler::with_middleware(self))
+	}
+
+	fn handler(&self, meta: &M) -> Arc<RpcHandler<M>> {
+		let mut state = self.0.state.lock();
+		state.last_request = Instant::now();
+
+		if let Some(session) = meta.session() {
+			let known = state.sessions.iter()
+				.any(|s| s.upgrade().map_or(false, |s| Arc::ptr_eq(&s, &session)));
+			if !known {
+				state.sessions.push(Arc::downgrade(&session));
+			}
+		}
+
+		match &state.handler {
+			Some(handler) => handler.clone(),
+			None => {
+				debug!("Building the RPC handler");
+				let handler = Arc::new((&mut *self.0.build.lock())());
+				state.handler = Some(handler.clone());
+				handler
+			},
+		}
+	}
+}
+
+impl<M: PubSubMetadata> Middleware<M> for LazyRpcHandler<M> {
+	type Future = middleware::NoopFuture;
+	type CallFuture = middleware::NoopCallFuture;
+
+	fn on_request<F, X>(&self, request: Request, meta: M, _next: F) -> Either<Self::Future, X>
+	where
+		F: Fn(Request, M) -> X + Send + Sync,
+		X: Future<Item = Option<Response>, Error = ()> + Send + 'static,
+	{
+		let handler = self.handler(&meta);
+		Either::A(Box::new(handler.handle_rpc_request(request, meta)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use jsonrpc_core::Value;
+
+	type Metadata = Option<Arc<Session>>;
+
+	#[test]
+	fn handler_is_built_on_first_request_and_dropped_when_idle() {
+		let builds = Arc::new(AtomicUsize::new(0));
+		let lazy = LazyRpcHandler::<Metadata>::new(Duration::from_secs(0), {
+			let builds = builds.clone();
+			move || {
+				builds.fetch_add(1, Ordering::SeqCst);
+				let mut io = RpcHandler::default();
+				io.add_method("test_hello", |_| Ok(Value::String("hello".into())));
+				io
+			}
+		});
+		let io = lazy.clone().into_rpc_handler();
+		assert!(!lazy.is_built());
+		assert_eq!(builds.load(Ordering::SeqCst), 0);
+
+		let request = r#"{"jsonrpc":"2.0","method":"test_hello","params":[],"id":1}"#;
+		let response = r#"{"jsonrpc":"2.0","result":"hello","id":1}"#;
+		assert_eq!(io.handle_request_sync(request, None), Some(response.into()));
+		assert_eq!(io.handle_request_sync(request, None), Some(response.into()));
+		assert!(lazy.is_built());
+		assert_eq!(builds.load(Ordering::SeqCst), 1);
+
+		lazy.drop_if_idle();
+		assert!(!lazy.is_built());
+		assert_eq!(io.handle_request_sync(request, None), Some(response.into()));
+		assert_eq!(builds.load(Ordering::SeqCst), 2);
+	}
+}
@@ -20,6 +20,8 @@
 
 #![warn(missing_docs)]
 
+mod lazy;
+
 use std::io;
 use jsonrpc_core::IoHandlerExtension;
 use log::error;
@@ -32,9 +34,10 @@ const MAX_PAYLOAD: usize = 15 * 1024 * 1024;
 const WS_MAX_CONNECTIONS: usize = 100;
 
 /// The RPC IoHandler containing all requested APIs.
-pub type RpcHandler<T> = pubsub::PubSubHandler<T>;
+pub type RpcHandler<T, S = jsonrpc_core::middleware::Noop> = pubsub::PubSubHandler<T, S>;
 
 pub use self::inner::*;
+pub use self::lazy::LazyRpcHandler;
 
 /// Construct rpc `IoHandler`
 pub fn rpc_handler<M: PubSubMetadata>(
@@ -72,10 +75,10 @@ mod inner {
 	/// Start HTTP server listening on given address.
 	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_http<M: pubsub::PubSubMetadata + Default>(
+	pub fn start_http<M: pubsub::PubSubMetadata + Default, S: jsonrpc_core::Middleware<M>>(
 		addr: &std::net::SocketAddr,
 		cors: Option<&Vec<String>>,
-		io: RpcHandler<M>,
+		io: RpcHandler<M, S>,
 	) -> io::Result<http::Server> {
 		http::ServerBuilder::new(io)
 			.threads(4)
@@ -94,9 +97,9 @@ mod inner {
 	/// Start IPC server listening on given path.
 	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_ipc<M: pubsub::PubSubMetadata + Default>(
+	pub fn start_ipc<M: pubsub::PubSubMetadata + Default, S: jsonrpc_core::Middleware<M>>(
 		addr: &str,
-		io: RpcHandler<M>,
+		io: RpcHandler<M, S>,
 	) -> io::Result<ipc::Server> {
 		let builder = ipc::ServerBuilder::new(io);
 		#[cfg(target_os = "unix")]
@@ -111,11 +114,14 @@ mod inner {
 	/// Start WS server listening on given address.
 	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_ws<M: pubsub::PubSubMetadata + From<jsonrpc_core::futures::sync::mpsc::Sender<String>>> (
+	pub fn start_ws<
+		M: pubsub::PubSubMetadata + From<jsonrpc_core::futures::sync::mpsc::Sender<String>>,
+		S: jsonrpc_core::Middleware<M>,
+	> (
 		addr: &std::net::SocketAddr,
 		max_connections: Option<usize>,
 		cors: Option<&Vec<String>>,
-		io: RpcHandler<M>,
+		io: RpcHandler<M, S>,
 	) -> io::Result<ws::Server> {
 		ws::ServerBuilder::with_meta_extractor(io, |context: &ws::RequestContext| context.sender().into())
 			.max_payload(MAX_PAYLOAD)
@@ -35,7 +35,7 @@ use futures::future::{ready, FutureExt, TryFutureExt};
 use sc_rpc_api::DenyUnsafe;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId, manager::SubscriptionManager};
 use codec::{Encode, Decode};
-use sp_core::{Bytes, traits::BareCryptoStorePtr};
+use sp_core::{Bytes, hexdisplay::HexDisplay, traits::BareCryptoStorePtr};
 use sp_api::ProvideRuntimeApi;
 use sp_runtime::generic;
 use sp_transaction_pool::{
@@ -49,7 +49,7 @@ pub use sc_rpc_api::author::*;
 use self::error::{Error, FutureResult, Result};
 
 /// Authoring API
-pub struct Author<P, Client> {
+pub struct Author<P: TransactionPool, Client> {
 	/// Substrate client
 	client: Arc<Client>,
 	/// Transactions pool
@@ -60,9 +60,11 @@ pub struct Author<P, Client> {
 	keystore: BareCryptoStorePtr,
 	/// Whether to deny unsafe calls
 	deny_unsafe: DenyUnsafe,
+	/// Called with the hash of every extrinsic before it is submitted to the pool.
+	on_submit: Option<Arc<dyn Fn(&TxHash<P>) + Send + Sync>>,
 }
 
-impl<P, Client> Author<P, Client> {
+impl<P: TransactionPool, Client> Author<P, Client> {
 	/// Create new instance of Authoring API.
 	pub fn new(
 		client: Arc<Client>,
@@ -77,8 +79,22 @@ impl<P, Client> Author<P, Client> {
 			subscriptions,
 			keystore,
 			deny_unsafe,
+			on_submit: None,
 		}
 	}
+
+	/// Calls `on_submit` with the hash of every extrinsic submitted through this API, before it
+	/// is handed over to the pool.
+	///
+	/// The pool doesn't tell the extrinsics submitted over RPC from the ones received from the
+	/// network, this lets the node treat them differently anyway.
+	pub fn with_submit_hook(
+		mut self,
+		on_submit: impl Fn(&TxHash<P>) + Send + Sync + 'static,
+	) -> Self {
+		self.on_submit = Some(Arc::new(on_submit));
+		self
+	}
 }
 
 /// Currently we treat all RPC transactions as externals.
@@ -146,6 +162,9 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 			Ok(xt) => xt,
 			Err(err) => return Box::new(result(Err(err.into()))),
 		};
+		if let Some(on_submit) = self.on_submit.as_ref() {
+			on_submit(&self.pool.hash_of(&xt));
+		}
 		let best_block_hash = self.client.info().best_hash;
 		Box::new(self.pool
 			.submit_one(&generic::BlockId::hash(best_block_hash), TX_SOURCE, xt)
@@ -160,6 +179,43 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 		Ok(self.pool.ready().map(|tx| tx.data().encode().into()).collect())
 	}
 
+	fn pending_extrinsics_status(&self) -> Result<Vec<pool::PoolTransaction<TxHash<P>>>> {
+		// Without unsafe calls, only the hashes and statuses are disclosed.
+		let detailed = self.deny_unsafe.check_if_safe().is_ok();
+		let describe = |
+			tx: &P::InPoolTransaction,
+			status: pool::PoolTransactionStatus,
+			reason: String,
+		| pool::PoolTransaction {
+			hash: tx.hash().clone(),
+			status,
+			reason: if detailed { Some(reason) } else { None },
+			priority: if detailed { Some(*tx.priority()) } else { None },
+			extrinsic: if detailed { Some(tx.data().encode().into()) } else { None },
+		};
+
+		let ready = self.pool.ready().map(|tx| describe(
+			&tx,
+			pool::PoolTransactionStatus::Ready,
+			format!("Waiting for inclusion in a block, with priority {}", tx.priority()),
+		));
+		let futures = self.pool.futures().into_iter().map(|(tx, missing_tags)| {
+			let missing_tags = missing_tags.iter()
+				.map(|tag| format!("0x{}", HexDisplay::from(tag)))
+				.collect::<Vec<_>>();
+			describe(
+				&tx,
+				pool::PoolTransactionStatus::Future,
+				format!(
+					"Waiting for tags provided by neither the chain nor the pool: {}",
+					missing_tags.join(", "),
+				),
+			)
+		});
+
+		Ok(ready.chain(futures).collect())
+	}
+
 	fn remove_extrinsic(
 		&self,
 		bytes_or_hash: Vec<hash::ExtrinsicOrHash<TxHash<P>>>,
@@ -194,6 +250,9 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 			let best_block_hash = self.client.info().best_hash;
 			let dxt = TransactionFor::<P>::decode(&mut &xt[..])
 				.map_err(error::Error::from)?;
+			if let Some(on_submit) = self.on_submit.as_ref() {
+				on_submit(&self.pool.hash_of(&dxt));
+			}
 			Ok(
 				self.pool
 					.submit_and_watch(&generic::BlockId::hash(best_block_hash), TX_SOURCE, dxt)
@@ -85,6 +85,7 @@ impl TestSetup {
 			subscriptions: SubscriptionManager::new(Arc::new(crate::testing::TaskExecutor)),
 			keystore: self.keystore.clone(),
 			deny_unsafe: DenyUnsafe::No,
+			on_submit: None,
 		}
 	}
 }
@@ -104,6 +105,20 @@ fn submit_transaction_should_not_cause_error() {
 	);
 }
 
+#[test]
+fn submit_hook_is_called_with_the_submitted_hashes() {
+	let submitted = Arc::new(parking_lot::Mutex::new(Vec::new()));
+	let p = {
+		let submitted = submitted.clone();
+		TestSetup::default().author().with_submit_hook(move |hash| submitted.lock().push(*hash))
+	};
+	let xt = uxt(AccountKeyring::Alice, 0).encode();
+	let h: H256 = blake2_256(&xt).into();
+
+	AuthorApi::submit_extrinsic(&p, xt.into()).wait().unwrap();
+	assert_eq!(*submitted.lock(), vec![h]);
+}
+
 #[test]
 fn submit_rich_transaction_should_not_cause_error() {
 	let p = TestSetup::default().author();
@@ -200,6 +215,46 @@ fn should_return_pending_extrinsics() {
 	);
 }
 
+#[test]
+fn should_report_future_extrinsics_with_the_reason() {
+	let setup = TestSetup::default();
+	let p = setup.author();
+
+	let ready = uxt(AccountKeyring::Alice, 0);
+	let ready_hash = p.submit_extrinsic(ready.encode().into()).wait().unwrap();
+	// Bob's transaction with nonce 0 is missing.
+	let future = uxt(AccountKeyring::Bob, 1);
+	let future_hash = p.submit_extrinsic(future.encode().into()).wait().unwrap();
+
+	let statuses = p.pending_extrinsics_status().unwrap();
+	assert_eq!(statuses.len(), 2);
+	assert_eq!(statuses[0].hash, ready_hash);
+	assert_eq!(statuses[0].status, pool::PoolTransactionStatus::Ready);
+	assert_eq!(statuses[0].extrinsic, Some(Bytes(ready.encode())));
+	assert_eq!(statuses[1].hash, future_hash);
+	assert_eq!(statuses[1].status, pool::PoolTransactionStatus::Future);
+	assert_eq!(statuses[1].extrinsic, Some(Bytes(future.encode())));
+	let missing_tag = (AccountKeyring::Bob.public(), 0u64).encode();
+	assert_eq!(
+		statuses[1].reason,
+		Some(format!(
+			"Waiting for tags provided by neither the chain nor the pool: 0x{}",
+			HexDisplay::from(&missing_tag),
+		)),
+	);
+
+	// Only the hashes and statuses are disclosed when unsafe calls are denied.
+	let p = Author { deny_unsafe: DenyUnsafe::Yes, ..setup.author() };
+	let statuses = p.pending_extrinsics_status().unwrap();
+	assert_eq!(statuses[1], pool::PoolTransaction {
+		hash: future_hash,
+		status: pool::PoolTransactionStatus::Future,
+		reason: None,
+		priority: None,
+		extrinsic: None,
+	});
+}
+
 #[test]
 fn should_remove_extrinsics() {
 	let setup = TestSetup::default();
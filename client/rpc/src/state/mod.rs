@@ -24,11 +24,11 @@ mod state_light;
 #[cfg(test)]
 mod tests;
 
-use std::sync::Arc;
+use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId, manager::SubscriptionManager};
 use rpc::{Result as RpcResult, futures::{Future, future::result}};
 
-use sc_rpc_api::state::ReadProof;
+use sc_rpc_api::{DenyUnsafe, state::{ReadProof, BlockTrace}};
 use sc_client_api::light::{RemoteBlockchain, Fetcher};
 use sp_core::{Bytes, storage::{StorageKey, PrefixedStorageKey, StorageData, StorageChangeSet}};
 use sp_version::RuntimeVersion;
@@ -40,11 +40,70 @@ use self::error::{Error, FutureResult};
 
 pub use sc_rpc_api::state::*;
 pub use sc_rpc_api::child_state::*;
-use sc_client_api::{ExecutorProvider, StorageProvider, BlockchainEvents, Backend, ProofProvider};
+use sc_client_api::{ExecutorProvider, StorageProvider, BlockchainEvents, Backend, ProofProvider, BlockBackend};
 use sp_blockchain::{HeaderMetadata, HeaderBackend};
 
 const STORAGE_KEYS_PAGED_MAX_COUNT: u32 = 1000;
 
+/// Limit on the number of runtime calls made concurrently by the state RPCs, e.g. `state_call`.
+///
+/// Clones share the same count, so that a single limit applies to all the RPC handlers. Calls
+/// above the limit are rejected rather than queued, keeping runtime instances available for block
+/// import and authorship, which aren't limited.
+///
+/// Only `state_call` and `state_traceBlock` of the full node backend are limited. The light client
+/// backend and custom RPCs calling the runtime API directly don't use it.
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeCallLimit {
+	max: Option<usize>,
+	in_flight: Arc<AtomicUsize>,
+}
+
+impl RuntimeCallLimit {
+	/// Creates a limit of `max` concurrent calls, or no limit if `None`.
+	pub fn new(max: Option<usize>) -> Self {
+		RuntimeCallLimit {
+			max,
+			in_flight: Arc::new(AtomicUsize::new(0)),
+		}
+	}
+
+	/// Reserves a call, returning `None` if the limit is reached.
+	///
+	/// The call is released when the returned guard is dropped.
+	pub fn try_acquire(&self) -> Option<RuntimeCallGuard> {
+		let mut in_flight = self.in_flight.load(Ordering::Acquire);
+		loop {
+			if self.max.map_or(false, |max| in_flight >= max) {
+				return None;
+			}
+			match self.in_flight.compare_exchange(
+				in_flight,
+				in_flight + 1,
+				Ordering::AcqRel,
+				Ordering::Acquire,
+			) {
+				Ok(_) => return Some(RuntimeCallGuard(self.in_flight.clone())),
+				Err(current) => in_flight = current,
+			}
+		}
+	}
+
+	fn acquire(&self) -> Result<RuntimeCallGuard, Error> {
+		self.try_acquire().ok_or_else(|| Error::TooManyRuntimeCalls(self.max.unwrap_or_default()))
+	}
+}
+
+/// A runtime call reserved with [`RuntimeCallLimit::try_acquire`].
+#[derive(Debug)]
+pub struct RuntimeCallGuard(Arc<AtomicUsize>);
+
+impl Drop for RuntimeCallGuard {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::AcqRel);
+	}
+}
+
 /// State backend API.
 pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 	where
@@ -137,6 +196,9 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		keys: Vec<StorageKey>,
 	) -> FutureResult<ReadProof<Block::Hash>>;
 
+	/// Re-executes a block on top of the state of its parent, tracing the storage accesses.
+	fn trace_block(&self, block: Block::Hash) -> FutureResult<BlockTrace<Block::Hash>>;
+
 	/// New runtime version subscription
 	fn subscribe_runtime_version(
 		&self,
@@ -171,21 +233,25 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 pub fn new_full<BE, Block: BlockT, Client>(
 	client: Arc<Client>,
 	subscriptions: SubscriptionManager,
+	deny_unsafe: DenyUnsafe,
+	runtime_call_limit: RuntimeCallLimit,
 ) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
 		BE: Backend<Block> + 'static,
 		Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + ProofProvider<Block> + HeaderBackend<Block>
 			+ HeaderMetadata<Block, Error = sp_blockchain::Error> + BlockchainEvents<Block>
-			+ CallApiAt<Block, Error = sp_blockchain::Error>
+			+ CallApiAt<Block, Error = sp_blockchain::Error> + BlockBackend<Block>
 			+ ProvideRuntimeApi<Block> + Send + Sync + 'static,
 		Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
-	let child_backend = Box::new(
-		self::state_full::FullState::new(client.clone(), subscriptions.clone())
-	);
-	let backend = Box::new(self::state_full::FullState::new(client, subscriptions));
-	(State { backend }, ChildState { backend: child_backend })
+	let child_backend = Box::new(self::state_full::FullState::new(
+		client.clone(),
+		subscriptions.clone(),
+		runtime_call_limit.clone(),
+	));
+	let backend = Box::new(self::state_full::FullState::new(client, subscriptions, runtime_call_limit));
+	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
 }
 
 /// Create new state API that works on light node.
@@ -194,6 +260,7 @@ pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 	subscriptions: SubscriptionManager,
 	remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 	fetcher: Arc<F>,
+	deny_unsafe: DenyUnsafe,
 ) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
@@ -217,12 +284,14 @@ pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 			remote_blockchain,
 			fetcher,
 	));
-	(State { backend }, ChildState { backend: child_backend })
+	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
 }
 
 /// State API with subscriptions support.
 pub struct State<Block, Client> {
 	backend: Box<dyn StateBackend<Block, Client>>,
+	/// Whether to deny unsafe calls
+	deny_unsafe: DenyUnsafe,
 }
 
 impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
@@ -307,6 +376,14 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.read_proof(block, keys)
 	}
 
+	fn trace_block(&self, block: Block::Hash) -> FutureResult<BlockTrace<Block::Hash>> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())));
+		}
+
+		self.backend.trace_block(block)
+	}
+
 	fn subscribe_storage(
 		&self,
 		meta: Self::Metadata,
@@ -20,11 +20,12 @@ use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::ops::Range;
 use futures::{future, StreamExt as _, TryStreamExt as _};
+use codec::Encode;
 use log::warn;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId, manager::SubscriptionManager};
 use rpc::{Result as RpcResult, futures::{stream, Future, Sink, Stream, future::result}};
 
-use sc_rpc_api::state::ReadProof;
+use sc_rpc_api::state::{ReadProof, BlockTrace};
 use sc_client_api::backend::Backend;
 use sp_blockchain::{Result as ClientResult, Error as ClientError, HeaderMetadata, CachedHeaderMetadata, HeaderBackend};
 use sc_client_api::BlockchainEvents;
@@ -34,14 +35,14 @@ use sp_core::{
 };
 use sp_version::RuntimeVersion;
 use sp_runtime::{
-	generic::BlockId, traits::{Block as BlockT, NumberFor, SaturatedConversion, CheckedSub},
+	generic::BlockId, traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion, CheckedSub},
 };
 
 use sp_api::{Metadata, ProvideRuntimeApi, CallApiAt};
 
-use super::{StateBackend, ChildStateBackend, error::{FutureResult, Error, Result}, client_err};
+use super::{StateBackend, ChildStateBackend, RuntimeCallLimit, error::{FutureResult, Error, Result}, client_err};
 use std::marker::PhantomData;
-use sc_client_api::{CallExecutor, StorageProvider, ExecutorProvider, ProofProvider};
+use sc_client_api::{CallExecutor, StorageProvider, ExecutorProvider, ProofProvider, BlockBackend};
 
 /// Ranges to query in state_queryStorage.
 struct QueryStorageRange<Block: BlockT> {
@@ -61,6 +62,7 @@ struct QueryStorageRange<Block: BlockT> {
 pub struct FullState<BE, Block: BlockT, Client> {
 	client: Arc<Client>,
 	subscriptions: SubscriptionManager,
+	runtime_call_limit: RuntimeCallLimit,
 	_phantom: PhantomData<(BE, Block)>
 }
 
@@ -72,8 +74,12 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 		Block: BlockT + 'static,
 {
 	/// Create new state API backend for full nodes.
-	pub fn new(client: Arc<Client>, subscriptions: SubscriptionManager) -> Self {
-		Self { client, subscriptions, _phantom: PhantomData }
+	pub fn new(
+		client: Arc<Client>,
+		subscriptions: SubscriptionManager,
+		runtime_call_limit: RuntimeCallLimit,
+	) -> Self {
+		Self { client, subscriptions, runtime_call_limit, _phantom: PhantomData }
 	}
 
 	/// Returns given block hash or best block hash if None is passed.
@@ -222,7 +228,7 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 	Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + ProofProvider<Block> + HeaderBackend<Block>
 		+ HeaderMetadata<Block, Error = sp_blockchain::Error> + BlockchainEvents<Block>
 		+ CallApiAt<Block, Error = sp_blockchain::Error> + ProvideRuntimeApi<Block>
-		+ Send + Sync + 'static,
+		+ BlockBackend<Block> + Send + Sync + 'static,
 	Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
 	fn call(
@@ -231,6 +237,11 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		method: String,
 		call_data: Bytes,
 	) -> FutureResult<Bytes> {
+		let _call = match self.runtime_call_limit.acquire() {
+			Ok(call) => call,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+
 		let r = self.block_or_best(block)
 			.and_then(|block| self
 				.client
@@ -371,6 +382,44 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		))
 	}
 
+	fn trace_block(&self, block: Block::Hash) -> FutureResult<BlockTrace<Block::Hash>> {
+		let _call = match self.runtime_call_limit.acquire() {
+			Ok(call) => call,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+
+		let trace = || -> ClientResult<_> {
+			let (mut header, extrinsics) = self.client.block(&BlockId::Hash(block))?
+				.ok_or_else(|| ClientError::UnknownBlock(format!("{:?}", block)))?
+				.block
+				.deconstruct();
+			// the seal is added after the execution, the runtime rejects blocks that carry one.
+			header.digest_mut().logs.retain(|d| d.as_seal().is_none());
+			let parent = *header.parent_hash();
+			let (_, trace) = self.client.executor().trace_call(
+				&BlockId::Hash(parent),
+				"Core_execute_block",
+				&Block::new(header, extrinsics).encode(),
+			)?;
+			Ok(trace)
+		};
+
+		Box::new(result(
+			trace()
+				.map(|trace| BlockTrace {
+					at: block,
+					reads: trace.reads.into_iter().map(StorageKey).collect(),
+					writes: trace.writes.into_iter()
+						.map(|(key, value)| (StorageKey(key), value.map(StorageData)))
+						.collect(),
+				})
+				.map_err(|e| match e {
+					ClientError::StateUnavailable(parent) => Error::StateUnavailable(parent),
+					e => client_err(e),
+				}),
+		))
+	}
+
 	fn subscribe_runtime_version(
 		&self,
 		_meta: crate::metadata::Metadata,
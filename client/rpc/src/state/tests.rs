@@ -55,7 +55,12 @@ fn should_return_storage() {
 		.add_extra_child_storage(&child_info, KEY.to_vec(), CHILD_VALUE.to_vec())
 		.build();
 	let genesis_hash = client.genesis_hash();
-	let (client, child) = new_full(Arc::new(client), SubscriptionManager::new(Arc::new(TaskExecutor)));
+	let (client, child) = new_full(
+		Arc::new(client),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		Default::default(),
+	);
 	let key = StorageKey(KEY.to_vec());
 
 	assert_eq!(
@@ -90,7 +95,12 @@ fn should_return_child_storage() {
 		.add_child_storage(&child_info, "key", vec![42_u8])
 		.build());
 	let genesis_hash = client.genesis_hash();
-	let (_client, child) = new_full(client, SubscriptionManager::new(Arc::new(TaskExecutor)));
+	let (_client, child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		Default::default(),
+	);
 	let child_key = prefixed_storage_key();
 	let key = StorageKey(b"key".to_vec());
 
@@ -125,7 +135,12 @@ fn should_return_child_storage() {
 fn should_call_contract() {
 	let client = Arc::new(substrate_test_runtime_client::new());
 	let genesis_hash = client.genesis_hash();
-	let (client, _child) = new_full(client, SubscriptionManager::new(Arc::new(TaskExecutor)));
+	let (client, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		Default::default(),
+	);
 
 	assert_matches!(
 		client.call("balanceOf".into(), Bytes(vec![1,2,3]), Some(genesis_hash).into()).wait(),
@@ -139,7 +154,12 @@ fn should_notify_about_storage_changes() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), SubscriptionManager::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			Default::default(),
+		);
 
 		api.subscribe_storage(Default::default(), subscriber, None.into());
 
@@ -173,7 +193,12 @@ fn should_send_initial_storage_changes_and_notifications() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), SubscriptionManager::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			Default::default(),
+		);
 
 		let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
 
@@ -211,7 +236,12 @@ fn should_send_initial_storage_changes_and_notifications() {
 #[test]
 fn should_query_storage() {
 	fn run_tests(mut client: Arc<TestClient>, has_changes_trie_config: bool) {
-		let (api, _child) = new_full(client.clone(), SubscriptionManager::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			Default::default(),
+		);
 
 		let mut add_block = |nonce| {
 			let mut builder = client.new_block(Default::default()).unwrap();
@@ -428,7 +458,12 @@ fn should_split_ranges() {
 #[test]
 fn should_return_runtime_version() {
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let (api, _child) = new_full(client.clone(), SubscriptionManager::new(Arc::new(TaskExecutor)));
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		Default::default(),
+	);
 
 	let result = "{\"specName\":\"test\",\"implName\":\"parity-test\",\"authoringVersion\":1,\
 		\"specVersion\":2,\"implVersion\":2,\"apis\":[[\"0xdf6acb689907609b\",3],\
@@ -451,7 +486,12 @@ fn should_notify_on_runtime_version_initially() {
 
 	{
 		let client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), SubscriptionManager::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			Default::default(),
+		);
 
 		api.subscribe_runtime_version(Default::default(), subscriber);
 
@@ -477,3 +517,138 @@ fn should_deserialize_storage_key() {
 
 	assert_eq!(k.0.len(), 32);
 }
+
+#[test]
+fn should_trace_block_storage_accesses() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(runtime::Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = block.hash();
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	let alice_balance_key = StorageKey(
+		blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into())).to_vec()
+	);
+	let ferdie_balance_key = StorageKey(
+		blake2_256(&runtime::system::balance_of_key(AccountKeyring::Ferdie.into())).to_vec()
+	);
+
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		Default::default(),
+	);
+	let trace = api.trace_block(block_hash).wait().unwrap();
+	assert_eq!(trace.at, block_hash);
+	assert!(trace.reads.contains(&alice_balance_key));
+	assert!(trace.reads.contains(&ferdie_balance_key));
+	let written = trace.writes.iter().map(|(key, _)| key).collect::<Vec<_>>();
+	assert!(written.contains(&&alice_balance_key));
+	assert!(written.contains(&&ferdie_balance_key));
+
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::Yes,
+		Default::default(),
+	);
+	assert_matches!(api.trace_block(block_hash).wait(), Err(Error::UnsafeRpcCalled(_)));
+}
+
+#[test]
+fn should_trace_sealed_block() {
+	use sp_runtime::traits::Header as _;
+
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(runtime::Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	let block = builder.build().unwrap().block;
+	let unsealed_hash = block.hash();
+	client.import(BlockOrigin::Own, block.clone()).unwrap();
+
+	// the same block, as it is stored once sealed by the consensus engine.
+	let (mut header, extrinsics) = block.deconstruct();
+	header.digest_mut().push(sp_runtime::generic::DigestItem::Seal(*b"test", vec![1, 2, 3]));
+	let sealed = runtime::Block::new(header, extrinsics);
+	let sealed_hash = sealed.hash();
+	client.import(BlockOrigin::Own, sealed).unwrap();
+
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		Default::default(),
+	);
+	let unsealed = api.trace_block(unsealed_hash).wait().unwrap();
+	let sealed = api.trace_block(sealed_hash).wait().unwrap();
+	assert_eq!(sealed.at, sealed_hash);
+	// the seal is stripped before the execution.
+	assert_eq!(sealed.reads, unsealed.reads);
+	assert_eq!(sealed.writes, unsealed.writes);
+}
+
+#[test]
+fn should_reject_runtime_calls_above_limit_without_blocking_import() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let limit = RuntimeCallLimit::new(Some(2));
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		limit.clone(),
+	);
+
+	let api = Arc::new(api);
+
+	// calls being made on behalf of other clients, holding their permits until the block is
+	// imported.
+	let acquired = Arc::new(std::sync::Barrier::new(3));
+	let imported = Arc::new(std::sync::Barrier::new(3));
+	let holders = (0..2).map(|_| {
+		let (limit, acquired, imported) = (limit.clone(), acquired.clone(), imported.clone());
+		std::thread::spawn(move || {
+			let call = limit.try_acquire().unwrap();
+			acquired.wait();
+			imported.wait();
+			drop(call);
+		})
+	}).collect::<Vec<_>>();
+	acquired.wait();
+
+	// concurrent callers are rejected while the permits are held.
+	let callers = (0..4).map(|_| {
+		let api = api.clone();
+		std::thread::spawn(move || (0..10).all(|_| matches!(
+			api.call("Core_version".into(), Bytes(Vec::new()), None).wait(),
+			Err(Error::TooManyRuntimeCalls(2))
+		)))
+	}).collect::<Vec<_>>();
+
+	// block import isn't limited.
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, block).unwrap();
+	assert_eq!(client.chain_info().best_number, 1);
+
+	for caller in callers {
+		assert!(caller.join().unwrap());
+	}
+	assert!(limit.try_acquire().is_none());
+
+	imported.wait();
+	for holder in holders {
+		holder.join().unwrap();
+	}
+	assert!(api.call("Core_version".into(), Bytes(Vec::new()), None).wait().is_ok());
+}
@@ -23,14 +23,21 @@ mod tests;
 
 use futures::{future::BoxFuture, FutureExt, TryFutureExt};
 use futures::{channel::oneshot, compat::Compat};
+use sc_client_api::backend::StateCache;
 use sc_rpc_api::{DenyUnsafe, Receiver};
+use sc_tracing::logging;
+use sp_inherents::{InherentDataProviders, InspectedInherent};
 use sp_utils::mpsc::TracingUnboundedSender;
 use sp_runtime::traits::{self, Header as HeaderT};
+use std::sync::Arc;
 
 use self::error::Result;
 
 pub use sc_rpc_api::system::*;
-pub use self::helpers::{SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{
+	SystemInfo, Health, PeerInfo, NodeRole, AuthorityStatus, StateCacheStats, StateCacheFlush,
+	InherentDataEntry,
+};
 pub use self::gen_client::Client as SystemClient;
 
 macro_rules! bail_if_unsafe {
@@ -45,6 +52,8 @@ macro_rules! bail_if_unsafe {
 pub struct System<B: traits::Block> {
 	info: SystemInfo,
 	send_back: TracingUnboundedSender<Request<B>>,
+	state_cache: Option<Arc<dyn StateCache>>,
+	inherent_data_providers: Option<InherentDataProviders>,
 	deny_unsafe: DenyUnsafe,
 }
 
@@ -66,7 +75,9 @@ pub enum Request<B: traits::Block> {
 	/// Must return any potential parse error.
 	NetworkRemoveReservedPeer(String, oneshot::Sender<Result<()>>),
 	/// Must return the node role.
-	NodeRoles(oneshot::Sender<Vec<NodeRole>>)
+	NodeRoles(oneshot::Sender<Vec<NodeRole>>),
+	/// Must return the authority status of the node.
+	AuthorityStatus(oneshot::Sender<AuthorityStatus>),
 }
 
 impl<B: traits::Block> System<B> {
@@ -74,14 +85,23 @@ impl<B: traits::Block> System<B> {
 	///
 	/// The `send_back` will be used to transmit some of the requests. The user is responsible for
 	/// reading from that channel and answering the requests.
+	///
+	/// The `state_cache` is the one of the backend, if any, flushed by `system_flushStateCache`.
+	///
+	/// The `inherent_data_providers` are the ones used to author blocks, if any, run by
+	/// `system_inherentData`.
 	pub fn new(
 		info: SystemInfo,
 		send_back: TracingUnboundedSender<Request<B>>,
+		state_cache: Option<Arc<dyn StateCache>>,
+		inherent_data_providers: Option<InherentDataProviders>,
 		deny_unsafe: DenyUnsafe,
 	) -> Self {
 		System {
 			info,
 			send_back,
+			state_cache,
+			inherent_data_providers,
 			deny_unsafe,
 		}
 	}
@@ -189,4 +209,62 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		let _ = self.send_back.unbounded_send(Request::NodeRoles(tx));
 		Receiver(Compat::new(rx))
 	}
+
+	fn system_authority_status(&self) -> Receiver<AuthorityStatus> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::AuthorityStatus(tx));
+		Receiver(Compat::new(rx))
+	}
+
+	fn system_flush_state_cache(&self) -> Result<StateCacheFlush> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let state_cache = self.state_cache.as_ref().ok_or(error::Error::NoStateCache)?;
+		let stats = |stats: sc_client_api::backend::StateCacheStats| StateCacheStats {
+			hits: stats.hits,
+			misses: stats.misses,
+		};
+		let before = stats(state_cache.flush());
+		let after = stats(state_cache.stats());
+		Ok(StateCacheFlush { before, after })
+	}
+
+	fn system_log_filter(&self) -> Result<String> {
+		self.deny_unsafe.check_if_safe()?;
+
+		logging::log_filter().map_err(|e| error::Error::LogFilter(e.to_string()))
+	}
+
+	fn system_set_log_filter(&self, directives: String) -> Result<()> {
+		self.deny_unsafe.check_if_safe()?;
+
+		logging::set_log_filter(&directives).map_err(|e| error::Error::LogFilter(e.to_string()))
+	}
+
+	fn system_inherent_data(&self) -> Result<Vec<InherentDataEntry>> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let providers = self.inherent_data_providers.as_ref()
+			.ok_or(error::Error::NoInherentDataProviders)?;
+		let entries = providers.inspect_inherent_data().into_iter()
+			.map(|(identifier, inspected)| {
+				let identifier = String::from_utf8_lossy(&identifier).into_owned();
+				match inspected {
+					InspectedInherent::Provided { data, decoded } => InherentDataEntry {
+						identifier,
+						data: Some(data.into()),
+						decoded,
+						error: None,
+					},
+					InspectedInherent::Failed(error) => InherentDataEntry {
+						identifier,
+						data: None,
+						decoded: None,
+						error: Some(error),
+					},
+				}
+			})
+			.collect();
+		Ok(entries)
+	}
 }
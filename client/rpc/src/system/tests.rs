@@ -18,9 +18,14 @@
 
 use super::*;
 
+use sc_block_builder::BlockBuilderProvider;
+use sc_client_api::{backend::Backend as _, StorageProvider};
 use sc_network::{self, PeerId};
 use sc_network::config::Role;
-use substrate_test_runtime_client::runtime::Block;
+use codec::Decode;
+use sp_core::storage::StorageKey;
+use sp_runtime::generic::BlockId;
+use substrate_test_runtime_client::{prelude::*, runtime::Block, sp_consensus::BlockOrigin};
 use assert_matches::assert_matches;
 use futures::prelude::*;
 use sp_utils::mpsc::tracing_unbounded;
@@ -107,22 +112,29 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				}
+				Request::AuthorityStatus(sender) => {
+					let _ = sender.send(AuthorityStatus {
+						roles: vec![NodeRole::Authority],
+						has_session_keys: Some(true),
+						is_active_authority: Some(false),
+					});
+				}
 			};
 
 			future::ready(())
 		}))
 	});
-	System::new(
-		SystemInfo {
-			impl_name: "testclient".into(),
-			impl_version: "0.2.0".into(),
-			chain_name: "testchain".into(),
-			properties: Default::default(),
-			chain_type: Default::default(),
-		},
-		tx,
-		sc_rpc_api::DenyUnsafe::No
-	)
+	System::new(system_info(), tx, None, None, sc_rpc_api::DenyUnsafe::No)
+}
+
+fn system_info() -> SystemInfo {
+	SystemInfo {
+		impl_name: "testclient".into(),
+		impl_version: "0.2.0".into(),
+		chain_name: "testchain".into(),
+		properties: Default::default(),
+		chain_type: Default::default(),
+	}
 }
 
 fn wait_receiver<T>(rx: Receiver<T>) -> T {
@@ -297,6 +309,18 @@ fn system_node_roles() {
 	);
 }
 
+#[test]
+fn system_authority_status() {
+	assert_eq!(
+		wait_receiver(api(None).system_authority_status()),
+		AuthorityStatus {
+			roles: vec![NodeRole::Authority],
+			has_session_keys: Some(true),
+			is_active_authority: Some(false),
+		}
+	);
+}
+
 #[test]
 fn system_network_add_reserved() {
 	let good_peer_id = "/ip4/198.51.100.19/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV";
@@ -320,3 +344,127 @@ fn system_network_remove_reserved() {
 	assert_eq!(runtime.block_on(good_fut), Ok(()));
 	assert!(runtime.block_on(bad_fut).is_err());
 }
+
+#[test]
+fn system_flush_state_cache_resets_counters() {
+	let (mut client, backend) = TestClientBuilder::new().build_with_backend();
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_storage_change(b"key".to_vec(), Some(b"value".to_vec())).unwrap();
+	let block = builder.build().unwrap().block;
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	let (tx, _rx) = tracing_unbounded("rpc_system_tests");
+	let api = System::<Block>::new(
+		system_info(),
+		tx,
+		backend.state_cache(),
+		None,
+		sc_rpc_api::DenyUnsafe::No,
+	);
+	let read = |key: &[u8]| client.storage(&BlockId::Number(1), &StorageKey(key.to_vec())).unwrap();
+
+	// The value written by the import is cached.
+	assert!(read(b"key").is_some());
+	assert!(read(b"missing").is_none());
+
+	let flush = api.system_flush_state_cache().unwrap();
+	assert!(flush.before.hits >= 1);
+	assert!(flush.before.misses >= 1);
+	assert_eq!(flush.after, StateCacheStats::default());
+
+	// Evicted, but still readable.
+	assert_eq!(read(b"key").map(|data| data.0), Some(b"value".to_vec()));
+	assert_eq!(
+		api.system_flush_state_cache().unwrap().before,
+		StateCacheStats { hits: 0, misses: 1 },
+	);
+}
+
+#[test]
+fn system_flush_state_cache_is_unsafe() {
+	let (_client, backend) = TestClientBuilder::new().build_with_backend();
+	let (tx, _rx) = tracing_unbounded("rpc_system_tests");
+	let api = System::<Block>::new(
+		system_info(),
+		tx,
+		backend.state_cache(),
+		None,
+		sc_rpc_api::DenyUnsafe::Yes,
+	);
+
+	assert_matches!(api.system_flush_state_cache(), Err(error::Error::UnsafeRpcCalled(_)));
+}
+
+#[test]
+fn system_set_log_filter_changes_log_output() {
+	struct Capture(Arc<parking_lot::Mutex<Vec<String>>>);
+
+	impl log::Log for Capture {
+		fn enabled(&self, _: &log::Metadata) -> bool {
+			true
+		}
+
+		fn log(&self, record: &log::Record) {
+			// other tests of this binary log concurrently.
+			if record.target() == "rpc_system_tests" {
+				self.0.lock().push(record.args().to_string());
+			}
+		}
+
+		fn flush(&self) {}
+	}
+
+	let logged = Arc::new(parking_lot::Mutex::new(Vec::new()));
+	sc_tracing::logging::init_logger(Box::new(Capture(logged.clone())), "info").unwrap();
+
+	let (tx, _rx) = tracing_unbounded("rpc_system_tests");
+	let api = System::<Block>::new(system_info(), tx, None, None, sc_rpc_api::DenyUnsafe::No);
+	assert_eq!(api.system_log_filter().unwrap(), "info");
+
+	log::debug!(target: "rpc_system_tests", "filtered out");
+	api.system_set_log_filter("rpc_system_tests=debug,info".into()).unwrap();
+	log::debug!(target: "rpc_system_tests", "debug");
+	log::trace!(target: "rpc_system_tests", "filtered out");
+
+	// An invalid filter leaves the current one in place.
+	assert_matches!(
+		api.system_set_log_filter("rpc_system_tests=loud".into()),
+		Err(error::Error::LogFilter(_))
+	);
+	assert_eq!(api.system_log_filter().unwrap(), "rpc_system_tests=debug,info");
+	log::debug!(target: "rpc_system_tests", "still debug");
+
+	assert_eq!(*logged.lock(), vec!["debug".to_string(), "still debug".to_string()]);
+
+	let (tx, _rx) = tracing_unbounded("rpc_system_tests");
+	let api = System::<Block>::new(system_info(), tx, None, None, sc_rpc_api::DenyUnsafe::Yes);
+	assert_matches!(api.system_set_log_filter("trace".into()), Err(error::Error::UnsafeRpcCalled(_)));
+}
+
+#[test]
+fn system_inherent_data_decodes_the_timestamp() {
+	let (tx, _rx) = tracing_unbounded("rpc_system_tests");
+	let providers = sp_inherents::InherentDataProviders::new();
+	providers.register_provider(sp_timestamp::InherentDataProvider).unwrap();
+	let api = System::<Block>::new(system_info(), tx, None, Some(providers), sc_rpc_api::DenyUnsafe::No);
+
+	let entries = api.system_inherent_data().unwrap();
+	assert_eq!(entries.len(), 1);
+	let entry = &entries[0];
+	assert_eq!(entry.identifier, "timstap0");
+	assert_eq!(entry.error, None);
+	let timestamp = u64::decode(&mut &entry.data.as_ref().unwrap().0[..]).unwrap();
+	assert_eq!(entry.decoded, Some(format!("{} ms since unix epoch", timestamp)));
+}
+
+#[test]
+fn system_inherent_data_needs_providers_and_is_unsafe() {
+	let (tx, _rx) = tracing_unbounded("rpc_system_tests");
+	let api = System::<Block>::new(system_info(), tx, None, None, sc_rpc_api::DenyUnsafe::No);
+	assert_matches!(api.system_inherent_data(), Err(error::Error::NoInherentDataProviders));
+
+	let (tx, _rx) = tracing_unbounded("rpc_system_tests");
+	let providers = sp_inherents::InherentDataProviders::new();
+	let api = System::<Block>::new(system_info(), tx, None, Some(providers), sc_rpc_api::DenyUnsafe::Yes);
+	assert_matches!(api.system_inherent_data(), Err(error::Error::UnsafeRpcCalled(_)));
+}
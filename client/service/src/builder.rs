@@ -18,14 +18,19 @@
 
 use crate::{
 	NetworkStatus, NetworkState, error::Error, DEFAULT_PROTOCOL_ID, MallocSizeOfWasm,
-	start_rpc_servers, build_network_future, TransactionPoolAdapter, TaskManager, SpawnTaskHandle,
-	status_sinks, metrics::MetricsService,
-	client::{light, Client, ClientConfig},
-	config::{Configuration, KeystoreConfig, PrometheusConfig, OffchainWorkerConfig},
+	start_rpc_servers, wait_for_rpc_readiness, RpcServersConfig, build_network_future,
+	TransactionPoolAdapter, TaskManager, SpawnTaskHandle,
+	status_sinks, metrics::{self, MetricsService, MetricsExporter}, events, peer_reputations,
+	client::{light, genesis, Client, ClientConfig},
+	config::{
+		Configuration, KeystoreBackup, KeystoreConfig, PrometheusConfig, OffchainWorkerConfig,
+		RpcReadiness, TaskExecutor,
+	},
 };
 use sc_client_api::{
 	self, light::RemoteBlockchain, execution_extensions::ExtensionsFactory, ExecutorProvider, 
-	ForkBlocks, BadBlocks, CloneableSpawn, UsageProvider, backend::RemoteBackend,
+	ForkBlocks, BadBlocks, CloneableSpawn, UsageProvider, CallExecutor, ExecutionStrategy,
+	backend::{Backend as _, RemoteBackend},
 };
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender, TracingUnboundedReceiver};
 use sc_chain_spec::get_extension;
@@ -38,33 +43,52 @@ use futures::{
 	future::ready,
 };
 use jsonrpc_pubsub::manager::SubscriptionManager;
-use sc_keystore::Store as Keystore;
-use log::{info, warn, error};
-use sc_network::config::{Role, FinalityProofProvider, OnDemand, BoxFinalityProofRequestBuilder};
-use sc_network::NetworkService;
+use sc_keystore::{Store as Keystore, KeyStorePtr};
+use log::{info, warn, error, debug};
+use sc_network::config::{
+	Role, FinalityProofProvider, OnDemand, BoxFinalityProofRequestBuilder, SecondaryFinalityProofConfig,
+	ImmediateScheduler, RequestScheduler,
+};
+use sc_network::{NetworkService, ReputationChange};
 use parking_lot::{Mutex, RwLock};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{
-	Block as BlockT, SaturatedConversion, HashFor, Zero, BlockIdTo,
+	Block as BlockT, Header as HeaderT, SaturatedConversion, HashFor, Zero, BlockIdTo,
 };
 use sp_api::{ProvideRuntimeApi, CallApiAt};
-use sc_executor::{NativeExecutor, NativeExecutionDispatch, RuntimeInfo};
-use std::{collections::HashMap, marker::PhantomData, sync::Arc, pin::Pin};
+use sp_version::RuntimeVersion;
+use sc_executor::{NativeExecutor, NativeExecutionDispatch, RuntimeInfo, sp_wasm_interface::Function};
+use std::{collections::HashMap, marker::PhantomData, path::Path, sync::Arc, pin::Pin, time::Duration};
 use wasm_timer::SystemTime;
 use sc_telemetry::{telemetry, SUBSTRATE_INFO};
-use sp_transaction_pool::{LocalTransactionPool, MaintainedTransactionPool};
+use sp_transaction_pool::{
+	InPoolTransaction, LocalTransactionPool, MaintainedTransactionPool, TransactionPool,
+	TransactionSource, TxHash,
+};
+use rand::Rng;
 use prometheus_endpoint::Registry;
 use sc_client_db::{Backend, DatabaseSettings};
-use sp_core::traits::CodeExecutor;
+use sp_core::{
+	H256, crypto::KeyTypeId, storage::well_known_keys,
+	traits::{BareCryptoStore, CodeExecutor, KeystoreExt},
+};
+use sp_externalities::Extensions;
+use codec::{Decode, Encode};
 use sp_runtime::BuildStorage;
 use sc_client_api::{
 	BlockBackend, BlockchainEvents,
-	backend::StorageProvider,
+	backend::{AuxStore, BlockImportOperation, StateBackend, StorageProvider},
 	proof_provider::ProofProvider,
 	execution_extensions::ExecutionExtensions
 };
 use sp_blockchain::{HeaderMetadata, HeaderBackend};
-use crate::{ServiceComponents, TelemetryOnConnectSinks, RpcHandlers, NetworkStatusSinks};
+use sp_inherents::{InherentDataProviders, InherentCheckFailureHook};
+use crate::{
+	ServiceComponents, TelemetryOnConnectSinks, RpcHandlers, NetworkStatusSinks, AuthoritySetProvider,
+	DelayedTransactions, Heartbeat,
+};
+#[cfg(not(target_os = "unknown"))]
+use crate::finality_webhook;
 
 /// Aggregator for the components required to build a service.
 ///
@@ -96,11 +120,16 @@ pub struct ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
 	pub (crate) import_queue: TImpQu,
 	finality_proof_request_builder: Option<TFprb>,
 	finality_proof_provider: Option<TFpp>,
+	secondary_finality_proof: Option<SecondaryFinalityProofConfig<TBl>>,
 	transaction_pool: Arc<TExPool>,
 	rpc_extensions_builder: Box<dyn RpcExtensionBuilder<Output = TRpc> + Send>,
 	remote_backend: Option<Arc<dyn RemoteBlockchain<TBl>>>,
 	marker: PhantomData<(TBl, TRtApi)>,
 	block_announce_validator_builder: Option<Box<dyn FnOnce(Arc<TCl>) -> Box<dyn BlockAnnounceValidator<TBl> + Send> + Send>>,
+	metrics_exporter: Option<Box<dyn MetricsExporter<TBl>>>,
+	authority_set_provider: Option<Arc<dyn AuthoritySetProvider>>,
+	inherent_data_providers: Option<InherentDataProviders>,
+	subscription_executor: Option<TaskExecutor>,
 }
 
 /// A utility trait for building an RPC extension given a `DenyUnsafe` instance.
@@ -198,7 +227,9 @@ pub type TLightCallExecutor<TBl, TExecDisp> = sc_light::GenesisCallExecutor<
 	>,
 >;
 
-type TFullParts<TBl, TRtApi, TExecDisp> = (
+/// Full client, backend, keystore and task manager, as returned by
+/// [`new_full_parts_with_host_functions`].
+pub type TFullParts<TBl, TRtApi, TExecDisp> = (
 	TFullClient<TBl, TRtApi, TExecDisp>,
 	Arc<TFullBackend<TBl>>,
 	Arc<RwLock<sc_keystore::Store>>,
@@ -220,9 +251,23 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 ) -> Result<TFullParts<TBl, TRtApi, TExecDisp>,	Error> where
 	TBl: BlockT,
 	TExecDisp: NativeExecutionDispatch + 'static,
+{
+	new_full_parts_with_host_functions(config, Vec::new())
+}
+
+/// Creates the parts of a full node, the executor registering `host_functions` in addition to the
+/// ones of `TExecDisp`.
+///
+/// Fails if one of `host_functions` has the same name as another host function.
+pub fn new_full_parts_with_host_functions<TBl, TRtApi, TExecDisp>(
+	config: &Configuration,
+	host_functions: Vec<&'static dyn Function>,
+) -> Result<TFullParts<TBl, TRtApi, TExecDisp>,	Error> where
+	TBl: BlockT,
+	TExecDisp: NativeExecutionDispatch + 'static,
 {
 	let keystore = match &config.keystore {
-		KeystoreConfig::Path { path, password } => Keystore::open(
+		KeystoreConfig::Path { path, password, .. } => Keystore::open(
 			path.clone(),
 			password.clone()
 		)?,
@@ -231,14 +276,20 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 
 	let task_manager = {
 		let registry = config.prometheus_config.as_ref().map(|cfg| &cfg.registry);
-		TaskManager::new(config.task_executor.clone(), registry)?
+		TaskManager::new(
+			config.task_executor.clone(),
+			registry,
+			config.task_poll_time_accounting,
+			config.task_watchdog_timeout,
+		)?
 	};
 
-	let executor = NativeExecutor::<TExecDisp>::new(
+	let executor = NativeExecutor::<TExecDisp>::with_host_functions(
 		config.wasm_method,
 		config.default_heap_pages,
 		config.max_runtime_instances,
-	);
+		host_functions,
+	).map_err(|e| Error::Other(e.to_string()))?;
 
 	let chain_spec = &config.chain_spec;
 	let fork_blocks = get_extension::<ForkBlocks<TBl>>(chain_spec.extensions())
@@ -250,13 +301,7 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 		.unwrap_or_default();
 
 	let (client, backend) = {
-		let db_config = sc_client_db::DatabaseSettings {
-			state_cache_size: config.state_cache_size,
-			state_cache_child_ratio:
-			config.state_cache_child_ratio.map(|v| (v, 100)),
-			pruning: config.pruning.clone(),
-			source: config.database.clone(),
-		};
+		let db_config = database_settings(config);
 
 		let extensions = sc_client_api::execution_extensions::ExecutionExtensions::new(
 			config.execution_strategies.clone(),
@@ -275,6 +320,11 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 			ClientConfig {
 				offchain_worker_enabled : config.offchain_worker.enabled ,
 				offchain_indexing_api: config.offchain_worker.indexing_enabled,
+				skip_genesis_check: config.skip_genesis_check,
+				genesis_fingerprint: chain_spec.as_json(false).ok()
+					.map(|json| H256::from(sp_core::hashing::blake2_256(json.as_bytes()))),
+				expected_genesis_code_hash: config.expected_genesis_code_hash,
+				reject_runtime_panics: config.reject_runtime_panics,
 			},
 		)?
 	};
@@ -282,6 +332,94 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 	Ok((client, backend, keystore, task_manager))
 }
 
+/// Initialize the empty database of `config` with a state snapshot written by
+/// [`ServiceComponents::export_state_snapshot`](crate::ServiceComponents::export_state_snapshot).
+///
+/// This must be done before building the service, the node then starts from the block of the
+/// snapshot. The state of the blocks before it isn't available, including the genesis one that
+/// `expected_genesis_code_hash` is checked against.
+pub fn import_state_snapshot<TBl: BlockT>(config: &Configuration, path: &Path) -> Result<(), Error> {
+	let snapshot = crate::chain_ops::read_state_snapshot::<TBl>(path)?;
+	let settings = database_settings(config);
+	let backend = open_with_retries(
+		settings.open_retries,
+		|| Backend::<TBl>::new(settings.clone(), CANONICALIZATION_DELAY),
+	)?;
+
+	info!(
+		"Importing the state of #{} ({}) from {}",
+		snapshot.header.number(),
+		snapshot.header.hash(),
+		path.display(),
+	);
+	backend.import_state_snapshot(snapshot.genesis, snapshot.header, snapshot.storage)?;
+	Ok(())
+}
+
+fn database_settings(config: &Configuration) -> DatabaseSettings {
+	DatabaseSettings {
+		state_cache_size: config.state_cache_size,
+		state_cache_child_ratio: config.state_cache_child_ratio.map(|v| (v, 100)),
+		pruning: config.pruning.clone(),
+		source: config.database.clone(),
+		open_retries: config.database_open_retries,
+		write_buffer_size: config.database_write_buffer_size,
+		max_open_files: config.database_max_open_files,
+		justification_retention: config.justification_retention,
+		genesis_build_threads: config.genesis_build_threads,
+		max_tracked_forks: config.max_tracked_forks,
+	}
+}
+
+
+/// How long to wait for the finality webhook to answer a post before retrying it.
+#[cfg(not(target_os = "unknown"))]
+const FINALITY_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of blocks after which the state of non-finalized blocks is canonicalized.
+const CANONICALIZATION_DELAY: u64 = 4096;
+
+/// Delay before the first retry of a failed database open. Doubled after every attempt.
+const DATABASE_OPEN_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Returns true if the database failed to open for a reason that may go away on its own, such as
+/// the database lock still being held by another process.
+fn is_transient_open_error(err: &sp_blockchain::Error) -> bool {
+	match err {
+		sp_blockchain::Error::Backend(msg) => {
+			let msg = msg.to_lowercase();
+			msg.contains("lock") && (
+				msg.contains("temporarily unavailable") ||
+				msg.contains("wouldblock") ||
+				msg.contains("would block")
+			)
+		},
+		_ => false,
+	}
+}
+
+/// Calls `open` until it succeeds, fails with a permanent error or `retries` retries are used up.
+fn open_with_retries<T>(
+	retries: u32,
+	mut open: impl FnMut() -> sp_blockchain::Result<T>,
+) -> sp_blockchain::Result<T> {
+	let mut delay = DATABASE_OPEN_RETRY_DELAY;
+	let mut attempt = 0;
+	loop {
+		match open() {
+			Err(err) if attempt < retries && is_transient_open_error(&err) => {
+				attempt += 1;
+				warn!(
+					"Failed to open the database ({}), retrying in {:?} ({}/{})",
+					err, delay, attempt, retries,
+				);
+				std::thread::sleep(delay);
+				delay *= 2;
+			},
+			result => return result,
+		}
+	}
+}
 
 /// Create an instance of db-backed client.
 pub fn new_client<E, Block, RA>(
@@ -309,9 +447,16 @@ pub fn new_client<E, Block, RA>(
 		Block: BlockT,
 		E: CodeExecutor + RuntimeInfo,
 {
-	const CANONICALIZATION_DELAY: u64 = 4096;
-
-	let backend = Arc::new(Backend::new(settings, CANONICALIZATION_DELAY)?);
+	let backend = Arc::new(open_with_retries(
+		settings.open_retries,
+		|| Backend::new(settings.clone(), CANONICALIZATION_DELAY),
+	)?);
+	if !config.skip_genesis_check {
+		check_genesis(&backend, genesis_storage, config.genesis_fingerprint)?;
+	}
+	if let Some(expected) = config.expected_genesis_code_hash {
+		check_genesis_code(&backend, genesis_storage, expected)?;
+	}
 	let executor = crate::client::LocalCallExecutor::new(backend.clone(), executor, spawn_handle, config.clone());
 	Ok((
 		crate::client::Client::new(
@@ -328,6 +473,82 @@ pub fn new_client<E, Block, RA>(
 	))
 }
 
+/// Auxiliary key under which the fingerprint of the chain specification the database was checked
+/// against is stored.
+const GENESIS_FINGERPRINT_KEY: &[u8] = b"genesis_fingerprint";
+
+/// Checks that the genesis block of the database, if it has already been initialized, is the one
+/// built from `genesis_storage`.
+///
+/// Building the genesis block is expensive, so once it matched, the `fingerprint` of the chain
+/// specification is stored in the database and the genesis block is only built again when the
+/// fingerprint changes. A fresh database is initialized with the fingerprint right away.
+fn check_genesis<Block: BlockT>(
+	backend: &Backend<Block>,
+	genesis_storage: &dyn BuildStorage,
+	fingerprint: Option<H256>,
+) -> sp_blockchain::Result<()> {
+	let store_fingerprint = || match fingerprint {
+		Some(fingerprint) =>
+			backend.insert_aux(&[(GENESIS_FINGERPRINT_KEY, fingerprint.as_bytes())], &[]),
+		None => Ok(()),
+	};
+
+	let stored = match backend.blockchain().hash(Zero::zero())? {
+		Some(stored) => stored,
+		None => return store_fingerprint(),
+	};
+	if let Some(fingerprint) = fingerprint {
+		if backend.get_aux(GENESIS_FINGERPRINT_KEY)?.as_deref() == Some(fingerprint.as_bytes()) {
+			return Ok(());
+		}
+	}
+
+	// Built the way the client initializes a fresh database, the operation is never committed.
+	let mut op = backend.begin_operation()?;
+	backend.begin_state_operation(&mut op, BlockId::Hash(Default::default()))?;
+	let state_root = op.reset_storage(genesis_storage.build_storage()?)?;
+	let expected = genesis::construct_genesis_block::<Block>(state_root).header().hash();
+	if stored != expected {
+		return Err(sp_blockchain::Error::GenesisMismatch(
+			format!("{:?}", stored),
+			format!("{:?}", expected),
+		));
+	}
+
+	store_fingerprint()
+}
+
+/// Checks that the runtime code of the genesis block has the Blake2-256 hash `expected`.
+///
+/// The code is read from the genesis state of the database if it is still available, so runtime
+/// upgrades applied since then don't matter, and from `genesis_storage` otherwise.
+fn check_genesis_code<Block: BlockT>(
+	backend: &Backend<Block>,
+	genesis_storage: &dyn BuildStorage,
+	expected: H256,
+) -> sp_blockchain::Result<()> {
+	let stored_code = match backend.blockchain().hash(Zero::zero())? {
+		Some(genesis_hash) => backend.state_at(BlockId::Hash(genesis_hash)).ok()
+			.and_then(|state| state.storage(well_known_keys::CODE).ok().flatten()),
+		None => None,
+	};
+	let code = match stored_code {
+		Some(code) => Some(code),
+		None => genesis_storage.build_storage()?.top.remove(well_known_keys::CODE),
+	};
+
+	let actual = code.map(|code| H256::from(sp_core::hashing::blake2_256(&code)));
+	if actual != Some(expected) {
+		return Err(sp_blockchain::Error::GenesisCodeMismatch(
+			actual.map_or_else(|| "none".into(), |actual| format!("{:?}", actual)),
+			format!("{:?}", expected),
+		));
+	}
+
+	Ok(())
+}
+
 impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 	/// Start the service builder with a configuration.
 	pub fn new_full<TBl: BlockT, TRtApi, TExecDisp: NativeExecutionDispatch + 'static>(
@@ -345,7 +566,33 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 		(),
 		TFullBackend<TBl>,
 	>, Error> {
-		let (client, backend, keystore, task_manager) = new_full_parts(&config)?;
+		Self::new_full_with_host_functions(config, Vec::new())
+	}
+
+	/// Start the service builder with a configuration, the executor registering `host_functions`
+	/// in addition to the ones of `TExecDisp`.
+	pub fn new_full_with_host_functions<
+		TBl: BlockT,
+		TRtApi,
+		TExecDisp: NativeExecutionDispatch + 'static,
+	>(
+		config: Configuration,
+		host_functions: Vec<&'static dyn Function>,
+	) -> Result<ServiceBuilder<
+		TBl,
+		TRtApi,
+		TFullClient<TBl, TRtApi, TExecDisp>,
+		Arc<OnDemand<TBl>>,
+		(),
+		(),
+		BoxFinalityProofRequestBuilder<TBl>,
+		Arc<dyn FinalityProofProvider<TBl>>,
+		(),
+		(),
+		TFullBackend<TBl>,
+	>, Error> {
+		let (client, backend, keystore, task_manager) =
+			new_full_parts_with_host_functions(&config, host_functions)?;
 
 		let client = Arc::new(client);
 
@@ -360,10 +607,15 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			import_queue: (),
 			finality_proof_request_builder: None,
 			finality_proof_provider: None,
+			secondary_finality_proof: None,
 			transaction_pool: Arc::new(()),
 			rpc_extensions_builder: Box::new(|_| ()),
 			remote_backend: None,
 			block_announce_validator_builder: None,
+			metrics_exporter: None,
+			authority_set_provider: None,
+			inherent_data_providers: None,
+			subscription_executor: None,
 			marker: PhantomData,
 		})
 	}
@@ -383,14 +635,44 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 		(),
 		(),
 		TLightBackend<TBl>,
+	>, Error> {
+		Self::new_light_with_request_scheduler(config, Box::new(ImmediateScheduler))
+	}
+
+	/// Start the service builder with a configuration, the requests of the light client being
+	/// sent to the network by `request_scheduler`.
+	pub fn new_light_with_request_scheduler<
+		TBl: BlockT,
+		TRtApi,
+		TExecDisp: NativeExecutionDispatch + 'static,
+	>(
+		config: Configuration,
+		request_scheduler: Box<dyn RequestScheduler<TBl>>,
+	) -> Result<ServiceBuilder<
+		TBl,
+		TRtApi,
+		TLightClient<TBl, TRtApi, TExecDisp>,
+		Arc<OnDemand<TBl>>,
+		(),
+		(),
+		BoxFinalityProofRequestBuilder<TBl>,
+		Arc<dyn FinalityProofProvider<TBl>>,
+		(),
+		(),
+		TLightBackend<TBl>,
 	>, Error> {
 		let task_manager = {
 			let registry = config.prometheus_config.as_ref().map(|cfg| &cfg.registry);
-			TaskManager::new(config.task_executor.clone(), registry)?
+			TaskManager::new(
+				config.task_executor.clone(),
+				registry,
+				config.task_poll_time_accounting,
+				config.task_watchdog_timeout,
+			)?
 		};
 
 		let keystore = match &config.keystore {
-			KeystoreConfig::Path { path, password } => Keystore::open(
+			KeystoreConfig::Path { path, password, .. } => Keystore::open(
 				path.clone(),
 				password.clone()
 			)?,
@@ -404,14 +686,7 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 		);
 
 		let db_storage = {
-			let db_settings = sc_client_db::DatabaseSettings {
-				state_cache_size: config.state_cache_size,
-				state_cache_child_ratio:
-					config.state_cache_child_ratio.map(|v| (v, 100)),
-				pruning: config.pruning.clone(),
-				source: config.database.clone(),
-			};
-			sc_client_db::light::LightStorage::new(db_settings)?
+			sc_client_db::light::LightStorage::new(database_settings(config))?
 		};
 		let light_blockchain = sc_light::new_light_blockchain(db_storage);
 		let fetch_checker = Arc::new(
@@ -421,7 +696,9 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 				Box::new(task_manager.spawn_handle()),
 			),
 		);
-		let fetcher = Arc::new(sc_network::config::OnDemand::new(fetch_checker));
+		let fetcher = Arc::new(
+			sc_network::config::OnDemand::with_scheduler(fetch_checker, request_scheduler),
+		);
 		let backend = sc_light::new_light_backend(light_blockchain);
 		let remote_blockchain = backend.remote_blockchain();
 		let client = Arc::new(light::new_light(
@@ -443,10 +720,15 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			import_queue: (),
 			finality_proof_request_builder: None,
 			finality_proof_provider: None,
+			secondary_finality_proof: None,
 			transaction_pool: Arc::new(()),
 			rpc_extensions_builder: Box::new(|_| ()),
 			remote_backend: Some(remote_blockchain),
 			block_announce_validator_builder: None,
+			metrics_exporter: None,
+			authority_set_provider: None,
+			inherent_data_providers: None,
+			subscription_executor: None,
 			marker: PhantomData,
 		})
 	}
@@ -551,6 +833,11 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			metrics_exporter: self.metrics_exporter,
+			secondary_finality_proof: self.secondary_finality_proof,
+			authority_set_provider: self.authority_set_provider,
+			inherent_data_providers: self.inherent_data_providers,
+			subscription_executor: self.subscription_executor,
 			marker: self.marker,
 		})
 	}
@@ -596,6 +883,11 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			metrics_exporter: self.metrics_exporter,
+			secondary_finality_proof: self.secondary_finality_proof,
+			authority_set_provider: self.authority_set_provider,
+			inherent_data_providers: self.inherent_data_providers,
+			subscription_executor: self.subscription_executor,
 			marker: self.marker,
 		})
 	}
@@ -634,6 +926,11 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			metrics_exporter: self.metrics_exporter,
+			secondary_finality_proof: self.secondary_finality_proof,
+			authority_set_provider: self.authority_set_provider,
+			inherent_data_providers: self.inherent_data_providers,
+			subscription_executor: self.subscription_executor,
 			marker: self.marker,
 		})
 	}
@@ -700,6 +997,11 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			metrics_exporter: self.metrics_exporter,
+			secondary_finality_proof: self.secondary_finality_proof,
+			authority_set_provider: self.authority_set_provider,
+			inherent_data_providers: self.inherent_data_providers,
+			subscription_executor: self.subscription_executor,
 			marker: self.marker,
 		})
 	}
@@ -752,6 +1054,11 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			metrics_exporter: self.metrics_exporter,
+			secondary_finality_proof: self.secondary_finality_proof,
+			authority_set_provider: self.authority_set_provider,
+			inherent_data_providers: self.inherent_data_providers,
+			subscription_executor: self.subscription_executor,
 			marker: self.marker,
 		})
 	}
@@ -789,6 +1096,11 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: Box::new(rpc_extensions_builder),
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			metrics_exporter: self.metrics_exporter,
+			secondary_finality_proof: self.secondary_finality_proof,
+			authority_set_provider: self.authority_set_provider,
+			inherent_data_providers: self.inherent_data_providers,
+			subscription_executor: self.subscription_executor,
 			marker: self.marker,
 		})
 	}
@@ -834,9 +1146,92 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: Some(Box::new(block_announce_validator_builder)),
+			metrics_exporter: self.metrics_exporter,
+			secondary_finality_proof: self.secondary_finality_proof,
+			authority_set_provider: self.authority_set_provider,
+			inherent_data_providers: self.inherent_data_providers,
+			subscription_executor: self.subscription_executor,
 			marker: self.marker,
 		})
 	}
+
+	/// Defines a `MetricsExporter` that periodically receives the metrics of the service, in
+	/// addition to the Prometheus endpoint (if enabled).
+	pub fn with_metrics_exporter(
+		mut self,
+		metrics_exporter: impl MetricsExporter<TBl> + 'static,
+	) -> Result<Self, Error> {
+		self.metrics_exporter = Some(Box::new(metrics_exporter));
+		Ok(self)
+	}
+
+	/// Defines a hook called for every inherent failing the check while the import queue using
+	/// `inherent_data_providers` imports a block, e.g. to log or count the failures.
+	///
+	/// The hook may accept the block anyway only if `unsafe_override_inherent_checks` is set in
+	/// the configuration, otherwise its verdict is ignored and the block is rejected.
+	pub fn with_inherent_check_failure_hook(
+		self,
+		inherent_data_providers: &InherentDataProviders,
+		hook: impl InherentCheckFailureHook + 'static,
+	) -> Result<Self, Error> {
+		let allow_override = self.config.unsafe_override_inherent_checks;
+		if allow_override {
+			warn!("Blocks with invalid inherents may be imported, only use this on test networks");
+		}
+		inherent_data_providers.set_check_failure_hook(hook, allow_override);
+		Ok(self)
+	}
+
+	/// Defines the `AuthoritySetProvider` the `system_authorityStatus` RPC checks the local
+	/// session keys against.
+	pub fn with_authority_set_provider(
+		mut self,
+		authority_set_provider: impl AuthoritySetProvider + 'static,
+	) -> Result<Self, Error> {
+		self.authority_set_provider = Some(Arc::new(authority_set_provider));
+		Ok(self)
+	}
+
+	/// Defines the `InherentDataProviders` used to author blocks, which the
+	/// `system_inherentData` RPC runs.
+	pub fn with_inherent_data_providers(
+		mut self,
+		inherent_data_providers: &InherentDataProviders,
+	) -> Result<Self, Error> {
+		self.inherent_data_providers = Some(inherent_data_providers.clone());
+		Ok(self)
+	}
+
+	/// Defines an additional finality proof protocol, e.g. to serve proofs in the format expected
+	/// by the verifier of a bridged chain to relayers.
+	///
+	/// `protocol_name` is appended to the protocol id of the chain and must differ from
+	/// `finality-proof/1`. If the closure returns `None`, the node only sends requests on the
+	/// protocol.
+	pub fn with_secondary_finality_proof_provider(
+		mut self,
+		protocol_name: impl Into<String>,
+		build: impl FnOnce(Arc<TCl>, Arc<Backend>) -> Result<Option<Arc<dyn FinalityProofProvider<TBl>>>, Error>,
+	) -> Result<Self, Error> {
+		let provider = build(self.client.clone(), self.backend.clone())?;
+		self.secondary_finality_proof = Some(SecondaryFinalityProofConfig {
+			protocol_name: protocol_name.into(),
+			provider,
+		});
+		Ok(self)
+	}
+
+	/// Defines a dedicated executor the RPC subscriptions are delivered on, so that they don't
+	/// contend with the other tasks of the service. Subscription tasks are still stopped when the
+	/// service shuts down.
+	pub fn with_subscription_executor(
+		mut self,
+		subscription_executor: impl Into<TaskExecutor>,
+	) -> Result<Self, Error> {
+		self.subscription_executor = Some(subscription_executor.into());
+		Ok(self)
+	}
 }
 
 impl<TBl, TRtApi, TBackend, TSc, TImpQu, TExPool, TRpc, TCl>
@@ -893,20 +1288,32 @@ ServiceBuilder<
 			import_queue,
 			finality_proof_request_builder,
 			finality_proof_provider,
+			secondary_finality_proof,
 			transaction_pool,
 			rpc_extensions_builder,
 			remote_backend,
 			block_announce_validator_builder,
+			metrics_exporter,
+			authority_set_provider,
+			inherent_data_providers,
+			subscription_executor,
 		} = self;
 
 		let chain_info = client.usage_info().chain;
 
-		sp_session::generate_initial_session_keys(
+		generate_initial_session_keys(
 			client.clone(),
 			&BlockId::Hash(chain_info.best_hash),
-			config.dev_key_seed.clone().map(|s| vec![s]).unwrap_or_default(),
+			&keystore,
+			config.generate_session_keys,
+			config.overwrite_dev_keys,
+			config.dev_key_seed.clone(),
 		)?;
 
+		if config.require_authoring_keys {
+			ensure_authoring_keys(&*client, &BlockId::Hash(chain_info.best_hash), &keystore, &config.role)?;
+		}
+
 		info!("📦 Highest known block at #{}", chain_info.best_number);
 		telemetry!(
 			SUBSTRATE_INFO;
@@ -917,10 +1324,13 @@ ServiceBuilder<
 
 		let (system_rpc_tx, system_rpc_rx) = tracing_unbounded("mpsc_system_rpc");
 
+		let delayed_transactions = Arc::new(DelayedTransactions::new());
+		let network_started = wasm_timer::Instant::now();
 		let (network, network_status_sinks, network_future) = build_network(
 			&config, client.clone(), transaction_pool.clone(), task_manager.spawn_handle(),
 			on_demand.clone(), block_announce_validator_builder, finality_proof_request_builder,
-			finality_proof_provider, system_rpc_rx, import_queue
+			finality_proof_provider, secondary_finality_proof, system_rpc_rx, import_queue,
+			keystore.clone(), authority_set_provider, delayed_transactions.clone(),
 		)?;
 
 		let spawn_handle = task_manager.spawn_handle();
@@ -939,8 +1349,12 @@ ServiceBuilder<
 
 		let offchain_storage = backend.offchain_storage();
 		let offchain_workers = match (config.offchain_worker.clone(), offchain_storage.clone()) {
-			(OffchainWorkerConfig {enabled: true, .. }, Some(db)) => {
-				Some(Arc::new(sc_offchain::OffchainWorkers::new(client.clone(), db)))
+			(OffchainWorkerConfig {enabled: true, max_global_http_requests, .. }, Some(db)) => {
+				Some(Arc::new(sc_offchain::OffchainWorkers::with_http_request_limit(
+					client.clone(),
+					db,
+					max_global_http_requests,
+				)))
 			},
 			(OffchainWorkerConfig {enabled: true, .. }, None) => {
 				warn!("Offchain workers disabled, due to lack of offchain storage support in backend.");
@@ -952,9 +1366,33 @@ ServiceBuilder<
 		// Inform the tx pool about imported and finalized blocks.
 		spawn_handle.spawn(
 			"txpool-notifications",
-			sc_transaction_pool::notification_future(client.clone(), transaction_pool.clone()),
+			sc_transaction_pool::notification_future(
+				client.clone(),
+				transaction_pool.clone(),
+				config.coalesce_finality_notifications,
+			),
 		);
 
+		// Drop long-stale future transactions while the pool is idle.
+		if let Some(interval) = config.transaction_pool.compaction_interval {
+			spawn_handle.spawn(
+				"txpool-compaction",
+				sc_transaction_pool::compaction_future(
+					transaction_pool.clone(),
+					interval,
+					config.transaction_pool.future_stale_after,
+				),
+			);
+		}
+
+		// Periodically back up the keystore. Writing the backup blocks on the file system.
+		if let Some(backup) = config.keystore.backup() {
+			spawn_handle.spawn_blocking(
+				"keystore-backup",
+				keystore_backup_future(keystore.clone(), backup.clone()),
+			);
+		}
+
 		// Inform the offchain worker about new imported blocks
 		if let Some(offchain) = offchain_workers.clone() {
 			spawn_handle.spawn(
@@ -971,13 +1409,35 @@ ServiceBuilder<
 
 		spawn_handle.spawn(
 			"on-transaction-imported",
-			transaction_notifications(transaction_pool.clone(), network.clone()),
+			transaction_notifications(
+				transaction_pool.clone(),
+				network.clone(),
+				config.tx_propagation_delay,
+				delayed_transactions.clone(),
+			),
 		);
 
 		// Prometheus metrics.
 		let metrics_service = if let Some(PrometheusConfig { port, registry }) = config.prometheus_config.clone() {
 			// Set static metrics.
 			let metrics = MetricsService::with_prometheus(&registry, &config)?;
+
+			// Record how long it takes to connect to the first peer.
+			let (netstat_tx, netstat_rx) = tracing_unbounded::<(NetworkStatus<_>, NetworkState)>(
+				"mpsc_netstat_first_peer"
+			);
+			network_status_sinks.push(Duration::from_millis(500), netstat_tx);
+			spawn_handle.spawn(
+				"time-to-first-peer",
+				metrics::record_time_to_first_peer(
+					registry.clone(),
+					network_started,
+					netstat_rx.map(|(status, _)| status),
+				),
+			);
+
+			// All the metrics of the service are registered by now.
+			let registry = metrics::with_static_labels(registry, &config.prometheus_static_labels)?;
 			spawn_handle.spawn(
 				"prometheus-endpoint",
 				prometheus_endpoint::init_prometheus(port, registry).map(drop)
@@ -988,27 +1448,119 @@ ServiceBuilder<
 			MetricsService::new()
 		};
 
+		let metrics_service = if config.telemetry_metrics {
+			metrics_service.with_telemetry_metrics()
+		} else {
+			metrics_service
+		};
+		let metrics_service = metrics_service
+			.with_healthy_peer_threshold(config.telemetry_healthy_peer_threshold);
+
+		let metrics_service = match metrics_exporter {
+			Some(exporter) => {
+				let (metrics_service, exporter_future) = metrics_service.with_exporter(exporter);
+				spawn_handle.spawn("metrics-exporter", exporter_future);
+				metrics_service
+			},
+			None => metrics_service,
+		};
+
 		// Periodically notify the telemetry.
 		spawn_handle.spawn("telemetry-periodic-send", telemetry_periodic_send(
 			client.clone(), transaction_pool.clone(), metrics_service, network_status_sinks.clone()
 		));
 
 		// Periodically send the network state to the telemetry.
+		{
+			let network_status_sinks = network_status_sinks.clone();
+			spawn_handle.spawn_watched("telemetry-periodic-network-state", move |heartbeat| {
+				telemetry_periodic_network_state(network_status_sinks.clone(), heartbeat)
+			});
+		}
+
+		// Report the runtime version to the telemetry, at startup and on upgrades.
 		spawn_handle.spawn(
-			"telemetry-periodic-network-state",
-			telemetry_periodic_network_state(network_status_sinks.clone()),
+			"telemetry-runtime-version",
+			report_runtime_versions(client.clone(), telemetry_runtime_version),
 		);
 
+		// Post the finalized headers to the webhook, if any.
+		#[cfg(not(target_os = "unknown"))]
+		if let Some(url) = config.finality_webhook.clone() {
+			let webhook = finality_webhook::FinalityWebhook::new(
+				url,
+				finality_webhook::http_post(FINALITY_WEBHOOK_TIMEOUT),
+				config.prometheus_config.as_ref().map(|config| &config.registry),
+			)?;
+			let finalized = client.finality_notification_stream()
+				.map(|notification| notification.header);
+			spawn_handle.spawn("finality-webhook", webhook.run(finalized));
+		}
+
 		// RPC
-		let gen_handler = |deny_unsafe: sc_rpc::DenyUnsafe| gen_handler(
-			deny_unsafe, &config, &task_manager, client.clone(), transaction_pool.clone(),
-			keystore.clone(), on_demand.clone(), remote_backend.clone(), &*rpc_extensions_builder,
-			offchain_storage.clone(), system_rpc_tx.clone()
+		let subscriptions_spawn_handle = match subscription_executor {
+			Some(executor) => task_manager.spawn_handle_with_executor(executor),
+			None => task_manager.spawn_handle(),
+		};
+		let system_info = sc_rpc::system::SystemInfo {
+			chain_name: config.chain_spec.name().into(),
+			impl_name: config.impl_name.clone(),
+			impl_version: config.impl_version.clone(),
+			properties: config.chain_spec.properties(),
+			chain_type: config.chain_spec.chain_type(),
+		};
+		let runtime_call_limit = sc_rpc::state::RuntimeCallLimit::new(
+			config.rpc_max_concurrent_runtime_calls,
 		);
-		let rpc = start_rpc_servers(&config, gen_handler)?;
+		let mut gen_handler = {
+			let client = client.clone();
+			let transaction_pool = transaction_pool.clone();
+			let keystore = keystore.clone();
+			let on_demand = on_demand.clone();
+			let remote_backend = remote_backend.clone();
+			let offchain_storage = offchain_storage.clone();
+			let state_cache = backend.state_cache();
+			let inherent_data_providers = inherent_data_providers.clone();
+			let system_rpc_tx = system_rpc_tx.clone();
+			// The RPC submissions only need to be recorded if they are to be delayed.
+			let rpc_submissions = config.tx_propagation_delay.map(|_| delayed_transactions.clone());
+			move |deny_unsafe: sc_rpc::DenyUnsafe| gen_handler(
+				deny_unsafe, &system_info, subscriptions_spawn_handle.clone(), client.clone(),
+				transaction_pool.clone(), keystore.clone(), on_demand.clone(), remote_backend.clone(),
+				&*rpc_extensions_builder, offchain_storage.clone(), state_cache.clone(),
+				inherent_data_providers.clone(), system_rpc_tx.clone(), runtime_call_limit.clone(),
+				rpc_submissions.clone(),
+			)
+		};
 		// This is used internally, so don't restrict access to unsafe RPC
 		let rpc_handlers = Arc::new(RpcHandlers(gen_handler(sc_rpc::DenyUnsafe::No)));
 
+		let rpc_config = RpcServersConfig::new(&config);
+		let rpc = match config.rpc_serve_after {
+			RpcReadiness::Immediately => Some(start_rpc_servers(&rpc_config, &spawn_handle, gen_handler)?),
+			readiness => {
+				// Hold the servers back until the network reports the node as ready.
+				let (netstat_tx, netstat_rx) = tracing_unbounded::<(NetworkStatus<_>, NetworkState)>(
+					"mpsc_netstat_rpc_readiness"
+				);
+				network_status_sinks.push(Duration::from_millis(500), netstat_tx);
+				let servers_spawn_handle = spawn_handle.clone();
+				spawn_handle.spawn("rpc-servers", async move {
+					if !wait_for_rpc_readiness(readiness, netstat_rx.map(|(status, _)| status)).await {
+						return;
+					}
+					match start_rpc_servers(&rpc_config, &servers_spawn_handle, gen_handler) {
+						Ok(_servers) => {
+							info!("RPC servers started ({:?})", readiness);
+							futures::future::pending::<()>().await
+						},
+						Err(e) => error!("Failed to start RPC servers: {}", e),
+					}
+				});
+				None
+			},
+		};
+
 		let telemetry_connection_sinks: Arc<Mutex<Vec<TracingUnboundedSender<()>>>> = Default::default();
 
 		// Telemetry
@@ -1058,13 +1610,32 @@ ServiceBuilder<
 			config.informant_output_format,
 		));
 
-		task_manager.keep_alive((telemetry, config.base_path, rpc, rpc_handlers.clone()));
+		// Feed the structured lifecycle events to the configured sink, if any.
+		let node_stopped = if let Some(sink) = config.structured_events_sink.take() {
+			let (netstat_tx, netstat_rx) = tracing_unbounded::<(NetworkStatus<_>, NetworkState)>(
+				"mpsc_netstat_events"
+			);
+			network_status_sinks.push(Duration::from_secs(1), netstat_tx);
+			spawn_handle.spawn(
+				"sync-events",
+				events::sync_events(sink.clone(), client.clone(), netstat_rx.map(|(status, _)| status)),
+			);
+			spawn_handle.spawn("reorg-events", events::reorg_events(sink.clone(), client.clone()));
+
+			events::node_started(&sink, &chain_info);
+			Some(events::NodeStoppedOnDrop(sink))
+		} else {
+			None
+		};
+
+		task_manager.keep_alive((telemetry, config.base_path, rpc, rpc_handlers.clone(), node_stopped));
 
 		Ok(ServiceComponents {
 			client,
 			task_manager,
 			network,
 			select_chain,
+			transaction_pool_ban_list: transaction_pool.ban_list(),
 			transaction_pool,
 			rpc_handlers,
 			keystore,
@@ -1125,33 +1696,268 @@ ServiceBuilder<
 	}
 }
 
-async fn transaction_notifications<TBl, TExPool>(
+/// Generates the initial session keys from the development seed, unless `generate` is `false`.
+///
+/// Unless `overwrite` is `true`, only the session key types `keystore` holds no key of, e.g.
+/// inserted manually, are generated.
+///
+/// Function extracted to simplify the test and prevent building a whole service.
+fn generate_initial_session_keys<TBl, TCl>(
+	client: Arc<TCl>,
+	at: &BlockId<TBl>,
+	keystore: &KeyStorePtr,
+	generate: bool,
+	overwrite: bool,
+	dev_key_seed: Option<String>,
+) -> Result<(), Error>
+	where
+		TBl: BlockT,
+		TCl: ProvideRuntimeApi<TBl> + ExecutorProvider<TBl>,
+		<TCl as ProvideRuntimeApi<TBl>>::Api:
+			sp_session::SessionKeys<TBl> + sp_api::ApiErrorExt<Error = sp_blockchain::Error>,
+{
+	if !generate {
+		info!("🔑 Skipping generation of the initial session keys");
+		return Ok(());
+	}
+
+	let dev_key_seed = match dev_key_seed {
+		Some(seed) => seed,
+		None => return Ok(()),
+	};
+
+	if overwrite {
+		generate_session_keys(&*client, at, keystore, Some(&dev_key_seed))?;
+		return Ok(());
+	}
+
+	let missing = missing_session_key_types(&*client, at, keystore)?;
+	if missing.is_empty() {
+		info!("🔑 The keystore already holds all the session keys, not generating them from the development seed");
+		return Ok(());
+	}
+	debug!(
+		"Generating the session keys of type {} from the development seed",
+		key_type_names(&missing).join(", "),
+	);
+
+	// The runtime generates all the session keys at once, so they are generated into a throwaway
+	// keystore and only the ones of the missing types are copied over.
+	let generator = Keystore::new_in_memory();
+	let generated = generate_session_keys(&*client, at, &generator, Some(&dev_key_seed))?;
+	let generator = generator.read();
+	let mut keystore = keystore.write();
+	for (public, key_type) in generated {
+		if missing.contains(&key_type) {
+			keystore.insert_from(&generator, &public, key_type)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Generates the session keys from `seed` with the runtime into `keystore`, returning the public
+/// keys along with their type.
+///
+/// `keystore` replaces the one of the client for this call only.
+fn generate_session_keys<TBl, TCl>(
+	client: &TCl,
+	at: &BlockId<TBl>,
+	keystore: &KeyStorePtr,
+	seed: Option<&str>,
+) -> Result<Vec<(Vec<u8>, KeyTypeId)>, Error>
+	where
+		TBl: BlockT,
+		TCl: ProvideRuntimeApi<TBl> + ExecutorProvider<TBl>,
+		<TCl as ProvideRuntimeApi<TBl>>::Api:
+			sp_session::SessionKeys<TBl> + sp_api::ApiErrorExt<Error = sp_blockchain::Error>,
+{
+	use sp_session::SessionKeys;
+
+	let mut extensions = Extensions::new();
+	extensions.register(KeystoreExt(keystore.clone()));
+	let session_keys = client.executor().call(
+		at,
+		"SessionKeys_generate_session_keys",
+		&seed.map(|seed| seed.as_bytes().to_vec()).encode(),
+		ExecutionStrategy::NativeElseWasm,
+		Some(extensions),
+	)?;
+	let session_keys = Vec::<u8>::decode(&mut &session_keys[..]).map_err(|e| Error::Other(
+		format!("Failed to decode the session keys generated by the runtime: {}", e)
+	))?;
+	client.runtime_api().decode_session_keys(at, session_keys)?.ok_or_else(|| Error::Other(
+		"Failed to decode the session keys generated by the runtime".into()
+	))
+}
+
+/// Backs up `keystore` once every `backup.interval`.
+///
+/// The keystore is only locked while its keys are read, the backup is written afterwards.
+async fn keystore_backup_future(keystore: KeyStorePtr, backup: KeystoreBackup) {
+	loop {
+		futures_timer::Delay::new(backup.interval).await;
+		let snapshot = keystore.read().snapshot();
+		match snapshot.and_then(|snapshot| snapshot.write_to(&backup.path)) {
+			Ok(()) => debug!("Backed up the keystore to {}", backup.path.display()),
+			Err(e) => warn!("Failed to back up the keystore to {}: {}", backup.path.display(), e),
+		}
+	}
+}
+
+/// Checks that the keystore of an authority node holds a key for every key type that is part of
+/// the runtime's session keys.
+///
+/// Always succeeds for nodes that aren't authorities, as they don't need any key.
+fn ensure_authoring_keys<TBl, TCl>(
+	client: &TCl,
+	at: &BlockId<TBl>,
+	keystore: &KeyStorePtr,
+	role: &Role,
+) -> Result<(), Error>
+	where
+		TBl: BlockT,
+		TCl: ProvideRuntimeApi<TBl> + ExecutorProvider<TBl>,
+		<TCl as ProvideRuntimeApi<TBl>>::Api:
+			sp_session::SessionKeys<TBl> + sp_api::ApiErrorExt<Error = sp_blockchain::Error>,
+{
+	if !role.is_authority() {
+		return Ok(());
+	}
+
+	let missing = missing_session_key_types(client, at, keystore)?;
+	if missing.is_empty() {
+		Ok(())
+	} else {
+		let missing = key_type_names(&missing);
+		error!("🔑 The keystore is missing session keys of type {}, no blocks will be authored", missing.join(", "));
+		Err(Error::MissingAuthoringKeys(missing))
+	}
+}
+
+/// Returns the key types that are part of the runtime's session keys, and that `keystore` holds
+/// no key of.
+fn missing_session_key_types<TBl, TCl>(
+	client: &TCl,
+	at: &BlockId<TBl>,
+	keystore: &KeyStorePtr,
+) -> Result<Vec<KeyTypeId>, Error>
+	where
+		TBl: BlockT,
+		TCl: ProvideRuntimeApi<TBl> + ExecutorProvider<TBl>,
+		<TCl as ProvideRuntimeApi<TBl>>::Api:
+			sp_session::SessionKeys<TBl> + sp_api::ApiErrorExt<Error = sp_blockchain::Error>,
+{
+	let key_types = session_key_types(client, at)?;
+	let keystore = keystore.read();
+	Ok(key_types.into_iter()
+		.filter(|key_type| keystore.keys(*key_type).map(|keys| keys.is_empty()).unwrap_or(true))
+		.collect())
+}
+
+/// Returns the key types that are part of the runtime's session keys, sorted and deduplicated.
+///
+/// The runtime only exposes them through the keys it generates, so a set of keys is generated
+/// into a throwaway in-memory keystore, the node's keystore is never touched.
+fn session_key_types<TBl, TCl>(
+	client: &TCl,
+	at: &BlockId<TBl>,
+) -> Result<Vec<KeyTypeId>, Error>
+	where
+		TBl: BlockT,
+		TCl: ProvideRuntimeApi<TBl> + ExecutorProvider<TBl>,
+		<TCl as ProvideRuntimeApi<TBl>>::Api:
+			sp_session::SessionKeys<TBl> + sp_api::ApiErrorExt<Error = sp_blockchain::Error>,
+{
+	let probe = Keystore::new_in_memory();
+	let mut key_types = generate_session_keys(client, at, &probe, None)?
+		.into_iter()
+		.map(|(_, key_type)| key_type)
+		.collect::<Vec<_>>();
+	key_types.sort();
+	key_types.dedup();
+	Ok(key_types)
+}
+
+fn key_type_names(key_types: &[KeyTypeId]) -> Vec<String> {
+	key_types.iter()
+		.map(|key_type| String::from_utf8_lossy(&key_type.0).into_owned())
+		.collect()
+}
+
+fn transaction_notifications<TBl, TExPool>(
 	transaction_pool: Arc<TExPool>,
-	network: Arc<NetworkService<TBl, <TBl as BlockT>::Hash>>
-)
+	network: Arc<NetworkService<TBl, <TBl as BlockT>::Hash>>,
+	propagation_delay: Option<(Duration, Duration)>,
+	delayed_transactions: Arc<DelayedTransactions<TBl::Hash>>,
+) -> impl Future<Output = ()>
 	where
 		TBl: BlockT,
 		TExPool: MaintainedTransactionPool<Block=TBl, Hash = <TBl as BlockT>::Hash>,
 {
-	// transaction notifications
+	propagate_imported_transactions(
+		transaction_pool,
+		propagation_delay,
+		delayed_transactions,
+		move |hash| network.propagate_transaction(hash),
+	)
+}
+
+/// Calls `propagate` with every transaction imported into the pool.
+///
+/// The transactions submitted locally or over RPC are propagated after a random delay within
+/// `propagation_delay`, if any, so that their origin can't be told from the timing. They are
+/// held back in `delayed_transactions` meanwhile. The others are propagated immediately.
+fn propagate_imported_transactions<TExPool>(
+	transaction_pool: Arc<TExPool>,
+	propagation_delay: Option<(Duration, Duration)>,
+	delayed_transactions: Arc<DelayedTransactions<TxHash<TExPool>>>,
+	propagate: impl Fn(TxHash<TExPool>) + Send + Sync + 'static,
+) -> impl Future<Output = ()>
+	where
+		TExPool: TransactionPool,
+{
+	let propagate = Arc::new(propagate);
+	// subscribe right away, so that no transaction imported in the meantime is missed.
 	transaction_pool.import_notification_stream()
-		.for_each(move |hash| {
-			network.propagate_transaction(hash);
-			let status = transaction_pool.status();
-			telemetry!(SUBSTRATE_INFO; "txpool.import";
-				"ready" => status.ready,
-				"future" => status.future
-			);
-			ready(())
+		.for_each_concurrent(None, move |hash| {
+			let from_rpc = delayed_transactions.take_rpc_submission(&hash);
+			let is_local = transaction_pool.ready_transaction(&hash)
+				.map_or(false, |tx| tx.source() == TransactionSource::Local);
+			let delay = propagation_delay.filter(|_| is_local || from_rpc).map(|(min, max)| {
+				if max > min {
+					rand::thread_rng().gen_range(min, max)
+				} else {
+					min
+				}
+			});
+			if delay.is_some() {
+				delayed_transactions.start_delay(hash.clone());
+			}
+			let transaction_pool = transaction_pool.clone();
+			let delayed_transactions = delayed_transactions.clone();
+			let propagate = propagate.clone();
+
+			async move {
+				if let Some(delay) = delay {
+					futures_timer::Delay::new(delay).await;
+					delayed_transactions.end_delay(&hash);
+				}
+				propagate(hash);
+				let status = transaction_pool.status();
+				telemetry!(SUBSTRATE_INFO; "txpool.import";
+					"ready" => status.ready,
+					"future" => status.future
+				);
+			}
 		})
-		.await;
 }
 
 // Periodically notify the telemetry.
 async fn telemetry_periodic_send<TBl, TExPool, TCl>(
 	client: Arc<TCl>,
 	transaction_pool: Arc<TExPool>,
-	mut metrics_service: MetricsService,
+	mut metrics_service: MetricsService<TBl>,
 	network_status_sinks: Arc<status_sinks::StatusSinks<(NetworkStatus<TBl>, NetworkState)>>
 )
 	where
@@ -1173,12 +1979,14 @@ async fn telemetry_periodic_send<TBl, TExPool, TCl>(
 }
 
 async fn telemetry_periodic_network_state<TBl: BlockT>(
-	network_status_sinks: Arc<status_sinks::StatusSinks<(NetworkStatus<TBl>, NetworkState)>>
+	network_status_sinks: Arc<status_sinks::StatusSinks<(NetworkStatus<TBl>, NetworkState)>>,
+	heartbeat: Heartbeat,
 ) {
 	// Periodically send the network state to the telemetry.
 	let (netstat_tx, netstat_rx) = tracing_unbounded::<(NetworkStatus<_>, NetworkState)>("mpsc_netstat2");
 	network_status_sinks.push(std::time::Duration::from_secs(30), netstat_tx);
 	netstat_rx.for_each(move |(_, network_state)| {
+		heartbeat.ping();
 		telemetry!(
 			SUBSTRATE_INFO;
 			"system.network_state";
@@ -1188,6 +1996,63 @@ async fn telemetry_periodic_network_state<TBl: BlockT>(
 	}).await;
 }
 
+/// Calls `report` with the runtime version of the best block at startup, and again every time a
+/// block changes the runtime code.
+///
+/// If the runtime version can't be queried at startup, e.g. because the state of the genesis block
+/// isn't available, it is reported for the first imported block instead.
+async fn report_runtime_versions<TBl, TCl>(
+	client: Arc<TCl>,
+	mut report: impl FnMut(&RuntimeVersion),
+)
+	where
+		TBl: BlockT,
+		TCl: BlockchainEvents<TBl> + HeaderBackend<TBl> + CallApiAt<TBl, Error=sp_blockchain::Error>,
+{
+	// subscribe before querying the best block, so that no upgrade is missed in between.
+	let code_key = [sp_core::storage::StorageKey(sp_core::storage::well_known_keys::CODE.to_vec())];
+	let mut code_changes = match client.storage_changes_notification_stream(Some(&code_key), None) {
+		Ok(code_changes) => code_changes,
+		Err(e) => {
+			warn!("Unable to watch runtime upgrades: {}", e);
+			return;
+		},
+	};
+	let mut imports = client.import_notification_stream();
+	let version_at = |hash| CallApiAt::runtime_version_at(&*client, &BlockId::Hash(hash));
+
+	match version_at(client.info().best_hash) {
+		Ok(version) => report(&version),
+		Err(e) => {
+			debug!("Runtime version unavailable at startup, waiting for the first block: {}", e);
+			while let Some(notification) = imports.next().await {
+				if let Ok(version) = version_at(notification.hash) {
+					report(&version);
+					break;
+				}
+			}
+		},
+	}
+	drop(imports);
+
+	while let Some((hash, _)) = code_changes.next().await {
+		match version_at(hash) {
+			Ok(version) => report(&version),
+			Err(e) => warn!("Unable to query the runtime version at {:?}: {}", hash, e),
+		}
+	}
+}
+
+fn telemetry_runtime_version(version: &RuntimeVersion) {
+	telemetry!(
+		SUBSTRATE_INFO;
+		"node.runtime_version";
+		"spec_name" => version.spec_name.to_string(),
+		"spec_version" => version.spec_version,
+		"impl_version" => version.impl_version,
+	);
+}
+
 fn build_telemetry<TBl: BlockT>(
 	config: &mut Configuration,
 	endpoints: sc_telemetry::TelemetryEndpoints,
@@ -1237,8 +2102,8 @@ fn build_telemetry<TBl: BlockT>(
 
 fn gen_handler<TBl, TBackend, TExPool, TRpc, TCl>(
 	deny_unsafe: sc_rpc::DenyUnsafe,
-	config: &Configuration,
-	task_manager: &TaskManager,
+	system_info: &sc_rpc::system::SystemInfo,
+	subscriptions_spawn_handle: SpawnTaskHandle,
 	client: Arc<TCl>,
 	transaction_pool: Arc<TExPool>,
 	keystore: Arc<RwLock<Keystore>>,
@@ -1246,7 +2111,11 @@ fn gen_handler<TBl, TBackend, TExPool, TRpc, TCl>(
 	remote_backend: Option<Arc<dyn RemoteBlockchain<TBl>>>,
 	rpc_extensions_builder: &(dyn RpcExtensionBuilder<Output = TRpc> + Send),
 	offchain_storage: Option<<TBackend as sc_client_api::backend::Backend<TBl>>::OffchainStorage>,
-	system_rpc_tx: TracingUnboundedSender<sc_rpc::system::Request<TBl>>
+	state_cache: Option<Arc<dyn sc_client_api::backend::StateCache>>,
+	inherent_data_providers: Option<InherentDataProviders>,
+	system_rpc_tx: TracingUnboundedSender<sc_rpc::system::Request<TBl>>,
+	runtime_call_limit: sc_rpc::state::RuntimeCallLimit,
+	rpc_submissions: Option<Arc<DelayedTransactions<TBl::Hash>>>,
 ) -> jsonrpc_pubsub::PubSubHandler<sc_rpc::Metadata>
 	where
 		TBl: BlockT,
@@ -1263,15 +2132,7 @@ fn gen_handler<TBl, TBackend, TExPool, TRpc, TCl>(
 {
 	use sc_rpc::{chain, state, author, system, offchain};
 
-	let system_info = sc_rpc::system::SystemInfo {
-		chain_name: config.chain_spec.name().into(),
-		impl_name: config.impl_name.clone(),
-		impl_version: config.impl_version.clone(),
-		properties: config.chain_spec.properties(),
-		chain_type: config.chain_spec.chain_type(),
-	};
-
-	let subscriptions = SubscriptionManager::new(Arc::new(task_manager.spawn_handle()));
+	let subscriptions = SubscriptionManager::new(Arc::new(subscriptions_spawn_handle));
 
 	let (chain, state, child_state) = if let (Some(remote_backend), Some(on_demand)) =
 		(remote_backend, on_demand) {
@@ -1286,25 +2147,40 @@ fn gen_handler<TBl, TBackend, TExPool, TRpc, TCl>(
 			client.clone(),
 			subscriptions.clone(),
 			remote_backend.clone(),
-			on_demand.clone()
+			on_demand.clone(),
+			deny_unsafe,
 		);
 		(chain, state, child_state)
 
 	} else {
 		// Full nodes
 		let chain = sc_rpc::chain::new_full(client.clone(), subscriptions.clone());
-		let (state, child_state) = sc_rpc::state::new_full(client.clone(), subscriptions.clone());
+		let (state, child_state) = sc_rpc::state::new_full(
+			client.clone(),
+			subscriptions.clone(),
+			deny_unsafe,
+			runtime_call_limit,
+		);
 		(chain, state, child_state)
 	};
 
-	let author = sc_rpc::author::Author::new(
+	let mut author = sc_rpc::author::Author::new(
 		client.clone(),
 		transaction_pool.clone(),
 		subscriptions,
 		keystore.clone(),
 		deny_unsafe,
 	);
-	let system = system::System::new(system_info, system_rpc_tx.clone(), deny_unsafe);
+	if let Some(rpc_submissions) = rpc_submissions {
+		author = author.with_submit_hook(move |hash| rpc_submissions.on_rpc_submission(*hash));
+	}
+	let system = system::System::new(
+		system_info.clone(),
+		system_rpc_tx.clone(),
+		state_cache,
+		inherent_data_providers,
+		deny_unsafe,
+	);
 
 	let maybe_offchain_rpc = offchain_storage.clone()
 	.map(|storage| {
@@ -1337,8 +2213,12 @@ fn build_network<TBl, TExPool, TImpQu, TCl>(
 	>>,
 	finality_proof_request_builder: Option<BoxFinalityProofRequestBuilder<TBl>>,
 	finality_proof_provider: Option<Arc<dyn FinalityProofProvider<TBl>>>,
+	secondary_finality_proof: Option<SecondaryFinalityProofConfig<TBl>>,
 	system_rpc_rx: TracingUnboundedReceiver<sc_rpc::system::Request<TBl>>,
-	import_queue: TImpQu
+	import_queue: TImpQu,
+	keystore: KeyStorePtr,
+	authority_set_provider: Option<Arc<dyn AuthoritySetProvider>>,
+	delayed_transactions: Arc<DelayedTransactions<TBl::Hash>>,
 ) -> Result<
 	(
 		Arc<NetworkService<TBl, <TBl as BlockT>::Hash>>,
@@ -1359,6 +2239,7 @@ fn build_network<TBl, TExPool, TImpQu, TCl>(
 		imports_external_transactions: !matches!(config.role, Role::Light),
 		pool: transaction_pool.clone(),
 		client: client.clone(),
+		delayed_transactions,
 	});
 
 	let protocol_id = {
@@ -1391,17 +2272,30 @@ fn build_network<TBl, TExPool, TImpQu, TCl>(
 		chain: client.clone(),
 		finality_proof_provider,
 		finality_proof_request_builder,
+		secondary_finality_proof,
 		on_demand: on_demand.clone(),
 		transaction_pool: transaction_pool_adapter.clone() as _,
 		import_queue: Box::new(import_queue),
 		protocol_id,
 		block_announce_validator,
+		suppress_block_announcements: config.suppress_block_announcements,
+		max_announce_block_age: config.max_announce_block_age,
+		block_announce_validation_limit: config.block_announce_validation_limit,
+		sync_request_timeout: config.sync_request_timeout,
 		metrics_registry: config.prometheus_config.as_ref().map(|config| config.registry.clone())
 	};
 
 	let has_bootnodes = !network_params.network_config.boot_nodes.is_empty();
 	let network_mut = sc_network::NetworkWorker::new(network_params)?;
 	let network = network_mut.service().clone();
+
+	if let Some(path) = config.persist_peer_reputations.as_ref() {
+		let reputations = peer_reputations::load(path, config.peer_reputations_ttl);
+		debug!("Restoring the reputation of {} peers", reputations.len());
+		for (peer_id, reputation) in reputations {
+			network.report_peer(peer_id, ReputationChange::new(reputation, "Restored reputation"));
+		}
+	}
 	let network_status_sinks = Arc::new(status_sinks::StatusSinks::new());
 
 	let future = build_network_future(
@@ -1412,7 +2306,291 @@ fn build_network<TBl, TExPool, TImpQu, TCl>(
 		system_rpc_rx,
 		has_bootnodes,
 		config.announce_block,
+		keystore,
+		authority_set_provider,
+		config.persist_peer_reputations.clone(),
 	).boxed();
 
 	Ok((network, network_status_sinks, future))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{Pair, testing::{ECDSA, ED25519, SR25519}};
+	use sc_client_db::{DatabaseSettingsSrc, PruningMode};
+	use substrate_test_runtime_client::{
+		TestClientBuilder, DefaultTestClientBuilderExt, TestClientBuilderExt, runtime::Block,
+	};
+
+	#[test]
+	fn session_keys_generation_can_be_skipped() {
+		// The test runtime ignores the seed, the keys it generates only persist on disk.
+		let dir = tempfile::tempdir().unwrap();
+		let keystore = sc_keystore::Store::open(dir.path(), None).unwrap();
+		let client = Arc::new(TestClientBuilder::new().set_keystore(keystore.clone()).build());
+		let best = BlockId::Hash(client.info().best_hash);
+
+		generate_initial_session_keys(client.clone(), &best, &keystore, false, true, Some("//Alice".into()))
+			.expect("Skipping the generation never fails");
+		assert!(keystore.read().sr25519_public_keys(SR25519).is_empty());
+
+		generate_initial_session_keys(client, &best, &keystore, true, true, Some("//Alice".into()))
+			.expect("Generates the session keys");
+		assert_eq!(keystore.read().sr25519_public_keys(SR25519).len(), 1);
+	}
+
+	#[test]
+	fn manually_inserted_session_keys_are_kept_unless_overwritten() {
+		let dir = tempfile::tempdir().unwrap();
+		let keystore = sc_keystore::Store::open(dir.path(), None).unwrap();
+		let client = Arc::new(TestClientBuilder::new().set_keystore(keystore.clone()).build());
+		let best = BlockId::Hash(client.info().best_hash);
+
+		let manual = keystore.write().insert_by_type::<sp_core::sr25519::Pair>(SR25519, "//Bob").unwrap();
+		keystore.write().insert_by_type::<sp_core::ed25519::Pair>(ED25519, "//Bob").unwrap();
+		keystore.write().insert_by_type::<sp_core::ecdsa::Pair>(ECDSA, "//Bob").unwrap();
+		let key_files = || {
+			let mut files = std::fs::read_dir(dir.path()).unwrap()
+				.map(|entry| entry.unwrap().file_name())
+				.collect::<Vec<_>>();
+			files.sort();
+			files
+		};
+		let before = key_files();
+
+		generate_initial_session_keys(client.clone(), &best, &keystore, true, false, Some("//Alice".into()))
+			.expect("Nothing to generate");
+		assert_eq!(keystore.read().sr25519_public_keys(SR25519), vec![manual.public()]);
+		assert_eq!(key_files(), before);
+
+		// The previous behaviour, the development keys are added next to the manual ones.
+		generate_initial_session_keys(client, &best, &keystore, true, true, Some("//Alice".into()))
+			.expect("Generates the session keys");
+		assert_eq!(keystore.read().sr25519_public_keys(SR25519).len(), 2);
+	}
+
+	#[test]
+	fn authority_nodes_require_all_session_key_types() {
+		let keystore = sc_keystore::Store::new_in_memory();
+		let client = TestClientBuilder::new().set_keystore(keystore.clone()).build();
+		let best = BlockId::Hash(client.info().best_hash);
+		let authority = Role::Authority { sentry_nodes: Vec::new() };
+
+		keystore.write()
+			.insert_ephemeral_from_seed_by_type::<sp_core::sr25519::Pair>("//Alice", SR25519)
+			.unwrap();
+
+		match ensure_authoring_keys(&client, &best, &keystore, &authority) {
+			Err(Error::MissingAuthoringKeys(missing)) => assert_eq!(missing, vec!["ecds", "ed25"]),
+			other => panic!("Unexpected result: {:?}", other),
+		}
+		// Discovering the session key types leaves no key behind.
+		assert_eq!(keystore.read().sr25519_public_keys(SR25519).len(), 1);
+		assert!(keystore.read().ed25519_public_keys(ED25519).is_empty());
+
+		// The check is skipped on nodes that don't author blocks.
+		ensure_authoring_keys(&client, &best, &keystore, &Role::Full)
+			.expect("Non-authority nodes don't need any key");
+
+		keystore.write()
+			.insert_ephemeral_from_seed_by_type::<sp_core::ed25519::Pair>("//Alice", ED25519)
+			.unwrap();
+		keystore.write()
+			.insert_ephemeral_from_seed_by_type::<sp_core::ecdsa::Pair>("//Alice", ECDSA)
+			.unwrap();
+		ensure_authoring_keys(&client, &best, &keystore, &authority)
+			.expect("All the session keys are in the keystore");
+	}
+
+	#[test]
+	fn discovering_session_key_types_leaves_the_keystore_untouched() {
+		let dir = tempfile::tempdir().unwrap();
+		let keystore = sc_keystore::Store::open(dir.path(), None).unwrap();
+		let client = TestClientBuilder::new().set_keystore(keystore.clone()).build();
+		let best = BlockId::Hash(client.info().best_hash);
+		let authority = Role::Authority { sentry_nodes: Vec::new() };
+
+		keystore.write().insert_by_type::<sp_core::sr25519::Pair>(SR25519, "//Alice").unwrap();
+		let key_files = || {
+			let mut files = std::fs::read_dir(dir.path()).unwrap()
+				.map(|entry| entry.unwrap().file_name())
+				.collect::<Vec<_>>();
+			files.sort();
+			files
+		};
+		let before = key_files();
+
+		assert!(ensure_authoring_keys(&client, &best, &keystore, &authority).is_err());
+		assert_eq!(key_files(), before);
+		assert_eq!(keystore.read().sr25519_public_keys(SR25519).len(), 1);
+	}
+
+	#[test]
+	fn database_open_is_retried_until_lock_is_released() {
+		let tmp = tempfile::tempdir().unwrap();
+		let settings = DatabaseSettings {
+			state_cache_size: 1 << 20,
+			state_cache_child_ratio: None,
+			pruning: PruningMode::ArchiveAll,
+			source: DatabaseSettingsSrc::RocksDb {
+				path: tmp.path().into(),
+				cache_size: 16,
+			},
+			open_retries: 5,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: None,
+		};
+
+		let locked = Backend::<Block>::new(settings.clone(), 0).unwrap();
+		let err = Backend::<Block>::new(settings.clone(), 0).err().unwrap();
+		assert!(is_transient_open_error(&err), "unexpected error: {}", err);
+
+		let release = std::thread::spawn(move || {
+			std::thread::sleep(Duration::from_millis(100));
+			drop(locked);
+		});
+
+		let mut attempts = 0;
+		open_with_retries(settings.open_retries, || {
+			attempts += 1;
+			Backend::<Block>::new(settings.clone(), 0)
+		}).expect("Opens once the lock is released");
+		assert!(attempts > 1);
+		release.join().unwrap();
+	}
+
+	#[test]
+	fn permanent_database_open_errors_are_not_retried() {
+		let mut attempts = 0;
+		let result: sp_blockchain::Result<()> = open_with_retries(5, || {
+			attempts += 1;
+			Err(sp_blockchain::Error::Backend("Corruption: bad block checksum".into()))
+		});
+		assert!(result.is_err());
+		assert_eq!(attempts, 1);
+	}
+
+	#[test]
+	fn runtime_version_is_reported_at_start_and_on_upgrade() {
+		use futures::executor::block_on;
+		use sc_block_builder::BlockBuilderProvider;
+		use sp_consensus::BlockOrigin;
+		use sp_core::storage::well_known_keys;
+		use substrate_test_runtime_client::{BlockBuilderExt, ClientBlockImportExt, runtime};
+
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let (tx, mut versions) = tracing_unbounded("mpsc_test_runtime_version");
+		let mut reporting = report_runtime_versions(client.clone(), move |version| {
+			tx.unbounded_send(version.spec_version).unwrap();
+		}).boxed();
+
+		block_on(async {
+			assert!(futures::poll!(&mut reporting).is_pending());
+			assert_eq!(versions.try_next().unwrap(), Some(runtime::VERSION.spec_version));
+
+			// blocks that don't touch the code aren't reported
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			client.import(BlockOrigin::Own, block).unwrap();
+			assert!(futures::poll!(&mut reporting).is_pending());
+			assert!(versions.try_next().is_err());
+
+			let mut builder = client.new_block(Default::default()).unwrap();
+			builder.push_storage_change(
+				well_known_keys::CODE.to_vec(),
+				Some(runtime::WASM_BINARY.to_vec()),
+			).unwrap();
+			let block = builder.build().unwrap().block;
+			client.import(BlockOrigin::Own, block).unwrap();
+			assert!(futures::poll!(&mut reporting).is_pending());
+			assert_eq!(versions.try_next().unwrap(), Some(runtime::VERSION.spec_version));
+		});
+	}
+
+	#[test]
+	fn local_and_rpc_transactions_are_propagated_after_a_delay() {
+		use futures::{channel::mpsc, executor::block_on, future::{select, Either}};
+		use sc_transaction_pool::{BasicPool, FullChainApi};
+		use substrate_test_runtime_client::{AccountKeyring, runtime::Transfer};
+		use wasm_timer::Instant;
+		use crate::transactions_to_propagate;
+
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let pool = BasicPool::new_full(
+			Default::default(),
+			Arc::new(FullChainApi::new(client.clone(), None)),
+			None,
+			sp_core::testing::SpawnBlockingExecutor::new(),
+			client.clone(),
+		);
+		let (min, max) = (Duration::from_millis(200), Duration::from_millis(400));
+		let (tx, mut propagated) = mpsc::unbounded();
+		let delayed = Arc::new(DelayedTransactions::new());
+		let mut notifications = propagate_imported_transactions(
+			pool.clone(),
+			Some((min, max)),
+			delayed.clone(),
+			move |hash| tx.unbounded_send((hash, Instant::now())).unwrap(),
+		).boxed();
+		let mut propagated_next = |count| {
+			let next = propagated.by_ref().take(count).collect::<Vec<_>>();
+			match block_on(select(&mut notifications, next)) {
+				Either::Right((propagated, _)) => propagated,
+				Either::Left(_) => panic!("The notifications never end"),
+			}
+		};
+		let to_propagate = || {
+			let mut hashes = transactions_to_propagate(&*pool, &delayed).into_iter()
+				.map(|(hash, _)| hash)
+				.collect::<Vec<_>>();
+			hashes.sort();
+			hashes
+		};
+
+		let transfer = |from: AccountKeyring| Transfer {
+			amount: 5,
+			nonce: 0,
+			from: from.into(),
+			to: Default::default(),
+		}.into_signed_tx();
+		let best = BlockId::Hash(client.info().best_hash);
+		let start = Instant::now();
+		let local = block_on(
+			pool.submit_one(&best, TransactionSource::Local, transfer(AccountKeyring::Alice)),
+		).unwrap();
+		let external = block_on(
+			pool.submit_one(&best, TransactionSource::External, transfer(AccountKeyring::Bob)),
+		).unwrap();
+		// the RPC submissions reach the pool as external transactions.
+		let rpc = transfer(AccountKeyring::Charlie);
+		delayed.on_rpc_submission(pool.hash_of(&rpc));
+		let rpc = block_on(pool.submit_one(&best, TransactionSource::External, rpc)).unwrap();
+
+		// network transactions are propagated right away, when the import notifications are
+		// handled.
+		let propagated = propagated_next(1);
+		assert_eq!(propagated[0].0, external);
+		let handled = propagated[0].1;
+		// the periodic propagation leaves the delayed ones out.
+		assert_eq!(to_propagate(), vec![external]);
+
+		// local and RPC ones are propagated after the delay.
+		let mut propagated = propagated_next(2);
+		propagated.sort_by_key(|(hash, _)| *hash);
+		let mut delayed_hashes = vec![local, rpc];
+		delayed_hashes.sort();
+		assert_eq!(propagated.iter().map(|(hash, _)| *hash).collect::<Vec<_>>(), delayed_hashes);
+		for (_, at) in propagated {
+			assert!(at - start >= min);
+			// the delays start when the notifications are handled, leave some generous room for
+			// the scheduling of the timer.
+			assert!(at - handled < max + Duration::from_millis(500));
+		}
+		let mut all = vec![local, external, rpc];
+		all.sort();
+		assert_eq!(to_propagate(), all);
+	}
+}
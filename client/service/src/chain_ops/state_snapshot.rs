@@ -0,0 +1,191 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use codec::{Decode, Encode};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, HashFor, Zero};
+use sp_runtime::generic::BlockId;
+use sp_core::storage::{ChildInfo, Storage, StorageChild};
+use sp_blockchain::HeaderBackend;
+use sc_client_api::{StorageProvider, UsageProvider};
+use super::export_raw_state;
+
+use std::{fs, path::Path, sync::Arc};
+
+/// Prefix of the snapshot files, followed by the format version.
+const MAGIC: &[u8; 8] = b"substate";
+const VERSION: u32 = 1;
+
+/// The state of a block, with what is needed to start a node from it.
+#[derive(Debug, Clone)]
+pub struct StateSnapshot<B: BlockT> {
+	/// Header of the genesis block of the chain.
+	pub genesis: B::Header,
+	/// Header of the block.
+	pub header: B::Header,
+	/// Full state of the block, child tries included.
+	pub storage: Storage,
+}
+
+#[derive(Encode, Decode)]
+struct EncodedSnapshot<H> {
+	genesis: H,
+	header: H,
+	top: Vec<(Vec<u8>, Vec<u8>)>,
+	children: Vec<(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>)>,
+}
+
+impl<B: BlockT> StateSnapshot<B> {
+	/// Checks that the state matches the state root of the block.
+	pub fn verify(&self) -> Result<(), Error> {
+		let state = sp_state_machine::InMemoryBackend::<HashFor<B>>::from(self.storage.clone());
+		if state.root() != self.header.state_root() {
+			return Err(Error::Other(format!(
+				"State snapshot of #{} doesn't match its state root {}",
+				self.header.number(),
+				self.header.state_root(),
+			)));
+		}
+		Ok(())
+	}
+
+	fn encode(&self) -> Vec<u8> {
+		let encoded = EncodedSnapshot {
+			genesis: self.genesis.clone(),
+			header: self.header.clone(),
+			top: self.storage.top.clone().into_iter().collect(),
+			children: self.storage.children_default.iter()
+				.map(|(key, child)| (key.clone(), child.data.clone().into_iter().collect()))
+				.collect(),
+		};
+
+		let mut bytes = MAGIC.to_vec();
+		VERSION.encode_to(&mut bytes);
+		encoded.encode_to(&mut bytes);
+		bytes
+	}
+
+	fn decode(bytes: &[u8]) -> Result<Self, Error> {
+		let invalid = |reason: &str| Error::Other(format!("Invalid state snapshot: {}", reason));
+
+		if !bytes.starts_with(MAGIC) {
+			return Err(invalid("not a state snapshot"));
+		}
+		let mut input = &bytes[MAGIC.len()..];
+		let version = u32::decode(&mut input).map_err(|e| invalid(&e.to_string()))?;
+		if version != VERSION {
+			return Err(invalid(&format!("unsupported version {}", version)));
+		}
+		let encoded = EncodedSnapshot::<B::Header>::decode(&mut input)
+			.map_err(|e| invalid(&e.to_string()))?;
+
+		let children_default = encoded.children.into_iter()
+			.map(|(key, data)| {
+				let child_info = ChildInfo::new_default(&key);
+				(key, StorageChild { data: data.into_iter().collect(), child_info })
+			})
+			.collect();
+
+		Ok(StateSnapshot {
+			genesis: encoded.genesis,
+			header: encoded.header,
+			storage: Storage {
+				top: encoded.top.into_iter().collect(),
+				children_default,
+			},
+		})
+	}
+}
+
+/// Export the state of `block` to a snapshot file at `path`.
+///
+/// The snapshot is verified against the state root of the block before it is written.
+pub fn export_state_snapshot<B, BA, C>(
+	client: Arc<C>,
+	block: BlockId<B>,
+	path: &Path,
+) -> Result<StateSnapshot<B>, Error>
+where
+	C: UsageProvider<B> + StorageProvider<B, BA> + HeaderBackend<B>,
+	B: BlockT,
+	BA: sc_client_api::backend::Backend<B>,
+{
+	let missing = |block: BlockId<B>| Error::Other(format!("Missing header of {}", block));
+	let header = client.header(block.clone())?.ok_or_else(|| missing(block))?;
+	let genesis_id = BlockId::Number(Zero::zero());
+	let genesis = client.header(genesis_id.clone())?.ok_or_else(|| missing(genesis_id))?;
+	let storage = export_raw_state(client, Some(BlockId::Hash(header.hash())))?;
+
+	let snapshot = StateSnapshot { genesis, header, storage };
+	snapshot.verify()?;
+	fs::write(path, snapshot.encode())?;
+	Ok(snapshot)
+}
+
+/// Read the snapshot file at `path`, and verify it against the state root of its block.
+pub fn read_state_snapshot<B: BlockT>(path: &Path) -> Result<StateSnapshot<B>, Error> {
+	let snapshot = StateSnapshot::<B>::decode(&fs::read(path)?)?;
+	snapshot.verify()?;
+	Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use substrate_test_runtime_client::runtime::{Block, Header};
+
+	fn snapshot() -> StateSnapshot<Block> {
+		let mut storage = Storage::default();
+		storage.top.insert(b"key".to_vec(), b"value".to_vec());
+		let child_info = ChildInfo::new_default(b"child");
+		storage.children_default.insert(b"child".to_vec(), StorageChild {
+			data: vec![(b"child_key".to_vec(), b"child_value".to_vec())].into_iter().collect(),
+			child_info,
+		});
+
+		let state = sp_state_machine::InMemoryBackend::<HashFor<Block>>::from(storage.clone());
+		let header = |number, state_root| Header {
+			parent_hash: Default::default(),
+			number,
+			state_root,
+			extrinsics_root: Default::default(),
+			digest: Default::default(),
+		};
+		StateSnapshot {
+			genesis: header(0, Default::default()),
+			header: header(10, *state.root()),
+			storage,
+		}
+	}
+
+	#[test]
+	fn snapshot_round_trips_and_is_verified() {
+		let snapshot = snapshot();
+		let decoded = StateSnapshot::<Block>::decode(&snapshot.encode()).unwrap();
+		assert_eq!(decoded.genesis, snapshot.genesis);
+		assert_eq!(decoded.header, snapshot.header);
+		assert_eq!(decoded.storage.top, snapshot.storage.top);
+		assert_eq!(decoded.storage.children_default, snapshot.storage.children_default);
+		decoded.verify().unwrap();
+
+		assert!(StateSnapshot::<Block>::decode(b"not a snapshot").is_err());
+
+		let mut tampered = snapshot;
+		tampered.storage.children_default.get_mut(&b"child"[..]).unwrap()
+			.data.insert(b"child_key".to_vec(), b"other_value".to_vec());
+		assert!(tampered.verify().is_err());
+	}
+}
@@ -23,8 +23,9 @@ use sp_runtime::{
 };
 use sp_state_machine::{
 	self, OverlayedChanges, Ext, ExecutionManager, StateMachine, ExecutionStrategy,
-	backend::Backend as _, StorageProof,
+	backend::Backend as _, StorageProof, ExecutionTrace,
 };
+use sp_blockchain::HeaderBackend as _;
 use sc_executor::{RuntimeVersion, RuntimeInfo, NativeVersion};
 use sp_externalities::Extensions;
 use sp_core::{NativeOrEncoded, NeverNativeValue, traits::CodeExecutor, offchain::storage::OffchainOverlayedChanges};
@@ -254,6 +255,32 @@ where
 		.map_err(Into::into)
 	}
 
+	fn trace_call(
+		&self,
+		id: &BlockId<Block>,
+		method: &str,
+		call_data: &[u8],
+	) -> sp_blockchain::Result<(Vec<u8>, ExecutionTrace)> {
+		let hash = self.backend.blockchain().expect_block_hash_from_id(id)?;
+		let number = self.backend.blockchain().expect_block_number_from_id(id)?;
+		if !self.backend.have_state_at(&hash, number) {
+			return Err(sp_blockchain::Error::StateUnavailable(format!("{:?}", hash)));
+		}
+
+		let state = self.backend.state_at(*id)?;
+		let state_runtime_code = sp_state_machine::backend::BackendRuntimeCode::new(&state);
+		sp_state_machine::trace_execution::<_, _, NumberFor<Block>, _>(
+			&state,
+			&mut OverlayedChanges::default(),
+			&self.executor,
+			self.spawn_handle.clone(),
+			method,
+			call_data,
+			&state_runtime_code.runtime_code()?,
+		)
+		.map_err(Into::into)
+	}
+
 	fn native_runtime_version(&self) -> Option<&NativeVersion> {
 		Some(self.executor.native_version())
 	}
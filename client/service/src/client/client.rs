@@ -30,7 +30,7 @@ use hash_db::Prefix;
 use sp_core::{
 	convert_hash,
 	storage::{well_known_keys, ChildInfo, PrefixedStorageKey, StorageData, StorageKey},
-	ChangesTrieConfiguration, ExecutionContext, NativeOrEncoded,
+	ChangesTrieConfiguration, ExecutionContext, NativeOrEncoded, H256,
 };
 use sc_telemetry::{telemetry, SUBSTRATE_INFO};
 use sp_runtime::{
@@ -179,6 +179,17 @@ pub struct ClientConfig {
 	pub offchain_worker_enabled: bool,
 	/// If true, allows access from the runtime to write into offchain worker db.
 	pub offchain_indexing_api: bool,
+	/// If true, the genesis block of an existing database isn't checked against the chain
+	/// specification.
+	pub skip_genesis_check: bool,
+	/// Hash identifying the chain specification, e.g. of its JSON. The genesis block of an
+	/// existing database is only built again to be checked when it changes.
+	pub genesis_fingerprint: Option<H256>,
+	/// If set, the Blake2-256 hash the runtime code of the genesis block must have.
+	pub expected_genesis_code_hash: Option<H256>,
+	/// If true, a block received from the network that the runtime panics on while it is
+	/// imported is rejected as a bad block, instead of failing the import with an error.
+	pub reject_runtime_panics: bool,
 }
 
 /// Create a client with the explicitly provided backend.
@@ -1682,11 +1693,30 @@ impl<B, E, Block, RA> sp_consensus::BlockImport<Block> for &Client<B, E, Block,
 		let span = tracing::span!(tracing::Level::DEBUG, "import_block");
 		let _enter = span.enter();
 
-		if let Some(res) = self.prepare_block_storage_changes(&mut import_block).map_err(|e| {
-			warn!("Block prepare storage changes error:\n{:?}", e);
-			ConsensusError::ClientImport(e.to_string())
-		})? {
-			return Ok(res)
+		match self.prepare_block_storage_changes(&mut import_block) {
+			Ok(Some(res)) => return Ok(res),
+			Ok(None) => {},
+			// blocks authored locally still fail with an error, so that the author learns about
+			// the panic.
+			Err(e) => match runtime_panic(&e) {
+				Some(panic) if self.config.reject_runtime_panics
+					&& import_block.origin != BlockOrigin::Own =>
+				{
+					let hash = import_block.post_hash();
+					let number = *import_block.header.number();
+					warn!("Rejecting block #{} ({:?}), the runtime panicked: {}", number, hash, panic);
+					telemetry!(SUBSTRATE_INFO; "block.runtime_panicked";
+						"height" => number.saturated_into::<u64>(),
+						"hash" => ?hash,
+						"panic" => panic,
+					);
+					return Ok(ImportResult::RuntimePanicked);
+				},
+				_ => {
+					warn!("Block prepare storage changes error:\n{:?}", e);
+					return Err(ConsensusError::ClientImport(e.to_string()));
+				},
+			},
 		}
 
 		self.lock_import_and_run(|operation| {
@@ -1735,7 +1765,27 @@ impl<B, E, Block, RA> sp_consensus::BlockImport<Block> for &Client<B, E, Block,
 			BlockStatus::InChainWithState | BlockStatus::Queued if !import_existing  => return Ok(ImportResult::AlreadyInChain),
 			BlockStatus::InChainWithState | BlockStatus::Queued => {},
 			BlockStatus::InChainPruned => return Ok(ImportResult::AlreadyInChain),
-			BlockStatus::Unknown => {},
+			BlockStatus::Unknown => {
+				// the finalized chain is known, so an unknown block at or below the last finalized
+				// height is on a fork that reverts finality.
+				let info = self.backend.blockchain().info();
+				if number <= info.finalized_number {
+					warn!(
+						"Rejecting block #{} ({:?}) conflicting with finalized block #{} ({:?})",
+						number,
+						hash,
+						info.finalized_number,
+						info.finalized_hash,
+					);
+					telemetry!(SUBSTRATE_INFO; "block.conflicts_with_finality";
+						"height" => number.saturated_into::<u64>(),
+						"hash" => ?hash,
+						"finalized_height" => info.finalized_number.saturated_into::<u64>(),
+						"finalized_hash" => ?info.finalized_hash,
+					);
+					return Ok(ImportResult::ConflictsWithFinality);
+				}
+			},
 			BlockStatus::KnownBad => return Ok(ImportResult::KnownBad),
 		}
 
@@ -1989,3 +2039,14 @@ impl<BE, E, B, RA> sp_consensus::block_validation::Chain<B> for Client<BE, E, B,
 		Client::block_status(self, id).map_err(|e| Box::new(e) as Box<_>)
 	}
 }
+
+/// Returns the panic message if `error` is the runtime panicking.
+fn runtime_panic(error: &Error) -> Option<String> {
+	match error {
+		Error::Execution(e) => (**e).as_any()
+			.downcast_ref::<sc_executor::error::Error>()
+			.filter(|e| e.is_runtime_panic())
+			.map(|e| e.to_string()),
+		_ => None,
+	}
+}
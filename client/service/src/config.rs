@@ -18,18 +18,27 @@
 
 //! Service configuration.
 
-pub use sc_client_db::{Database, PruningMode, DatabaseSettingsSrc as DatabaseConfig};
+pub use sc_client_db::{
+	Database, PruningMode, JustificationRetention, DatabaseSettingsSrc as DatabaseConfig,
+};
 pub use sc_network::Multiaddr;
 pub use sc_network::config::{ExtTransport, MultiaddrWithPeerId, NetworkConfiguration, Role, NodeKeyConfig};
+pub use sc_network::config::{AnnounceOverflow, AnnounceValidationLimit};
 pub use sc_executor::WasmExecutionMethod;
 use sc_client_api::execution_extensions::ExecutionStrategies;
 
-use std::{io, future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc};
+use std::{
+	collections::HashMap, io, future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr,
+	num::NonZeroUsize, sync::Arc, time::Duration,
+};
 pub use sc_transaction_pool::txpool::Options as TransactionPoolOptions;
 use sc_chain_spec::ChainSpec;
-use sp_core::crypto::SecretString;
+use sp_core::{crypto::SecretString, H256};
 pub use sc_telemetry::TelemetryEndpoints;
+pub use url::Url;
 use prometheus_endpoint::Registry;
+use sp_utils::mpsc::TracingUnboundedSender;
+use crate::ServiceEvent;
 #[cfg(not(target_os = "unknown"))]
 use tempfile::TempDir;
 
@@ -46,18 +55,50 @@ pub struct Configuration {
 	pub task_executor: TaskExecutor,
 	/// Extrinsic pool configuration.
 	pub transaction_pool: TransactionPoolOptions,
+	/// Range of the random delay before propagating the transactions submitted locally or over
+	/// RPC, so that their origin can't be told from the timing. `None` to propagate them
+	/// immediately.
+	///
+	/// Transactions received from the network are always propagated immediately.
+	pub tx_propagation_delay: Option<(Duration, Duration)>,
 	/// Network configuration.
 	pub network: NetworkConfiguration,
+	/// File the peer reputations are periodically written to, and restored from on startup, so
+	/// that misbehaving peers stay banned across restarts. `None` if they aren't persisted.
+	pub persist_peer_reputations: Option<PathBuf>,
+	/// Maximum age of the persisted peer reputations, older ones are discarded on startup.
+	pub peer_reputations_ttl: Duration,
 	/// Configuration for the keystore.
 	pub keystore: KeystoreConfig,
 	/// Configuration for the database.
 	pub database: DatabaseConfig,
+	/// How many times opening the database is retried after a transient failure.
+	pub database_open_retries: u32,
+	/// Size in MiB of the database write buffer of each column, if not the default.
+	pub database_write_buffer_size: Option<usize>,
+	/// Maximum number of files kept open by the database, if not the default.
+	pub database_max_open_files: Option<u32>,
+	/// Number of threads used to build the genesis state, if more than one.
+	pub genesis_build_threads: Option<usize>,
+	/// Maximum number of forks tracked by the backend, if limited.
+	pub max_tracked_forks: Option<usize>,
+	/// Don't check the genesis block of an existing database against the chain specification.
+	pub skip_genesis_check: bool,
+	/// Blake2-256 hash the runtime code of the genesis block must have, if any.
+	pub expected_genesis_code_hash: Option<H256>,
 	/// Size of internal state cache in Bytes
 	pub state_cache_size: usize,
+	/// Maximum memory in bytes used by the blocks waiting in the import queue, if any.
+	pub import_queue_mem_budget: Option<usize>,
+	/// Tolerance for blocks ahead of the local clock, on top of the next slot. Can't be above
+	/// `sc_consensus_slots::MAX_BLOCK_TIME_DRIFT_TOLERANCE`.
+	pub block_time_drift_tolerance: Duration,
 	/// Size in percent of cache size dedicated to child tries
 	pub state_cache_child_ratio: Option<usize>,
 	/// Pruning settings.
 	pub pruning: PruningMode,
+	/// Which justifications of finalized blocks are kept in the database.
+	pub justification_retention: JustificationRetention,
 	/// Chain configuration.
 	pub chain_spec: Box<dyn ChainSpec>,
 	/// Wasm execution method.
@@ -72,23 +113,54 @@ pub struct Configuration {
 	pub rpc_ipc: Option<String>,
 	/// Maximum number of connections for WebSockets RPC server. `None` if default.
 	pub rpc_ws_max_connections: Option<usize>,
+	/// Serve the HTTP and WebSockets RPC on both the IPv4 and IPv6 wildcard addresses when
+	/// `rpc_http` or `rpc_ws` is a wildcard address.
+	pub rpc_dual_stack: bool,
 	/// CORS settings for HTTP & WS servers. `None` if all origins are allowed.
 	pub rpc_cors: Option<Vec<String>>,
 	/// RPC methods to expose (by default only a safe subset or all of them).
 	pub rpc_methods: RpcMethods,
+	/// When to start serving RPC over HTTP, Websockets and IPC.
+	pub rpc_serve_after: RpcReadiness,
+	/// When to build the handlers of the HTTP, Websockets and IPC RPC servers.
+	pub rpc_handler_mode: RpcHandlerMode,
+	/// Maximum number of runtime calls made concurrently on behalf of RPC clients, e.g. through
+	/// `state_call`. `None` if unlimited.
+	///
+	/// Calls made for block import and authorship are not counted, and nor are those of light
+	/// clients or of custom RPCs calling the runtime API directly. Must be below
+	/// `max_runtime_instances`.
+	pub rpc_max_concurrent_runtime_calls: Option<usize>,
 	/// Prometheus endpoint configuration. `None` if disabled.
 	pub prometheus_config: Option<PrometheusConfig>,
+	/// Labels added to every metric exposed by the Prometheus endpoint, e.g. `chain` and
+	/// `instance`. None of them may already be used by a metric.
+	pub prometheus_static_labels: HashMap<String, String>,
 	/// Telemetry service URL. `None` if disabled.
 	pub telemetry_endpoints: Option<TelemetryEndpoints>,
 	/// External WASM transport for the telemetry. If `Some`, when connection to a telemetry
 	/// endpoint, this transport will be tried in priority before all others.
 	pub telemetry_external_transport: Option<ExtTransport>,
+	/// Whether the core metrics are also sent to the telemetry, as `node.metrics` events.
+	pub telemetry_metrics: bool,
+	/// Minimum number of connected peers for the node to be reported as healthy to the telemetry.
+	/// `0` to always report it as healthy, e.g. for single node development chains.
+	pub telemetry_healthy_peer_threshold: usize,
+	/// HTTP endpoint the header of every finalized block is posted to, as JSON. `None` if
+	/// disabled.
+	pub finality_webhook: Option<Url>,
 	/// The default number of 64KB pages to allocate for Wasm execution
 	pub default_heap_pages: Option<u64>,
 	/// Should offchain workers be executed.
 	pub offchain_worker: OffchainWorkerConfig,
 	/// Enable authoring even when offline.
 	pub force_authoring: bool,
+	/// Let the hook set with `ServiceBuilder::with_inherent_check_failure_hook` accept blocks
+	/// whose inherents fail the check. Only meant for test networks.
+	pub unsafe_override_inherent_checks: bool,
+	/// Reject the blocks received from the network that the runtime panics on, and penalize the
+	/// peers that sent them, instead of failing their import with an error.
+	pub reject_runtime_panics: bool,
 	/// Disable GRANDPA when running in validator mode
 	pub disable_grandpa: bool,
 	/// Development key seed.
@@ -97,6 +169,18 @@ pub struct Configuration {
 	///
 	/// Should only be set when `node` is running development mode.
 	pub dev_key_seed: Option<String>,
+	/// Generate the initial session keys from `dev_key_seed` when the service is built.
+	///
+	/// Disabling this allows to run the node with a runtime that doesn't expose the `SessionKeys`
+	/// API, or with a keystore that can't be written to. The node will sync but won't be able to
+	/// author blocks unless keys are inserted by other means.
+	pub generate_session_keys: bool,
+	/// Generate the session keys from `dev_key_seed` even if the keystore already holds a key for
+	/// every session key type, e.g. inserted manually. `true` by default, as it always has been.
+	pub overwrite_dev_keys: bool,
+	/// On authority nodes, refuse to start unless the keystore holds a key for each of the key
+	/// types that make up the runtime's session keys.
+	pub require_authoring_keys: bool,
 	/// Tracing targets
 	pub tracing_targets: Option<String>,
 	/// Tracing receiver
@@ -107,10 +191,38 @@ pub struct Configuration {
 	pub max_runtime_instances: usize,
 	/// Announce block automatically after they have been imported
 	pub announce_block: bool,
+	/// Never announce blocks to peers, neither automatically nor explicitly. The node still
+	/// imports blocks and serves them to peers that request them.
+	pub suppress_block_announcements: bool,
+	/// Ignore the announcements of blocks this many blocks or more below the best block, to
+	/// mitigate the spam of peers announcing very old blocks. `None` to consider all of them.
+	///
+	/// Announcements are never ignored during a major sync, where old blocks may still be needed.
+	pub max_announce_block_age: Option<u32>,
+	/// Limit on the block announcements of a single peer that are validated at once, so that a
+	/// peer flooding the node with announcements can't delay the validation of the others'.
+	/// `None` to validate all of them as they arrive.
+	pub block_announce_validation_limit: Option<AnnounceValidationLimit>,
+	/// Time after which a block request to an unresponsive peer times out and is issued to
+	/// another peer.
+	pub sync_request_timeout: Duration,
 	/// Base path of the configuration
 	pub base_path: Option<BasePath>,
 	/// Configuration of the output format that the informant uses.
 	pub informant_output_format: sc_informant::OutputFormat,
+	/// Merge finality notifications that arrive together into a single transaction pool
+	/// maintenance, instead of maintaining the pool once per finalized block.
+	pub coalesce_finality_notifications: bool,
+	/// Sink receiving structured events at key points of the service lifecycle (startup, end of
+	/// a major sync, reorgs and shutdown), in addition to the regular log lines.
+	pub structured_events_sink: Option<TracingUnboundedSender<ServiceEvent>>,
+	/// Accumulate the time spent polling each task of the service, as reported by
+	/// `TaskManager::task_metrics`.
+	pub task_poll_time_accounting: bool,
+	/// How long the tasks spawned with `spawn_watched` may go without heartbeating before they are
+	/// restarted, or the service is shut down for essential tasks. The watchdog is disabled if
+	/// `None`.
+	pub task_watchdog_timeout: Option<Duration>,
 }
 
 /// Type for tasks spawned by the executor.
@@ -130,7 +242,9 @@ pub enum KeystoreConfig {
 		/// The path of the keystore.
 		path: PathBuf,
 		/// Node keystore's password.
-		password: Option<SecretString>
+		password: Option<SecretString>,
+		/// Periodic backups of the keystore, if any.
+		backup: Option<KeystoreBackup>,
 	},
 	/// In-memory keystore. Recommended for in-browser nodes.
 	InMemory,
@@ -144,7 +258,28 @@ impl KeystoreConfig {
 			Self::InMemory => None,
 		}
 	}
+
+	/// Returns the periodic backups of the keystore, if any.
+	pub fn backup(&self) -> Option<&KeystoreBackup> {
+		match self {
+			Self::Path { backup, .. } => backup.as_ref(),
+			Self::InMemory => None,
+		}
+	}
+}
+
+/// Periodic backups of an on-disk keystore.
+///
+/// Keys are backed up as they are stored on disk, so a password-protected keystore stays protected
+/// by the same password in the backup.
+#[derive(Debug, Clone)]
+pub struct KeystoreBackup {
+	/// Directory the keystore is copied to. Every backup replaces the previous one.
+	pub path: PathBuf,
+	/// Time between two backups.
+	pub interval: Duration,
 }
+
 /// Configuration of the database of the client.
 #[derive(Debug, Clone, Default)]
 pub struct OffchainWorkerConfig {
@@ -152,6 +287,8 @@ pub struct OffchainWorkerConfig {
 	pub enabled: bool,
 	/// allow writes from the runtime to the offchain worker database.
 	pub indexing_enabled: bool,
+	/// Maximum number of HTTP requests in flight across all offchain workers. Unlimited if `None`.
+	pub max_global_http_requests: Option<NonZeroUsize>,
 }
 
 /// Configuration of the Prometheus endpoint.
@@ -206,6 +343,43 @@ impl Default for RpcMethods {
 	}
 }
 
+/// When to start serving RPC requests coming from outside of the node.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RpcReadiness {
+	/// As soon as the service is built.
+	Immediately,
+	/// Once the node is connected to at least one peer.
+	AfterFirstPeer,
+	/// Once the node is connected to peers and not syncing anymore.
+	AfterSynced,
+}
+
+impl Default for RpcReadiness {
+	fn default() -> RpcReadiness {
+		RpcReadiness::Immediately
+	}
+}
+
+/// When to build the handlers answering the RPC requests coming from outside of the node.
+///
+/// The handler used by the node itself, i.e. `RpcHandlers`, is always built with the service.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RpcHandlerMode {
+	/// Along with the servers.
+	Eager,
+	/// On the first request, and dropped again once unused for `idle_timeout`.
+	Lazy {
+		/// How long the handlers are kept without any request or open connection.
+		idle_timeout: Duration,
+	},
+}
+
+impl Default for RpcHandlerMode {
+	fn default() -> RpcHandlerMode {
+		RpcHandlerMode::Eager
+	}
+}
+
 /// The base path that is used for everything that needs to be write on disk to run a node.
 #[derive(Debug)]
 pub enum BasePath {
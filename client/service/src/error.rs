@@ -45,6 +45,10 @@ pub enum Error {
 	/// Tasks executor is missing.
 	#[display(fmt="Tasks executor hasn't been provided.")]
 	TaskExecutorRequired,
+	/// The keystore of an authority node lacks keys of some of the runtime's session key types.
+	#[display(fmt="Keystore is missing session keys of type(s) {:?}.", _0)]
+	#[from(ignore)]
+	MissingAuthoringKeys(Vec<String>),
 	/// Other error.
 	Other(String),
 }
@@ -0,0 +1,208 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Structured events emitted at key points of the service lifecycle.
+
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use sc_client_api::BlockchainEvents;
+use sc_network::{NetworkStatus, SyncState};
+use serde::Serialize;
+use sp_blockchain::{HeaderBackend, HeaderMetadata, Info};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion};
+use sp_utils::mpsc::TracingUnboundedSender;
+
+/// A key event in the lifecycle of the service.
+///
+/// These are emitted in addition to the regular log lines, and serialize to JSON objects tagged
+/// with an `event` field so that they can be fed to log processing pipelines as is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event")]
+pub enum ServiceEvent {
+	/// The service has been built and is running.
+	NodeStarted {
+		/// Number of the best block at startup.
+		best_number: u64,
+		/// Hash of the best block at startup.
+		best_hash: String,
+	},
+	/// The node has caught up with the network after a major sync.
+	SyncCompleted {
+		/// Number of the best block once synced.
+		best_number: u64,
+		/// Hash of the best block once synced.
+		best_hash: String,
+	},
+	/// The best chain has switched to another fork.
+	Reorg {
+		/// Number of the previous best block.
+		from_number: u64,
+		/// Hash of the previous best block.
+		from_hash: String,
+		/// Number of the new best block.
+		to_number: u64,
+		/// Hash of the new best block.
+		to_hash: String,
+		/// Number of the common ancestor of both blocks.
+		common_number: u64,
+		/// Hash of the common ancestor of both blocks.
+		common_hash: String,
+	},
+	/// The service is shutting down.
+	NodeStopped,
+}
+
+/// Sends `event` to `sink`.
+///
+/// Returns `false` if the sink has been closed, in which case the event is discarded.
+fn emit(sink: &TracingUnboundedSender<ServiceEvent>, event: ServiceEvent) -> bool {
+	sink.unbounded_send(event).is_ok()
+}
+
+fn block<B: BlockT>(number: NumberFor<B>, hash: B::Hash) -> (u64, String) {
+	(number.saturated_into::<u64>(), format!("{:?}", hash))
+}
+
+/// Emits [`ServiceEvent::NodeStarted`] for a node whose chain is at `info`.
+pub(crate) fn node_started<B: BlockT>(sink: &TracingUnboundedSender<ServiceEvent>, info: &Info<B>) {
+	let (best_number, best_hash) = block::<B>(info.best_number, info.best_hash);
+	emit(sink, ServiceEvent::NodeStarted { best_number, best_hash });
+}
+
+/// Emits [`ServiceEvent::SyncCompleted`] every time the node leaves the major sync state.
+///
+/// Ends once the sink is closed.
+pub(crate) async fn sync_events<B, C>(
+	sink: TracingUnboundedSender<ServiceEvent>,
+	client: Arc<C>,
+	mut statuses: impl Stream<Item = NetworkStatus<B>> + Unpin,
+) where
+	B: BlockT,
+	C: HeaderBackend<B>,
+{
+	let mut was_syncing = false;
+	while let Some(status) = statuses.next().await {
+		let is_syncing = status.sync_state == SyncState::Downloading;
+		if was_syncing && !is_syncing {
+			let info = client.info();
+			let (best_number, best_hash) = block::<B>(info.best_number, info.best_hash);
+			if !emit(&sink, ServiceEvent::SyncCompleted { best_number, best_hash }) {
+				return;
+			}
+		}
+		was_syncing = is_syncing;
+	}
+}
+
+/// Emits [`ServiceEvent::Reorg`] every time a new best block isn't a child of the previous one.
+///
+/// Ends once the sink is closed.
+pub(crate) async fn reorg_events<B, C>(sink: TracingUnboundedSender<ServiceEvent>, client: Arc<C>)
+where
+	B: BlockT,
+	C: HeaderBackend<B> + HeaderMetadata<B, Error = sp_blockchain::Error> + BlockchainEvents<B>,
+{
+	let mut last_best = {
+		let info = client.info();
+		(info.best_number, info.best_hash)
+	};
+	let mut imports = client.import_notification_stream();
+
+	while let Some(notification) = imports.next().await {
+		if !notification.is_new_best {
+			continue;
+		}
+
+		let (last_number, last_hash) = last_best;
+		last_best = (*notification.header.number(), notification.hash);
+		if *notification.header.parent_hash() == last_hash {
+			continue;
+		}
+
+		match sp_blockchain::lowest_common_ancestor(&*client, last_hash, notification.hash) {
+			Ok(ancestor) if ancestor.hash != last_hash => {
+				let (from_number, from_hash) = block::<B>(last_number, last_hash);
+				let (to_number, to_hash) = block::<B>(last_best.0, last_best.1);
+				let (common_number, common_hash) = block::<B>(ancestor.number, ancestor.hash);
+				let event = ServiceEvent::Reorg {
+					from_number,
+					from_hash,
+					to_number,
+					to_hash,
+					common_number,
+					common_hash,
+				};
+				if !emit(&sink, event) {
+					return;
+				}
+			},
+			Ok(_) => {},
+			Err(e) => log::warn!("Error computing tree route: {}", e),
+		}
+	}
+}
+
+/// Emits [`ServiceEvent::NodeStopped`] when dropped along with the rest of the service.
+pub(crate) struct NodeStoppedOnDrop(pub(crate) TracingUnboundedSender<ServiceEvent>);
+
+impl Drop for NodeStoppedOnDrop {
+	fn drop(&mut self) {
+		emit(&self.0, ServiceEvent::NodeStopped);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_block_builder::BlockBuilderProvider;
+	use sp_consensus::BlockOrigin;
+	use sp_utils::mpsc::tracing_unbounded;
+	use substrate_test_runtime_client::{prelude::*, runtime::Block};
+
+	#[test]
+	fn node_started_reports_best_block() {
+		let mut client = TestClientBuilder::new().build();
+		for _ in 0..3 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			client.import(BlockOrigin::Own, block).unwrap();
+		}
+
+		let (sink, mut events) = tracing_unbounded("mpsc_test_service_events");
+		node_started::<Block>(&sink, &client.info());
+		drop(NodeStoppedOnDrop(sink));
+
+		match events.try_next().unwrap() {
+			Some(ServiceEvent::NodeStarted { best_number, best_hash }) => {
+				assert_eq!(best_number, 3);
+				assert_eq!(best_hash, format!("{:?}", client.info().best_hash));
+			},
+			other => panic!("Unexpected event: {:?}", other),
+		}
+		assert_eq!(events.try_next().unwrap(), Some(ServiceEvent::NodeStopped));
+	}
+
+	#[test]
+	fn closed_sink_is_ignored() {
+		let (sink, events) = tracing_unbounded("mpsc_test_service_events");
+		drop(events);
+
+		node_started::<Block>(&sink, &substrate_test_runtime_client::new().info());
+		drop(NodeStoppedOnDrop(sink));
+	}
+}
@@ -0,0 +1,253 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Posting the headers of the finalized blocks to an HTTP endpoint, e.g. for bridges and indexers.
+
+use std::{collections::VecDeque, time::Duration};
+
+use futures::{future::{select, BoxFuture, Either}, FutureExt, Stream, StreamExt};
+use log::{debug, warn};
+use prometheus_endpoint::{register, Counter, PrometheusError, Registry, U64};
+use serde::Serialize;
+use url::Url;
+
+/// Maximum number of headers waiting to be posted, the oldest are dropped beyond that.
+const QUEUE_LIMIT: usize = 1024;
+/// Delay before retrying a post that failed for the first time.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Maximum delay between two attempts to post the same header.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Posts a JSON body to the endpoint, returning an error message if it hasn't been accepted.
+pub(crate) type PostFuture = BoxFuture<'static, Result<(), String>>;
+
+/// Delivers the finalized headers to the webhook, one request per header, in order.
+pub(crate) struct FinalityWebhook<H, P> {
+	url: Url,
+	post: P,
+	queue: VecDeque<H>,
+	queue_limit: usize,
+	initial_backoff: Duration,
+	/// Number of headers dropped because the queue was full.
+	dropped: u64,
+	dropped_metric: Option<Counter<U64>>,
+}
+
+impl<H, P> FinalityWebhook<H, P>
+where
+	H: Serialize,
+	P: FnMut(&Url, Vec<u8>) -> PostFuture,
+{
+	/// Creates the webhook, `post` is used to send the requests.
+	pub(crate) fn new(url: Url, post: P, registry: Option<&Registry>) -> Result<Self, PrometheusError> {
+		let dropped_metric = registry.map(|registry| register(
+			Counter::new(
+				"finality_webhook_dropped_headers_total",
+				"Number of finalized headers dropped because the webhook couldn't keep up",
+			)?,
+			registry,
+		)).transpose()?;
+
+		Ok(FinalityWebhook {
+			url,
+			post,
+			queue: VecDeque::new(),
+			queue_limit: QUEUE_LIMIT,
+			initial_backoff: INITIAL_BACKOFF,
+			dropped: 0,
+			dropped_metric,
+		})
+	}
+
+	fn enqueue(&mut self, header: H) {
+		self.queue.push_back(header);
+		self.drop_oldest_if_full();
+	}
+
+	fn drop_oldest_if_full(&mut self) {
+		if self.queue.len() > self.queue_limit {
+			self.queue.pop_front();
+			self.dropped += 1;
+			if let Some(metric) = &self.dropped_metric {
+				metric.inc();
+			}
+			warn!(
+				"Finality webhook {} is lagging behind, dropped the oldest header ({} so far)",
+				self.url, self.dropped,
+			);
+		}
+	}
+
+	/// Posts the headers of `finalized` as they come, retrying failed posts with an exponential
+	/// backoff while queuing the next ones.
+	///
+	/// Ends once the stream has ended and all the queued headers have been posted.
+	pub(crate) async fn run(mut self, mut finalized: impl Stream<Item = H> + Unpin) {
+		let mut backoff = self.initial_backoff;
+		let mut ended = false;
+		loop {
+			let header = match self.queue.pop_front() {
+				Some(header) => header,
+				None if ended => return,
+				None => match finalized.next().await {
+					Some(header) => header,
+					None => return,
+				},
+			};
+
+			let body = serde_json::to_vec(&header).expect("Headers always serialize to JSON; qed");
+			let url = self.url.clone();
+			let mut delivery = (self.post)(&self.url, body).then(move |result| async move {
+				match result {
+					Ok(()) => true,
+					Err(e) => {
+						debug!("Failed to post finalized header to {}: {}, retrying in {:?}", url, e, backoff);
+						futures_timer::Delay::new(backoff).await;
+						false
+					}
+				}
+			}).boxed();
+
+			// Keep queuing the finalized headers while posting.
+			let delivered = loop {
+				if ended {
+					break delivery.await;
+				}
+				match select(delivery, finalized.next()).await {
+					Either::Left((delivered, _)) => break delivered,
+					Either::Right((Some(next), pending)) => {
+						self.enqueue(next);
+						delivery = pending;
+					},
+					Either::Right((None, pending)) => {
+						ended = true;
+						delivery = pending;
+					},
+				}
+			};
+
+			if delivered {
+				backoff = self.initial_backoff;
+			} else {
+				backoff = std::cmp::min(backoff * 2, MAX_BACKOFF);
+				self.queue.push_front(header);
+				self.drop_oldest_if_full();
+			}
+		}
+	}
+}
+
+/// Returns a function posting the bodies with an HTTP(S) client, giving up on requests that
+/// take longer than `timeout`.
+pub(crate) fn http_post(timeout: Duration) -> impl FnMut(&Url, Vec<u8>) -> PostFuture {
+	let client = hyper::Client::builder()
+		.build::<_, hyper::Body>(hyper_rustls::HttpsConnector::new());
+
+	move |url, body| {
+		let request = hyper::Request::post(url.as_str())
+			.header(hyper::header::CONTENT_TYPE, "application/json")
+			.body(hyper::Body::from(body));
+		let client = client.clone();
+		async move {
+			let request = request.map_err(|e| e.to_string())?;
+			match select(client.request(request), futures_timer::Delay::new(timeout)).await {
+				Either::Left((Ok(response), _)) if response.status().is_success() => Ok(()),
+				Either::Left((Ok(response), _)) => Err(format!("Status {}", response.status())),
+				Either::Left((Err(e), _)) => Err(e.to_string()),
+				Either::Right(_) => Err(format!("No response within {:?}", timeout)),
+			}
+		}.boxed()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Arc;
+	use futures::{executor::block_on, future, stream};
+	use parking_lot::Mutex;
+	use sc_block_builder::BlockBuilderProvider;
+	use sc_client_api::BlockchainEvents;
+	use sp_consensus::BlockOrigin;
+	use sp_runtime::{generic::BlockId, traits::Block as _};
+	use substrate_test_runtime_client::prelude::*;
+
+	#[test]
+	fn finalized_headers_are_posted_in_order_and_retried() {
+		let mut client = TestClientBuilder::new().build();
+		let finalized = client.finality_notification_stream()
+			.map(|notification| notification.header)
+			.take(3);
+
+		let mut headers = Vec::new();
+		for _ in 0..3 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			headers.push(block.header.clone());
+			client.import(BlockOrigin::Own, block.clone()).unwrap();
+			client.finalize_block(BlockId::Hash(block.hash()), None).unwrap();
+		}
+
+		let received = Arc::new(Mutex::new(Vec::new()));
+		let mut attempts = 0;
+		let post = {
+			let received = received.clone();
+			move |url: &Url, body: Vec<u8>| {
+				assert_eq!(url.as_str(), "http://127.0.0.1:8080/finalized");
+				attempts += 1;
+				// The endpoint is down on the first attempt.
+				if attempts == 1 {
+					return future::ready(Err(String::from("Connection refused"))).boxed();
+				}
+				received.lock().push(body);
+				future::ready(Ok(())).boxed()
+			}
+		};
+		let url = "http://127.0.0.1:8080/finalized".parse().unwrap();
+		let mut webhook = FinalityWebhook::new(url, post, None).unwrap();
+		webhook.initial_backoff = Duration::from_millis(10);
+		block_on(webhook.run(finalized));
+
+		let expected = headers.iter().map(|header| serde_json::to_vec(header).unwrap()).collect::<Vec<_>>();
+		assert_eq!(*received.lock(), expected);
+	}
+
+	#[test]
+	fn oldest_headers_are_dropped_when_the_queue_is_full() {
+		let received = Arc::new(Mutex::new(Vec::new()));
+		let post = {
+			let received = received.clone();
+			move |_: &Url, body: Vec<u8>| {
+				received.lock().push(body);
+				future::ready(Ok(())).boxed()
+			}
+		};
+		let registry = Registry::new();
+		let url = "http://127.0.0.1:8080/finalized".parse().unwrap();
+		let mut webhook = FinalityWebhook::new(url, post, Some(&registry)).unwrap();
+		webhook.queue_limit = 2;
+
+		for number in 1..=4u32 {
+			webhook.enqueue(number);
+		}
+		assert_eq!(webhook.dropped, 2);
+		assert_eq!(webhook.dropped_metric.as_ref().unwrap().get(), 2);
+
+		block_on(webhook.run(stream::empty()));
+		assert_eq!(*received.lock(), vec![b"3".to_vec(), b"4".to_vec()]);
+	}
+}
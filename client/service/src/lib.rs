@@ -27,6 +27,10 @@ pub mod chain_ops;
 pub mod error;
 
 mod metrics;
+mod events;
+#[cfg(not(target_os = "unknown"))]
+mod finality_webhook;
+mod peer_reputations;
 mod builder;
 #[cfg(feature = "test-helpers")]
 pub mod client;
@@ -35,35 +39,41 @@ mod client;
 mod task_manager;
 
 use std::{io, pin::Pin};
-use std::net::SocketAddr;
-use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use std::task::Poll;
 use parking_lot::Mutex;
+use sc_keystore::KeyStorePtr;
+use sp_core::{crypto::KeyTypeId, traits::BareCryptoStore};
 
 use futures::{Future, FutureExt, Stream, StreamExt, stream, compat::*};
 use sc_network::{NetworkStatus, network_state::NetworkState, PeerId};
 use log::{warn, debug, error};
 use codec::{Encode, Decode};
 use sp_runtime::generic::BlockId;
-use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor};
 use parity_util_mem::MallocSizeOf;
 use sp_utils::{status_sinks, mpsc::{tracing_unbounded, TracingUnboundedReceiver,  TracingUnboundedSender}};
 
 pub use self::error::Error;
 pub use self::builder::{
-	new_full_client, new_client,
-	ServiceBuilder, TFullClient, TLightClient, TFullBackend, TLightBackend,
+	new_full_client, new_client, new_full_parts_with_host_functions, import_state_snapshot,
+	ServiceBuilder, TFullParts, TFullClient, TLightClient, TFullBackend, TLightBackend,
 	TFullCallExecutor, TLightCallExecutor, RpcExtensionBuilder,
 };
 pub use config::{
-	BasePath, Configuration, DatabaseConfig, PruningMode, Role, RpcMethods, TaskExecutor, TaskType,
+	BasePath, Configuration, DatabaseConfig, PruningMode, JustificationRetention, Role, RpcMethods,
+	RpcReadiness, RpcHandlerMode, TaskExecutor, TaskType,
 };
 pub use sc_chain_spec::{
 	ChainSpec, GenericChainSpec, Properties, RuntimeGenesis, Extension as ChainSpecExtension,
 	NoExtension, ChainType,
 };
-pub use sp_transaction_pool::{TransactionPool, InPoolTransaction, error::IntoPoolError};
+pub use sp_transaction_pool::{
+	TransactionPool, InPoolTransaction, BanList, SenderKey, error::IntoPoolError,
+};
 pub use sc_transaction_pool::txpool::Options as TransactionPoolOptions;
 pub use sc_rpc::Metadata as RpcMetadata;
 pub use sc_executor::NativeExecutionDispatch;
@@ -75,13 +85,21 @@ pub use sc_network::config::{
 	TransactionImportFuture,
 };
 pub use sc_tracing::TracingReceiver;
-pub use task_manager::SpawnTaskHandle;
+pub use metrics::MetricsExporter;
+pub use events::ServiceEvent;
+pub use task_manager::{Heartbeat, SpawnTaskHandle};
 pub use task_manager::TaskManager;
 pub use sp_consensus::import_queue::ImportQueue;
+pub use sc_consensus::SwappableSelectChain;
+pub use sp_inherents::{CheckFailureVerdict, InherentCheckFailureHook};
 use sc_client_api::{Backend, BlockchainEvents};
+use sp_blockchain::HeaderBackend;
 
 const DEFAULT_PROTOCOL_ID: &str = "sup";
 
+/// How long to wait for each step of the shutdown of a service before giving up on it.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// A type that implements `MallocSizeOf` on native but not wasm.
 #[cfg(not(target_os = "unknown"))]
 pub trait MallocSizeOfWasm: MallocSizeOf {}
@@ -114,6 +132,16 @@ impl RpcHandlers {
 	}
 }
 
+/// Provides the authority set the `system_authorityStatus` RPC checks the local session keys
+/// against.
+pub trait AuthoritySetProvider: Send + Sync {
+	/// Type of the keys the authorities are identified by.
+	fn key_type(&self) -> KeyTypeId;
+
+	/// Returns the public keys of the authorities in the current set.
+	fn authorities(&self) -> Result<Vec<Vec<u8>>, String>;
+}
+
 /// Sinks to propagate network status updates.
 /// For each element, every time the `Interval` fires we push an element on the sender.
 pub struct NetworkStatusSinks<Block: BlockT>(
@@ -155,6 +183,10 @@ pub struct ServiceComponents<TBl: BlockT, TBackend: Backend<TBl>, TSc, TExPool,
 	pub client: Arc<TCl>,
 	/// A shared transaction pool instance.
 	pub transaction_pool: Arc<TExPool>,
+	/// A handle to the ban list of the transaction pool, if it supports one.
+	///
+	/// Changes are picked up by the running pool.
+	pub transaction_pool_ban_list: Option<BanList<<TBl as BlockT>::Hash>>,
 	/// The chain task manager.
 	pub task_manager: TaskManager,
 	/// A keystore that stores keys.
@@ -177,6 +209,156 @@ pub struct ServiceComponents<TBl: BlockT, TBackend: Backend<TBl>, TSc, TExPool,
 	>>>,
 }
 
+impl<TBl, TBackend, TSc, TExPool, TCl> ServiceComponents<TBl, TBackend, TSc, TExPool, TCl> where
+	TBl: BlockT,
+	TBackend: Backend<TBl>,
+	TCl: BlockchainEvents<TBl> + HeaderBackend<TBl>,
+{
+	/// Returns a future that resolves with the hash of the first block at height `min` or above
+	/// to be finalized.
+	///
+	/// If `min` is already finalized, the future resolves with the current finalized hash.
+	pub fn wait_for_finalized(&self, min: NumberFor<TBl>) -> impl Future<Output = TBl::Hash> {
+		wait_for_finalized(&*self.client, min)
+	}
+
+	/// Returns the genesis hash, and the number and hash of the best and finalized blocks.
+	///
+	/// The best block is never reported below the finalized one.
+	pub fn chain_info(&self) -> sp_blockchain::Info<TBl> {
+		chain_info(&*self.client)
+	}
+}
+
+impl<TBl, TBackend, TExPool, TCl> ServiceComponents<TBl, TBackend, SwappableSelectChain<TBl>, TExPool, TCl>
+where
+	TBl: BlockT,
+	TBackend: Backend<TBl>,
+{
+	/// Replaces the chain selection rule, for every task using a clone of `select_chain`, e.g.
+	/// block authorship and finality.
+	///
+	/// Returns `false` if the service has no chain selection.
+	pub fn set_select_chain(&self, rule: impl sp_consensus::SelectChain<TBl> + 'static) -> bool {
+		match &self.select_chain {
+			Some(select_chain) => {
+				select_chain.swap(rule);
+				true
+			},
+			None => false,
+		}
+	}
+}
+
+fn chain_info<B, C>(client: &C) -> sp_blockchain::Info<B>
+where
+	B: BlockT,
+	C: HeaderBackend<B>,
+{
+	let mut info = client.info();
+	// the best block is updated separately from the finalized one, e.g. while the chain is
+	// reorganized on finalization, so it may briefly lag behind.
+	if info.best_number < info.finalized_number {
+		info.best_number = info.finalized_number;
+		info.best_hash = info.finalized_hash;
+	}
+	info
+}
+
+impl<TBl, TBackend, TSc, TExPool, TCl> ServiceComponents<TBl, TBackend, TSc, TExPool, TCl> where
+	TBl: BlockT,
+	TBackend: Backend<TBl>,
+{
+	/// Adjusts the reputation of `who` by `delta`, for misbehaviour (negative) or good service
+	/// (positive) noticed above the networking layer.
+	///
+	/// Like for the reports of the network itself, the peer is disconnected and banned for a
+	/// while once its reputation falls below the network's threshold.
+	pub fn report_peer(&self, who: PeerId, delta: i32, reason: &'static str) {
+		self.network.report_peer(who, sc_network::ReputationChange::new(delta, reason));
+	}
+
+	/// Exports the state of `block`, child tries included, to a snapshot file at `path`.
+	///
+	/// The snapshot is verified against the state root of the block. It can be imported with
+	/// [`import_state_snapshot`] into the empty database of another node before its service is
+	/// built, which then starts from `block`. Exporting the state of a finalized block is
+	/// recommended, since it is kept until it is pruned.
+	pub fn export_state_snapshot(&self, block: BlockId<TBl>, path: &Path) -> Result<(), Error>
+	where
+		TCl: sc_client_api::UsageProvider<TBl> + sc_client_api::StorageProvider<TBl, TBackend>
+			+ HeaderBackend<TBl>,
+	{
+		chain_ops::export_state_snapshot(self.client.clone(), block, path).map(|_| ())
+	}
+
+	/// Returns a future that resolves once the service has completely shut down, which happens
+	/// when it, or its task manager, is dropped.
+	///
+	/// The shutdown is complete when all the tasks of the service have stopped and the client has
+	/// been dropped, closing the backend and flushing its pending writes to the database. A task
+	/// that doesn't stop, or a reference to the client that is kept elsewhere, makes the future
+	/// resolve with a warning after a timeout instead.
+	pub fn on_shutdown_complete(&self) -> impl Future<Output = ()> + Send + 'static
+	where
+		TCl: Send + Sync + 'static,
+	{
+		on_shutdown_complete(
+			self.task_manager.on_shutdown_complete(SHUTDOWN_TIMEOUT),
+			Arc::downgrade(&self.client),
+			SHUTDOWN_TIMEOUT,
+		)
+	}
+}
+
+fn on_shutdown_complete<C>(
+	tasks_stopped: impl Future<Output = Vec<&'static str>>,
+	client: std::sync::Weak<C>,
+	timeout: Duration,
+) -> impl Future<Output = ()> {
+	async move {
+		// the tasks hold references to the client, so it can't be dropped before they stop.
+		tasks_stopped.await;
+		if !task_manager::wait_until(|| client.strong_count() == 0, timeout).await {
+			warn!(
+				"The client is still referenced {:?} after the service shut down, the database may \
+				not have been closed.",
+				timeout,
+			);
+		}
+	}
+}
+
+fn wait_for_finalized<B, C>(client: &C, min: NumberFor<B>) -> impl Future<Output = B::Hash>
+where
+	B: BlockT,
+	C: BlockchainEvents<B> + HeaderBackend<B>,
+{
+	// subscribe before looking at the chain, so no notification is missed in between.
+	let mut finality_notifications = client.finality_notification_stream();
+	let info = client.info();
+	let already_finalized = if info.finalized_number >= min {
+		Some(info.finalized_hash)
+	} else {
+		None
+	};
+
+	async move {
+		if let Some(hash) = already_finalized {
+			return hash;
+		}
+
+		while let Some(notification) = finality_notifications.next().await {
+			if *notification.header.number() >= min {
+				return notification.hash;
+			}
+		}
+
+		// the client is gone, so nothing will ever be finalized.
+		futures::future::pending().await
+	}
+}
+
 /// Builds a never-ending future that continuously polls the network.
 ///
 /// The `status_sink` contain a list of senders to send a periodic network status to.
@@ -192,9 +374,18 @@ async fn build_network_future<
 	mut rpc_rx: TracingUnboundedReceiver<sc_rpc::system::Request<B>>,
 	should_have_peers: bool,
 	announce_imported_blocks: bool,
+	keystore: KeyStorePtr,
+	authority_set_provider: Option<Arc<dyn AuthoritySetProvider>>,
+	persist_peer_reputations: Option<PathBuf>,
 ) {
 	let mut imported_blocks_stream = client.import_notification_stream().fuse();
 
+	// Fires when the peer reputations have to be written to disk, never if they aren't persisted.
+	let mut persist_reputations_timer = match persist_peer_reputations {
+		Some(_) => futures_timer::Delay::new(peer_reputations::PERSIST_INTERVAL).fuse(),
+		None => futures::future::Fuse::terminated(),
+	};
+
 	// Stream of finalized blocks reported by the client.
 	let mut finality_notification_stream = {
 		let mut finality_notification_stream = client.finality_notification_stream().fuse();
@@ -296,16 +487,14 @@ async fn build_network_future<
 						};
 					}
 					sc_rpc::system::Request::NodeRoles(sender) => {
-						use sc_rpc::system::NodeRole;
-
-						let node_role = match role {
-							Role::Authority { .. } => NodeRole::Authority,
-							Role::Light => NodeRole::LightClient,
-							Role::Full => NodeRole::Full,
-							Role::Sentry { .. } => NodeRole::Sentry,
-						};
-
-						let _ = sender.send(vec![node_role]);
+						let _ = sender.send(node_roles(&role));
+					}
+					sc_rpc::system::Request::AuthorityStatus(sender) => {
+						let _ = sender.send(authority_status(
+							&role,
+							&keystore,
+							authority_set_provider.as_deref(),
+						));
 					}
 				}
 			}
@@ -315,12 +504,24 @@ async fn build_network_future<
 			// the network.
 			_ = (&mut network).fuse() => {}
 
+			// Write the peer reputations to disk, so that they are restored after a restart.
+			_ = persist_reputations_timer => {
+				if let Some(path) = persist_peer_reputations.as_ref() {
+					if let Err(e) = peer_reputations::save(path, &network.peer_reputations()) {
+						warn!("Failed to persist the peer reputations to {}: {}", path.display(), e);
+					}
+				}
+				persist_reputations_timer =
+					futures_timer::Delay::new(peer_reputations::PERSIST_INTERVAL).fuse();
+			}
+
 			// At a regular interval, we send the state of the network on what is called
 			// the "status sinks".
 			ready_sink = status_sinks.next().fuse() => {
 				let status = NetworkStatus {
 					sync_state: network.sync_state(),
 					best_seen_block: network.best_seen_block(),
+					best_queued_block: network.best_queued_block(),
 					num_sync_peers: network.num_sync_peers(),
 					num_connected_peers: network.num_connected_peers(),
 					num_active_peers: network.num_active_peers(),
@@ -334,6 +535,55 @@ async fn build_network_future<
 	}
 }
 
+/// Returns the roles reported by the `system_nodeRoles` RPC.
+fn node_roles(role: &Role) -> Vec<sc_rpc::system::NodeRole> {
+	use sc_rpc::system::NodeRole;
+
+	let node_role = match role {
+		Role::Authority { .. } => NodeRole::Authority,
+		Role::Light => NodeRole::LightClient,
+		Role::Full => NodeRole::Full,
+		Role::Sentry { .. } => NodeRole::Sentry,
+	};
+
+	vec![node_role]
+}
+
+/// Returns whether the keystore holds session keys and whether one of them belongs to the
+/// current authority set. The keys themselves are not part of the status.
+fn authority_status(
+	role: &Role,
+	keystore: &KeyStorePtr,
+	authority_set_provider: Option<&dyn AuthoritySetProvider>,
+) -> sc_rpc::system::AuthorityStatus {
+	let (has_session_keys, is_active_authority) = match authority_set_provider {
+		Some(provider) => {
+			let key_type = provider.key_type();
+			let keystore = keystore.read();
+			let has_session_keys = keystore.keys(key_type)
+				.map(|keys| !keys.is_empty())
+				.unwrap_or(false);
+			let is_active_authority = match provider.authorities() {
+				Ok(authorities) => Some(authorities.into_iter()
+					.any(|public| keystore.has_keys(&[(public, key_type)]))
+				),
+				Err(e) => {
+					warn!("Failed to fetch the current authority set: {}", e);
+					None
+				},
+			};
+			(Some(has_session_keys), is_active_authority)
+		},
+		None => (None, None),
+	};
+
+	sc_rpc::system::AuthorityStatus {
+		roles: node_roles(role),
+		has_session_keys,
+		is_active_authority,
+	}
+}
+
 #[cfg(not(target_os = "unknown"))]
 // Wrapper for HTTP and WS servers that makes sure they are properly shut down.
 mod waiting {
@@ -368,30 +618,203 @@ mod waiting {
 	}
 }
 
+/// Settings of the RPC servers.
+///
+/// They are taken out of the [`Configuration`] so that the servers can be started after the
+/// service has been built.
+struct RpcServersConfig {
+	http: Option<SocketAddr>,
+	ws: Option<SocketAddr>,
+	ipc: Option<String>,
+	ws_max_connections: Option<usize>,
+	cors: Option<Vec<String>>,
+	methods: RpcMethods,
+	handler_mode: RpcHandlerMode,
+	dual_stack: bool,
+}
+
+impl RpcServersConfig {
+	fn new(config: &Configuration) -> Self {
+		RpcServersConfig {
+			http: config.rpc_http,
+			ws: config.rpc_ws,
+			ipc: config.rpc_ipc.clone(),
+			ws_max_connections: config.rpc_ws_max_connections,
+			cors: config.rpc_cors.clone(),
+			methods: config.rpc_methods,
+			handler_mode: config.rpc_handler_mode,
+			dual_stack: config.rpc_dual_stack,
+		}
+	}
+}
+
+/// Waits until the network reports a status that satisfies `readiness`.
+///
+/// Returns `false` if `statuses` ends before that, i.e. the service is shutting down.
+async fn wait_for_rpc_readiness<B: BlockT>(
+	readiness: RpcReadiness,
+	mut statuses: impl Stream<Item = NetworkStatus<B>> + Unpin,
+) -> bool {
+	if readiness == RpcReadiness::Immediately {
+		return true;
+	}
+
+	while let Some(status) = statuses.next().await {
+		let is_ready = match readiness {
+			RpcReadiness::Immediately => true,
+			RpcReadiness::AfterFirstPeer => status.num_connected_peers > 0,
+			RpcReadiness::AfterSynced =>
+				status.num_connected_peers > 0 && status.sync_state == sc_network::SyncState::Idle,
+		};
+		if is_ready {
+			return true;
+		}
+	}
+
+	false
+}
+
 /// Starts RPC servers that run in their own thread, and returns an opaque object that keeps them alive.
+///
+/// In `RpcHandlerMode::Lazy`, the handlers of the servers are built on their first request, and a
+/// task dropping them once idle is spawned with `spawn_handle`.
 #[cfg(not(target_os = "unknown"))]
-fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
-	config: &Configuration,
-	mut gen_handler: H
-) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error> {
-	fn maybe_start_server<T, F>(address: Option<SocketAddr>, mut start: F) -> Result<Option<T>, io::Error>
-		where F: FnMut(&SocketAddr) -> Result<T, io::Error>,
-	{
-		Ok(match address {
-			Some(mut address) => Some(start(&address)
-				.or_else(|e| match e.kind() {
-					io::ErrorKind::AddrInUse |
-					io::ErrorKind::PermissionDenied => {
-						warn!("Unable to bind RPC server to {}. Trying random port.", address);
-						address.set_port(0);
-						start(&address)
-					},
-					_ => Err(e),
-				})?),
-			None => None,
-		})
+fn start_rpc_servers<H>(
+	config: &RpcServersConfig,
+	spawn_handle: &SpawnTaskHandle,
+	gen_handler: H,
+) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error>
+	where H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata> + Send + 'static,
+{
+	let idle_timeout = match config.handler_mode {
+		RpcHandlerMode::Eager => return start_rpc_servers_with(config, gen_handler),
+		RpcHandlerMode::Lazy { idle_timeout } => idle_timeout,
+	};
+
+	let gen_handler = Arc::new(Mutex::new(gen_handler));
+	let mut lazy_handlers = Vec::new();
+	let servers = start_rpc_servers_with(config, |deny_unsafe| {
+		let gen_handler = gen_handler.clone();
+		let lazy = sc_rpc_server::LazyRpcHandler::new(
+			idle_timeout,
+			move || (&mut *gen_handler.lock())(deny_unsafe),
+		);
+		lazy_handlers.push(lazy.clone());
+		lazy.into_rpc_handler()
+	})?;
+
+	let check_period = std::cmp::max(idle_timeout / 2, Duration::from_secs(1));
+	spawn_handle.spawn("rpc-lazy-handlers", async move {
+		loop {
+			futures_timer::Delay::new(check_period).await;
+			for handler in &lazy_handlers {
+				handler.drop_if_idle();
+			}
+		}
+	});
+
+	Ok(servers)
+}
+
+#[cfg(not(target_os = "unknown"))]
+fn maybe_start_server<T, F>(
+	address: Option<SocketAddr>,
+	mut start: F,
+) -> Result<Option<T>, io::Error>
+	where F: FnMut(&SocketAddr) -> Result<T, io::Error>,
+{
+	Ok(match address {
+		Some(mut address) => Some(start(&address)
+			.or_else(|e| match e.kind() {
+				io::ErrorKind::AddrInUse |
+				io::ErrorKind::PermissionDenied => {
+					warn!("Unable to bind RPC server to {}. Trying random port.", address);
+					address.set_port(0);
+					start(&address)
+				},
+				_ => Err(e),
+			})?),
+		None => None,
+	})
+}
+
+/// Starts servers on both the IPv6 and IPv4 wildcard addresses if `dual_stack` is set and
+/// `address` is a wildcard address, and on `address` only otherwise.
+///
+/// If the port is in use or can't be bound, both servers fall back to the same random port,
+/// which `local_addr` reads from the IPv6 one.
+#[cfg(not(target_os = "unknown"))]
+fn start_servers<T, F, L>(
+	address: Option<SocketAddr>,
+	dual_stack: bool,
+	mut start: F,
+	local_addr: L,
+) -> Result<Vec<T>, io::Error>
+	where
+		F: FnMut(&SocketAddr) -> Result<T, io::Error>,
+		L: Fn(&T) -> SocketAddr,
+{
+	let port = match address {
+		Some(address) if dual_stack && address.ip().is_unspecified() => address.port(),
+		address => return Ok(maybe_start_server(address, start)?.into_iter().collect()),
+	};
+
+	// The IPv6 listener is bound first: where it accepts IPv4 connections as well, the IPv4
+	// wildcard address can't be bound anymore, but is served anyway.
+	let mut ipv6 = SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), port);
+	let mut ipv4 = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port);
+	let ipv6_server = match start(&ipv6) {
+		Ok(server) => server,
+		Err(ref e) if is_ipv6_unavailable(e) => {
+			warn!("Unable to bind RPC server to {}, only serving IPv4: {}", ipv6, e);
+			return Ok(maybe_start_server(Some(ipv4), start)?.into_iter().collect());
+		},
+		Err(ref e) if e.kind() == io::ErrorKind::AddrInUse ||
+			e.kind() == io::ErrorKind::PermissionDenied =>
+		{
+			warn!("Unable to bind RPC server to {}. Trying random port.", ipv6);
+			ipv6.set_port(0);
+			let server = start(&ipv6)?;
+			ipv6.set_port(local_addr(&server).port());
+			ipv4.set_port(ipv6.port());
+			server
+		},
+		Err(e) => return Err(e),
+	};
+
+	let mut servers = vec![ipv6_server];
+	match start(&ipv4) {
+		Ok(server) => servers.push(server),
+		Err(ref e) if e.kind() == io::ErrorKind::AddrInUse =>
+			debug!("Not binding RPC server to {}, IPv4 is served by {}", ipv4, ipv6),
+		Err(e) => warn!("Unable to bind RPC server to {}, only serving IPv6: {}", ipv4, e),
 	}
+	Ok(servers)
+}
 
+/// Whether `e` means that IPv6 isn't available on this host.
+#[cfg(not(target_os = "unknown"))]
+fn is_ipv6_unavailable(e: &io::Error) -> bool {
+	// The address family isn't supported, i.e. `EAFNOSUPPORT`.
+	#[cfg(target_os = "linux")]
+	const EAFNOSUPPORT: i32 = 97;
+	#[cfg(windows)]
+	const EAFNOSUPPORT: i32 = 10047;
+	#[cfg(not(any(target_os = "linux", windows)))]
+	const EAFNOSUPPORT: i32 = 47;
+
+	e.kind() == io::ErrorKind::AddrNotAvailable || e.raw_os_error() == Some(EAFNOSUPPORT)
+}
+
+#[cfg(not(target_os = "unknown"))]
+fn start_rpc_servers_with<S, H>(
+	config: &RpcServersConfig,
+	mut gen_handler: H
+) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error>
+	where
+		S: jsonrpc_core::Middleware<sc_rpc::Metadata>,
+		H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata, S>,
+{
 	fn deny_unsafe(addr: &SocketAddr, methods: &RpcMethods) -> sc_rpc::DenyUnsafe {
 		let is_exposed_addr = !addr.ip().is_loopback();
 		match (is_exposed_addr, methods) {
@@ -402,31 +825,36 @@ fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<s
 	}
 
 	Ok(Box::new((
-		config.rpc_ipc.as_ref().map(|path| sc_rpc_server::start_ipc(&*path, gen_handler(sc_rpc::DenyUnsafe::No))),
-		maybe_start_server(
-			config.rpc_http,
+		config.ipc.as_ref().map(|path| sc_rpc_server::start_ipc(&*path, gen_handler(sc_rpc::DenyUnsafe::No))),
+		start_servers(
+			config.http,
+			config.dual_stack,
 			|address| sc_rpc_server::start_http(
 				address,
-				config.rpc_cors.as_ref(),
-				gen_handler(deny_unsafe(&address, &config.rpc_methods)),
+				config.cors.as_ref(),
+				gen_handler(deny_unsafe(&address, &config.methods)),
 			),
-		)?.map(|s| waiting::HttpServer(Some(s))),
-		maybe_start_server(
-			config.rpc_ws,
+			|server: &sc_rpc_server::HttpServer| *server.address(),
+		)?.into_iter().map(|s| waiting::HttpServer(Some(s))).collect::<Vec<_>>(),
+		start_servers(
+			config.ws,
+			config.dual_stack,
 			|address| sc_rpc_server::start_ws(
 				address,
-				config.rpc_ws_max_connections,
-				config.rpc_cors.as_ref(),
-				gen_handler(deny_unsafe(&address, &config.rpc_methods)),
+				config.ws_max_connections,
+				config.cors.as_ref(),
+				gen_handler(deny_unsafe(&address, &config.methods)),
 			),
-		)?.map(|s| waiting::WsServer(Some(s))),
+			|server: &sc_rpc_server::WsServer| *server.addr(),
+		)?.into_iter().map(|s| waiting::WsServer(Some(s))).collect::<Vec<_>>(),
 	)))
 }
 
 /// Starts RPC servers that run in their own thread, and returns an opaque object that keeps them alive.
 #[cfg(target_os = "unknown")]
 fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
-	_: &Configuration,
+	_: &RpcServersConfig,
+	_: &SpawnTaskHandle,
 	_: H
 ) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error> {
 	Ok(Box::new(()))
@@ -453,17 +881,72 @@ impl RpcSession {
 	}
 }
 
+/// How long a transaction submitted over RPC is remembered while waiting for its import.
+const RPC_SUBMISSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Transactions held back from propagation.
+///
+/// The transactions submitted over RPC are recorded until they are imported into the pool, so
+/// that they are delayed like the local ones. The transactions whose propagation delay hasn't
+/// elapsed yet are left out of the periodic propagation.
+pub(crate) struct DelayedTransactions<H> {
+	/// Transactions submitted over RPC, with the time of their submission.
+	rpc_submissions: Mutex<HashMap<H, wasm_timer::Instant>>,
+	/// Transactions waiting for their propagation delay to elapse.
+	delayed: Mutex<HashSet<H>>,
+}
+
+impl<H: std::hash::Hash + Eq> DelayedTransactions<H> {
+	pub(crate) fn new() -> Self {
+		DelayedTransactions {
+			rpc_submissions: Mutex::new(HashMap::new()),
+			delayed: Mutex::new(HashSet::new()),
+		}
+	}
+
+	/// Records a transaction submitted over RPC.
+	///
+	/// The submissions that were never imported are forgotten after `RPC_SUBMISSION_TIMEOUT`.
+	pub(crate) fn on_rpc_submission(&self, hash: H) {
+		let now = wasm_timer::Instant::now();
+		let mut rpc_submissions = self.rpc_submissions.lock();
+		rpc_submissions.retain(|_, submitted| now - *submitted < RPC_SUBMISSION_TIMEOUT);
+		rpc_submissions.insert(hash, now);
+	}
+
+	/// Returns whether the transaction was submitted over RPC, and forgets about it.
+	pub(crate) fn take_rpc_submission(&self, hash: &H) -> bool {
+		self.rpc_submissions.lock().remove(hash).is_some()
+	}
+
+	/// Holds the transaction back until `end_delay` is called.
+	pub(crate) fn start_delay(&self, hash: H) {
+		self.delayed.lock().insert(hash);
+	}
+
+	/// Lets the transaction be propagated again.
+	pub(crate) fn end_delay(&self, hash: &H) {
+		self.delayed.lock().remove(hash);
+	}
+
+	/// Returns whether the transaction is held back from propagation.
+	pub(crate) fn is_delayed(&self, hash: &H) -> bool {
+		self.delayed.lock().contains(hash)
+	}
+}
+
 /// Transaction pool adapter.
-pub struct TransactionPoolAdapter<C, P> {
+pub struct TransactionPoolAdapter<C, P: TransactionPool> {
 	imports_external_transactions: bool,
 	pool: Arc<P>,
 	client: Arc<C>,
+	delayed_transactions: Arc<DelayedTransactions<P::Hash>>,
 }
 
 /// Get transactions for propagation.
 ///
 /// Function extracted to simplify the test and prevent creating `ServiceFactory`.
-fn transactions_to_propagate<Pool, B, H, E>(pool: &Pool)
+fn transactions_to_propagate<Pool, B, H, E>(pool: &Pool, delayed: &DelayedTransactions<H>)
 	-> Vec<(H, B::Extrinsic)>
 where
 	Pool: TransactionPool<Block=B, Hash=H, Error=E>,
@@ -472,7 +955,7 @@ where
 	E: IntoPoolError + From<sp_transaction_pool::error::Error>,
 {
 	pool.ready()
-		.filter(|t| t.is_propagable())
+		.filter(|t| t.is_propagable() && !delayed.is_delayed(t.hash()))
 		.map(|t| {
 			let hash = t.hash().clone();
 			let ex: B::Extrinsic = t.data().clone();
@@ -491,7 +974,7 @@ where
 	E: 'static + IntoPoolError + From<sp_transaction_pool::error::Error>,
 {
 	fn transactions(&self) -> Vec<(H, B::Extrinsic)> {
-		transactions_to_propagate(&*self.pool)
+		transactions_to_propagate(&*self.pool, &self.delayed_transactions)
 	}
 
 	fn hash_of(&self, transaction: &B::Extrinsic) -> H {
@@ -543,6 +1026,9 @@ where
 	}
 
 	fn transaction(&self, hash: &H) -> Option<B::Extrinsic> {
+		if self.delayed_transactions.is_delayed(hash) {
+			return None;
+		}
 		self.pool.ready_transaction(hash)
 			.and_then(
 				// Only propagable transactions should be resolved for network service.
@@ -560,6 +1046,218 @@ mod tests {
 	use substrate_test_runtime_client::{prelude::*, runtime::{Extrinsic, Transfer}};
 	use sc_transaction_pool::{BasicPool, FullChainApi};
 
+	type TestBlock = substrate_test_runtime_client::runtime::Block;
+	type TestServiceClient = TFullClient<
+		TestBlock,
+		substrate_test_runtime_client::runtime::RuntimeApi,
+		substrate_test_runtime_client::LocalExecutor,
+	>;
+	type TestService<TSc> = ServiceComponents<
+		TestBlock,
+		TFullBackend<TestBlock>,
+		TSc,
+		BasicPool<FullChainApi<TestServiceClient, TestBlock>, TestBlock>,
+		TestServiceClient,
+	>;
+
+	/// Genesis of the test runtime, as a chain spec needs it to be serializable.
+	#[derive(serde::Serialize, serde::Deserialize)]
+	struct TestGenesis;
+
+	impl sp_runtime::BuildStorage for TestGenesis {
+		fn assimilate_storage(
+			&self,
+			storage: &mut sp_core::storage::Storage,
+		) -> Result<(), String> {
+			use substrate_test_runtime_client::{GenesisInit, GenesisParameters};
+
+			let genesis = GenesisParameters::default().genesis_storage();
+			storage.top.extend(genesis.top);
+			storage.children_default.extend(genesis.children_default);
+			Ok(())
+		}
+	}
+
+	/// Builds a full service of the test runtime in `path`, only reachable in memory.
+	///
+	/// `configure` adjusts the default configuration before the service is built.
+	fn build_test_service<TSc>(
+		path: &Path,
+		configure: impl FnOnce(&mut Configuration),
+		select_chain: impl FnOnce(&Arc<TFullBackend<TestBlock>>) -> Option<TSc>,
+	) -> Result<TestService<TSc>, Error>
+	where
+		TSc: sp_consensus::SelectChain<TestBlock> + 'static,
+	{
+		use sc_network::{config::TransportConfig, multiaddr, NetworkConfiguration};
+
+		let task_executor: TaskExecutor = (|future, _| {
+			std::thread::spawn(move || block_on(future));
+		}).into();
+		let chain_spec = GenericChainSpec::<TestGenesis>::from_genesis(
+			"Test",
+			"test",
+			ChainType::Development,
+			|| TestGenesis,
+			Vec::new(),
+			None,
+			None,
+			None,
+			None,
+		);
+
+		let mut network = NetworkConfiguration::new(
+			"Test node",
+			"test-client",
+			Default::default(),
+			None,
+		);
+		network.listen_addresses = vec![multiaddr::Protocol::Memory(rand::random()).into()];
+		network.transport = TransportConfig::MemoryOnly;
+		network.allow_non_globals_in_dht = true;
+
+		let mut config = Configuration {
+			impl_name: String::from("service-test-impl"),
+			impl_version: String::from("0.1"),
+			role: Role::Full,
+			task_executor,
+			transaction_pool: Default::default(),
+			tx_propagation_delay: None,
+			network,
+			keystore: config::KeystoreConfig::Path {
+				path: path.join("key"),
+				password: None,
+				backup: None,
+			},
+			database: DatabaseConfig::RocksDb {
+				path: path.join("db"),
+				cache_size: 128,
+			},
+			database_open_retries: 0,
+			database_write_buffer_size: None,
+			database_max_open_files: None,
+			genesis_build_threads: None,
+			max_tracked_forks: None,
+			skip_genesis_check: false,
+			expected_genesis_code_hash: None,
+			state_cache_size: 16777216,
+			import_queue_mem_budget: None,
+			block_time_drift_tolerance: Default::default(),
+			state_cache_child_ratio: None,
+			pruning: Default::default(),
+			justification_retention: Default::default(),
+			chain_spec: Box::new(chain_spec),
+			wasm_method: config::WasmExecutionMethod::Interpreted,
+			execution_strategies: Default::default(),
+			rpc_http: None,
+			rpc_ipc: None,
+			rpc_ws: None,
+			rpc_ws_max_connections: None,
+			rpc_dual_stack: false,
+			rpc_cors: None,
+			rpc_methods: Default::default(),
+			rpc_serve_after: Default::default(),
+			rpc_handler_mode: Default::default(),
+			rpc_max_concurrent_runtime_calls: None,
+			prometheus_config: None,
+			prometheus_static_labels: Default::default(),
+			telemetry_endpoints: None,
+			telemetry_external_transport: None,
+			telemetry_metrics: false,
+			telemetry_healthy_peer_threshold: 1,
+			finality_webhook: None,
+			persist_peer_reputations: None,
+			peer_reputations_ttl: Duration::from_secs(3600),
+			default_heap_pages: None,
+			offchain_worker: Default::default(),
+			force_authoring: false,
+			unsafe_override_inherent_checks: false,
+			reject_runtime_panics: false,
+			disable_grandpa: false,
+			dev_key_seed: None,
+			generate_session_keys: true,
+			overwrite_dev_keys: true,
+			require_authoring_keys: false,
+			tracing_targets: None,
+			tracing_receiver: Default::default(),
+			max_runtime_instances: 8,
+			announce_block: true,
+			suppress_block_announcements: false,
+			max_announce_block_age: None,
+			block_announce_validation_limit: None,
+			sync_request_timeout: sc_network::config::DEFAULT_SYNC_REQUEST_TIMEOUT,
+			base_path: Some(BasePath::new(path)),
+			informant_output_format: Default::default(),
+			coalesce_finality_notifications: false,
+			structured_events_sink: None,
+			task_poll_time_accounting: false,
+			task_watchdog_timeout: None,
+		};
+		configure(&mut config);
+
+		ServiceBuilder::new_full::<
+			TestBlock,
+			substrate_test_runtime_client::runtime::RuntimeApi,
+			substrate_test_runtime_client::LocalExecutor,
+		>(config)?
+			.with_opt_select_chain(|_config, backend| Ok(select_chain(backend)))?
+			.with_transaction_pool(|builder| {
+				let pool_api = FullChainApi::new(builder.client().clone(), None);
+				Ok(Arc::new(BasicPool::new_full(
+					builder.config().transaction_pool.clone(),
+					Arc::new(pool_api),
+					None,
+					builder.spawn_handle(),
+					builder.client().clone(),
+				)))
+			})?
+			.with_import_queue(|_config, client, _select_chain, _pool, spawn_handle, registry| {
+				Ok(sp_consensus::import_queue::BasicQueue::new(
+					AcceptAll,
+					Box::new(client),
+					None,
+					None,
+					spawn_handle,
+					registry,
+				))
+			})?
+			.build_full()
+	}
+
+	/// Verifier of the test services, which accepts every block.
+	struct AcceptAll;
+
+	impl<B: BlockT> sp_consensus::import_queue::Verifier<B> for AcceptAll {
+		fn verify(
+			&mut self,
+			origin: sp_consensus::BlockOrigin,
+			header: B::Header,
+			justification: Option<sp_runtime::Justification>,
+			body: Option<Vec<B::Extrinsic>>,
+		) -> Result<
+			(
+				sp_consensus::BlockImportParams<B, ()>,
+				Option<Vec<(sp_consensus::import_queue::CacheKeyId, Vec<u8>)>>,
+			),
+			String,
+		> {
+			let mut import = sp_consensus::BlockImportParams::new(origin, header);
+			import.body = body;
+			import.justification = justification;
+			import.fork_choice = Some(sp_consensus::ForkChoiceStrategy::LongestChain);
+			Ok((import, None))
+		}
+	}
+
+	/// Waits for `condition` to hold, panicking if it takes too long.
+	fn wait_until(condition: impl Fn() -> bool) {
+		let start = std::time::Instant::now();
+		while !condition() {
+			assert!(start.elapsed() < Duration::from_secs(30), "Timed out waiting for a condition");
+			std::thread::sleep(Duration::from_millis(50));
+		}
+	}
+
 	#[test]
 	fn should_not_propagate_transactions_that_are_marked_as_such() {
 		// given
@@ -590,7 +1288,7 @@ mod tests {
 		assert_eq!(pool.status().ready, 2);
 
 		// when
-		let transactions = transactions_to_propagate(&*pool);
+		let transactions = transactions_to_propagate(&*pool, &DelayedTransactions::new());
 
 		// then
 		assert_eq!(transactions.len(), 1);
@@ -598,4 +1296,438 @@ mod tests {
 		// this should not panic
 		let _ = transactions[0].1.transfer();
 	}
+
+	struct StaticAuthoritySet(Vec<Vec<u8>>);
+
+	impl AuthoritySetProvider for StaticAuthoritySet {
+		fn key_type(&self) -> KeyTypeId {
+			sp_core::testing::SR25519
+		}
+
+		fn authorities(&self) -> Result<Vec<Vec<u8>>, String> {
+			Ok(self.0.clone())
+		}
+	}
+
+	#[test]
+	fn authority_status_reports_keyed_and_unkeyed_nodes() {
+		use sp_core::Pair;
+		use sc_rpc::system::{AuthorityStatus, NodeRole};
+
+		let role = Role::Authority { sentry_nodes: Vec::new() };
+		let authority = sp_core::sr25519::Pair::from_string("//Alice", None).unwrap();
+		let authorities = StaticAuthoritySet(vec![authority.public().to_vec()]);
+
+		let unkeyed = sc_keystore::Store::new_in_memory();
+		assert_eq!(
+			authority_status(&role, &unkeyed, Some(&authorities)),
+			AuthorityStatus {
+				roles: vec![NodeRole::Authority],
+				has_session_keys: Some(false),
+				is_active_authority: Some(false),
+			},
+		);
+
+		// Keys inserted into an in-memory keystore are only kept when derived from a seed.
+		let keyed = sc_keystore::Store::new_in_memory();
+		keyed.write()
+			.insert_ephemeral_from_seed_by_type::<sp_core::sr25519::Pair>(
+				"//Alice",
+				sp_core::testing::SR25519,
+			)
+			.unwrap();
+		assert_eq!(
+			authority_status(&role, &keyed, Some(&authorities)),
+			AuthorityStatus {
+				roles: vec![NodeRole::Authority],
+				has_session_keys: Some(true),
+				is_active_authority: Some(true),
+			},
+		);
+
+		// Keys that aren't part of the current set don't make the node an active authority.
+		let others = StaticAuthoritySet(vec![
+			sp_core::sr25519::Pair::from_string("//Bob", None).unwrap().public().to_vec(),
+		]);
+		assert_eq!(
+			authority_status(&role, &keyed, Some(&others)).is_active_authority,
+			Some(false),
+		);
+
+		// Without knowing the authority set, the status is unknown.
+		assert_eq!(
+			authority_status(&role, &keyed, None),
+			AuthorityStatus {
+				roles: vec![NodeRole::Authority],
+				has_session_keys: None,
+				is_active_authority: None,
+			},
+		);
+	}
+
+	#[test]
+	fn wait_for_finalized_resolves_at_requested_height() {
+		use sc_block_builder::BlockBuilderProvider;
+		use sp_consensus::BlockOrigin;
+
+		let mut client = substrate_test_runtime_client::new();
+		let mut hashes = Vec::new();
+		for _ in 0..3 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			hashes.push(block.hash());
+			client.import(BlockOrigin::Own, block).unwrap();
+		}
+		client.finalize_block(BlockId::Number(1), None).unwrap();
+
+		block_on(async {
+			// already finalized blocks resolve right away with the current finalized hash
+			assert_eq!(wait_for_finalized(&client, 0).await, hashes[0]);
+			assert_eq!(wait_for_finalized(&client, 1).await, hashes[0]);
+
+			let mut waiting = wait_for_finalized(&client, 3).boxed();
+			client.finalize_block(BlockId::Number(2), None).unwrap();
+			assert!(futures::poll!(&mut waiting).is_pending());
+
+			client.finalize_block(BlockId::Number(3), None).unwrap();
+			assert_eq!(waiting.await, hashes[2]);
+		});
+	}
+
+	#[test]
+	fn chain_info_reports_best_and_finalized_blocks() {
+		use sc_block_builder::BlockBuilderProvider;
+		use sp_consensus::BlockOrigin;
+
+		let mut client = substrate_test_runtime_client::new();
+		let genesis_hash = client.info().genesis_hash;
+		let info = chain_info(&client);
+		assert_eq!(info.best_hash, genesis_hash);
+		assert_eq!(info.finalized_hash, genesis_hash);
+
+		let mut hashes = Vec::new();
+		for _ in 0..3 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			hashes.push(block.hash());
+			client.import(BlockOrigin::Own, block).unwrap();
+		}
+		client.finalize_block(BlockId::Number(2), None).unwrap();
+
+		let info = chain_info(&client);
+		assert_eq!(info.genesis_hash, genesis_hash);
+		assert_eq!((info.best_number, info.best_hash), (3, hashes[2]));
+		assert_eq!((info.finalized_number, info.finalized_hash), (2, hashes[1]));
+	}
+
+	#[test]
+	fn reported_peers_are_disconnected_once_banned() {
+		use sc_network::{config::MultiaddrWithPeerId, multiaddr, Multiaddr};
+
+		let dir = tempfile::tempdir().unwrap();
+		let address: Multiaddr = multiaddr::Protocol::Memory(rand::random()).into();
+		let first = build_test_service(
+			&dir.path().join("first"),
+			|config| config.network.listen_addresses = vec![address.clone()],
+			|backend| Some(sc_consensus::LongestChain::new(backend.clone())),
+		).unwrap();
+		let boot_node = MultiaddrWithPeerId {
+			multiaddr: address,
+			peer_id: first.network.local_peer_id().clone(),
+		};
+		let second = build_test_service(
+			&dir.path().join("second"),
+			|config| config.network.boot_nodes = vec![boot_node],
+			|backend| Some(sc_consensus::LongestChain::new(backend.clone())),
+		).unwrap();
+		wait_until(|| first.network.num_connected() == 1);
+
+		// a penalty above the ban threshold doesn't disconnect the peer.
+		let second_peer_id = second.network.local_peer_id().clone();
+		first.report_peer(second_peer_id.clone(), -1000, "Test penalty");
+		std::thread::sleep(Duration::from_millis(500));
+		assert_eq!(first.network.num_connected(), 1);
+
+		first.report_peer(second_peer_id, i32::min_value(), "Test ban");
+		wait_until(|| first.network.num_connected() == 0);
+	}
+
+	#[test]
+	fn select_chain_of_a_built_service_can_be_replaced() {
+		use sc_block_builder::BlockBuilderProvider;
+		use sc_consensus::LongestChain;
+		use sp_consensus::{BlockOrigin, Error as ConsensusError};
+
+		/// Always answers with the same block.
+		#[derive(Clone)]
+		struct Pinned(<TestBlock as BlockT>::Header);
+
+		impl SelectChain<TestBlock> for Pinned {
+			fn leaves(&self) -> Result<Vec<<TestBlock as BlockT>::Hash>, ConsensusError> {
+				Ok(vec![self.0.hash()])
+			}
+
+			fn best_chain(&self) -> Result<<TestBlock as BlockT>::Header, ConsensusError> {
+				Ok(self.0.clone())
+			}
+		}
+
+		let dir = tempfile::tempdir().unwrap();
+		let mut backend = None;
+		let service = build_test_service(
+			&dir.path().join("swappable"),
+			|_| (),
+			|service_backend| {
+				backend = Some(service_backend.clone());
+				Some(SwappableSelectChain::new(LongestChain::new(service_backend.clone())))
+			},
+		).unwrap();
+		let used_by_authorship = service.select_chain.clone().unwrap();
+
+		let mut client = service.client.clone();
+		let genesis = client.header(&BlockId::Number(0)).unwrap().unwrap();
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, block.clone()).unwrap();
+		assert_eq!(used_by_authorship.best_chain().unwrap().hash(), block.hash());
+
+		assert!(service.set_select_chain(Pinned(genesis.clone())));
+		assert_eq!(used_by_authorship.best_chain().unwrap(), genesis);
+		assert_eq!(used_by_authorship.leaves().unwrap(), vec![genesis.hash()]);
+
+		assert!(service.set_select_chain(LongestChain::new(backend.unwrap())));
+		assert_eq!(used_by_authorship.best_chain().unwrap().hash(), block.hash());
+
+		// without a chain selection, there is nothing to replace.
+		let service = build_test_service(
+			&dir.path().join("none"),
+			|_| (),
+			|_| None::<SwappableSelectChain<TestBlock>>,
+		).unwrap();
+		assert!(service.select_chain.is_none());
+		assert!(!service.set_select_chain(Pinned(genesis)));
+	}
+
+	#[test]
+	fn only_missing_session_keys_are_generated_unless_overwritten() {
+		use sp_core::{Pair, testing::{ECDSA, ED25519, SR25519}};
+
+		let dir = tempfile::tempdir().unwrap();
+		let build = |name: &str, overwrite_dev_keys| {
+			let path = dir.path().join(name);
+			let manual = sc_keystore::Store::open(path.join("key"), None).unwrap()
+				.read()
+				.insert_by_type::<sp_core::sr25519::Pair>(SR25519, "//Bob")
+				.unwrap()
+				.public();
+			let service = build_test_service(
+				&path,
+				|config| {
+					config.dev_key_seed = Some("//Alice".into());
+					config.overwrite_dev_keys = overwrite_dev_keys;
+				},
+				|backend| Some(sc_consensus::LongestChain::new(backend.clone())),
+			).unwrap();
+			(service, manual)
+		};
+
+		// only the key types the keystore holds no key of are generated. The test runtime ignores
+		// the seed, so the keys it generates are random ones written to disk.
+		let (service, manual) = build("kept", false);
+		let keystore = service.keystore.read();
+		assert_eq!(keystore.sr25519_public_keys(SR25519), vec![manual]);
+		assert_eq!(keystore.ed25519_public_keys(ED25519).len(), 1);
+		assert_eq!(keystore.ecdsa_public_keys(ECDSA).len(), 1);
+		drop(keystore);
+		assert_eq!(std::fs::read_dir(dir.path().join("kept").join("key")).unwrap().count(), 3);
+
+		// the development keys are added next to the manual ones.
+		let (service, manual) = build("overwritten", true);
+		let sr25519_keys = service.keystore.read().sr25519_public_keys(SR25519);
+		assert_eq!(sr25519_keys.len(), 2);
+		assert!(sr25519_keys.contains(&manual));
+	}
+
+	#[test]
+	fn session_keys_generation_can_be_skipped_with_a_read_only_keystore() {
+		use sp_core::testing::{ECDSA, ED25519, SR25519};
+
+		let dir = tempfile::tempdir().unwrap();
+		let keystore_path = dir.path().join("key");
+		std::fs::create_dir(&keystore_path).unwrap();
+		let writable = std::fs::metadata(&keystore_path).unwrap().permissions();
+		let mut read_only = writable.clone();
+		read_only.set_readonly(true);
+		std::fs::set_permissions(&keystore_path, read_only).unwrap();
+
+		let service = build_test_service(
+			dir.path(),
+			|config| {
+				config.dev_key_seed = Some("//Alice".into());
+				config.generate_session_keys = false;
+			},
+			|backend| Some(sc_consensus::LongestChain::new(backend.clone())),
+		).unwrap();
+
+		let keystore = service.keystore.read();
+		assert!(keystore.sr25519_public_keys(SR25519).is_empty());
+		assert!(keystore.ed25519_public_keys(ED25519).is_empty());
+		assert!(keystore.ecdsa_public_keys(ECDSA).is_empty());
+		drop(keystore);
+		assert_eq!(std::fs::read_dir(&keystore_path).unwrap().count(), 0);
+		// The node can still follow the chain, it just can't author.
+		assert_eq!(service.client.chain_info().best_number, 0);
+
+		drop(service);
+		std::fs::set_permissions(&keystore_path, writable).unwrap();
+	}
+
+	#[test]
+	fn shutdown_completes_once_the_backend_is_dropped() {
+		use std::sync::atomic::{AtomicBool, Ordering};
+
+		let executor: TaskExecutor = (|future, _| {
+			std::thread::spawn(move || block_on(future));
+		}).into();
+		let task_manager = TaskManager::new(executor, None, false, None).unwrap();
+
+		let client = Arc::new(TestClientBuilder::new().build());
+		let backend_dropped = Arc::new(AtomicBool::new(false));
+		// Keeps the client until the task is interrupted, then takes a while to release it.
+		struct SlowRelease(Arc<TestClient>, Arc<AtomicBool>);
+		impl Drop for SlowRelease {
+			fn drop(&mut self) {
+				std::thread::sleep(Duration::from_millis(200));
+				self.1.store(true, Ordering::SeqCst);
+			}
+		}
+		let release = SlowRelease(client.clone(), backend_dropped.clone());
+		task_manager.spawn_handle().spawn("holds-client", async move {
+			let _release = release;
+			futures::future::pending::<()>().await;
+		});
+
+		let shutdown_complete = on_shutdown_complete(
+			task_manager.on_shutdown_complete(SHUTDOWN_TIMEOUT),
+			Arc::downgrade(&client),
+			SHUTDOWN_TIMEOUT,
+		);
+		drop(client);
+		drop(task_manager);
+
+		block_on(shutdown_complete);
+		assert!(backend_dropped.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn rpc_readiness_waits_for_network_status() {
+		use sc_network::SyncState;
+		use substrate_test_runtime_client::runtime::Block;
+
+		let status = |sync_state, num_connected_peers| NetworkStatus::<Block> {
+			sync_state,
+			best_seen_block: None,
+			best_queued_block: None,
+			num_sync_peers: num_connected_peers as u32,
+			num_connected_peers,
+			num_active_peers: num_connected_peers,
+			average_download_per_sec: 0,
+			average_upload_per_sec: 0,
+		};
+
+		block_on(async {
+			let (_tx, rx) = tracing_unbounded("mpsc_test_rpc_readiness");
+			assert!(wait_for_rpc_readiness::<Block>(RpcReadiness::Immediately, rx).await);
+
+			for (readiness, ready_status) in vec![
+				(RpcReadiness::AfterFirstPeer, status(SyncState::Downloading, 1)),
+				(RpcReadiness::AfterSynced, status(SyncState::Idle, 1)),
+			] {
+				let (tx, rx) = tracing_unbounded("mpsc_test_rpc_readiness");
+				let mut waiting = wait_for_rpc_readiness(readiness, rx).boxed();
+
+				tx.unbounded_send(status(SyncState::Idle, 0)).unwrap();
+				assert!(futures::poll!(&mut waiting).is_pending());
+				if readiness == RpcReadiness::AfterSynced {
+					tx.unbounded_send(status(SyncState::Downloading, 3)).unwrap();
+					assert!(futures::poll!(&mut waiting).is_pending());
+				}
+
+				tx.unbounded_send(ready_status).unwrap();
+				assert!(waiting.await);
+			}
+
+			// the service shutting down before being ready stops the wait
+			let (tx, rx) = tracing_unbounded("mpsc_test_rpc_readiness");
+			let waiting = wait_for_rpc_readiness::<Block>(RpcReadiness::AfterFirstPeer, rx);
+			drop(tx);
+			assert!(!waiting.await);
+		});
+	}
+
+	#[test]
+	fn dual_stack_rpc_answers_on_both_address_families() {
+		use std::io::{Read, Write};
+		use std::net::{TcpListener, TcpStream};
+
+		if TcpListener::bind("[::1]:0").is_err() {
+			// Not a dual-stack system, only IPv4 would be served.
+			return;
+		}
+
+		let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+		let config = RpcServersConfig {
+			http: Some(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port)),
+			ws: None,
+			ipc: None,
+			ws_max_connections: None,
+			cors: None,
+			methods: RpcMethods::Unsafe,
+			handler_mode: RpcHandlerMode::Eager,
+			dual_stack: true,
+		};
+		let _servers = start_rpc_servers_with(&config, |_| {
+			let mut io = sc_rpc_server::RpcHandler::default();
+			io.add_method("system_health", |_| Ok(jsonrpc_core::Value::String("healthy".into())));
+			io
+		}).unwrap();
+
+		for host in &["127.0.0.1", "[::1]"] {
+			let mut stream = TcpStream::connect(format!("{}:{}", host, port)).unwrap();
+			write!(stream, "GET /health HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", host).unwrap();
+			let mut response = String::new();
+			stream.read_to_string(&mut response).unwrap();
+			assert!(response.starts_with("HTTP/1.1 200"), "{} answered {}", host, response);
+			assert!(response.contains("healthy"), "{} answered {}", host, response);
+		}
+	}
+
+	#[test]
+	fn dual_stack_rpc_falls_back_to_a_random_port_shared_by_both_families() {
+		let port = 9933;
+		let wildcard = Some(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port));
+		// Fails to bind the IPv6 address on `port` with `error`, and binds random ports to 4242.
+		let start = |error: io::ErrorKind| move |address: &SocketAddr| {
+			if address.is_ipv6() && address.port() == port {
+				return Err(io::Error::from(error));
+			}
+			let mut address = *address;
+			if address.port() == 0 {
+				address.set_port(4242);
+			}
+			Ok(address)
+		};
+		let local_addr = |address: &SocketAddr| *address;
+
+		for &error in &[io::ErrorKind::AddrInUse, io::ErrorKind::PermissionDenied] {
+			let servers = start_servers(wildcard, true, start(error), local_addr).unwrap();
+			let expected: Vec<SocketAddr> =
+				vec!["[::]:4242".parse().unwrap(), "0.0.0.0:4242".parse().unwrap()];
+			assert_eq!(servers, expected, "{:?}", error);
+		}
+
+		// only IPv4 is served when IPv6 is unavailable.
+		let unavailable = start(io::ErrorKind::AddrNotAvailable);
+		let servers = start_servers(wildcard, true, unavailable, local_addr).unwrap();
+		assert_eq!(servers, vec![wildcard.unwrap()]);
+
+		assert!(start_servers(wildcard, true, start(io::ErrorKind::Other), local_addr).is_err());
+	}
 }
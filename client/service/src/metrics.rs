@@ -16,10 +16,12 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{convert::TryFrom, time::SystemTime};
+use std::{collections::HashMap, convert::TryFrom, time::SystemTime};
 
 use crate::{NetworkStatus, config::Configuration};
+use futures::{Future, Stream, StreamExt, channel::mpsc};
 use prometheus_endpoint::{register, Gauge, U64, F64, Registry, PrometheusError, Opts, GaugeVec};
+use prometheus_endpoint::prometheus::{core::{Collector, Desc}, proto::{LabelPair, MetricFamily}};
 use sc_telemetry::{telemetry, SUBSTRATE_INFO};
 use sp_runtime::traits::{NumberFor, Block, SaturatedConversion, UniqueSaturatedInto};
 use sp_transaction_pool::PoolStatus;
@@ -28,12 +30,35 @@ use sc_client_api::ClientInfo;
 use sc_network::config::Role;
 
 use sysinfo::{self, ProcessExt, SystemExt};
+use wasm_timer::Instant;
 
 #[cfg(all(any(unix, windows), not(target_os = "android"), not(target_os = "ios")))]
 use netstat2::{
 	TcpState, ProtocolSocketInfo, iterate_sockets_info, AddressFamilyFlags, ProtocolFlags,
 };
 
+/// Maximum number of samples waiting to be handled by a `MetricsExporter`. Samples produced while
+/// the queue is full are dropped.
+const EXPORTER_QUEUE_SIZE: usize = 16;
+
+/// Minimum number of connected peers for the node to be reported as healthy, unless configured.
+const DEFAULT_HEALTHY_PEER_THRESHOLD: usize = 1;
+
+/// Exports the values sampled by the service to a custom monitoring system (e.g. StatsD or
+/// OpenTelemetry), in addition to the Prometheus endpoint.
+pub trait MetricsExporter<T: Block>: Send {
+	/// Called with the latest client, transaction pool and network status every time the
+	/// service samples its metrics.
+	fn export(
+		&mut self,
+		info: &ClientInfo<T>,
+		txpool_status: &PoolStatus,
+		net_status: &NetworkStatus<T>,
+	);
+}
+
+type ExporterSample<T> = (ClientInfo<T>, PoolStatus, NetworkStatus<T>);
+
 struct PrometheusMetrics {
 	// system
 	#[cfg(all(any(unix, windows), not(target_os = "android"), not(target_os = "ios")))]
@@ -53,6 +78,7 @@ struct PrometheusMetrics {
 	block_height: GaugeVec<U64>,
 	number_leaves: Gauge<U64>,
 	ready_transactions_number: Gauge<U64>,
+	import_lag_blocks: Gauge<U64>,
 
 	// I/O
 	network_per_sec_bytes: GaugeVec<U64>,
@@ -135,6 +161,11 @@ impl PrometheusMetrics {
 				"ready_transactions_number", "Number of transactions in the ready queue",
 			)?, registry)?,
 
+			import_lag_blocks: register(Gauge::new(
+				"import_lag_blocks",
+				"Number of blocks between the best block received from the network and the best imported block",
+			)?, registry)?,
+
 			// I/ O
 			network_per_sec_bytes: register(GaugeVec::new(
 				Opts::new("network_per_sec_bytes", "Networking bytes per second"),
@@ -184,21 +215,29 @@ struct ProcessInfo {
 	open_fd: Option<FdCounter>,
 }
 
-pub struct MetricsService {
+pub struct MetricsService<T: Block> {
 	metrics: Option<PrometheusMetrics>,
+	exporter: Option<mpsc::Sender<ExporterSample<T>>>,
+	/// Whether the core metrics are sent to the telemetry.
+	telemetry_metrics: bool,
+	/// Minimum number of connected peers for the node to be reported as healthy.
+	healthy_peer_threshold: usize,
 	#[cfg(all(any(unix, windows), not(target_os = "android"), not(target_os = "ios")))]
 	system: sysinfo::System,
 	pid: Option<sysinfo::Pid>,
 }
 
 #[cfg(target_os = "linux")]
-impl MetricsService {
+impl<T: Block> MetricsService<T> {
 	fn inner_new(metrics: Option<PrometheusMetrics>) -> Self {
 		let process = procfs::process::Process::myself()
 			.expect("Procfs doesn't fail on unix. qed");
 
 		Self {
 			metrics,
+			exporter: None,
+			telemetry_metrics: false,
+			healthy_peer_threshold: DEFAULT_HEALTHY_PEER_THRESHOLD,
 			system: sysinfo::System::new(),
 			pid: Some(process.pid),
 		}
@@ -230,10 +269,13 @@ impl MetricsService {
 }
 
 #[cfg(all(any(unix, windows), not(target_os = "android"), not(target_os = "ios"), not(target_os = "linux")))]
-impl MetricsService {
+impl<T: Block> MetricsService<T> {
 	fn inner_new(metrics: Option<PrometheusMetrics>) -> Self {
 		Self {
 			metrics,
+			exporter: None,
+			telemetry_metrics: false,
+			healthy_peer_threshold: DEFAULT_HEALTHY_PEER_THRESHOLD,
 			system: sysinfo::System::new(),
 			pid: sysinfo::get_current_pid().ok(),
 		}
@@ -246,10 +288,13 @@ impl MetricsService {
 
 
 #[cfg(not(all(any(unix, windows), not(target_os = "android"), not(target_os = "ios"))))]
-impl MetricsService {
+impl<T: Block> MetricsService<T> {
 	fn inner_new(metrics: Option<PrometheusMetrics>) -> Self {
 		Self {
 			metrics,
+			exporter: None,
+			telemetry_metrics: false,
+			healthy_peer_threshold: DEFAULT_HEALTHY_PEER_THRESHOLD,
 			pid: None,
 		}
 	}
@@ -260,7 +305,8 @@ impl MetricsService {
 }
 
 
-impl MetricsService {
+impl<T: Block> MetricsService<T> {
+	/// Registers the metrics of the service in `registry`.
 	pub fn with_prometheus(registry: &Registry, config: &Configuration)
 		-> Result<Self, PrometheusError>
 	{
@@ -271,15 +317,55 @@ impl MetricsService {
 			Role::Authority { .. } => 4u64,
 		};
 
-		PrometheusMetrics::setup(registry, &config.network.node_name, &config.impl_version, role_bits).map(|p| {
-			Self::inner_new(Some(p))
-		})
+		let metrics = PrometheusMetrics::setup(
+			registry,
+			&config.network.node_name,
+			&config.impl_version,
+			role_bits,
+		)?;
+		Ok(Self::inner_new(Some(metrics)))
 	}
 
 	pub fn new() -> Self {
 		Self::inner_new(None)
 	}
 
+	/// Forward every sample to the given `exporter`.
+	///
+	/// The exporter is called from the returned future, which must be spawned. A slow exporter
+	/// never delays `tick`: samples produced while the exporter is lagging behind are dropped.
+	pub fn with_exporter(
+		mut self,
+		mut exporter: Box<dyn MetricsExporter<T>>,
+	) -> (Self, impl Future<Output = ()> + Send) {
+		let (tx, rx) = mpsc::channel::<ExporterSample<T>>(EXPORTER_QUEUE_SIZE);
+		self.exporter = Some(tx);
+
+		let future = rx.for_each(move |(info, txpool_status, net_status)| {
+			exporter.export(&info, &txpool_status, &net_status);
+			futures::future::ready(())
+		});
+
+		(self, future)
+	}
+
+	/// Also send the core metrics to the telemetry on every tick, as a `node.metrics` event.
+	///
+	/// Meant for setups where Prometheus can't be scraped but the telemetry can be pushed to.
+	pub fn with_telemetry_metrics(mut self) -> Self {
+		self.telemetry_metrics = true;
+		self
+	}
+
+	/// Report the node as healthy to the telemetry when it has at least `threshold` connected
+	/// peers. With `0`, the node is always reported as healthy, e.g. for single node chains.
+	///
+	/// The reported peer count itself is unaffected.
+	pub fn with_healthy_peer_threshold(mut self, threshold: usize) -> Self {
+		self.healthy_peer_threshold = threshold;
+		self
+	}
+
 	#[cfg(all(any(unix, windows), not(target_os = "android"), not(target_os = "ios")))]
 	fn process_info_for(&mut self, pid: &sysinfo::Pid) -> ProcessInfo {
 		let mut info = ProcessInfo::default();
@@ -325,7 +411,7 @@ impl MetricsService {
 		})
 	}
 
-	pub fn tick<T: Block>(
+	pub fn tick(
 		&mut self,
 		info: &ClientInfo<T>,
 		txpool_status: &PoolStatus,
@@ -335,17 +421,35 @@ impl MetricsService {
 		let best_number = info.chain.best_number.saturated_into::<u64>();
 		let best_hash = info.chain.best_hash;
 		let num_peers = net_status.num_connected_peers;
+		let healthy = num_peers >= self.healthy_peer_threshold;
 		let finalized_number: u64 = info.chain.finalized_number.saturated_into::<u64>();
 		let bandwidth_download = net_status.average_download_per_sec;
 		let bandwidth_upload = net_status.average_upload_per_sec;
 		let best_seen_block = net_status.best_seen_block
 			.map(|num: NumberFor<T>| num.unique_saturated_into() as u64);
+		// Blocks that were imported in the meantime, or that failed to import, don't count.
+		let import_lag = net_status.best_queued_block
+			.map(|num: NumberFor<T>| (num.unique_saturated_into() as u64).saturating_sub(best_number))
+			.unwrap_or(0);
 		let process_info = self.process_info();
 
+		if let Some(exporter) = self.exporter.as_mut() {
+			let sample = (info.clone(), txpool_status.clone(), net_status.clone());
+			if let Err(e) = exporter.try_send(sample) {
+				if e.is_full() {
+					log::debug!(target: "metrics", "Metrics exporter is lagging behind, dropping sample");
+				} else {
+					// The exporter is gone, no need to keep sampling for it.
+					self.exporter = None;
+				}
+			}
+		}
+
 		telemetry!(
 			SUBSTRATE_INFO;
 			"system.interval";
 			"peers" => num_peers,
+			"healthy" => healthy,
 			"height" => best_number,
 			"best" => ?best_hash,
 			"txcount" => txpool_status.ready,
@@ -369,6 +473,20 @@ impl MetricsService {
 				.unwrap_or(0),
 		);
 
+		if self.telemetry_metrics {
+			telemetry!(
+				SUBSTRATE_INFO;
+				"node.metrics";
+				"best_height" => best_number,
+				"finalized_height" => finalized_number,
+				"finality_lag" => best_number.saturating_sub(finalized_number),
+				"peers" => num_peers,
+				"healthy" => healthy,
+				"ready_transactions" => txpool_status.ready,
+				"future_transactions" => txpool_status.future,
+			);
+		}
+
 		if let Some(metrics) = self.metrics.as_ref() {
 			metrics.cpu_usage_percentage.set(process_info.cpu_usage as f64);
 			// `sysinfo::Process::memory` returns memory usage in KiB and not bytes.
@@ -403,6 +521,7 @@ impl MetricsService {
 			}
 
 			metrics.ready_transactions_number.set(txpool_status.ready as u64);
+			metrics.import_lag_blocks.set(import_lag);
 
 			if let Some(best_seen_block) = best_seen_block {
 				metrics.block_height.with_label_values(&["sync_target"]).set(best_seen_block);
@@ -442,3 +561,367 @@ impl MetricsService {
 		}
 	}
 }
+
+/// Returns the name of the first of the static `labels` already used by a metric of `family`.
+fn conflicting_label<'a>(family: &MetricFamily, labels: &'a [LabelPair]) -> Option<&'a str> {
+	labels.iter()
+		.map(|label| label.get_name())
+		.find(|name| family.get_metric().iter()
+			.flat_map(|metric| metric.get_label())
+			.any(|label| label.get_name() == *name)
+		)
+}
+
+/// Exposes the metrics of a registry with the static labels added to each of them.
+struct StaticLabels {
+	registry: Registry,
+	labels: Vec<LabelPair>,
+}
+
+impl Collector for StaticLabels {
+	fn desc(&self) -> Vec<&Desc> {
+		Vec::new()
+	}
+
+	fn collect(&self) -> Vec<MetricFamily> {
+		let mut families = self.registry.gather();
+		// Metrics registered after the endpoint started haven't been checked, a family using one
+		// of the static labels itself is left out of the scrape rather than exposed with a label
+		// of ambiguous meaning.
+		families.retain(|family| match conflicting_label(family, &self.labels) {
+			Some(label) => {
+				log::error!(
+					target: "metrics",
+					"Static label `{}` is already used by metric `{}`, it won't be exposed",
+					label,
+					family.get_name(),
+				);
+				false
+			},
+			None => true,
+		});
+		for metric in families.iter_mut().flat_map(|family| family.mut_metric().iter_mut()) {
+			metric.mut_label().extend(self.labels.iter().cloned());
+		}
+		families
+	}
+}
+
+/// Returns a registry exposing all the metrics of `registry`, with the static `labels` added to
+/// each of them.
+///
+/// Meant to be called once all the metrics of the service are registered: fails if one of the
+/// `labels` isn't a valid label name, or is already used by a metric of `registry`. Metrics
+/// registered later that use one of the `labels` are left out of the scrapes.
+pub(crate) fn with_static_labels(
+	registry: Registry,
+	labels: &HashMap<String, String>,
+) -> Result<Registry, PrometheusError> {
+	if labels.is_empty() {
+		return Ok(registry);
+	}
+	Desc::new("static_labels".into(), "Static labels".into(), Vec::new(), labels.clone())?;
+
+	let mut labels = labels.iter()
+		.map(|(name, value)| {
+			let mut label = LabelPair::default();
+			label.set_name(name.clone());
+			label.set_value(value.clone());
+			label
+		})
+		.collect::<Vec<_>>();
+	labels.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+
+	for family in registry.gather() {
+		if let Some(label) = conflicting_label(&family, &labels) {
+			return Err(PrometheusError::Msg(format!(
+				"Static label `{}` is already used by metric `{}`",
+				label,
+				family.get_name(),
+			)));
+		}
+	}
+
+	let labeled = Registry::new();
+	labeled.register(Box::new(StaticLabels { registry, labels }))
+		.expect("The collector has no descriptor, it can't conflict with another one; qed");
+	Ok(labeled)
+}
+
+/// Waits for the first network status with at least one connected peer and records how long it
+/// took since `started` in the `time_to_first_peer_seconds` gauge.
+///
+/// The gauge is only registered once a peer connected, so a node that never finds one (e.g. a
+/// bootnode without any configured peers) doesn't report a misleading value.
+pub(crate) async fn record_time_to_first_peer<T: Block>(
+	registry: Registry,
+	started: Instant,
+	mut statuses: impl Stream<Item = NetworkStatus<T>> + Unpin,
+) {
+	while let Some(status) = statuses.next().await {
+		if status.num_connected_peers == 0 {
+			continue;
+		}
+
+		let elapsed = started.elapsed().as_secs_f64();
+		match Gauge::<F64>::new(
+			"time_to_first_peer_seconds",
+			"Number of seconds between the start of the network and the first connected peer",
+		).and_then(|gauge| register(gauge, &registry)) {
+			Ok(gauge) => gauge.set(elapsed),
+			Err(e) => log::warn!(target: "metrics", "Failed to register time to first peer metric: {}", e),
+		}
+		return;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{collections::HashMap, sync::{Arc, atomic::{AtomicUsize, Ordering}}};
+	use futures::executor::block_on;
+	use sc_network::SyncState;
+	use sp_blockchain::Info;
+	use substrate_test_runtime_client::runtime::Block;
+
+	struct RecordingExporter(Arc<AtomicUsize>);
+
+	impl MetricsExporter<Block> for RecordingExporter {
+		fn export(&mut self, info: &ClientInfo<Block>, _: &PoolStatus, net_status: &NetworkStatus<Block>) {
+			assert_eq!(info.chain.best_number, 10);
+			assert_eq!(net_status.num_connected_peers, 3);
+			self.0.fetch_add(1, Ordering::SeqCst);
+		}
+	}
+
+	fn client_info() -> ClientInfo<Block> {
+		ClientInfo {
+			chain: Info {
+				best_hash: Default::default(),
+				best_number: 10,
+				genesis_hash: Default::default(),
+				finalized_hash: Default::default(),
+				finalized_number: 8,
+				number_leaves: 1,
+			},
+			usage: None,
+		}
+	}
+
+	fn pool_status() -> PoolStatus {
+		PoolStatus { ready: 0, ready_bytes: 0, future: 0, future_bytes: 0 }
+	}
+
+	fn net_status() -> NetworkStatus<Block> {
+		NetworkStatus {
+			sync_state: SyncState::Idle,
+			best_seen_block: None,
+			best_queued_block: None,
+			num_sync_peers: 3,
+			num_connected_peers: 3,
+			num_active_peers: 3,
+			average_download_per_sec: 0,
+			average_upload_per_sec: 0,
+		}
+	}
+
+	#[test]
+	fn exporter_receives_every_tick() {
+		let exported = Arc::new(AtomicUsize::new(0));
+		let (mut metrics, exporter) = MetricsService::<Block>::new()
+			.with_exporter(Box::new(RecordingExporter(exported.clone())));
+
+		for _ in 0..5 {
+			metrics.tick(&client_info(), &pool_status(), &net_status());
+		}
+		drop(metrics);
+		block_on(exporter);
+
+		assert_eq!(exported.load(Ordering::SeqCst), 5);
+	}
+
+	#[test]
+	fn lagging_exporter_does_not_block_tick() {
+		let exported = Arc::new(AtomicUsize::new(0));
+		let (mut metrics, exporter) = MetricsService::<Block>::new()
+			.with_exporter(Box::new(RecordingExporter(exported.clone())));
+
+		// The exporter isn't polled at all while ticking, so the queue overflows.
+		for _ in 0..EXPORTER_QUEUE_SIZE * 4 {
+			metrics.tick(&client_info(), &pool_status(), &net_status());
+		}
+		drop(metrics);
+		block_on(exporter);
+
+		let exported = exported.load(Ordering::SeqCst);
+		assert!(exported > 0);
+		assert!(exported <= EXPORTER_QUEUE_SIZE + 1);
+	}
+
+	/// Telemetry events, as their message and fields.
+	type Events = Arc<std::sync::Mutex<Vec<(String, HashMap<String, String>)>>>;
+
+	struct RecordingDrain(Events);
+
+	impl slog::Drain for RecordingDrain {
+		type Ok = ();
+		type Err = slog::Never;
+
+		fn log(&self, record: &slog::Record, _: &slog::OwnedKVList) -> Result<(), slog::Never> {
+			let mut fields = RecordingSerializer(HashMap::new());
+			let _ = slog::KV::serialize(&record.kv(), record, &mut fields);
+			self.0.lock().unwrap().push((record.msg().to_string(), fields.0));
+			Ok(())
+		}
+	}
+
+	struct RecordingSerializer(HashMap<String, String>);
+
+	impl slog::Serializer for RecordingSerializer {
+		fn emit_arguments(&mut self, key: slog::Key, val: &std::fmt::Arguments) -> slog::Result {
+			self.0.insert(key.to_string(), val.to_string());
+			Ok(())
+		}
+	}
+
+	fn tick_events(metrics: &mut MetricsService<Block>) -> Vec<(String, HashMap<String, String>)> {
+		let events = Events::default();
+		let logger = slog::Logger::root(RecordingDrain(events.clone()), slog::o!());
+		let txpool_status = PoolStatus { ready: 4, future: 1, ..pool_status() };
+		slog_scope::scope(&logger, || metrics.tick(&client_info(), &txpool_status, &net_status()));
+		let events = events.lock().unwrap().clone();
+		events
+	}
+
+	#[test]
+	fn metrics_are_sent_to_the_telemetry_when_enabled() {
+		let is_metrics_event = |(msg, _): &(String, _)| msg == "node.metrics";
+		assert!(!tick_events(&mut MetricsService::new()).iter().any(is_metrics_event));
+
+		let events = tick_events(&mut MetricsService::new().with_telemetry_metrics());
+		let (_, fields) = events.into_iter().find(is_metrics_event).expect("metrics are sent");
+		let expected = vec![
+			("best_height", "10"),
+			("finalized_height", "8"),
+			("finality_lag", "2"),
+			("peers", "3"),
+			("ready_transactions", "4"),
+			("future_transactions", "1"),
+		];
+		for (key, value) in expected {
+			assert_eq!(fields.get(key).map(String::as_str), Some(value), "{}", key);
+		}
+	}
+
+	#[test]
+	fn health_respects_the_peer_threshold() {
+		let health = |metrics: MetricsService<Block>| {
+			let events = tick_events(&mut metrics.with_telemetry_metrics());
+			let field = |msg: &str, key: &str| events.iter()
+				.find(|(m, _)| m == msg)
+				.and_then(|(_, fields)| fields.get(key).cloned());
+			// The peer count is reported as is, whatever the threshold.
+			assert_eq!(field("system.interval", "peers").as_deref(), Some("3"));
+			assert_eq!(field("system.interval", "healthy"), field("node.metrics", "healthy"));
+			field("system.interval", "healthy")
+		};
+
+		assert_eq!(health(MetricsService::new()).as_deref(), Some("true"));
+		assert_eq!(health(MetricsService::new().with_healthy_peer_threshold(3)).as_deref(), Some("true"));
+		assert_eq!(health(MetricsService::new().with_healthy_peer_threshold(4)).as_deref(), Some("false"));
+		assert_eq!(health(MetricsService::new().with_healthy_peer_threshold(0)).as_deref(), Some("true"));
+	}
+
+	#[test]
+	fn import_lag_follows_the_blocks_waiting_in_the_import_queue() {
+		let registry = Registry::new();
+		let prometheus = PrometheusMetrics::setup(&registry, "test", "0.1.0", 1).unwrap();
+		let mut metrics = MetricsService::<Block>::inner_new(Some(prometheus));
+		let mut import_lag = |best_queued_block| {
+			let net_status = NetworkStatus { best_queued_block, ..net_status() };
+			metrics.tick(&client_info(), &pool_status(), &net_status);
+			metrics.metrics.as_ref().unwrap().import_lag_blocks.get()
+		};
+
+		// Blocks are received faster than the best block (#10) is imported.
+		assert_eq!(import_lag(Some(12)), 2);
+		assert_eq!(import_lag(Some(15)), 5);
+		assert_eq!(import_lag(Some(20)), 10);
+		// The import queue is drained.
+		assert_eq!(import_lag(None), 0);
+		// Only blocks that are already imported are still queued, e.g. re-imports.
+		assert_eq!(import_lag(Some(9)), 0);
+	}
+
+	fn with_peers(num_connected_peers: usize) -> NetworkStatus<Block> {
+		NetworkStatus { num_connected_peers, ..net_status() }
+	}
+
+	fn time_to_first_peer_samples(registry: &Registry) -> usize {
+		registry.gather().iter()
+			.filter(|family| family.get_name() == "time_to_first_peer_seconds")
+			.map(|family| family.get_metric().len())
+			.sum()
+	}
+
+	#[test]
+	fn time_to_first_peer_is_recorded_once() {
+		let registry = Registry::new();
+		let mut statuses = futures::stream::iter(vec![
+			with_peers(0), with_peers(0), with_peers(1), with_peers(2),
+		]);
+
+		block_on(record_time_to_first_peer(registry.clone(), Instant::now(), &mut statuses));
+
+		assert_eq!(time_to_first_peer_samples(&registry), 1);
+		// Later samples are not observed anymore.
+		assert_eq!(block_on(statuses.next()).map(|s| s.num_connected_peers), Some(2));
+	}
+
+	#[test]
+	fn static_labels_are_added_to_every_metric() {
+		use prometheus_endpoint::prometheus::{Encoder, TextEncoder};
+
+		let registry = Registry::new();
+		PrometheusMetrics::setup(&registry, "test", "0.1.0", 1).unwrap();
+		let roles = register(GaugeVec::<U64>::new(
+			Opts::new("peers", "Peers by role"), &["role"],
+		).unwrap(), &registry).unwrap();
+		roles.with_label_values(&["full"]).set(2);
+
+		let labels = |labels: &[(&str, &str)]| labels.iter()
+			.map(|(name, value)| (name.to_string(), value.to_string()))
+			.collect::<HashMap<_, _>>();
+		assert!(with_static_labels(registry.clone(), &labels(&[("role", "validator")])).is_err());
+		assert!(with_static_labels(registry.clone(), &labels(&[("not a label", "test")])).is_err());
+
+		let static_labels = labels(&[("chain", "dev"), ("instance", "node-1")]);
+		let labeled = with_static_labels(registry.clone(), &static_labels).unwrap();
+
+		// Registered once the endpoint started, this one can't carry the static labels.
+		register(GaugeVec::<U64>::new(
+			Opts::new("chains", "Chains by name"), &["chain"],
+		).unwrap(), &registry).unwrap().with_label_values(&["dev"]).set(1);
+
+		let mut scraped = Vec::new();
+		TextEncoder::new().encode(&labeled.gather(), &mut scraped).unwrap();
+		let scraped = String::from_utf8(scraped).unwrap();
+		let samples = scraped.lines().filter(|line| !line.starts_with('#')).collect::<Vec<_>>();
+		assert!(samples.iter().any(|line| line.starts_with("node_roles{")));
+		assert!(samples.iter().any(|line| line.starts_with("peers{role=\"full\",")));
+		assert!(!scraped.contains("chains"));
+		for sample in samples {
+			assert!(sample.contains("chain=\"dev\",instance=\"node-1\""), "{}", sample);
+		}
+	}
+
+	#[test]
+	fn time_to_first_peer_is_unset_without_peers() {
+		let registry = Registry::new();
+		let statuses = futures::stream::iter(vec![with_peers(0), with_peers(0)]);
+
+		block_on(record_time_to_first_peer(registry.clone(), Instant::now(), statuses));
+
+		assert_eq!(time_to_first_peer_samples(&registry), 0);
+	}
+}
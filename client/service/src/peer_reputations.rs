@@ -0,0 +1,170 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Persistence of the peer reputations, so that misbehaving peers stay banned across restarts.
+
+use std::{collections::HashMap, fs, io, path::Path, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use log::{debug, warn};
+use sc_network::PeerId;
+use serde::{Deserialize, Serialize};
+
+/// Time between two writes of the peer reputations to disk.
+pub(crate) const PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize)]
+struct PersistedReputations {
+	/// Number of seconds since the UNIX epoch when the reputations were written.
+	saved_at: u64,
+	/// Reputation of the peers, by base58-encoded `PeerId`.
+	reputations: HashMap<String, i32>,
+}
+
+/// Writes `reputations` to `path`, replacing the ones written before.
+pub(crate) fn save(path: &Path, reputations: &[(PeerId, i32)]) -> io::Result<()> {
+	let persisted = PersistedReputations {
+		saved_at: unix_time(SystemTime::now()),
+		reputations: reputations.iter()
+			.map(|(peer_id, reputation)| (peer_id.to_base58(), *reputation))
+			.collect(),
+	};
+
+	// Written to another file first, so that a crash never leaves a truncated file behind.
+	let tmp_path = path.with_extension("tmp");
+	fs::write(&tmp_path, serde_json::to_vec(&persisted)?)?;
+	fs::rename(&tmp_path, path)
+}
+
+/// Reads the reputations written to `path` by [`save`].
+///
+/// Nothing is restored if the file doesn't exist or can't be read, or if the reputations were
+/// written more than `ttl` ago.
+pub(crate) fn load(path: &Path, ttl: Duration) -> Vec<(PeerId, i32)> {
+	let persisted = match fs::read(path) {
+		Ok(persisted) => persisted,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Vec::new(),
+		Err(e) => {
+			warn!("Failed to read the peer reputations from {}: {}", path.display(), e);
+			return Vec::new();
+		},
+	};
+	let persisted: PersistedReputations = match serde_json::from_slice(&persisted) {
+		Ok(persisted) => persisted,
+		Err(e) => {
+			warn!("Failed to decode the peer reputations in {}: {}", path.display(), e);
+			return Vec::new();
+		},
+	};
+
+	let age = unix_time(SystemTime::now()).saturating_sub(persisted.saved_at);
+	if age > ttl.as_secs() {
+		debug!("Discarding the peer reputations written {} seconds ago", age);
+		return Vec::new();
+	}
+
+	persisted.reputations.into_iter()
+		.filter_map(|(peer_id, reputation)| match peer_id.parse() {
+			Ok(peer_id) => Some((peer_id, reputation)),
+			Err(_) => {
+				warn!("Ignoring the reputation of invalid peer {} in {}", peer_id, path.display());
+				None
+			},
+		})
+		.collect()
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{pin::Pin, task::Poll};
+	use futures::{executor::block_on, future::poll_fn, Stream};
+	use sc_peerset::{Peerset, PeersetConfig, ReputationChange};
+
+	const TTL: Duration = Duration::from_secs(3600);
+
+	fn new_peerset() -> Peerset {
+		let (peerset, _) = Peerset::from_config(PeersetConfig {
+			in_peers: 25,
+			out_peers: 25,
+			bootnodes: vec![],
+			reserved_only: false,
+			priority_groups: vec![],
+		});
+		peerset
+	}
+
+	/// Polls the peerset for the reports to be processed.
+	fn process_reports(peerset: &mut Peerset) {
+		block_on(poll_fn(|cx| {
+			while let Poll::Ready(_) = Stream::poll_next(Pin::new(&mut *peerset), cx) {}
+			Poll::Ready(())
+		}));
+	}
+
+	#[test]
+	fn penalty_is_retained_across_restarts() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("reputations.json");
+		let bad_peer = PeerId::random();
+
+		let mut peerset = new_peerset();
+		peerset.report_peer(bad_peer.clone(), ReputationChange::new_fatal("Misbehaved"));
+		process_reports(&mut peerset);
+		save(&path, &peerset.reputations()).unwrap();
+		drop(peerset);
+
+		// Restart, restoring the reputations as the service does.
+		let mut peerset = new_peerset();
+		for (peer_id, reputation) in load(&path, TTL) {
+			peerset.report_peer(peer_id, ReputationChange::new(reputation, "Restored reputation"));
+		}
+		process_reports(&mut peerset);
+
+		let reputations = peerset.reputations();
+		assert_eq!(reputations.len(), 1);
+		assert_eq!(reputations[0].0, bad_peer);
+		// Reputations move towards zero every second, the peer is still banned though.
+		assert!(reputations[0].1 < i32::min_value() / 2);
+	}
+
+	#[test]
+	fn stale_reputations_are_discarded() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("reputations.json");
+		let peer_id = PeerId::random();
+
+		let persisted = |saved_at| serde_json::to_vec(&PersistedReputations {
+			saved_at,
+			reputations: vec![(peer_id.to_base58(), -1000)].into_iter().collect(),
+		}).unwrap();
+
+		let now = unix_time(SystemTime::now());
+		fs::write(&path, persisted(now - 60)).unwrap();
+		assert_eq!(load(&path, TTL), vec![(peer_id.clone(), -1000)]);
+
+		fs::write(&path, persisted(now - TTL.as_secs() - 60)).unwrap();
+		assert!(load(&path, TTL).is_empty());
+
+		// A missing file is the same as no reputations at all.
+		assert!(load(&dir.path().join("missing.json"), TTL).is_empty());
+	}
+}
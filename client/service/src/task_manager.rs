@@ -13,9 +13,10 @@
 
 //! Substrate service tasks management module.
 
-use std::{panic, result::Result, pin::Pin};
+use std::{collections::HashMap, panic, result::Result, pin::Pin, sync::Arc, time::Duration};
 use exit_future::Signal;
-use log::debug;
+use log::{debug, error, warn};
+use parking_lot::Mutex;
 use futures::{
 	Future, FutureExt, StreamExt,
 	future::{select, Either, BoxFuture},
@@ -32,14 +33,20 @@ use sc_client_api::CloneableSpawn;
 use sp_utils::mpsc::{TracingUnboundedSender, TracingUnboundedReceiver, tracing_unbounded};
 use crate::{config::{TaskExecutor, TaskType}, Error};
 
+mod poll_time;
 mod prometheus_future;
 
+use poll_time::PollTimes;
+
 /// An handle for spawning tasks in the service.
 #[derive(Clone)]
 pub struct SpawnTaskHandle {
 	on_exit: exit_future::Exit,
 	executor: TaskExecutor,
 	metrics: Option<Metrics>,
+	poll_times: Option<PollTimes>,
+	running_tasks: RunningTasks,
+	watchdog_timeout: Option<Duration>,
 }
 
 impl SpawnTaskHandle {
@@ -51,6 +58,9 @@ impl SpawnTaskHandle {
 	///
 	/// In other words, it would be a bad idea for someone to do for example
 	/// `spawn(format!("{:?}", some_public_key))`.
+	///
+	/// If the task panics, the panic is logged along with the name of the task, which is then
+	/// considered failed. The other tasks of the service keep running.
 	pub fn spawn(&self, name: &'static str, task: impl Future<Output = ()> + Send + 'static) {
 		self.spawn_inner(name, task, TaskType::Async)
 	}
@@ -60,6 +70,34 @@ impl SpawnTaskHandle {
 		self.spawn_inner(name, task, TaskType::Blocking)
 	}
 
+	/// Spawns the task built by `make_task`, restarting it if the watchdog is enabled and the task
+	/// stops pinging its [`Heartbeat`] for the watchdog timeout.
+	///
+	/// A restarted task is dropped and built again by calling `make_task`, so this is only for
+	/// tasks that can be safely restarted from scratch. Only tasks that are stuck waiting are
+	/// noticed, not tasks blocking the thread they run on. The task isn't restarted once it
+	/// completes. See also `spawn`.
+	pub fn spawn_watched<F, Fut>(&self, name: &'static str, mut make_task: F)
+	where
+		F: FnMut(Heartbeat) -> Fut + Send + 'static,
+		Fut: Future<Output = ()> + Send + 'static,
+	{
+		let timeout = match self.watchdog_timeout {
+			Some(timeout) => timeout,
+			None => return self.spawn(name, make_task(Heartbeat::new())),
+		};
+
+		self.spawn(name, async move {
+			loop {
+				let heartbeat = Heartbeat::new();
+				if !is_stalled(make_task(heartbeat.clone()), &heartbeat, timeout).await {
+					break
+				}
+				warn!("Task `{}` didn't heartbeat for {:?}, restarting it", name, timeout);
+			}
+		})
+	}
+
 	/// Helper function that implements the spawning logic. See `spawn` and `spawn_blocking`.
 	fn spawn_inner(
 		&self,
@@ -69,6 +107,11 @@ impl SpawnTaskHandle {
 	) {
 		let on_exit = self.on_exit.clone();
 		let metrics = self.metrics.clone();
+		let running = self.running_tasks.start(name);
+		let task = match &self.poll_times {
+			Some(poll_times) => Either::Left(poll_times.account(name, task)),
+			None => Either::Right(task),
+		};
 
 		// Note that we increase the started counter here and not within the future. This way,
 		// we could properly visualize on Prometheus situations where the spawning doesn't work.
@@ -79,35 +122,33 @@ impl SpawnTaskHandle {
 		}
 
 		let future = async move {
-			if let Some(metrics) = metrics {
-				// Add some wrappers around `task`.
-				let task = {
+			// Dropped with the future, whether it completed or not.
+			let _running = running;
+			let task = match &metrics {
+				Some(metrics) => {
 					let poll_duration = metrics.poll_duration.with_label_values(&[name]);
 					let poll_start = metrics.poll_start.with_label_values(&[name]);
-					let inner = prometheus_future::with_poll_durations(poll_duration, poll_start, task);
-					// The logic of `AssertUnwindSafe` here is ok considering that we throw
-					// away the `Future` after it has panicked.
-					panic::AssertUnwindSafe(inner).catch_unwind()
-				};
-				futures::pin_mut!(task);
-
-				match select(on_exit, task).await {
-					Either::Right((Err(payload), _)) => {
-						metrics.tasks_ended.with_label_values(&[name, "panic"]).inc();
-						panic::resume_unwind(payload)
-					}
-					Either::Right((Ok(()), _)) => {
-						metrics.tasks_ended.with_label_values(&[name, "finished"]).inc();
-					}
-					Either::Left(((), _)) => {
-						// The `on_exit` has triggered.
-						metrics.tasks_ended.with_label_values(&[name, "interrupted"]).inc();
-					}
+					Either::Left(prometheus_future::with_poll_durations(poll_duration, poll_start, task))
 				}
-
-			} else {
-				futures::pin_mut!(task);
-				let _ = select(on_exit, task).await;
+				None => Either::Right(task),
+			};
+			// A panicking task must not take down the thread of the executor it runs on, and with
+			// it the other tasks of that thread. The logic of `AssertUnwindSafe` here is ok
+			// considering that we throw away the `Future` after it has panicked.
+			let task = panic::AssertUnwindSafe(task).catch_unwind();
+			futures::pin_mut!(task);
+
+			let outcome = match select(on_exit, task).await {
+				Either::Right((Err(payload), _)) => {
+					error!("Task `{}` panicked: {}", name, panic_message(&*payload));
+					"panic"
+				}
+				Either::Right((Ok(()), _)) => "finished",
+				// The `on_exit` has triggered.
+				Either::Left(((), _)) => "interrupted",
+			};
+			if let Some(metrics) = metrics {
+				metrics.tasks_ended.with_label_values(&[name, outcome]).inc();
 			}
 		};
 
@@ -148,6 +189,105 @@ impl futures01::future::Executor<Boxed01Future01> for SpawnTaskHandle {
 	}
 }
 
+/// Liveness signal of a task monitored by the watchdog of the task manager.
+///
+/// See [`SpawnTaskHandle::spawn_watched`].
+#[derive(Clone)]
+pub struct Heartbeat(Arc<Mutex<wasm_timer::Instant>>);
+
+impl Heartbeat {
+	fn new() -> Self {
+		Heartbeat(Arc::new(Mutex::new(wasm_timer::Instant::now())))
+	}
+
+	/// Notes that the task is alive. It must be called more often than the watchdog timeout.
+	pub fn ping(&self) {
+		*self.0.lock() = wasm_timer::Instant::now();
+	}
+
+	fn elapsed(&self) -> Duration {
+		self.0.lock().elapsed()
+	}
+}
+
+/// Runs `task` until it completes, returning `false`, or until `heartbeat` hasn't been pinged for
+/// `timeout`, returning `true`.
+async fn is_stalled(
+	task: impl Future<Output = ()>,
+	heartbeat: &Heartbeat,
+	timeout: Duration,
+) -> bool {
+	futures::pin_mut!(task);
+	loop {
+		let elapsed = heartbeat.elapsed();
+		if elapsed >= timeout {
+			return true;
+		}
+		match select(task.as_mut(), futures_timer::Delay::new(timeout - elapsed)).await {
+			Either::Left(((), _)) => return false,
+			Either::Right(((), _)) => {},
+		}
+	}
+}
+
+/// Returns the message a panic was raised with, if it is a string.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+	payload.downcast_ref::<&'static str>().copied()
+		.or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+		.unwrap_or("Box<Any>")
+}
+
+/// Number of spawned tasks that haven't stopped yet, by task name.
+#[derive(Clone, Default)]
+struct RunningTasks(Arc<Mutex<HashMap<&'static str, usize>>>);
+
+impl RunningTasks {
+	/// Counts a task named `name` as running until the returned guard is dropped.
+	fn start(&self, name: &'static str) -> RunningTask {
+		*self.0.lock().entry(name).or_default() += 1;
+		RunningTask { name, tasks: self.clone() }
+	}
+
+	/// Returns the names of the tasks that are still running, sorted.
+	fn names(&self) -> Vec<&'static str> {
+		let mut names = self.0.lock().keys().copied().collect::<Vec<_>>();
+		names.sort();
+		names
+	}
+}
+
+struct RunningTask {
+	name: &'static str,
+	tasks: RunningTasks,
+}
+
+impl Drop for RunningTask {
+	fn drop(&mut self) {
+		let mut tasks = self.tasks.0.lock();
+		if let Some(count) = tasks.get_mut(self.name) {
+			*count -= 1;
+			if *count == 0 {
+				tasks.remove(self.name);
+			}
+		}
+	}
+}
+
+/// How often the conditions of a clean shutdown are checked.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Waits for `condition` to be true, returning `false` if it still isn't after `timeout`.
+pub(crate) async fn wait_until(mut condition: impl FnMut() -> bool, timeout: Duration) -> bool {
+	let start = wasm_timer::Instant::now();
+	while !condition() {
+		if start.elapsed() >= timeout {
+			return false;
+		}
+		futures_timer::Delay::new(SHUTDOWN_POLL_INTERVAL).await;
+	}
+	true
+}
+
 /// A wrapper over `SpawnTaskHandle` that will notify a receiver whenever any
 /// task spawned through it fails. The service should be on the receiver side
 /// and will shut itself down whenever it receives any message, i.e. an
@@ -187,6 +327,29 @@ impl SpawnEssentialTaskHandle {
 		self.spawn_inner(name, task, TaskType::Blocking)
 	}
 
+	/// Spawns the task built by `make_task`, shutting down the service if the watchdog is enabled
+	/// and the task stops pinging its [`Heartbeat`] for the watchdog timeout.
+	///
+	/// Unlike [`SpawnTaskHandle::spawn_watched`], the task isn't restarted, since essential tasks
+	/// can't be assumed to be safely restartable.
+	pub fn spawn_watched<Fut>(&self, name: &'static str, make_task: impl FnOnce(Heartbeat) -> Fut)
+	where
+		Fut: Future<Output = ()> + Send + 'static,
+	{
+		let heartbeat = Heartbeat::new();
+		let task = make_task(heartbeat.clone());
+		let timeout = match self.inner.watchdog_timeout {
+			Some(timeout) => timeout,
+			None => return self.spawn(name, task),
+		};
+
+		self.spawn(name, async move {
+			if is_stalled(task, &heartbeat, timeout).await {
+				error!("Essential task `{}` didn't heartbeat for {:?}", name, timeout);
+			}
+		})
+	}
+
 	fn spawn_inner(
 		&self,
 		name: &'static str,
@@ -196,8 +359,11 @@ impl SpawnEssentialTaskHandle {
 		let mut essential_failed = self.essential_failed_tx.clone();
 		let essential_task = std::panic::AssertUnwindSafe(task)
 			.catch_unwind()
-			.map(move |_| {
-				log::error!("Essential task `{}` failed. Shutting down service.", name);
+			.map(move |result| {
+				if let Err(payload) = result {
+					error!("Essential task `{}` panicked: {}", name, panic_message(&*payload));
+				}
+				error!("Essential task `{}` failed. Shutting down service.", name);
 				let _ = essential_failed.send(());
 			});
 
@@ -216,6 +382,8 @@ pub struct TaskManager {
 	executor: TaskExecutor,
 	/// Prometheus metric where to report the polling times.
 	metrics: Option<Metrics>,
+	/// Time spent polling the tasks, if accounting is enabled.
+	poll_times: Option<PollTimes>,
 	/// Send a signal when a spawned essential task has concluded. The next time
 	/// the service future is polled it should complete with an error.
 	essential_failed_tx: TracingUnboundedSender<()>,
@@ -223,14 +391,22 @@ pub struct TaskManager {
 	essential_failed_rx: TracingUnboundedReceiver<()>,
 	/// Things to keep alive until the task manager is dropped.
 	keep_alive: Box<dyn std::any::Any + Send + Sync>,
+	/// Tasks spawned through the handles of the task manager that haven't stopped yet.
+	running_tasks: RunningTasks,
+	/// How long a watched task may go without heartbeating, if the watchdog is enabled.
+	watchdog_timeout: Option<Duration>,
 }
 
 impl TaskManager {
  	/// If a Prometheus registry is passed, it will be used to report statistics about the
- 	/// service tasks.
+ 	/// service tasks. If `poll_time_accounting` is `true`, the time spent polling each task is
+ 	/// accumulated and can be retrieved with [`TaskManager::task_metrics`]. If a watchdog timeout
+ 	/// is passed, the tasks spawned with `spawn_watched` must heartbeat at least that often.
 	pub(super) fn new(
 		executor: TaskExecutor,
-		prometheus_registry: Option<&Registry>
+		prometheus_registry: Option<&Registry>,
+		poll_time_accounting: bool,
+		watchdog_timeout: Option<Duration>,
 	) -> Result<Self, PrometheusError> {
 		let (signal, on_exit) = exit_future::signal();
 		// A side-channel for essential tasks to communicate shutdown.
@@ -243,9 +419,12 @@ impl TaskManager {
 			signal: Some(signal),
 			executor,
 			metrics,
+			poll_times: if poll_time_accounting { Some(Default::default()) } else { None },
 			essential_failed_tx,
 			essential_failed_rx,
 			keep_alive: Box::new(()),
+			running_tasks: Default::default(),
+			watchdog_timeout,
 		})
 	}
 
@@ -256,6 +435,23 @@ impl TaskManager {
 			on_exit: self.on_exit.clone(),
 			executor: self.executor.clone(),
 			metrics: self.metrics.clone(),
+			poll_times: self.poll_times.clone(),
+			running_tasks: self.running_tasks.clone(),
+			watchdog_timeout: self.watchdog_timeout,
+		}
+	}
+
+	/// Get a handle for spawning tasks on `executor` instead of the executor of the service.
+	///
+	/// The tasks are still interrupted when the service shuts down.
+	pub fn spawn_handle_with_executor(&self, executor: TaskExecutor) -> SpawnTaskHandle {
+		SpawnTaskHandle {
+			on_exit: self.on_exit.clone(),
+			executor,
+			metrics: self.metrics.clone(),
+			poll_times: self.poll_times.clone(),
+			running_tasks: self.running_tasks.clone(),
+			watchdog_timeout: self.watchdog_timeout,
 		}
 	}
 
@@ -273,6 +469,40 @@ impl TaskManager {
 		})
 	}
 
+	/// Returns the time spent polling the tasks spawned so far, accumulated by task name, the
+	/// longest first.
+	///
+	/// Empty unless poll time accounting has been enabled in the configuration.
+	pub fn task_metrics(&self) -> Vec<(String, Duration)> {
+		self.poll_times.as_ref().map(PollTimes::snapshot).unwrap_or_default()
+	}
+
+	/// Returns a future that resolves once the task manager has been terminated, or dropped, and
+	/// all the tasks spawned through its handles have stopped.
+	///
+	/// Tasks that are still running `timeout` after the termination, e.g. a blocking task that
+	/// never yields, are logged and their names returned instead of waiting for them forever.
+	pub fn on_shutdown_complete(
+		&self,
+		timeout: Duration,
+	) -> impl Future<Output = Vec<&'static str>> + Send + 'static {
+		let on_exit = self.on_exit.clone();
+		let running_tasks = self.running_tasks.clone();
+		async move {
+			on_exit.await;
+			if wait_until(|| running_tasks.names().is_empty(), timeout).await {
+				return Vec::new();
+			}
+
+			let still_running = running_tasks.names();
+			warn!(
+				"Tasks still running {:?} after the service shut down: {:?}",
+				timeout, still_running,
+			);
+			still_running
+		}
+	}
+
 	/// Signal to terminate all the running tasks.
 	pub fn terminate(&mut self) {
 		if let Some(signal) = self.signal.take() {
@@ -340,3 +570,146 @@ impl Metrics {
 		})
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{channel::oneshot, executor::block_on};
+
+	#[test]
+	fn tasks_run_on_dedicated_executor_and_stop_with_the_service() {
+		let service_executor: TaskExecutor = (|_, _| {
+			panic!("Task spawned on the executor of the service");
+		}).into();
+		let dedicated_executor: TaskExecutor = (|future, _| {
+			std::thread::Builder::new()
+				.name("dedicated-executor".into())
+				.spawn(move || block_on(future))
+				.expect("Spawns the thread");
+		}).into();
+		let mut task_manager = TaskManager::new(service_executor, None, false, None).unwrap();
+		let spawn_handle = task_manager.spawn_handle_with_executor(dedicated_executor);
+
+		let (tx, rx) = oneshot::channel();
+		spawn_handle.spawn("thread-name", async move {
+			let _ = tx.send(std::thread::current().name().map(String::from));
+		});
+		assert_eq!(block_on(rx).unwrap().as_deref(), Some("dedicated-executor"));
+
+		// Tasks still running when the service shuts down are interrupted.
+		let (tx, rx) = oneshot::channel::<()>();
+		spawn_handle.spawn("never-ending", async move {
+			futures::future::pending::<()>().await;
+			let _ = tx.send(());
+		});
+		task_manager.terminate();
+		assert!(block_on(rx).is_err());
+	}
+
+	#[test]
+	fn shutdown_completes_once_tasks_have_stopped() {
+		let executor: TaskExecutor = (|future, _| {
+			std::thread::spawn(move || block_on(future));
+		}).into();
+		let mut task_manager = TaskManager::new(executor, None, false, None).unwrap();
+		let spawn_handle = task_manager.spawn_handle();
+
+		spawn_handle.spawn("never-ending", futures::future::pending());
+		// Blocks its thread without ever yielding, so it can't be interrupted.
+		spawn_handle.spawn_blocking("stubborn", async {
+			std::thread::sleep(Duration::from_millis(500));
+		});
+		// Give the tasks a chance to start.
+		std::thread::sleep(Duration::from_millis(50));
+
+		let shutdown_complete = task_manager.on_shutdown_complete(Duration::from_millis(100));
+		task_manager.terminate();
+		assert_eq!(block_on(shutdown_complete), vec!["stubborn"]);
+
+		let shutdown_complete = task_manager.on_shutdown_complete(Duration::from_secs(5));
+		assert!(block_on(shutdown_complete).is_empty());
+	}
+
+	#[test]
+	fn poll_time_is_accumulated_by_task_name() {
+		// Runs the tasks to completion before `spawn` returns.
+		let executor: TaskExecutor = (|future, _| block_on(future)).into();
+
+		let task_manager = TaskManager::new(executor.clone(), None, true, None).unwrap();
+		let spawn_handle = task_manager.spawn_handle();
+		spawn_handle.spawn("busy", async {
+			std::thread::sleep(Duration::from_millis(50));
+		});
+		spawn_handle.spawn("light", async {});
+
+		let metrics = task_manager.task_metrics();
+		let names = metrics.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+		assert_eq!(names, vec!["busy", "light"]);
+		assert!(metrics[0].1 >= Duration::from_millis(50));
+		assert!(metrics[1].1 < metrics[0].1);
+
+		// Nothing is accounted when disabled.
+		let task_manager = TaskManager::new(executor, None, false, None).unwrap();
+		task_manager.spawn_handle().spawn("busy", async {});
+		assert!(task_manager.task_metrics().is_empty());
+	}
+
+	#[test]
+	fn panicking_essential_task_fires_essential_failure() {
+		let executor: TaskExecutor = (|future, _| {
+			std::thread::spawn(move || block_on(future));
+		}).into();
+		let mut task_manager = TaskManager::new(executor, None, false, None).unwrap();
+
+		// A panicking non-essential task is only logged.
+		let (tx, rx) = oneshot::channel::<()>();
+		task_manager.spawn_handle().spawn("panicking", async move {
+			let _tx = tx;
+			panic!("Non-essential task panicked");
+		});
+		assert!(block_on(rx).is_err());
+		assert!(task_manager.future().now_or_never().is_none());
+
+		task_manager.spawn_essential_handle().spawn("essential-panicking", async {
+			panic!("Essential task panicked");
+		});
+		assert!(block_on(task_manager.future()).is_err());
+	}
+
+	#[test]
+	fn watched_task_is_restarted_once_it_stops_heartbeating() {
+		let executor: TaskExecutor = (|future, _| {
+			std::thread::spawn(move || block_on(future));
+		}).into();
+		let watchdog_timeout = Some(Duration::from_millis(100));
+		let mut task_manager = TaskManager::new(executor, None, false, watchdog_timeout).unwrap();
+
+		let (tx, rx) = std::sync::mpsc::channel();
+		let mut attempts = 0;
+		task_manager.spawn_handle().spawn_watched("stuck", move |heartbeat| {
+			attempts += 1;
+			let attempt = attempts;
+			let tx = tx.clone();
+			async move {
+				heartbeat.ping();
+				let _ = tx.send(attempt);
+				// The first attempts get stuck without heartbeating anymore.
+				if attempt < 3 {
+					futures::future::pending::<()>().await;
+				}
+			}
+		});
+		for attempt in 1..=3 {
+			assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(attempt));
+		}
+		// The last attempt completed, and isn't restarted.
+		assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+		// A stuck essential task isn't restarted, but shuts the service down.
+		task_manager.spawn_essential_handle().spawn_watched("essential-stuck", |heartbeat| async move {
+			heartbeat.ping();
+			futures::future::pending::<()>().await;
+		});
+		assert!(block_on(task_manager.future()).is_err());
+	}
+}
@@ -0,0 +1,74 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+//! Accounting of the time spent polling the tasks of the service.
+
+use futures::prelude::*;
+use parking_lot::Mutex;
+use std::{
+	collections::HashMap,
+	pin::Pin,
+	sync::{Arc, atomic::{AtomicU64, Ordering}},
+	task::{Context, Poll},
+	time::Duration,
+};
+use wasm_timer::Instant;
+
+/// Time spent polling tasks, accumulated by task name.
+#[derive(Clone, Default)]
+pub struct PollTimes {
+	/// Nanoseconds spent in `Future::poll`, for each task name.
+	by_name: Arc<Mutex<HashMap<&'static str, Arc<AtomicU64>>>>,
+}
+
+impl PollTimes {
+	/// Wraps around `inner`, adding the time spent polling it to the total of `name`.
+	pub fn account<T>(&self, name: &'static str, inner: T) -> PollTimeFuture<T> {
+		let total = self.by_name.lock().entry(name).or_default().clone();
+		PollTimeFuture { inner, total }
+	}
+
+	/// Returns the accumulated poll time of every task name, the longest first.
+	pub fn snapshot(&self) -> Vec<(String, Duration)> {
+		let mut times = self.by_name.lock().iter()
+			.map(|(name, total)| (name.to_string(), Duration::from_nanos(total.load(Ordering::Relaxed))))
+			.collect::<Vec<_>>();
+		times.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+		times
+	}
+}
+
+/// Wraps around `Future` and accumulates the time spent polling it.
+#[pin_project::pin_project]
+pub struct PollTimeFuture<T> {
+	/// The inner future doing the actual work.
+	#[pin]
+	inner: T,
+	total: Arc<AtomicU64>,
+}
+
+impl<T> Future for PollTimeFuture<T>
+where
+	T: Future,
+{
+	type Output = T::Output;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		let this = self.project();
+
+		let start = Instant::now();
+		let result = Future::poll(this.inner, cx);
+		this.total.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+		result
+	}
+}
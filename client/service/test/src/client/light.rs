@@ -55,7 +55,7 @@ use sp_blockchain::{
 };
 use std::panic::UnwindSafe;
 use std::cell::RefCell;
-use sp_state_machine::{OverlayedChanges, ExecutionManager};
+use sp_state_machine::{OverlayedChanges, ExecutionManager, ExecutionTrace};
 use parity_scale_codec::{Decode, Encode};
 use super::prepare_client_with_key_changes;
 use substrate_test_runtime_client::{
@@ -251,6 +251,15 @@ impl CallExecutor<Block> for DummyCallExecutor {
 		unreachable!()
 	}
 
+	fn trace_call(
+		&self,
+		_id: &BlockId<Block>,
+		_method: &str,
+		_call_data: &[u8],
+	) -> Result<(Vec<u8>, ExecutionTrace), ClientError> {
+		unreachable!()
+	}
+
 	fn native_runtime_version(&self) -> Option<&NativeVersion> {
 		unreachable!()
 	}
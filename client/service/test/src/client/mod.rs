@@ -33,6 +33,9 @@ use sc_client_api::{
 };
 use sc_client_db::{Backend, DatabaseSettings, DatabaseSettingsSrc, PruningMode};
 use sc_block_builder::BlockBuilderProvider;
+use sc_consensus::{
+	CompositeSelectChainBuilder, FinalitySource, FinalizedBlock, LongestChain, SwappableSelectChain,
+};
 use sc_service::client::{self, Client, LocalCallExecutor, new_in_mem};
 use sp_runtime::traits::{
 	BlakeTwo256, Block as BlockT, Header as HeaderT,
@@ -984,6 +987,117 @@ fn best_containing_on_longest_chain_with_max_depth_higher_than_best() {
 	assert_eq!(a2.hash(), longest_chain_select.finality_target(genesis_hash, Some(10)).unwrap().unwrap());
 }
 
+#[test]
+fn composite_select_chain_follows_the_heavier_finality_source() {
+	// block tree:
+	// G -> A1 -> A2 -> A3
+	//   -> B1 -> B2
+	let (mut client, backend) = TestClientBuilder::new().build_with_backend();
+
+	let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a1.clone()).unwrap();
+	let a2 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a2.clone()).unwrap();
+	let a3 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a3.clone()).unwrap();
+
+	let genesis_hash = client.chain_info().genesis_hash;
+	let mut builder = client.new_block_at(
+		&BlockId::Hash(genesis_hash),
+		Default::default(),
+		false,
+	).unwrap();
+	// this push is required as otherwise B1 has the same hash as A1 and won't get imported
+	builder.push_transfer(Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 41,
+		nonce: 0,
+	}).unwrap();
+	let b1 = builder.build().unwrap().block;
+	client.import(BlockOrigin::Own, b1.clone()).unwrap();
+	let b2 = client.new_block_at(
+		&BlockId::Hash(b1.hash()),
+		Default::default(),
+		false,
+	).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, b2.clone()).unwrap();
+
+	let finalized_by = |block: &Block, weight| -> Arc<dyn FinalitySource<Block>> {
+		let finalized = FinalizedBlock { hash: block.hash(), number: *block.header().number(), weight };
+		Arc::new(move || -> Result<_, ConsensusError> { Ok(finalized.clone()) })
+	};
+	let select_chain = |grandpa, overlay| CompositeSelectChainBuilder::new(backend.clone())
+		.with_finality_source("grandpa", grandpa)
+		.with_finality_source("overlay", overlay)
+		.build();
+
+	// the overlay has finalized the shorter fork with more weight.
+	let composite = select_chain(finalized_by(&a1, 10), finalized_by(&b1, 20));
+	assert_eq!(composite.best_chain().unwrap().hash(), b2.hash());
+	assert_eq!(composite.leaves().unwrap(), vec![b2.hash()]);
+	assert_eq!(composite.finality_target(genesis_hash, None).unwrap(), Some(b2.hash()));
+	assert_eq!(composite.finality_target(a2.hash(), None).unwrap(), None);
+
+	// same views, but GRANDPA has the most weight.
+	let composite = select_chain(finalized_by(&a1, 30), finalized_by(&b1, 20));
+	assert_eq!(composite.best_chain().unwrap().hash(), a3.hash());
+	assert_eq!(composite.leaves().unwrap(), vec![a3.hash()]);
+
+	// the sources agree, the lighter one is just ahead.
+	let composite = select_chain(finalized_by(&a1, 30), finalized_by(&a2, 20));
+	assert_eq!(composite.best_chain().unwrap().hash(), a3.hash());
+	assert_eq!(composite.finality_target(genesis_hash, Some(1)).unwrap(), None);
+	assert_eq!(composite.finality_target(genesis_hash, Some(3)).unwrap(), Some(a3.hash()));
+}
+
+#[test]
+fn swapped_select_chain_rule_is_used_by_every_clone() {
+	// block tree:
+	// G -> A1 -> A2
+	//   -> B1
+	let (mut client, backend) = TestClientBuilder::new().build_with_backend();
+
+	let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a1.clone()).unwrap();
+	let a2 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a2.clone()).unwrap();
+
+	let mut builder = client.new_block_at(
+		&BlockId::Hash(client.chain_info().genesis_hash),
+		Default::default(),
+		false,
+	).unwrap();
+	// this push is required as otherwise B1 has the same hash as A1 and won't get imported
+	builder.push_transfer(Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 41,
+		nonce: 0,
+	}).unwrap();
+	let b1 = builder.build().unwrap().block;
+	client.import(BlockOrigin::Own, b1.clone()).unwrap();
+
+	let select_chain = SwappableSelectChain::new(LongestChain::new(backend.clone()));
+	let used_by_consensus = select_chain.clone();
+	assert_eq!(used_by_consensus.best_chain().unwrap().hash(), a2.hash());
+
+	// a finality gadget that has finalized the shorter fork.
+	let finalized = FinalizedBlock { hash: b1.hash(), number: *b1.header().number(), weight: 1 };
+	let source: Arc<dyn FinalitySource<Block>> =
+		Arc::new(move || -> Result<_, ConsensusError> { Ok(finalized.clone()) });
+	select_chain.swap(
+		CompositeSelectChainBuilder::new(backend.clone())
+			.with_finality_source("overlay", source)
+			.build(),
+	);
+	assert_eq!(used_by_consensus.best_chain().unwrap().hash(), b1.hash());
+	assert_eq!(used_by_consensus.leaves().unwrap(), vec![b1.hash()]);
+
+	select_chain.swap(LongestChain::new(backend));
+	assert_eq!(used_by_consensus.best_chain().unwrap().hash(), a2.hash());
+}
+
 #[test]
 fn key_changes_works() {
 	let (client, _, test_cases) = prepare_client_with_key_changes();
@@ -1264,6 +1378,261 @@ fn state_reverted_on_reorg() {
 	assert_eq!(980, current_balance(&client));
 }
 
+#[test]
+fn in_memory_database_imports_and_finalizes_blocks() {
+	let backend = Arc::new(Backend::new(
+		DatabaseSettings {
+			state_cache_size: 1 << 20,
+			state_cache_child_ratio: Some((50, 100)),
+			pruning: PruningMode::keep_blocks(1),
+			source: DatabaseSettingsSrc::InMemory,
+			open_retries: 0,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: None,
+		},
+		u64::max_value(),
+	).unwrap());
+
+	let mut client = TestClientBuilder::with_backend(backend).build();
+
+	let mut hashes = Vec::new();
+	for _ in 0..3 {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		hashes.push(block.hash());
+		client.import(BlockOrigin::Own, block).unwrap();
+	}
+	ClientExt::finalize_block(&client, BlockId::Number(3), None).unwrap();
+
+	let info = client.chain_info();
+	assert_eq!((info.best_number, info.best_hash), (3, hashes[2]));
+	assert_eq!((info.finalized_number, info.finalized_hash), (3, hashes[2]));
+
+	// the pruning settings still apply.
+	assert_eq!(client.block_status(&BlockId::Number(1)).unwrap(), BlockStatus::InChainPruned);
+	assert_eq!(client.block_status(&BlockId::Number(3)).unwrap(), BlockStatus::InChainWithState);
+}
+
+#[test]
+fn state_snapshot_is_imported_into_a_fresh_backend() {
+	use sc_client_api::backend::Backend as _;
+	use sp_blockchain::HeaderBackend as _;
+
+	let settings = || DatabaseSettings {
+		state_cache_size: 1 << 20,
+		state_cache_child_ratio: Some((50, 100)),
+		pruning: PruningMode::keep_blocks(16),
+		source: DatabaseSettingsSrc::InMemory,
+		open_retries: 0,
+		write_buffer_size: None,
+		max_open_files: None,
+		justification_retention: Default::default(),
+		genesis_build_threads: None,
+		max_tracked_forks: None,
+	};
+	let child_info = sp_core::storage::ChildInfo::new_default(b"snapshot_child");
+
+	let backend = Arc::new(Backend::new(settings(), u64::max_value()).unwrap());
+	let mut client = TestClientBuilder::with_backend(backend)
+		.add_extra_child_storage(&child_info, b"key".to_vec(), b"value".to_vec())
+		.build();
+	for _ in 0..3 {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, block).unwrap();
+	}
+	ClientExt::finalize_block(&client, BlockId::Number(3), None).unwrap();
+	let info = client.chain_info();
+
+	let tmp = tempfile::tempdir().unwrap();
+	let path = tmp.path().join("snapshot");
+	let exported = sc_service::chain_ops::export_state_snapshot(
+		Arc::new(client),
+		BlockId::Number(3),
+		&path,
+	).unwrap();
+	assert_eq!(exported.header.hash(), info.finalized_hash);
+
+	let snapshot = sc_service::chain_ops::read_state_snapshot::<Block>(&path).unwrap();
+	let fresh = Backend::<Block>::new(settings(), u64::max_value()).unwrap();
+	fresh.import_state_snapshot(snapshot.genesis, snapshot.header, snapshot.storage).unwrap();
+
+	let fresh_info = fresh.blockchain().info();
+	assert_eq!(fresh_info.genesis_hash, info.genesis_hash);
+	assert_eq!((fresh_info.best_number, fresh_info.best_hash), (3, info.finalized_hash));
+	assert_eq!((fresh_info.finalized_number, fresh_info.finalized_hash), (3, info.finalized_hash));
+
+	let state = fresh.state_at(BlockId::Number(3)).unwrap();
+	assert_eq!(state.storage_root(std::iter::empty()).0, *exported.header.state_root());
+	assert_eq!(state.child_storage(&child_info, b"key").unwrap(), Some(b"value".to_vec()));
+
+	// the database isn't empty anymore.
+	assert!(fresh.import_state_snapshot(
+		exported.genesis,
+		exported.header,
+		exported.storage,
+	).is_err());
+}
+
+#[test]
+fn database_of_another_chain_is_rejected() {
+	let tmp = tempfile::tempdir().unwrap();
+	let settings = DatabaseSettings {
+		state_cache_size: 1 << 20,
+		state_cache_child_ratio: None,
+		pruning: PruningMode::ArchiveAll,
+		source: DatabaseSettingsSrc::RocksDb {
+			path: tmp.path().into(),
+			cache_size: 16,
+		},
+		open_retries: 0,
+		write_buffer_size: None,
+		max_open_files: None,
+		justification_retention: Default::default(),
+		genesis_build_threads: None,
+		max_tracked_forks: None,
+	};
+	let genesis_storage = |chain: &[u8]| sp_core::storage::Storage {
+		top: vec![(b"chain".to_vec(), chain.to_vec())].into_iter().collect(),
+		children_default: Default::default(),
+	};
+	let open = |settings: &DatabaseSettings, chain: &[u8], genesis_fingerprint: Option<H256>| {
+		sc_service::new_client::<_, Block, RuntimeApi>(
+			settings.clone(),
+			executor(),
+			&genesis_storage(chain),
+			None,
+			None,
+			Default::default(),
+			tasks_executor(),
+			None,
+			client::ClientConfig { genesis_fingerprint, ..Default::default() },
+		).map(|(client, _)| client.chain_info().genesis_hash)
+	};
+	let fingerprint = |spec: &[u8]| Some(H256::from(blake2_256(spec)));
+
+	// the fresh database is initialized, and then accepted for the same chain.
+	let genesis_a = open(&settings, b"A", fingerprint(b"spec A")).unwrap();
+	assert_eq!(open(&settings, b"A", fingerprint(b"spec A")).unwrap(), genesis_a);
+	// a new fingerprint of the same genesis is accepted once the genesis is built again.
+	assert_eq!(open(&settings, b"A", None).unwrap(), genesis_a);
+	assert_eq!(open(&settings, b"A", fingerprint(b"spec A v2")).unwrap(), genesis_a);
+
+	let other_tmp = tempfile::tempdir().unwrap();
+	let mut other_settings = settings.clone();
+	other_settings.source = DatabaseSettingsSrc::RocksDb {
+		path: other_tmp.path().into(),
+		cache_size: 16,
+	};
+	let genesis_b = open(&other_settings, b"B", None).unwrap();
+	for &genesis_fingerprint in &[None, fingerprint(b"spec B")] {
+		match open(&settings, b"B", genesis_fingerprint) {
+			Err(sp_blockchain::Error::GenesisMismatch(stored, expected)) => {
+				assert_eq!(stored, format!("{:?}", genesis_a));
+				assert_eq!(expected, format!("{:?}", genesis_b));
+			},
+			other => panic!("Expected a genesis mismatch, got {:?}", other),
+		}
+	}
+
+	// the genesis isn't built again as long as the fingerprint of the database is given.
+	assert_eq!(open(&settings, b"B", fingerprint(b"spec A v2")).unwrap(), genesis_a);
+}
+
+#[test]
+fn genesis_code_with_unexpected_hash_is_rejected() {
+	use sc_client_api::backend::{Backend as _, BlockImportOperation, NewBlockState};
+	use sp_blockchain::HeaderBackend as _;
+
+	let tmp = tempfile::tempdir().unwrap();
+	let settings = DatabaseSettings {
+		state_cache_size: 1 << 20,
+		state_cache_child_ratio: None,
+		pruning: PruningMode::ArchiveAll,
+		source: DatabaseSettingsSrc::RocksDb {
+			path: tmp.path().into(),
+			cache_size: 16,
+		},
+		open_retries: 0,
+		write_buffer_size: None,
+		max_open_files: None,
+		justification_retention: Default::default(),
+		genesis_build_threads: None,
+		max_tracked_forks: None,
+	};
+	let genesis_storage = sp_core::storage::Storage {
+		top: vec![(sp_core::storage::well_known_keys::CODE.to_vec(), b"genesis code".to_vec())]
+			.into_iter().collect(),
+		children_default: Default::default(),
+	};
+	let open = |expected_genesis_code_hash: H256| sc_service::new_client::<_, Block, RuntimeApi>(
+		settings.clone(),
+		executor(),
+		&genesis_storage,
+		None,
+		None,
+		Default::default(),
+		tasks_executor(),
+		None,
+		client::ClientConfig {
+			expected_genesis_code_hash: Some(expected_genesis_code_hash),
+			..Default::default()
+		},
+	).map(|(_, backend)| backend);
+
+	let code_hash = H256::from(blake2_256(b"genesis code"));
+	let other_hash = H256::from(blake2_256(b"other code"));
+
+	// Checked against the chain specification on a fresh database...
+	match open(other_hash) {
+		Err(sp_blockchain::Error::GenesisCodeMismatch(actual, expected)) => {
+			assert_eq!(actual, format!("{:?}", code_hash));
+			assert_eq!(expected, format!("{:?}", other_hash));
+		},
+		other => panic!("Expected a genesis code mismatch, got {:?}", other),
+	}
+	let backend = open(code_hash).unwrap();
+
+	// ...and against the genesis state once it has been initialized, even after the runtime has
+	// been upgraded.
+	let upgraded_hash = H256::from(blake2_256(b"upgraded code"));
+	let genesis_hash = backend.blockchain().info().genesis_hash;
+	let mut op = backend.begin_operation().unwrap();
+	backend.begin_state_operation(&mut op, BlockId::Hash(genesis_hash)).unwrap();
+	let upgrade = vec![
+		(sp_core::storage::well_known_keys::CODE.to_vec(), Some(b"upgraded code".to_vec())),
+	];
+	let (state_root, transaction) = backend.state_at(BlockId::Hash(genesis_hash)).unwrap()
+		.storage_root(upgrade.iter().map(|(k, v)| (&k[..], v.as_deref())));
+	op.update_db_storage(transaction).unwrap();
+	op.update_storage(upgrade, Vec::new()).unwrap();
+	let header = Header {
+		number: 1,
+		parent_hash: genesis_hash,
+		state_root,
+		extrinsics_root: Default::default(),
+		digest: Default::default(),
+	};
+	op.set_block_data(header, Some(Vec::new()), None, NewBlockState::Best).unwrap();
+	backend.commit_operation(op).unwrap();
+	let best = BlockId::Number(1);
+	assert_eq!(
+		backend.state_at(best).unwrap().storage(sp_core::storage::well_known_keys::CODE).unwrap(),
+		Some(b"upgraded code".to_vec()),
+	);
+	drop(backend);
+
+	for unexpected in &[other_hash, upgraded_hash] {
+		match open(*unexpected) {
+			Err(sp_blockchain::Error::GenesisCodeMismatch(actual, _)) =>
+				assert_eq!(actual, format!("{:?}", code_hash)),
+			other => panic!("Expected a genesis code mismatch, got {:?}", other),
+		}
+	}
+	open(code_hash).unwrap();
+}
+
 #[test]
 fn doesnt_import_blocks_that_revert_finality() {
 	let _ = env_logger::try_init();
@@ -1280,6 +1649,12 @@ fn doesnt_import_blocks_that_revert_finality() {
 				path: tmp.path().into(),
 				cache_size: 1024,
 			},
+			open_retries: 0,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: None,
 		},
 		u64::max_value(),
 	).unwrap());
@@ -1481,6 +1856,12 @@ fn returns_status_for_pruned_blocks() {
 				path: tmp.path().into(),
 				cache_size: 1024,
 			},
+			open_retries: 0,
+			write_buffer_size: None,
+			max_open_files: None,
+			justification_retention: Default::default(),
+			genesis_build_threads: None,
+			max_tracked_forks: None,
 		},
 		u64::max_value(),
 	).unwrap());
@@ -1802,3 +2183,56 @@ fn cleans_up_closed_notification_sinks_on_block_import() {
 	assert_eq!(client.finality_notification_sinks().lock().len(), 0);
 }
 
+
+#[test]
+fn blocks_the_runtime_panics_on_are_rejected() {
+	let import_block = |client: &mut TestClient, origin, block: Block| {
+		let (header, extrinsics) = block.deconstruct();
+		let mut import = BlockImportParams::new(origin, header);
+		import.body = Some(extrinsics);
+		import.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+		client.import_block(import, Default::default())
+	};
+	let panicking_block = |client: &TestClient| {
+		let mut block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		// the runtime panics on a state root that doesn't match the one it computes.
+		block.header.state_root = Default::default();
+		block
+	};
+
+	// By default the import fails.
+	let mut client = substrate_test_runtime_client::new();
+	let block = panicking_block(&client);
+	match import_block(&mut client, BlockOrigin::NetworkBroadcast, block) {
+		Err(ConsensusError::ClientImport(_)) => (),
+		other => panic!("Expected the import to fail, got {:?}", other),
+	}
+
+	let mut client = TestClientBuilder::new()
+		.set_client_config(client::ClientConfig {
+			reject_runtime_panics: true,
+			..Default::default()
+		})
+		.build();
+	let block = panicking_block(&client);
+	let hash = block.header.hash();
+	assert_eq!(
+		import_block(&mut client, BlockOrigin::NetworkBroadcast, block.clone()).unwrap(),
+		ImportResult::RuntimePanicked,
+	);
+	assert!(client.header(&BlockId::Hash(hash)).unwrap().is_none());
+
+	// The author of the block is told about the panic.
+	match import_block(&mut client, BlockOrigin::Own, block) {
+		Err(ConsensusError::ClientImport(_)) => (),
+		other => panic!("Expected the import to fail, got {:?}", other),
+	}
+
+	// The node keeps importing valid blocks.
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	assert_eq!(
+		import_block(&mut client, BlockOrigin::NetworkBroadcast, block).unwrap(),
+		ImportResult::imported(true),
+	);
+	assert_eq!(client.chain_info().best_number, 1);
+}
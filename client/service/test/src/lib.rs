@@ -234,18 +234,30 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 		role,
 		task_executor,
 		transaction_pool: Default::default(),
+		tx_propagation_delay: None,
 		network: network_config,
 		keystore: KeystoreConfig::Path {
 			path: root.join("key"),
-			password: None
+			password: None,
+			backup: None,
 		},
 		database: DatabaseConfig::RocksDb {
 			path: root.join("db"),
 			cache_size: 128,
 		},
+		database_open_retries: 0,
+		database_write_buffer_size: None,
+		database_max_open_files: None,
+		genesis_build_threads: None,
+		max_tracked_forks: None,
+		skip_genesis_check: false,
+		expected_genesis_code_hash: None,
 		state_cache_size: 16777216,
+		import_queue_mem_budget: None,
+		block_time_drift_tolerance: Default::default(),
 		state_cache_child_ratio: None,
 		pruning: Default::default(),
+		justification_retention: Default::default(),
 		chain_spec: Box::new((*spec).clone()),
 		wasm_method: sc_service::config::WasmExecutionMethod::Interpreted,
 		execution_strategies: Default::default(),
@@ -253,22 +265,45 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 		rpc_ipc: None,
 		rpc_ws: None,
 		rpc_ws_max_connections: None,
+		rpc_dual_stack: false,
 		rpc_cors: None,
 		rpc_methods: Default::default(),
+		rpc_serve_after: Default::default(),
+		rpc_handler_mode: Default::default(),
+		rpc_max_concurrent_runtime_calls: None,
 		prometheus_config: None,
+		prometheus_static_labels: Default::default(),
 		telemetry_endpoints: None,
 		telemetry_external_transport: None,
+		telemetry_metrics: false,
+		telemetry_healthy_peer_threshold: 1,
+		finality_webhook: None,
+		persist_peer_reputations: None,
+		peer_reputations_ttl: Duration::from_secs(3600),
 		default_heap_pages: None,
 		offchain_worker: Default::default(),
 		force_authoring: false,
+		unsafe_override_inherent_checks: false,
+		reject_runtime_panics: false,
 		disable_grandpa: false,
 		dev_key_seed: key_seed,
+		generate_session_keys: true,
+		overwrite_dev_keys: true,
+		require_authoring_keys: false,
 		tracing_targets: None,
 		tracing_receiver: Default::default(),
 		max_runtime_instances: 8,
 		announce_block: true,
+		suppress_block_announcements: false,
+		max_announce_block_age: None,
+		block_announce_validation_limit: None,
+		sync_request_timeout: sc_network::config::DEFAULT_SYNC_REQUEST_TIMEOUT,
 		base_path: Some(BasePath::new(root)),
 		informant_output_format: Default::default(),
+		coalesce_finality_notifications: false,
+		structured_events_sink: None,
+		task_poll_time_accounting: false,
+		task_watchdog_timeout: None,
 	}
 }
 
@@ -24,6 +24,8 @@
 //!
 //! Currently we provide `Log` (default), `Telemetry` variants for `Receiver`
 
+pub mod logging;
+
 use rustc_hash::FxHashMap;
 use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
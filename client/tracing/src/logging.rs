@@ -0,0 +1,156 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Log filter that can be changed while the node is running.
+//!
+//! The filter uses the `env_logger` syntax, e.g. `sync=debug,afg=trace,info`.
+
+use env_logger::filter::{Builder as FilterBuilder, Filter};
+use lazy_static::lazy_static;
+use log::{LevelFilter, Log, Metadata, Record};
+use parking_lot::RwLock;
+
+/// Log filter errors.
+#[derive(Debug, derive_more::Display)]
+pub enum Error {
+	/// The logger in place wasn't installed with [`init_logger`].
+	#[display(fmt = "The log filter can't be changed at runtime")]
+	Unavailable,
+	/// A logger has already been installed.
+	#[display(fmt = "A logger is already installed")]
+	AlreadyInstalled,
+	/// A directive of the filter couldn't be parsed.
+	#[display(fmt = "Invalid log filter directive: {}", _0)]
+	InvalidDirective(String),
+}
+
+impl std::error::Error for Error {}
+
+/// The current filter, along with the directives it was built from.
+struct LogFilter {
+	directives: String,
+	filter: Filter,
+}
+
+lazy_static! {
+	static ref LOG_FILTER: RwLock<Option<LogFilter>> = RwLock::new(None);
+}
+
+/// Forwards to the inner logger the records that pass the current filter.
+struct FilteredLogger {
+	inner: Box<dyn Log>,
+}
+
+impl Log for FilteredLogger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		LOG_FILTER.read().as_ref().map_or(false, |f| f.filter.enabled(metadata))
+			&& self.inner.enabled(metadata)
+	}
+
+	fn log(&self, record: &Record) {
+		if LOG_FILTER.read().as_ref().map_or(false, |f| f.filter.matches(record)) {
+			self.inner.log(record);
+		}
+	}
+
+	fn flush(&self) {
+		self.inner.flush();
+	}
+}
+
+/// Installs `inner` as the global logger, filtered by `directives`.
+///
+/// `inner` is expected to log everything it's given, the filter can be changed later on with
+/// [`set_log_filter`]. Directives that can't be parsed are ignored.
+pub fn init_logger(inner: Box<dyn Log>, directives: &str) -> Result<(), Error> {
+	log::set_boxed_logger(Box::new(FilteredLogger { inner }))
+		.map_err(|_| Error::AlreadyInstalled)?;
+	replace_filter(directives.into(), FilterBuilder::new().parse(directives).build());
+	Ok(())
+}
+
+/// Returns the directives of the current log filter.
+pub fn log_filter() -> Result<String, Error> {
+	LOG_FILTER.read().as_ref().map(|f| f.directives.clone()).ok_or(Error::Unavailable)
+}
+
+/// Replaces the current log filter with `directives`.
+///
+/// The current filter is left untouched if any of the directives is invalid.
+pub fn set_log_filter(directives: &str) -> Result<(), Error> {
+	if LOG_FILTER.read().is_none() {
+		return Err(Error::Unavailable);
+	}
+
+	let filter = parse_filter(directives)?;
+	replace_filter(directives.into(), filter);
+	Ok(())
+}
+
+fn replace_filter(directives: String, filter: Filter) {
+	let max_level = filter.filter();
+	*LOG_FILTER.write() = Some(LogFilter { directives, filter });
+	log::set_max_level(max_level);
+}
+
+/// Parses `directives`, failing on the first invalid one.
+///
+/// `env_logger` only prints a warning when it doesn't understand a directive, which would go
+/// unnoticed by whoever is changing the filter remotely.
+fn parse_filter(directives: &str) -> Result<Filter, Error> {
+	let mut parts = directives.split('/');
+	let spec = parts.next().unwrap_or_default();
+	if parts.count() > 1 {
+		return Err(Error::InvalidDirective(directives.into()));
+	}
+
+	for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+		let mut parts = directive.split('=');
+		let valid = match (parts.next(), parts.next(), parts.next()) {
+			(Some(target), Some(level), None) =>
+				!target.trim().is_empty() && level.trim().parse::<LevelFilter>().is_ok(),
+			// either a level, or a target to log at all levels.
+			(Some(_), None, None) => true,
+			_ => false,
+		};
+		if !valid {
+			return Err(Error::InvalidDirective(directive.into()));
+		}
+	}
+
+	Ok(FilterBuilder::new().parse(directives).build())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn invalid_directives_are_rejected() {
+		for directives in &["sync=loud", "=debug", "sync=debug=trace", "info/a/b"] {
+			match parse_filter(directives) {
+				Err(Error::InvalidDirective(_)) => {},
+				_ => panic!("`{}` should be rejected", directives),
+			}
+		}
+
+		for directives in &["", "info", "sync", "sync=debug, afg=TRACE,warn", "info/imported"] {
+			assert!(parse_filter(directives).is_ok(), "`{}` should be accepted", directives);
+		}
+	}
+}
@@ -25,6 +25,7 @@ use std::{
 	fmt,
 	hash,
 	sync::Arc,
+	time::Duration,
 };
 
 use log::{trace, debug, warn};
@@ -146,6 +147,10 @@ impl<Hash, Extrinsic> InPoolTransaction for Transaction<Hash, Extrinsic> {
 	fn is_propagable(&self) -> bool {
 		self.propagate
 	}
+
+	fn source(&self) -> Source {
+		self.source
+	}
 }
 
 impl<Hash: Clone, Extrinsic: Clone> Transaction<Hash, Extrinsic> {
@@ -383,6 +388,22 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		self.future.all()
 	}
 
+	/// Returns future transactions in the pool, along with the tags they are missing, sorted.
+	pub fn futures_with_missing_tags(&self) -> Vec<(Arc<Transaction<Hash, Ex>>, Vec<Tag>)> {
+		self.future.waiting()
+			.map(|waiting| {
+				let mut missing_tags = waiting.missing_tags.iter().cloned().collect::<Vec<_>>();
+				missing_tags.sort();
+				(waiting.transaction.clone(), missing_tags)
+			})
+			.collect()
+	}
+
+	/// Returns hashes of future transactions that have been waiting for at least `max_age`.
+	pub fn stale_futures(&self, max_age: Duration) -> Vec<Hash> {
+		self.future.stale(max_age)
+	}
+
 	/// Returns pool transactions given list of hashes.
 	///
 	/// Includes both ready and future pool. For every hash in the `hashes`
@@ -408,14 +429,38 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	/// Removes and returns worst transactions from the queues and all transactions that depend on them.
 	/// Technically the worst transaction should be evaluated by computing the entire pending set.
 	/// We use a simplified approach to remove the transaction that occupies the pool for the longest time.
-	pub fn enforce_limits(&mut self, ready: &Limit, future: &Limit) -> Vec<Arc<Transaction<Hash, Ex>>> {
+	///
+	/// Local transactions are not removed from the ready queue as long as they fit within
+	/// `local_reserve`, unless there is nothing else left to remove.
+	pub fn enforce_limits(
+		&mut self,
+		ready: &Limit,
+		future: &Limit,
+		local_reserve: &Limit,
+	) -> Vec<Arc<Transaction<Hash, Ex>>> {
 		let mut removed = vec![];
 
 		while ready.is_exceeded(self.ready.len(), self.ready.bytes()) {
+			let (local_count, local_bytes) = self.ready
+				.fold(|acc, current| {
+					let (count, bytes) = acc.unwrap_or((0, 0));
+					let transaction = &current.transaction.transaction;
+					if transaction.source == Source::Local {
+						Some((count + 1, bytes + transaction.bytes))
+					} else {
+						Some((count, bytes))
+					}
+				})
+				.unwrap_or((0, 0));
+			let protect_local = !local_reserve.is_exceeded(local_count, local_bytes);
+
 			// find the worst transaction
-			let minimal = self.ready
+			let mut find_minimal = |skip_local: bool| self.ready
 				.fold(|minimal, current| {
 					let transaction = &current.transaction;
+					if skip_local && transaction.transaction.source == Source::Local {
+						return minimal;
+					}
 					match minimal {
 						None => Some(transaction.clone()),
 						Some(ref tx) if tx.insertion_id > transaction.insertion_id => {
@@ -424,6 +469,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 						other => other,
 					}
 				});
+			let minimal = find_minimal(protect_local).or_else(|| find_minimal(false));
 
 			if let Some(minimal) = minimal {
 				removed.append(&mut self.remove_subtree(&[minimal.transaction.hash.clone()]))
@@ -576,6 +622,54 @@ mod tests {
 		assert_eq!(pool.ready.len(), 1);
 	}
 
+	#[test]
+	fn should_keep_local_transactions_within_reserved_capacity() {
+		// given
+		let mut pool = pool();
+		let limit = Limit { count: 3, total_bytes: 100 };
+		let local_reserve = Limit { count: 1, total_bytes: 100 };
+		let tx = |hash: u64, source| Transaction {
+			data: vec![hash as u8],
+			bytes: 1,
+			hash,
+			priority: 5u64,
+			valid_till: 64u64,
+			requires: vec![],
+			provides: vec![vec![hash as u8]],
+			propagate: true,
+			source,
+		};
+		let enforce = |pool: &mut BasePool<Hash, Vec<u8>>| pool
+			.enforce_limits(&limit, &limit, &local_reserve)
+			.into_iter()
+			.map(|tx| tx.hash)
+			.collect::<Vec<_>>();
+
+		// when the pool is filled by network transactions, they can use the reserved capacity
+		for hash in 1..=3 {
+			pool.import(tx(hash, Source::External)).unwrap();
+		}
+		assert!(enforce(&mut pool).is_empty());
+
+		// then a local transaction still gets in, in place of the oldest network one
+		pool.import(tx(4, Source::Local)).unwrap();
+		assert_eq!(enforce(&mut pool), vec![1]);
+
+		// and it is kept while newer network transactions keep coming
+		pool.import(tx(5, Source::External)).unwrap();
+		assert_eq!(enforce(&mut pool), vec![2]);
+		pool.import(tx(6, Source::External)).unwrap();
+		assert_eq!(enforce(&mut pool), vec![3]);
+
+		// and once it is the oldest one, a newer network transaction is evicted instead
+		pool.import(tx(7, Source::External)).unwrap();
+		assert_eq!(enforce(&mut pool), vec![5]);
+		assert_eq!(
+			pool.ready().map(|tx| tx.hash).collect::<HashSet<_>>(),
+			[4, 6, 7].iter().cloned().collect(),
+		);
+	}
+
 	#[test]
 	fn should_not_import_same_transaction_twice() {
 		// given
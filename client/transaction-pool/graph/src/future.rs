@@ -21,6 +21,7 @@ use std::{
 	fmt,
 	hash,
 	sync::Arc,
+	time::Duration,
 };
 
 use sp_core::hexdisplay::HexDisplay;
@@ -231,12 +232,27 @@ impl<Hash: hash::Hash + Eq + Clone, Ex> FutureTransactions<Hash, Ex> {
 		self.waiting.values().map(|waiting| &*waiting.transaction)
 	}
 
+	/// Returns iterator over all future transactions, along with the tags they are waiting for.
+	pub fn waiting(&self) -> impl Iterator<Item=&WaitingTransaction<Hash, Ex>> {
+		self.waiting.values()
+	}
+
 	/// Removes and returns all future transactions.
 	pub fn clear(&mut self) -> Vec<Arc<Transaction<Hash, Ex>>> {
 		self.wanted_tags.clear();
 		self.waiting.drain().map(|(_, tx)| tx.transaction).collect()
 	}
 
+	/// Returns hashes of transactions that have been waiting for at least `max_age`.
+	pub fn stale(&self, max_age: Duration) -> Vec<Hash> {
+		let now = Instant::now();
+		self.waiting
+			.iter()
+			.filter(|(_, tx)| now.duration_since(tx.imported_at) >= max_age)
+			.map(|(hash, _)| hash.clone())
+			.collect()
+	}
+
 	/// Returns number of transactions in the Future queue.
 	pub fn len(&self) -> usize {
 		self.waiting.len()
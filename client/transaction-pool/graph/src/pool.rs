@@ -19,12 +19,14 @@
 use std::{
 	collections::HashMap,
 	sync::Arc,
+	time::Duration,
 };
 
 use crate::{base_pool as base, watcher::Watcher};
 
 use futures::Future;
 use sp_runtime::{
+	Percent,
 	generic::BlockId,
 	traits::{self, SaturatedConversion, Block as BlockT},
 	transaction_validity::{
@@ -107,6 +109,14 @@ pub struct Options {
 	pub future: base::Limit,
 	/// Reject future transactions.
 	pub reject_future_transactions: bool,
+	/// Share of the ready queue reserved for local transactions.
+	///
+	/// Network transactions may still use it while there are no local ones.
+	pub local_reserve: Percent,
+	/// How often to compact the pool while it is idle, if at all.
+	pub compaction_interval: Option<Duration>,
+	/// How long a future transaction may wait before being dropped on compaction.
+	pub future_stale_after: Duration,
 }
 
 impl Default for Options {
@@ -121,6 +131,9 @@ impl Default for Options {
 				total_bytes: 1 * 1024 * 1024,
 			},
 			reject_future_transactions: false,
+			local_reserve: Percent::zero(),
+			compaction_interval: None,
+			future_stale_after: Duration::from_secs(600),
 		}
 	}
 }
@@ -19,7 +19,8 @@
 use std::{
 	collections::{HashSet, HashMap},
 	hash,
-	sync::Arc,
+	sync::{Arc, atomic::{AtomicU64, Ordering}},
+	time::Duration,
 };
 
 use crate::base_pool as base;
@@ -34,7 +35,7 @@ use sp_runtime::{
 	traits::{self, SaturatedConversion},
 	transaction_validity::{TransactionTag as Tag, ValidTransaction, TransactionSource},
 };
-use sp_transaction_pool::{error, PoolStatus};
+use sp_transaction_pool::{error, BanList, PoolStatus};
 use wasm_timer::Instant;
 use futures::channel::mpsc::{channel, Sender};
 use retain_mut::RetainMut;
@@ -101,6 +102,9 @@ pub struct ValidatedPool<B: ChainApi> {
 	>>,
 	import_notification_sinks: Mutex<Vec<Sender<ExtrinsicHash<B>>>>,
 	rotator: PoolRotator<ExtrinsicHash<B>>,
+	ban_list: BanList<ExtrinsicHash<B>>,
+	/// Generation of the ban list when banned transactions were last evicted.
+	evicted_generation: AtomicU64,
 }
 
 #[cfg(not(target_os = "unknown"))]
@@ -125,9 +129,16 @@ impl<B: ChainApi> ValidatedPool<B> {
 			pool: RwLock::new(base_pool),
 			import_notification_sinks: Default::default(),
 			rotator: Default::default(),
+			ban_list: Default::default(),
+			evicted_generation: AtomicU64::new(0),
 		}
 	}
 
+	/// Returns the operator ban list consulted on submission.
+	pub fn ban_list(&self) -> &BanList<ExtrinsicHash<B>> {
+		&self.ban_list
+	}
+
 	/// Bans given set of hashes.
 	pub fn ban(&self, now: &Instant, hashes: impl IntoIterator<Item=ExtrinsicHash<B>>) {
 		self.rotator.ban(now, hashes)
@@ -143,12 +154,15 @@ impl<B: ChainApi> ValidatedPool<B> {
 	/// If `ingore_banned` is `true`, it will not check if the transaction is banned.
 	///
 	/// It checks if the transaction is already imported or banned. If so, it returns an error.
+	/// Transactions on the operator ban list are rejected regardless of `ignore_banned`.
 	pub fn check_is_known(
 		&self,
 		tx_hash: &ExtrinsicHash<B>,
 		ignore_banned: bool,
 	) -> Result<(), B::Error> {
-		if !ignore_banned && self.is_banned(tx_hash) {
+		if self.ban_list.is_hash_banned(tx_hash) {
+			Err(error::Error::Banned.into())
+		} else if !ignore_banned && self.is_banned(tx_hash) {
 			Err(error::Error::TemporarilyBanned.into())
 		} else if self.pool.read().is_imported(tx_hash) {
 			Err(error::Error::AlreadyImported(Box::new(tx_hash.clone())).into())
@@ -183,6 +197,10 @@ impl<B: ChainApi> ValidatedPool<B> {
 	fn submit_one(&self, tx: ValidatedTransactionFor<B>) -> Result<ExtrinsicHash<B>, B::Error> {
 		match tx {
 			ValidatedTransaction::Valid(tx) => {
+				if self.ban_list.is_banned(&tx.hash, &tx.provides) {
+					return Err(error::Error::Banned.into());
+				}
+
 				let imported = self.pool.write().import(tx)?;
 
 				if let base::Imported::Ready { ref hash, .. } = imported {
@@ -221,6 +239,10 @@ impl<B: ChainApi> ValidatedPool<B> {
 		let status = self.pool.read().status();
 		let ready_limit = &self.options.ready;
 		let future_limit = &self.options.future;
+		let local_reserve = base::Limit {
+			count: self.options.local_reserve * ready_limit.count,
+			total_bytes: self.options.local_reserve * ready_limit.total_bytes,
+		};
 
 		log::debug!(target: "txpool", "Pool Status: {:?}", status);
 		if ready_limit.is_exceeded(status.ready, status.ready_bytes)
@@ -236,7 +258,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 			// clean up the pool
 			let removed = {
 				let mut pool = self.pool.write();
-				let removed = pool.enforce_limits(ready_limit, future_limit)
+				let removed = pool.enforce_limits(ready_limit, future_limit, &local_reserve)
 					.into_iter().map(|x| x.hash.clone()).collect::<HashSet<_>>();
 				// ban all removed transactions
 				self.rotator.ban(&Instant::now(), removed.iter().map(|x| x.clone()));
@@ -591,11 +613,58 @@ impl<B: ChainApi> ValidatedPool<B> {
 		self.pool.read().ready()
 	}
 
+	/// Get future transactions, along with the tags they are still waiting for.
+	pub fn futures(&self) -> Vec<(TransactionFor<B>, Vec<Tag>)> {
+		self.pool.read().futures_with_missing_tags()
+	}
+
 	/// Returns pool status.
 	pub fn status(&self) -> PoolStatus {
 		self.pool.read().status()
 	}
 
+	/// Removes future transactions that have been waiting for at least `max_age`.
+	///
+	/// The transactions are dropped rather than treated as invalid, so they are not banned and
+	/// may be submitted again. Returns hashes of removed transactions.
+	pub fn clear_stale_futures(&self, max_age: Duration) -> Vec<ExtrinsicHash<B>> {
+		let stale = self.pool.read().stale_futures(max_age);
+		if stale.is_empty() {
+			return vec![];
+		}
+
+		let removed = self.pool.write().remove_subtree(&stale);
+
+		let mut listener = self.listener.write();
+		for tx in &removed {
+			listener.dropped(&tx.hash, None);
+		}
+
+		removed.into_iter().map(|tx| tx.hash.clone()).collect()
+	}
+
+	/// Removes transactions matching entries added to the ban list since the last call.
+	///
+	/// Returns hashes of removed transactions.
+	pub fn remove_banned(&self) -> Vec<ExtrinsicHash<B>> {
+		let generation = self.ban_list.generation();
+		if self.evicted_generation.swap(generation, Ordering::SeqCst) == generation {
+			return Vec::new();
+		}
+
+		let banned = {
+			let pool = self.pool.read();
+			pool.ready()
+				.filter(|tx| self.ban_list.is_banned(&tx.hash, &tx.provides))
+				.map(|tx| tx.hash.clone())
+				.chain(pool.futures()
+					.filter(|tx| self.ban_list.is_banned(&tx.hash, &tx.provides))
+					.map(|tx| tx.hash.clone()))
+				.collect::<Vec<_>>()
+		};
+		self.remove_invalid(&banned).into_iter().map(|tx| tx.hash.clone()).collect()
+	}
+
 	/// Notify all watchers that transactions in the block with hash have been finalized
 	pub async fn on_block_finalized(&self, block_hash: BlockHash<B>) -> Result<(), B::Error> {
 		log::trace!(target: "txpool", "Attempting to notify watchers of finalization for {}", block_hash);
@@ -34,21 +34,23 @@ pub mod testing;
 pub use sc_transaction_graph as txpool;
 pub use crate::api::{FullChainApi, LightChainApi};
 
-use std::{collections::{HashMap, HashSet}, sync::Arc, pin::Pin};
-use futures::{prelude::*, future::{self, ready}, channel::oneshot};
+use std::{collections::{HashMap, HashSet}, sync::Arc, pin::Pin, time::Duration};
+use futures::{prelude::*, future::{self, ready}, channel::oneshot, task::Poll};
 use parking_lot::Mutex;
 
 use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, NumberFor, AtLeast32Bit, Extrinsic, Zero},
+	transaction_validity::TransactionTag,
 };
 use sp_core::traits::SpawnNamed;
 use sp_transaction_pool::{
 	TransactionPool, PoolStatus, ImportNotificationStream, TxHash, TransactionFor,
 	TransactionStatusStreamFor, MaintainedTransactionPool, PoolFuture, ChainEvent,
-	TransactionSource,
+	TransactionSource, BanList,
 };
 use sc_transaction_graph::{ChainApi, ExtrinsicHash};
+use sc_client_api::FinalityNotification;
 use wasm_timer::Instant;
 
 use prometheus_endpoint::Registry as PrometheusRegistry;
@@ -76,6 +78,7 @@ pub struct BasicPool<PoolApi, Block>
 	revalidation_queue: Arc<revalidation::RevalidationQueue<PoolApi>>,
 	ready_poll: Arc<Mutex<ReadyPoll<ReadyIteratorFor<PoolApi>, Block>>>,
 	metrics: PrometheusMetrics,
+	last_activity: Arc<Mutex<Instant>>,
 }
 
 struct ReadyPoll<T, Block: BlockT> {
@@ -169,6 +172,7 @@ impl<PoolApi, Block> BasicPool<PoolApi, Block>
 				revalidation_strategy: Arc::new(Mutex::new(RevalidationStrategy::Always)),
 				ready_poll: Default::default(),
 				metrics: Default::default(),
+				last_activity: Arc::new(Mutex::new(Instant::now())),
 			},
 			background_task,
 			notifier,
@@ -221,6 +225,7 @@ impl<PoolApi, Block> BasicPool<PoolApi, Block>
 			)),
 			ready_poll: Default::default(),
 			metrics: PrometheusMetrics::new(prometheus),
+			last_activity: Arc::new(Mutex::new(Instant::now())),
 		}
 	}
 
@@ -228,6 +233,11 @@ impl<PoolApi, Block> BasicPool<PoolApi, Block>
 	pub fn pool(&self) -> &Arc<sc_transaction_graph::Pool<PoolApi>> {
 		&self.pool
 	}
+
+	/// Records that the pool is in use, postponing idle compaction.
+	fn touch(&self) {
+		*self.last_activity.lock() = Instant::now();
+	}
 }
 
 impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
@@ -252,6 +262,7 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 		let at = *at;
 
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc_by(xts.len() as u64));
+		self.touch();
 
 		async move { pool.submit_at(&at, source, xts).await }.boxed()
 	}
@@ -266,6 +277,7 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 		let at = *at;
 
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
+		self.touch();
 
 		async move { pool.submit_one(&at, source, xt).await }.boxed()
 	}
@@ -280,6 +292,7 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 		let pool = self.pool.clone();
 
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
+		self.touch();
 
 		async move {
 			pool.submit_and_watch(&at, source, xt)
@@ -314,7 +327,12 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 		self.pool.validated_pool().ready_by_hash(hash)
 	}
 
+	fn futures(&self) -> Vec<(Arc<Self::InPoolTransaction>, Vec<TransactionTag>)> {
+		self.pool.validated_pool().futures()
+	}
+
 	fn ready_at(&self, at: NumberFor<Self::Block>) -> PolledIterator<PoolApi> {
+		self.touch();
 		if self.ready_poll.lock().updated_at() >= at {
 			log::trace!(target: "txpool", "Transaction pool already processed block  #{}", at);
 			let iterator: ReadyIteratorFor<PoolApi> = Box::new(self.pool.validated_pool().ready());
@@ -534,6 +552,13 @@ impl<PoolApi, Block> MaintainedTransactionPool for BasicPool<PoolApi, Block>
 		PoolApi: 'static + ChainApi<Block=Block>,
 {
 	fn maintain(&self, event: ChainEvent<Self::Block>) -> Pin<Box<dyn Future<Output=()> + Send>> {
+		self.touch();
+
+		let banned = self.pool.validated_pool().remove_banned();
+		if !banned.is_empty() {
+			log::debug!(target: "txpool", "Evicted {} banned transactions", banned.len());
+		}
+
 		match event {
 			ChainEvent::NewBlock { hash, tree_route, is_new_best, .. } => {
 				let pool = self.pool.clone();
@@ -681,26 +706,69 @@ impl<PoolApi, Block> MaintainedTransactionPool for BasicPool<PoolApi, Block>
 					revalidation_strategy.lock().clear();
 				}.boxed()
 			}
-			ChainEvent::Finalized { hash } => {
+			ChainEvent::Finalized { hash, tree_route } => {
 				let pool = self.pool.clone();
 				async move {
-					if let Err(e) = pool.validated_pool().on_block_finalized(hash).await {
-						log::warn!(
-							target: "txpool",
-							"Error [{}] occurred while attempting to notify watchers of finalization {}",
-							e, hash
-						)
+					for hash in tree_route.iter().chain(std::iter::once(&hash)) {
+						if let Err(e) = pool.validated_pool().on_block_finalized(*hash).await {
+							log::warn!(
+								target: "txpool",
+								"Error [{}] occurred while attempting to notify watchers of finalization {}",
+								e, hash
+							)
+						}
 					}
 				}.boxed()
 			}
 		}
 	}
+
+	fn compact_if_idle(&self, idle_for: Duration, stale_after: Duration) -> usize {
+		if self.last_activity.lock().elapsed() < idle_for {
+			return 0;
+		}
+
+		let removed = self.pool.validated_pool().clear_stale_futures(stale_after);
+		if !removed.is_empty() {
+			log::debug!(target: "txpool", "Compacted {} stale future transactions", removed.len());
+		}
+		removed.len()
+	}
+
+	fn ban_list(&self) -> Option<BanList<TxHash<Self>>> {
+		Some(self.pool.validated_pool().ban_list().clone())
+	}
+}
+
+/// Periodically compact the transaction pool while it is idle.
+///
+/// Every `interval`, future transactions that have been waiting for at least `stale_after`
+/// are dropped, unless the pool has been used since the previous tick.
+pub async fn compaction_future<Pool: MaintainedTransactionPool>(
+	txpool: Arc<Pool>,
+	interval: Duration,
+	stale_after: Duration,
+) {
+	use intervalier::IntoStream;
+
+	intervalier::Interval::new(interval)
+		.into_stream()
+		.for_each(|_| {
+			txpool.compact_if_idle(interval, stale_after);
+			future::ready(())
+		})
+		.await
 }
 
 /// Inform the transaction pool about imported and finalized blocks.
+///
+/// If `coalesce_finality` is `true`, finality notifications that are ready at the same time
+/// are merged into a single maintenance call covering all of the finalized blocks. This avoids
+/// maintaining the pool once per block while the node is catching up on finality.
 pub async fn notification_future<Client, Pool, Block>(
 	client: Arc<Client>,
-	txpool: Arc<Pool>
+	txpool: Arc<Pool>,
+	coalesce_finality: bool,
 )
 	where
 		Block: BlockT,
@@ -708,11 +776,46 @@ pub async fn notification_future<Client, Pool, Block>(
 		Pool: MaintainedTransactionPool<Block=Block>,
 {
 	let import_stream = client.import_notification_stream().map(Into::into).fuse();
-	let finality_stream = client.finality_notification_stream()
-		.map(Into::into)
-		.fuse();
+	let finality_stream = if coalesce_finality {
+		coalesce_finality_notifications(client.finality_notification_stream()).boxed()
+	} else {
+		client.finality_notification_stream().map(Into::into).boxed()
+	}.fuse();
 
 	futures::stream::select(import_stream, finality_stream)
 		.for_each(|evt| txpool.maintain(evt))
 		.await
 }
+
+/// Turns a stream of finality notifications into a stream of `ChainEvent::Finalized`, merging
+/// together all the notifications that happen to be ready when the stream is polled.
+///
+/// The last finalized block of a batch is reported as `hash`, while the blocks finalized
+/// before it are reported, in order, as the `tree_route` of the event.
+fn coalesce_finality_notifications<Block: BlockT>(
+	mut finality_stream: impl Stream<Item = FinalityNotification<Block>> + Unpin + Send + 'static,
+) -> impl Stream<Item = ChainEvent<Block>> + Send + 'static {
+	futures::stream::poll_fn(move |cx| {
+		let mut finalized = Vec::new();
+		let mut terminated = false;
+		loop {
+			match Pin::new(&mut finality_stream).poll_next(cx) {
+				Poll::Ready(Some(notification)) => finalized.push(notification.hash),
+				Poll::Ready(None) => {
+					terminated = true;
+					break;
+				},
+				Poll::Pending => break,
+			}
+		}
+
+		match finalized.pop() {
+			Some(hash) => Poll::Ready(Some(ChainEvent::Finalized {
+				hash,
+				tree_route: finalized.into(),
+			})),
+			None if terminated => Poll::Ready(None),
+			None => Poll::Pending,
+		}
+	})
+}
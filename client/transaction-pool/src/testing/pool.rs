@@ -17,7 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::*;
-use sp_transaction_pool::TransactionStatus;
+use sp_transaction_pool::{TransactionStatus, SenderKey};
 use futures::executor::{block_on, block_on_stream};
 use txpool::{self, Pool};
 use sp_runtime::{
@@ -433,7 +433,7 @@ fn should_push_watchers_during_maintaince() {
 	let header_hash = header.hash();
 	block_on(pool.maintain(block_event(header)));
 
-	let event = ChainEvent::Finalized { hash: header_hash.clone() };
+	let event = ChainEvent::Finalized { hash: header_hash.clone(), tree_route: Arc::new([]) };
 	block_on(pool.maintain(event));
 
 	// then
@@ -474,6 +474,30 @@ fn can_track_heap_size() {
 	assert!(parity_util_mem::malloc_size(&pool) > 3000);
 }
 
+#[test]
+fn stale_future_transactions_are_dropped_on_idle_compaction() {
+	let (pool, _guard, _notifier) = maintained_pool();
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 209))).expect("1. Imported");
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 211))).expect("2. Imported");
+	assert_eq!(pool.status().ready, 1);
+	assert_eq!(pool.status().future, 1);
+
+	std::thread::sleep(std::time::Duration::from_millis(20));
+
+	// pool has just been used, so nothing is compacted
+	assert_eq!(pool.compact_if_idle(Duration::from_secs(60), Duration::from_millis(10)), 0);
+	assert_eq!(pool.status().future, 1);
+
+	// once idle, the stale future transaction goes away, but the ready one stays
+	assert_eq!(pool.compact_if_idle(Duration::from_millis(0), Duration::from_millis(10)), 1);
+	assert_eq!(pool.status().ready, 1);
+	assert_eq!(pool.status().future, 0);
+
+	// it's dropped, not banned, so it can be submitted again
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 211))).expect("3. Imported");
+	assert_eq!(pool.status().future, 1);
+}
+
 #[test]
 fn finalization() {
 	let xt = uxt(Alice, 209);
@@ -494,7 +518,7 @@ fn finalization() {
 	};
 	block_on(pool.maintain(event));
 
-	let event = ChainEvent::Finalized { hash: header.hash() };
+	let event = ChainEvent::Finalized { hash: header.hash(), tree_route: Arc::new([]) };
 	block_on(pool.maintain(event));
 
 	let mut stream = futures::executor::block_on_stream(watcher);
@@ -504,6 +528,56 @@ fn finalization() {
 	assert_eq!(stream.next(), None);
 }
 
+#[test]
+fn coalesced_finalization_should_finalize_whole_route() {
+	let api = TestApi::empty();
+	api.push_block(1, vec![]);
+	let (pool, _background, _) = BasicPool::new_test(api.into());
+
+	let (finality_sink, finality_stream) = futures::channel::mpsc::unbounded();
+	let mut events = coalesce_finality_notifications(finality_stream);
+
+	let mut watchers = vec![];
+	for (number, who) in (2..5).zip(vec![Alice, Bob, Charlie]) {
+		pool.api.increment_nonce(who.into());
+		let xt = uxt(who, 1);
+		let watcher = block_on(
+			pool.submit_and_watch(&BlockId::number(number - 1), SOURCE, xt.clone())
+		).expect("1. Imported");
+		let header = pool.api.push_block(number, vec![xt]);
+		block_on(pool.maintain(block_event(header.clone())));
+		watchers.push((watcher, header.hash()));
+
+		finality_sink.unbounded_send(sc_client_api::FinalityNotification {
+			hash: header.hash(),
+			header,
+		}).unwrap();
+	}
+	assert_eq!(pool.status().ready, 0);
+
+	// all three notifications are reported as a single event.
+	let event = block_on(events.next()).expect("Finality notifications were sent");
+	match event {
+		ChainEvent::Finalized { hash, ref tree_route } => {
+			assert_eq!(hash, watchers[2].1);
+			assert_eq!(&tree_route[..], &[watchers[0].1, watchers[1].1][..]);
+		},
+		_ => panic!("Expected a finality event"),
+	}
+	assert!(events.next().now_or_never().is_none());
+
+	block_on(pool.maintain(event));
+
+	// every block of the route is finalized, exactly as if the events were processed one by one.
+	for (watcher, hash) in watchers {
+		let mut stream = futures::executor::block_on_stream(watcher);
+		assert_eq!(stream.next(), Some(TransactionStatus::Ready));
+		assert_eq!(stream.next(), Some(TransactionStatus::InBlock(hash)));
+		assert_eq!(stream.next(), Some(TransactionStatus::Finalized(hash)));
+		assert_eq!(stream.next(), None);
+	}
+}
+
 #[test]
 fn fork_aware_finalization() {
 	let api = TestApi::empty();
@@ -547,7 +621,7 @@ fn fork_aware_finalization() {
 		b1 = header.hash();
 		block_on(pool.maintain(event));
 		assert_eq!(pool.status().ready, 0);
-		let event = ChainEvent::Finalized { hash: b1 };
+		let event = ChainEvent::Finalized { hash: b1, tree_route: Arc::new([]) };
 		block_on(pool.maintain(event));
 	}
 
@@ -601,7 +675,7 @@ fn fork_aware_finalization() {
 		block_on(pool.maintain(event));
 		assert_eq!(pool.status().ready, 2);
 
-		let event = ChainEvent::Finalized { hash: header.hash() };
+		let event = ChainEvent::Finalized { hash: header.hash(), tree_route: Arc::new([]) };
 		block_on(pool.maintain(event));
 	}
 
@@ -624,7 +698,7 @@ fn fork_aware_finalization() {
 		d1 = header.hash();
 		block_on(pool.maintain(event));
 		assert_eq!(pool.status().ready, 2);
-		let event = ChainEvent::Finalized { hash: d1 };
+		let event = ChainEvent::Finalized { hash: d1, tree_route: Arc::new([]) };
 		block_on(pool.maintain(event));
 	}
 
@@ -642,7 +716,7 @@ fn fork_aware_finalization() {
 		};
 		block_on(pool.maintain(event));
 		assert_eq!(pool.status().ready, 0);
-		block_on(pool.maintain(ChainEvent::Finalized { hash: e1 }));
+		block_on(pool.maintain(ChainEvent::Finalized { hash: e1, tree_route: Arc::new([]) }));
 	}
 
 
@@ -720,7 +794,7 @@ fn prune_and_retract_tx_at_same_time() {
 		block_on(pool.maintain(event));
 		assert_eq!(pool.status().ready, 0);
 
-		let event = ChainEvent::Finalized { hash: header.hash() };
+		let event = ChainEvent::Finalized { hash: header.hash(), tree_route: Arc::new([]) };
 		block_on(pool.maintain(event));
 
 		header.hash()
@@ -1067,6 +1141,55 @@ fn import_notification_to_pool_maintain_works() {
 	assert_eq!(pool.status().ready, 0);
 }
 
+#[test]
+fn banned_sender_is_rejected_and_evicted_on_maintenance() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+
+	let pool = Arc::new(
+		BasicPool::new_test(Arc::new(FullChainApi::new(client.clone(), None))).0
+	);
+
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 0))).expect("1. Imported");
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Bob, 0))).expect("2. Imported");
+	assert_eq!(pool.status().ready, 2);
+
+	let ban_list = pool.ban_list().expect("Basic pool has a ban list");
+	let sender = |who, nonce_len| SenderKey::new(uxt(who, 0).transfer().from.encode(), nonce_len);
+	assert!(SenderKey::new(Vec::new(), 8).is_none());
+
+	// the test runtime tags transactions with the sender and its `u64` nonce, so a key with
+	// another nonce length doesn't match.
+	ban_list.ban_sender(sender(Bob, 4).unwrap());
+	assert!(block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Bob, 1))).is_ok());
+	assert!(ban_list.unban_sender(&sender(Bob, 4).unwrap()));
+
+	ban_list.ban_sender(sender(Alice, 8).unwrap());
+
+	// new transactions of the banned sender are rejected right away
+	assert_matches::assert_matches!(
+		block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 1))),
+		Err(error::Error::Pool(sp_transaction_pool::error::Error::Banned))
+	);
+
+	let xt = uxt(Bob, 2);
+	ban_list.ban_hash(pool.hash_of(&xt));
+	assert_matches::assert_matches!(
+		block_on(pool.submit_one(&BlockId::number(0), SOURCE, xt)),
+		Err(error::Error::Pool(sp_transaction_pool::error::Error::Banned))
+	);
+	assert_eq!(pool.status().ready, 3);
+
+	// the ones already in the pool are evicted on the next maintenance
+	let mut import_stream = block_on_stream(client.import_notification_stream());
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, block).unwrap();
+	let evt = import_stream.next().expect("Importing a block leads to an event");
+	block_on(pool.maintain(evt.into()));
+
+	let ready: Vec<_> = pool.ready().map(|tx| tx.data.transfer().from).collect();
+	assert_eq!(ready, vec![uxt(Bob, 0).transfer().from; 2]);
+}
+
 // When we prune transactions, we need to make sure that we remove
 #[test]
 fn pruning_a_transaction_should_remove_it_from_best_transaction() {
@@ -57,6 +57,10 @@ pub enum Error {
 	#[display(fmt = "UnknownBlock: {}", _0)]
 	#[from(ignore)]
 	UnknownBlock(String),
+	/// The state of a block is not available anymore, e.g. because it has been pruned.
+	#[display(fmt = "State of block {} is not available", _0)]
+	#[from(ignore)]
+	StateUnavailable(String),
 	/// The `apply_extrinsic` is not valid due to the given `TransactionValidityError`.
 	#[display(fmt = "{:?}", _0)]
 	ApplyExtrinsicFailed(ApplyExtrinsicFailed),
@@ -76,6 +80,14 @@ pub enum Error {
 	/// Genesis config is invalid.
 	#[display(fmt = "Genesis config provided is invalid")]
 	GenesisInvalid,
+	/// The genesis block of the database isn't the one of the chain specification.
+	#[display(fmt = "Database genesis {} doesn't match the chain specification genesis {}", _0, _1)]
+	#[from(ignore)]
+	GenesisMismatch(String, String),
+	/// The runtime code of the genesis block doesn't have the expected hash.
+	#[display(fmt = "Genesis runtime code hash {} doesn't match the expected hash {}", _0, _1)]
+	#[from(ignore)]
+	GenesisCodeMismatch(String, String),
 	/// Error decoding header justification.
 	#[display(fmt = "error decoding justification for header")]
 	JustificationDecode,
@@ -96,4 +96,10 @@ impl ProvideInherentData for InherentDataProvider {
 
 		sp_inherents::Error::decode(&mut &error[..]).map(|e| e.into_string()).ok()
 	}
+
+	fn data_to_string(&self, data: &[u8]) -> Option<String> {
+		use codec::Decode;
+
+		InherentType::decode(&mut &data[..]).map(|slot_num| format!("slot {}", slot_num)).ok()
+	}
 }
@@ -89,4 +89,8 @@ impl ProvideInherentData for InherentDataProvider {
 	fn error_to_string(&self, error: &[u8]) -> Option<String> {
 		Error::decode(&mut &error[..]).map(|e| e.into_string()).ok()
 	}
+
+	fn data_to_string(&self, data: &[u8]) -> Option<String> {
+		InherentType::decode(&mut &data[..]).map(|slot_number| format!("slot {}", slot_number)).ok()
+	}
 }
@@ -41,6 +41,10 @@ pub enum ImportResult {
 	UnknownParent,
 	/// Parent state is missing.
 	MissingState,
+	/// Block is on a fork that reverts an already finalized block.
+	ConflictsWithFinality,
+	/// The runtime panicked while executing the block.
+	RuntimePanicked,
 }
 
 /// Auxiliary data associated with an imported block result.
@@ -125,6 +125,14 @@ pub trait ImportQueue<B: BlockT>: Send {
 	/// task and notify later when more actions are ready to be polled. To continue the comparison,
 	/// it is as if this method always returned `Poll::Pending`.
 	fn poll_actions(&mut self, cx: &mut futures::task::Context, link: &mut dyn Link<B>);
+
+	/// Returns `true` if the queue can't take more blocks without exceeding its memory budget.
+	///
+	/// Blocks passed to `import_blocks` are still accepted, but shouldn't be requested until the
+	/// queue has room again.
+	fn is_saturated(&self) -> bool {
+		false
+	}
 }
 
 /// Hooks that the verification queue can use to influence the synchronization
@@ -174,6 +182,10 @@ pub enum BlockImportError {
 	VerificationFailed(Option<Origin>, String),
 	/// Block is known to be Bad
 	BadBlock(Option<Origin>),
+	/// Block conflicts with an already finalized block.
+	ConflictsWithFinality(Option<Origin>),
+	/// The runtime panicked while executing the block.
+	RuntimePanicked(Option<Origin>),
 	/// Parent state is missing.
 	MissingState,
 	/// Block has an unknown parent
@@ -241,6 +253,14 @@ pub(crate) fn import_single_block_metered<B: BlockT, V: Verifier<B>, Transaction
 				debug!(target: "sync", "Peer gave us a bad block {}: {:?}", number, hash);
 				Err(BlockImportError::BadBlock(peer.clone()))
 			},
+			Ok(ImportResult::ConflictsWithFinality) => {
+				debug!(target: "sync", "Peer gave us a block conflicting with finality {}: {:?}", number, hash);
+				Err(BlockImportError::ConflictsWithFinality(peer.clone()))
+			},
+			Ok(ImportResult::RuntimePanicked) => {
+				debug!(target: "sync", "Peer gave us a block the runtime panicked on {}: {:?}", number, hash);
+				Err(BlockImportError::RuntimePanicked(peer.clone()))
+			},
 			Err(e) => {
 				debug!(target: "sync", "Error importing block {}: {:?}: {:?}", number, hash, e);
 				Err(BlockImportError::Other(e))
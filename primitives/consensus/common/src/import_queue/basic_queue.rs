@@ -15,7 +15,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{mem, pin::Pin, time::Duration, marker::PhantomData};
+use std::{
+	mem, pin::Pin, time::Duration, marker::PhantomData, collections::VecDeque,
+	sync::{Arc, atomic::{AtomicUsize, Ordering}},
+};
+use codec::Encode;
 use futures::{prelude::*, task::Context, task::Poll};
 use futures_timer::Delay;
 use sp_runtime::{Justification, traits::{Block as BlockT, Header as HeaderT, NumberFor}};
@@ -40,6 +44,12 @@ pub struct BasicQueue<B: BlockT, Transaction> {
 	sender: TracingUnboundedSender<ToWorkerMsg<B>>,
 	/// Results coming from the worker task.
 	result_port: BufferedLinkReceiver<B>,
+	/// Memory used by the blocks sent to the worker task and not imported yet, in bytes.
+	buffered_memory: Arc<AtomicUsize>,
+	/// Maximum of `buffered_memory`, if any.
+	mem_budget: Option<usize>,
+	/// Blocks waiting for room in the memory budget, along with their size, oldest first.
+	pending_blocks: VecDeque<(BlockOrigin, IncomingBlock<B>, usize)>,
 	_phantom: PhantomData<Transaction>,
 }
 
@@ -70,12 +80,14 @@ impl<B: BlockT, Transaction: Send + 'static> BasicQueue<B, Transaction> {
 			.map_err(|err| { log::warn!("Failed to register Prometheus metrics: {}", err); })
 			.ok()
 		);
+		let buffered_memory = Arc::new(AtomicUsize::new(0));
 		let (future, worker_sender) = BlockImportWorker::new(
 			result_sender,
 			verifier,
 			block_import,
 			justification_import,
 			finality_proof_import,
+			buffered_memory.clone(),
 			metrics,
 		);
 
@@ -84,9 +96,61 @@ impl<B: BlockT, Transaction: Send + 'static> BasicQueue<B, Transaction> {
 		Self {
 			sender: worker_sender,
 			result_port,
+			buffered_memory,
+			mem_budget: None,
+			pending_blocks: VecDeque::new(),
 			_phantom: PhantomData,
 		}
 	}
+
+	/// Limits the memory used by the blocks waiting to be verified and imported to `budget` bytes.
+	///
+	/// Blocks that don't fit are kept back until enough of the previous ones are imported, and
+	/// the queue reports itself as saturated in the meantime. A block larger than the budget is
+	/// still imported, alone.
+	pub fn with_mem_budget(mut self, budget: Option<usize>) -> Self {
+		self.mem_budget = budget;
+		self
+	}
+
+	/// Returns the memory used by the blocks waiting to be verified and imported, in bytes,
+	/// including the ones kept back by the memory budget.
+	pub fn buffered_memory(&self) -> usize {
+		let pending = self.pending_blocks.iter().map(|(_, _, size)| size).sum::<usize>();
+		self.buffered_memory.load(Ordering::Acquire) + pending
+	}
+
+	/// Sends to the worker as many pending blocks as the memory budget allows.
+	fn send_pending_blocks(&mut self) {
+		let budget = self.mem_budget.unwrap_or(usize::max_value());
+		let mut buffered = self.buffered_memory.load(Ordering::Acquire);
+
+		while let Some(&(origin, _, _)) = self.pending_blocks.front() {
+			let mut batch = Vec::new();
+			let mut batch_size = 0;
+			while let Some((block_origin, _, size)) = self.pending_blocks.front() {
+				// Nothing buffered means nothing will free memory later on, so the block goes
+				// through even if it exceeds the budget on its own.
+				let fits = buffered == 0 || buffered.saturating_add(*size) <= budget;
+				if *block_origin != origin || !fits {
+					break;
+				}
+				let (_, block, size) = self.pending_blocks.pop_front()
+					.expect("front() returned Some; qed");
+				buffered = buffered.saturating_add(size);
+				batch_size += size;
+				batch.push(block);
+			}
+
+			if batch.is_empty() {
+				break;
+			}
+
+			trace!(target: "sync", "Scheduling {} blocks for import", batch.len());
+			self.buffered_memory.fetch_add(batch_size, Ordering::AcqRel);
+			let _ = self.sender.unbounded_send(ToWorkerMsg::ImportBlocks(origin, batch));
+		}
+	}
 }
 
 impl<B: BlockT, Transaction: Send> ImportQueue<B> for BasicQueue<B, Transaction> {
@@ -95,8 +159,11 @@ impl<B: BlockT, Transaction: Send> ImportQueue<B> for BasicQueue<B, Transaction>
 			return;
 		}
 
-		trace!(target: "sync", "Scheduling {} blocks for import", blocks.len());
-		let _ = self.sender.unbounded_send(ToWorkerMsg::ImportBlocks(origin, blocks));
+		self.pending_blocks.extend(blocks.into_iter().map(|block| {
+			let size = incoming_block_size(&block);
+			(origin, block, size)
+		}));
+		self.send_pending_blocks();
 	}
 
 	fn import_justification(
@@ -128,9 +195,21 @@ impl<B: BlockT, Transaction: Send> ImportQueue<B> for BasicQueue<B, Transaction>
 
 	fn poll_actions(&mut self, cx: &mut Context, link: &mut dyn Link<B>) {
 		self.result_port.poll_actions(cx, link);
+		self.send_pending_blocks();
+	}
+
+	fn is_saturated(&self) -> bool {
+		!self.pending_blocks.is_empty()
 	}
 }
 
+/// Returns the memory used by `block`, approximated by its encoded size.
+fn incoming_block_size<B: BlockT>(block: &IncomingBlock<B>) -> usize {
+	block.header.as_ref().map_or(0, |h| h.encoded_size())
+		+ block.body.as_ref().map_or(0, |b| b.iter().map(|x| x.encoded_size()).sum())
+		+ block.justification.as_ref().map_or(0, |j| j.len())
+}
+
 /// Message destinated to the background worker.
 #[derive(Debug)]
 enum ToWorkerMsg<B: BlockT> {
@@ -144,6 +223,7 @@ struct BlockImportWorker<B: BlockT, Transaction> {
 	justification_import: Option<BoxJustificationImport<B>>,
 	finality_proof_import: Option<BoxFinalityProofImport<B>>,
 	delay_between_blocks: Duration,
+	buffered_memory: Arc<AtomicUsize>,
 	metrics: Option<Metrics>,
 	_phantom: PhantomData<Transaction>,
 }
@@ -155,6 +235,7 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 		block_import: BoxBlockImport<B, Transaction>,
 		justification_import: Option<BoxJustificationImport<B>>,
 		finality_proof_import: Option<BoxFinalityProofImport<B>>,
+		buffered_memory: Arc<AtomicUsize>,
 		metrics: Option<Metrics>,
 	) -> (impl Future<Output = ()> + Send, TracingUnboundedSender<ToWorkerMsg<B>>) {
 		let (sender, mut port) = tracing_unbounded("mpsc_block_import_worker");
@@ -164,6 +245,7 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 			justification_import,
 			finality_proof_import,
 			delay_between_blocks: Duration::new(0, 0),
+			buffered_memory,
 			metrics,
 			_phantom: PhantomData,
 		};
@@ -257,7 +339,15 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 		let mut result_sender = self.result_sender.clone();
 		let metrics = self.metrics.clone();
 
-		import_many_blocks(block_import, origin, blocks, verifier, self.delay_between_blocks, metrics)
+		import_many_blocks(
+			block_import,
+			origin,
+			blocks,
+			verifier,
+			self.delay_between_blocks,
+			self.buffered_memory.clone(),
+			metrics,
+		)
 			.then(move |(imported, count, results, block_import, verifier)| {
 				result_sender.blocks_processed(imported, count, results);
 				future::ready((block_import, verifier))
@@ -322,12 +412,15 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 ///
 /// The returned `Future` yields at every imported block, which makes the execution more
 /// fine-grained and making it possible to interrupt the process.
+///
+/// The size of each block is subtracted from `buffered_memory` once it's been processed.
 fn import_many_blocks<B: BlockT, V: Verifier<B>, Transaction>(
 	import_handle: BoxBlockImport<B, Transaction>,
 	blocks_origin: BlockOrigin,
 	blocks: Vec<IncomingBlock<B>>,
 	verifier: V,
 	delay_between_blocks: Duration,
+	buffered_memory: Arc<AtomicUsize>,
 	metrics: Option<Metrics>,
 ) -> impl Future<
 	Output = (
@@ -396,6 +489,7 @@ fn import_many_blocks<B: BlockT, V: Verifier<B>, Transaction>(
 
 		let block_number = block.header.as_ref().map(|h| h.number().clone());
 		let block_hash = block.hash;
+		let block_size = incoming_block_size(&block);
 		let import_result = if has_error {
 			Err(BlockImportError::Cancelled)
 		} else {
@@ -421,6 +515,7 @@ fn import_many_blocks<B: BlockT, V: Verifier<B>, Transaction>(
 		}
 
 		results.push((import_result, block_hash));
+		buffered_memory.fetch_sub(block_size, Ordering::AcqRel);
 
 		// Notifies the current task again so that we re-execute this closure again for the next
 		// block.
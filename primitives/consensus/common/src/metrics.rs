@@ -61,6 +61,8 @@ impl Metrics {
 			Err(BlockImportError::IncompleteHeader(_)) => "incomplete_header",
 			Err(BlockImportError::VerificationFailed(_,_)) => "verification_failed",
 			Err(BlockImportError::BadBlock(_)) => "bad_block",
+			Err(BlockImportError::ConflictsWithFinality(_)) => "conflicts_with_finality",
+			Err(BlockImportError::RuntimePanicked(_)) => "runtime_panicked",
 			Err(BlockImportError::MissingState) => "missing_state",
 			Err(BlockImportError::UnknownParent) => "unknown_parent",
 			Err(BlockImportError::Cancelled) => "cancelled",
@@ -256,11 +256,50 @@ impl PartialEq for CheckInherentsResult {
 	}
 }
 
+/// What to do with a block whose inherent failed the check, as decided by an
+/// [`InherentCheckFailureHook`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckFailureVerdict {
+	/// Keep the failure, the block is rejected.
+	Reject,
+	/// Ignore the failure, as if the inherent was valid.
+	///
+	/// Only honoured when overriding failures was explicitly allowed, see
+	/// [`InherentDataProviders::set_check_failure_hook`].
+	Accept,
+}
+
+/// Something that is told about the inherents failing the check while importing a block, e.g. to
+/// log or count them.
+#[cfg(feature = "std")]
+pub trait InherentCheckFailureHook: Send + Sync {
+	/// Called with the identifier of the inherent that failed the check, and the error as
+	/// converted by [`InherentDataProviders::error_to_string`].
+	fn on_check_failure(&self, identifier: &InherentIdentifier, error: &str) -> CheckFailureVerdict;
+}
+
+#[cfg(feature = "std")]
+impl<F> InherentCheckFailureHook for F
+	where F: Fn(&InherentIdentifier, &str) -> CheckFailureVerdict + Send + Sync
+{
+	fn on_check_failure(&self, identifier: &InherentIdentifier, error: &str) -> CheckFailureVerdict {
+		(self)(identifier, error)
+	}
+}
+
+#[cfg(feature = "std")]
+struct CheckFailureHook {
+	hook: Box<dyn InherentCheckFailureHook>,
+	allow_override: bool,
+}
+
 /// All `InherentData` providers.
 #[cfg(feature = "std")]
 #[derive(Clone, Default)]
 pub struct InherentDataProviders {
 	providers: Arc<RwLock<Vec<Box<dyn ProvideInherentData + Send + Sync>>>>,
+	check_failure_hook: Arc<RwLock<Option<CheckFailureHook>>>,
 }
 
 #[cfg(feature = "std")]
@@ -311,6 +350,28 @@ impl InherentDataProviders {
 		Ok(data)
 	}
 
+	/// Runs every provider, in registration order, reporting what each of them provided.
+	///
+	/// Unlike `create_inherent_data`, a failing provider doesn't stop the following ones from
+	/// being run.
+	pub fn inspect_inherent_data(&self) -> Vec<(InherentIdentifier, InspectedInherent)> {
+		let mut data = InherentData::new();
+		self.providers.read().iter().map(|p| {
+			let identifier = *p.inherent_identifier();
+			let inspected = match p.provide_inherent_data(&mut data) {
+				Ok(()) => match data.data.get(&identifier) {
+					Some(encoded) => InspectedInherent::Provided {
+						data: encoded.clone(),
+						decoded: p.data_to_string(encoded),
+					},
+					None => InspectedInherent::Failed("No inherent data was provided".into()),
+				},
+				Err(e) => InspectedInherent::Failed(e.into_string()),
+			};
+			(identifier, inspected)
+		}).collect()
+	}
+
 	/// Converts a given encoded error into a `String`.
 	///
 	/// Useful if the implementation encounters an error for an identifier it does not know.
@@ -334,6 +395,52 @@ impl InherentDataProviders {
 			)
 		}
 	}
+
+	/// Set the hook that is called for every inherent failing the check while importing a block,
+	/// replacing the previous one.
+	///
+	/// The hook can only make the block be accepted anyway if `allow_override` is `true`. This is
+	/// unsafe outside of test networks, since blocks with invalid inherents are then imported.
+	pub fn set_check_failure_hook(
+		&self,
+		hook: impl InherentCheckFailureHook + 'static,
+		allow_override: bool,
+	) {
+		*self.check_failure_hook.write() = Some(CheckFailureHook {
+			hook: Box::new(hook),
+			allow_override,
+		});
+	}
+
+	/// Reports an inherent that failed the check to the hook, if any.
+	///
+	/// Returns whether the failure is overridden, i.e. the inherent must be considered valid.
+	pub fn is_check_failure_overridden(&self, identifier: &InherentIdentifier, error: &[u8]) -> bool {
+		let check_failure_hook = self.check_failure_hook.read();
+		let check_failure_hook = match check_failure_hook.as_ref() {
+			Some(check_failure_hook) => check_failure_hook,
+			None => return false,
+		};
+
+		let error = self.error_to_string(identifier, error);
+		let verdict = check_failure_hook.hook.on_check_failure(identifier, &error);
+		verdict == CheckFailureVerdict::Accept && check_failure_hook.allow_override
+	}
+}
+
+/// What a provider returned for its inherent, see `InherentDataProviders::inspect_inherent_data`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InspectedInherent {
+	/// The provider put data for its inherent.
+	Provided {
+		/// The encoded data.
+		data: Vec<u8>,
+		/// The data in a human readable form, if the provider can decode it.
+		decoded: Option<String>,
+	},
+	/// The provider failed with the given error.
+	Failed(String),
 }
 
 /// Something that provides inherent data.
@@ -357,6 +464,13 @@ pub trait ProvideInherentData {
 	///
 	/// If the given error could not be decoded, `None` should be returned.
 	fn error_to_string(&self, error: &[u8]) -> Option<String>;
+
+	/// Convert the given encoded inherent data to a human readable string.
+	///
+	/// If the data could not be decoded, `None` should be returned.
+	fn data_to_string(&self, _data: &[u8]) -> Option<String> {
+		None
+	}
 }
 
 /// A fallback function, if the decoding of an error fails.
@@ -552,6 +666,84 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn inspect_inherent_data_reports_every_provider() {
+		struct FailingInherentDataProvider;
+
+		impl ProvideInherentData for FailingInherentDataProvider {
+			fn inherent_identifier(&self) -> &'static InherentIdentifier {
+				&TEST_INHERENT_1
+			}
+
+			fn provide_inherent_data(&self, _: &mut InherentData) -> Result<(), Error> {
+				Err("No data!".into())
+			}
+
+			fn error_to_string(&self, _: &[u8]) -> Option<String> {
+				None
+			}
+		}
+
+		let providers = InherentDataProviders::new();
+		providers.register_provider(FailingInherentDataProvider).unwrap();
+		providers.register_provider(TestInherentDataProvider::new()).unwrap();
+
+		assert_eq!(
+			providers.inspect_inherent_data(),
+			vec![
+				(TEST_INHERENT_1, InspectedInherent::Failed("No data!".into())),
+				(TEST_INHERENT_0, InspectedInherent::Provided { data: 42u32.encode(), decoded: None }),
+			],
+		);
+	}
+
+	#[test]
+	fn check_failure_hook_is_invoked_for_a_bad_timestamp() {
+		const TIMESTAMP_INHERENT: InherentIdentifier = *b"timstap0";
+
+		struct TimestampInherentDataProvider;
+
+		impl ProvideInherentData for TimestampInherentDataProvider {
+			fn inherent_identifier(&self) -> &'static InherentIdentifier {
+				&TIMESTAMP_INHERENT
+			}
+
+			fn provide_inherent_data(&self, data: &mut InherentData) -> Result<(), Error> {
+				data.put_data(TIMESTAMP_INHERENT, &42u64)
+			}
+
+			fn error_to_string(&self, error: &[u8]) -> Option<String> {
+				u64::decode(&mut &error[..]).ok().map(|t| format!("Timestamp {} is too far in the future", t))
+			}
+		}
+
+		let mut result = CheckInherentsResult::new();
+		result.put_error(TIMESTAMP_INHERENT, &MakeFatalError(u64::max_value())).unwrap();
+		let (identifier, error) = result.into_errors().next().unwrap();
+
+		let providers = InherentDataProviders::new();
+		providers.register_provider(TimestampInherentDataProvider).unwrap();
+		assert!(!providers.is_check_failure_overridden(&identifier, &error));
+
+		let failures = Arc::new(RwLock::new(Vec::new()));
+		let hook = |failures: Arc<RwLock<Vec<_>>>| move |identifier: &InherentIdentifier, error: &str| {
+			failures.write().push((*identifier, error.to_string()));
+			CheckFailureVerdict::Accept
+		};
+
+		// The failure is reported, but can't be overridden.
+		providers.set_check_failure_hook(hook(failures.clone()), false);
+		assert!(!providers.is_check_failure_overridden(&identifier, &error));
+		assert_eq!(
+			*failures.read(),
+			vec![(TIMESTAMP_INHERENT, format!("Timestamp {} is too far in the future", u64::max_value()))],
+		);
+
+		providers.set_check_failure_hook(hook(failures.clone()), true);
+		assert!(providers.is_check_failure_overridden(&identifier, &error));
+		assert_eq!(failures.read().len(), 2);
+	}
+
 	#[test]
 	fn check_inherents_result_encodes_and_decodes() {
 		let mut result = CheckInherentsResult::new();
@@ -17,14 +17,23 @@
 
 /// State Machine Errors
 
-use std::fmt;
+use std::{any::Any, fmt};
 
 /// State Machine Error bound.
 ///
 /// This should reflect Wasm error type bound for future compatibility.
-pub trait Error: 'static + fmt::Debug + fmt::Display + Send {}
+pub trait Error: 'static + fmt::Debug + fmt::Display + Send {
+	/// Returns the error as `Any`, to downcast it to its concrete type.
+	///
+	/// On a `Box<dyn Error>`, call it on the boxed error, e.g. `(*error).as_any()`.
+	fn as_any(&self) -> &dyn Any;
+}
 
-impl<T: 'static + fmt::Debug + fmt::Display + Send> Error for T {}
+impl<T: 'static + fmt::Debug + fmt::Display + Send> Error for T {
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+}
 
 /// Externalities Error.
 ///
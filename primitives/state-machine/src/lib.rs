@@ -43,11 +43,13 @@ mod trie_backend;
 mod trie_backend_essence;
 mod stats;
 mod read_only;
+mod tracing_backend;
 
 pub use sp_trie::{trie_types::{Layout, TrieDBMut}, StorageProof, TrieMut, DBValue, MemoryDB};
 pub use testing::TestExternalities;
 pub use basic::BasicExternalities;
 pub use read_only::{ReadOnlyExternalities, InspectState};
+pub use tracing_backend::{TracingBackend, ExecutionTrace};
 pub use ext::Ext;
 pub use backend::Backend;
 pub use changes_trie::{
@@ -540,6 +542,51 @@ where
 	Ok((result.into_encoded(), proof))
 }
 
+/// Execute a call using the given state backend and overlayed changes, tracing the storage
+/// accesses.
+///
+/// Like for proofs, the call is always executed in wasm. The changes are left in `overlay`.
+pub fn trace_execution<B, H, N, Exec>(
+	backend: &B,
+	overlay: &mut OverlayedChanges,
+	exec: &Exec,
+	spawn_handle: Box<dyn CloneableSpawn>,
+	method: &str,
+	call_data: &[u8],
+	runtime_code: &RuntimeCode,
+) -> Result<(Vec<u8>, ExecutionTrace), Box<dyn Error>>
+where
+	B: Backend<H>,
+	H: Hasher,
+	H::Out: Ord + 'static + codec::Codec,
+	Exec: CodeExecutor + 'static + Clone,
+	N: crate::changes_trie::BlockNumber,
+{
+	let mut offchain_overlay = OffchainOverlayedChanges::default();
+	let tracing_backend = TracingBackend::new(backend);
+	let result = {
+		let mut sm = StateMachine::<_, H, N, Exec>::new(
+			&tracing_backend,
+			None,
+			overlay,
+			&mut offchain_overlay,
+			exec,
+			method,
+			call_data,
+			Extensions::default(),
+			runtime_code,
+			spawn_handle,
+		);
+
+		sm.execute_using_consensus_failure_handler::<_, NeverNativeValue, fn() -> _>(
+			always_wasm(),
+			None,
+		)?
+	};
+	let trace = ExecutionTrace::new(tracing_backend.into_reads(), overlay);
+	Ok((result.into_encoded(), trace))
+}
+
 /// Check execution proof, generated by `prove_execution` call.
 pub fn execution_proof_check<H, N, Exec>(
 	root: H::Out,
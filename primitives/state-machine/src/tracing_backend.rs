@@ -0,0 +1,201 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! State machine backend that records the storage keys read from it.
+
+use std::collections::BTreeSet;
+use hash_db::Hasher;
+use parking_lot::Mutex;
+use sp_core::storage::ChildInfo;
+use crate::{Backend, StorageKey, StorageValue, UsageInfo, OverlayedChanges};
+
+/// Storage accesses made while executing a call.
+///
+/// Only the top-level storage is traced, accesses to child tries are not recorded.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExecutionTrace {
+	/// Keys read from the state the call was executed on, in lexicographic order.
+	///
+	/// Reads of values that the call wrote itself are not part of it.
+	pub reads: Vec<StorageKey>,
+	/// Keys written by the call, in lexicographic order, with their new value or `None` if
+	/// they were removed.
+	pub writes: Vec<(StorageKey, Option<StorageValue>)>,
+}
+
+impl ExecutionTrace {
+	/// Builds the trace out of the keys read from the backend and the changes left in `overlay`.
+	pub fn new(reads: BTreeSet<StorageKey>, overlay: &OverlayedChanges) -> Self {
+		let mut writes = overlay.changes()
+			.map(|(key, value)| (key.clone(), value.value().cloned()))
+			.collect::<Vec<_>>();
+		writes.sort_by(|a, b| a.0.cmp(&b.0));
+
+		ExecutionTrace {
+			reads: reads.into_iter().collect(),
+			writes,
+		}
+	}
+}
+
+/// Backend that forwards everything to another backend, recording the top-level storage keys
+/// that are read.
+pub struct TracingBackend<'a, B, H> {
+	backend: &'a B,
+	reads: Mutex<BTreeSet<StorageKey>>,
+	_marker: std::marker::PhantomData<H>,
+}
+
+impl<'a, B: Backend<H>, H: Hasher> TracingBackend<'a, B, H> {
+	/// Create a new tracing backend on top of `backend`.
+	pub fn new(backend: &'a B) -> Self {
+		TracingBackend {
+			backend,
+			reads: Mutex::new(BTreeSet::new()),
+			_marker: Default::default(),
+		}
+	}
+
+	/// Consumes the backend, returning the keys that have been read.
+	pub fn into_reads(self) -> BTreeSet<StorageKey> {
+		self.reads.into_inner()
+	}
+
+	fn record(&self, key: &[u8]) {
+		self.reads.lock().insert(key.to_vec());
+	}
+}
+
+impl<'a, B, H> std::fmt::Debug for TracingBackend<'a, B, H> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "TracingBackend")
+	}
+}
+
+impl<'a, B: Backend<H>, H: Hasher> Backend<H> for TracingBackend<'a, B, H> {
+	type Error = B::Error;
+	type Transaction = B::Transaction;
+	type TrieBackendStorage = B::TrieBackendStorage;
+
+	fn storage(&self, key: &[u8]) -> Result<Option<StorageValue>, Self::Error> {
+		self.record(key);
+		self.backend.storage(key)
+	}
+
+	fn storage_hash(&self, key: &[u8]) -> Result<Option<H::Out>, Self::Error> {
+		self.record(key);
+		self.backend.storage_hash(key)
+	}
+
+	fn exists_storage(&self, key: &[u8]) -> Result<bool, Self::Error> {
+		self.record(key);
+		self.backend.exists_storage(key)
+	}
+
+	fn child_storage(
+		&self,
+		child_info: &ChildInfo,
+		key: &[u8],
+	) -> Result<Option<StorageValue>, Self::Error> {
+		self.backend.child_storage(child_info, key)
+	}
+
+	fn for_keys_in_child_storage<F: FnMut(&[u8])>(
+		&self,
+		child_info: &ChildInfo,
+		f: F,
+	) {
+		self.backend.for_keys_in_child_storage(child_info, f)
+	}
+
+	fn next_storage_key(&self, key: &[u8]) -> Result<Option<StorageKey>, Self::Error> {
+		self.backend.next_storage_key(key)
+	}
+
+	fn next_child_storage_key(
+		&self,
+		child_info: &ChildInfo,
+		key: &[u8],
+	) -> Result<Option<StorageKey>, Self::Error> {
+		self.backend.next_child_storage_key(child_info, key)
+	}
+
+	fn for_keys_with_prefix<F: FnMut(&[u8])>(&self, prefix: &[u8], f: F) {
+		self.backend.for_keys_with_prefix(prefix, f)
+	}
+
+	fn for_child_keys_with_prefix<F: FnMut(&[u8])>(
+		&self,
+		child_info: &ChildInfo,
+		prefix: &[u8],
+		f: F,
+	) {
+		self.backend.for_child_keys_with_prefix(child_info, prefix, f)
+	}
+
+	fn storage_root<'b>(
+		&self,
+		delta: impl Iterator<Item=(&'b [u8], Option<&'b [u8]>)>,
+	) -> (H::Out, Self::Transaction) where H::Out: Ord {
+		self.backend.storage_root(delta)
+	}
+
+	fn child_storage_root<'b>(
+		&self,
+		child_info: &ChildInfo,
+		delta: impl Iterator<Item=(&'b [u8], Option<&'b [u8]>)>,
+	) -> (H::Out, bool, Self::Transaction) where H::Out: Ord {
+		self.backend.child_storage_root(child_info, delta)
+	}
+
+	fn pairs(&self) -> Vec<(StorageKey, StorageValue)> {
+		self.backend.pairs()
+	}
+
+	fn for_key_values_with_prefix<F: FnMut(&[u8], &[u8])>(&self, prefix: &[u8], f: F) {
+		self.backend.for_key_values_with_prefix(prefix, f);
+	}
+
+	fn register_overlay_stats(&mut self, _stats: &crate::stats::StateMachineStats) { }
+
+	fn usage_info(&self) -> UsageInfo {
+		self.backend.usage_info()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::InMemoryBackend;
+	use sp_runtime::traits::BlakeTwo256;
+
+	#[test]
+	fn records_read_keys() {
+		let backend = InMemoryBackend::<BlakeTwo256>::from(vec![
+			(None, vec![(b"a".to_vec(), Some(b"1".to_vec())), (b"b".to_vec(), Some(b"2".to_vec()))]),
+		]);
+		let tracing = TracingBackend::new(&backend);
+
+		assert_eq!(tracing.storage(b"b").unwrap(), Some(b"2".to_vec()));
+		assert_eq!(tracing.storage(b"c").unwrap(), None);
+		assert!(tracing.exists_storage(b"b").unwrap());
+		assert_eq!(
+			tracing.into_reads().into_iter().collect::<Vec<_>>(),
+			vec![b"b".to_vec(), b"c".to_vec()],
+		);
+	}
+}
@@ -107,6 +107,10 @@ impl ProvideInherentData for InherentDataProvider {
 	fn error_to_string(&self, error: &[u8]) -> Option<String> {
 		InherentError::try_from(&INHERENT_IDENTIFIER, error).map(|e| format!("{:?}", e))
 	}
+
+	fn data_to_string(&self, data: &[u8]) -> Option<String> {
+		InherentType::decode(&mut &data[..]).map(|t| format!("{} ms since unix epoch", t)).ok()
+	}
 }
 
 
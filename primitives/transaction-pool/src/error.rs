@@ -42,6 +42,9 @@ pub enum Error {
 	/// The transaction is temporarily banned.
 	#[display(fmt="Temporarily Banned")]
 	TemporarilyBanned,
+	/// The transaction or its sender is on the operator ban list.
+	#[display(fmt="Banned")]
+	Banned,
 	/// The transaction is already in the pool.
 	#[display(fmt="[{:?}] Already imported", _0)]
 	AlreadyImported(Box<dyn std::any::Any + Send>),
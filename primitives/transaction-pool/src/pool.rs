@@ -18,12 +18,14 @@
 //! Transaction pool primitives types & Runtime API.
 
 use std::{
-	collections::HashMap,
+	collections::{HashMap, HashSet},
 	hash::Hash,
 	sync::Arc,
 	pin::Pin,
+	time::Duration,
 };
 use futures::{Future, Stream};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use sp_runtime::{
 	generic::BlockId,
@@ -34,7 +36,7 @@ use sp_runtime::{
 };
 
 /// Transaction pool status.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PoolStatus {
 	/// Number of transactions in the ready queue.
 	pub ready: usize,
@@ -170,6 +172,10 @@ pub trait InPoolTransaction {
 	fn provides(&self) -> &[TransactionTag];
 	/// Return a flag indicating if the transaction should be propagated to other peers.
 	fn is_propagable(&self) -> bool;
+	/// Get where the transaction comes from.
+	fn source(&self) -> TransactionSource {
+		TransactionSource::External
+	}
 }
 
 /// Transaction pool interface.
@@ -223,6 +229,12 @@ pub trait TransactionPool: Send + Sync {
 	/// Get an iterator for ready transactions ordered by priority.
 	fn ready(&self) -> Box<dyn Iterator<Item=Arc<Self::InPoolTransaction>> + Send>;
 
+	/// Get the future transactions, along with the tags they require that are provided neither
+	/// by the chain nor by other transactions in the pool, e.g. a nonce gap.
+	fn futures(&self) -> Vec<(Arc<Self::InPoolTransaction>, Vec<TransactionTag>)> {
+		Vec::new()
+	}
+
 	// *** Block production
 	/// Remove transactions identified by given hashes (and dependent transactions) from the pool.
 	fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>>;
@@ -265,13 +277,180 @@ pub enum ChainEvent<B: BlockT> {
 	Finalized {
 		/// Hash of just finalized block
 		hash: B::Hash,
+		/// Blocks finalized together with `hash` that have not been reported yet, ordered from
+		/// the oldest to the newest (not including `hash` itself).
+		///
+		/// Empty if only `hash` has been finalized.
+		tree_route: Arc<[B::Hash]>,
 	},
 }
 
+/// Sender of transactions on a [`BanList`].
+///
+/// Runtimes identify the transactions of a sender by a `provides` tag made of the encoded
+/// sender followed by the encoded nonce, e.g. `(AccountId, Index)` in FRAME. A tag belongs to
+/// the sender if removing the `nonce_len` last bytes leaves exactly the encoded sender.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SenderKey {
+	sender: Vec<u8>,
+	nonce_len: usize,
+}
+
+impl SenderKey {
+	/// Creates the key of the encoded `sender`, whose nonces are encoded in `nonce_len` bytes.
+	///
+	/// Returns `None` if `sender` is empty, since it would match the tags of every sender.
+	pub fn new(sender: Vec<u8>, nonce_len: usize) -> Option<Self> {
+		if sender.is_empty() {
+			None
+		} else {
+			Some(SenderKey { sender, nonce_len })
+		}
+	}
+}
+
+/// Transactions that are not allowed to enter the pool, as configured by the node operator.
+///
+/// Clones share the same list, so it can be updated while the pool is running. Senders are
+/// matched against the `provides` tags of transactions, see [`SenderKey`].
+pub struct BanList<H> {
+	inner: Arc<RwLock<BanListInner<H>>>,
+}
+
+struct BanListInner<H> {
+	hashes: HashSet<H>,
+	/// Banned encoded senders, by the length of their nonces.
+	senders: HashMap<usize, HashSet<Vec<u8>>>,
+	generation: u64,
+}
+
+impl<H> BanListInner<H> {
+	fn insert_sender(&mut self, key: SenderKey) -> bool {
+		self.senders.entry(key.nonce_len).or_default().insert(key.sender)
+	}
+
+	fn is_sender_banned(&self, tag: &[u8]) -> bool {
+		self.senders.iter().any(|(nonce_len, senders)|
+			tag.len() > *nonce_len && senders.contains(&tag[..tag.len() - nonce_len])
+		)
+	}
+}
+
+impl<H> Clone for BanList<H> {
+	fn clone(&self) -> Self {
+		BanList { inner: self.inner.clone() }
+	}
+}
+
+impl<H: Hash + Eq> Default for BanList<H> {
+	fn default() -> Self {
+		BanList {
+			inner: Arc::new(RwLock::new(BanListInner {
+				hashes: Default::default(),
+				senders: Default::default(),
+				generation: 0,
+			})),
+		}
+	}
+}
+
+impl<H: Hash + Eq> BanList<H> {
+	/// Bans the transaction with given hash.
+	pub fn ban_hash(&self, hash: H) {
+		let mut inner = self.inner.write();
+		if inner.hashes.insert(hash) {
+			inner.generation += 1;
+		}
+	}
+
+	/// Lifts the ban of the transaction with given hash.
+	///
+	/// Returns `false` if the hash wasn't banned.
+	pub fn unban_hash(&self, hash: &H) -> bool {
+		self.inner.write().hashes.remove(hash)
+	}
+
+	/// Bans all transactions of the given sender.
+	pub fn ban_sender(&self, sender: SenderKey) {
+		let mut inner = self.inner.write();
+		if inner.insert_sender(sender) {
+			inner.generation += 1;
+		}
+	}
+
+	/// Lifts the ban of the given sender.
+	///
+	/// Returns `false` if the sender wasn't banned.
+	pub fn unban_sender(&self, sender: &SenderKey) -> bool {
+		let mut inner = self.inner.write();
+		let senders = match inner.senders.get_mut(&sender.nonce_len) {
+			Some(senders) => senders,
+			None => return false,
+		};
+		let removed = senders.remove(&sender.sender);
+		if senders.is_empty() {
+			inner.senders.remove(&sender.nonce_len);
+		}
+		removed
+	}
+
+	/// Replaces the whole list at once.
+	pub fn replace(
+		&self,
+		hashes: impl IntoIterator<Item = H>,
+		senders: impl IntoIterator<Item = SenderKey>,
+	) {
+		let mut inner = self.inner.write();
+		inner.hashes = hashes.into_iter().collect();
+		inner.senders.clear();
+		for sender in senders {
+			inner.insert_sender(sender);
+		}
+		inner.generation += 1;
+	}
+
+	/// Returns `true` if the transaction with given hash is banned.
+	///
+	/// This doesn't take banned senders into account, since it requires the transaction to be
+	/// validated first.
+	pub fn is_hash_banned(&self, hash: &H) -> bool {
+		self.inner.read().hashes.contains(hash)
+	}
+
+	/// Returns `true` if the transaction with given hash and `provides` tags is banned.
+	pub fn is_banned(&self, hash: &H, provides: &[TransactionTag]) -> bool {
+		let inner = self.inner.read();
+		inner.hashes.contains(hash) || provides.iter().any(|tag| inner.is_sender_banned(tag))
+	}
+
+	/// Returns a number that changes every time new entries are banned.
+	///
+	/// Allows the pool to only look for already imported transactions to evict when needed.
+	pub fn generation(&self) -> u64 {
+		self.inner.read().generation
+	}
+}
+
 /// Trait for transaction pool maintenance.
 pub trait MaintainedTransactionPool: TransactionPool {
 	/// Perform maintenance
 	fn maintain(&self, event: ChainEvent<Self::Block>) -> Pin<Box<dyn Future<Output=()> + Send>>;
+
+	/// Drop future transactions older than `stale_after`, provided the pool has
+	/// seen no activity for at least `idle_for`.
+	///
+	/// Returns the number of dropped transactions.
+	fn compact_if_idle(&self, _idle_for: Duration, _stale_after: Duration) -> usize {
+		0
+	}
+
+	/// Returns a handle to the operator ban list of the pool, if it has one.
+	///
+	/// Transactions matching the list are rejected on submission, and those already in the
+	/// pool are evicted on the next maintenance.
+	fn ban_list(&self) -> Option<BanList<TxHash<Self>>> {
+		None
+	}
 }
 
 /// Transaction pool interface for submitting local transactions that exposes a
@@ -79,6 +79,7 @@ pub struct TestClientBuilder<Block: BlockT, Executor, Backend, G: GenesisInit> {
 	keystore: Option<BareCryptoStorePtr>,
 	fork_blocks: ForkBlocks<Block>,
 	bad_blocks: BadBlocks<Block>,
+	client_config: ClientConfig,
 }
 
 impl<Block: BlockT, Executor, G: GenesisInit> Default
@@ -114,6 +115,7 @@ impl<Block: BlockT, Executor, Backend, G: GenesisInit> TestClientBuilder<Block,
 			keystore: None,
 			fork_blocks: None,
 			bad_blocks: None,
+			client_config: Default::default(),
 		}
 	}
 
@@ -175,6 +177,12 @@ impl<Block: BlockT, Executor, Backend, G: GenesisInit> TestClientBuilder<Block,
 		self
 	}
 
+	/// Sets the configuration of the client.
+	pub fn set_client_config(mut self, client_config: ClientConfig) -> Self {
+		self.client_config = client_config;
+		self
+	}
+
 	/// Build the test client with the given native executor.
 	pub fn build_with_executor<RuntimeApi>(
 		self,
@@ -219,7 +227,7 @@ impl<Block: BlockT, Executor, Backend, G: GenesisInit> TestClientBuilder<Block,
 				self.keystore.clone(),
 			),
 			None,
-			ClientConfig::default(),
+			self.client_config,
 		).expect("Creates new client");
 
 		let longest_chain = sc_consensus::LongestChain::new(self.backend);
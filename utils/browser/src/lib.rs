@@ -27,7 +27,7 @@ use wasm_bindgen::prelude::*;
 use futures::{
 	prelude::*, channel::{oneshot, mpsc}, compat::*, future::{ready, ok, select}
 };
-use std::{sync::Arc, pin::Pin};
+use std::{sync::Arc, pin::Pin, time::Duration};
 use sc_chain_spec::Extension;
 use libp2p_wasm_ext::{ExtTransport, ffi};
 
@@ -66,6 +66,11 @@ where
 		chain_spec: Box::new(chain_spec),
 		task_executor: (|fut, _| wasm_bindgen_futures::spawn_local(fut)).into(),
 		telemetry_external_transport: Some(transport),
+		telemetry_metrics: false,
+		telemetry_healthy_peer_threshold: 1,
+		finality_webhook: None,
+		persist_peer_reputations: None,
+		peer_reputations_ttl: Duration::from_secs(3600),
 		role: Role::Light,
 		database: {
 			info!("Opening Indexed DB database '{}'...", name);
@@ -73,36 +78,65 @@ where
 
 			DatabaseConfig::Custom(sp_database::as_database(db))
 		},
+		database_open_retries: 0,
+		database_write_buffer_size: None,
+		database_max_open_files: None,
+		genesis_build_threads: None,
+		max_tracked_forks: None,
+		skip_genesis_check: false,
+		expected_genesis_code_hash: None,
 		keystore: KeystoreConfig::InMemory,
 		default_heap_pages: Default::default(),
 		dev_key_seed: Default::default(),
+		generate_session_keys: true,
+		overwrite_dev_keys: true,
+		require_authoring_keys: false,
 		disable_grandpa: Default::default(),
 		execution_strategies: Default::default(),
 		force_authoring: Default::default(),
+		unsafe_override_inherent_checks: false,
+		reject_runtime_panics: false,
 		impl_name: String::from("parity-substrate"),
 		impl_version: String::from("0.0.0"),
 		offchain_worker: Default::default(),
 		prometheus_config: Default::default(),
+		prometheus_static_labels: Default::default(),
 		pruning: Default::default(),
+		justification_retention: Default::default(),
 		rpc_cors: Default::default(),
 		rpc_http: Default::default(),
 		rpc_ipc: Default::default(),
 		rpc_ws: Default::default(),
 		rpc_ws_max_connections: Default::default(),
+		rpc_dual_stack: false,
 		rpc_methods: Default::default(),
+		rpc_serve_after: Default::default(),
+		rpc_handler_mode: Default::default(),
+		rpc_max_concurrent_runtime_calls: None,
 		state_cache_child_ratio: Default::default(),
 		state_cache_size: Default::default(),
+		import_queue_mem_budget: Default::default(),
+		block_time_drift_tolerance: Default::default(),
 		tracing_receiver: Default::default(),
 		tracing_targets: Default::default(),
 		transaction_pool: Default::default(),
+		tx_propagation_delay: None,
 		wasm_method: Default::default(),
 		max_runtime_instances: 8,
 		announce_block: true,
+		suppress_block_announcements: false,
+		max_announce_block_age: None,
+		block_announce_validation_limit: None,
+		sync_request_timeout: sc_network::config::DEFAULT_SYNC_REQUEST_TIMEOUT,
 		base_path: None,
 		informant_output_format: sc_informant::OutputFormat {
 			enable_color: false,
 			prefix: String::new(),
 		},
+		coalesce_finality_notifications: false,
+		structured_events_sink: None,
+		task_poll_time_accounting: false,
+		task_watchdog_timeout: None,
 	};
 
 	Ok(config)